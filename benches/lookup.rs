@@ -0,0 +1,101 @@
+//! Benchmarks of lookups in trees larger than the CPU's last-level cache, where each level of the
+//! descent misses. Compare with and without the `prefetch` feature (see `README.md`).
+extern crate rand;
+
+use btree_plus_store::{BTreeMap, BTreeStore};
+
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+// region benchmark abstraction / implementation
+trait Bencher {
+    fn black_box<T>(x: T) -> T;
+    fn iter<Return>(&mut self, f: impl FnMut() -> Return);
+}
+
+/// Doesn't actually bench, runs the benchmarks only to test and debug them.
+#[cfg(not(feature = "bench"))]
+struct MockBencher;
+
+#[cfg(feature = "bench")]
+impl<'a, M: criterion::measurement::Measurement> Bencher for criterion::Bencher<'a, M> {
+    fn black_box<T>(x: T) -> T {
+        criterion::black_box(x)
+    }
+
+    fn iter<Return>(&mut self, f: impl FnMut() -> Return) {
+        self.iter(f)
+    }
+}
+
+#[cfg(not(feature = "bench"))]
+impl Bencher for MockBencher {
+    fn black_box<T>(x: T) -> T {
+        x
+    }
+
+    fn iter<Return>(&mut self, mut f: impl FnMut() -> Return) {
+        f();
+    }
+}
+// endregion
+
+/// Number of lookups per iteration, so the per-iteration overhead doesn't dominate.
+const N_LOOKUPS: usize = 1000;
+
+/// Looks up `N_LOOKUPS` random keys, half present and half absent, in a map of `len` entries.
+fn bench_random_get<B: Bencher>(b: &mut B, len: u64) {
+    let store = BTreeStore::<u64, u64>::new();
+    // Even keys, so odd lookups miss
+    let map = BTreeMap::from_sorted_iter_in((0..len).map(|k| (k * 2, k)), &store);
+    let mut rng = SmallRng::seed_from_u64(0);
+
+    b.iter(|| {
+        for _ in 0..N_LOOKUPS {
+            B::black_box(map.get(&rng.gen_range(0..len * 2)));
+        }
+    });
+}
+
+macro_rules! generate_benches {
+    ($($bench_name:ident: $len:expr),* $(,)?) => {
+        $(
+            #[cfg(feature = "bench")]
+            fn $bench_name(c: &mut criterion::Criterion) {
+                c.bench_function(stringify!($bench_name), |b| bench_random_get(b, $len));
+            }
+
+            #[cfg(not(feature = "bench"))]
+            #[test]
+            fn $bench_name() {
+                // Building the full tree takes too long to only test the benchmark
+                bench_random_get(&mut MockBencher, ($len as u64).min(1 << 12));
+            }
+        )*
+
+        #[cfg(feature = "bench")]
+        criterion::criterion_group! {
+            name = benches;
+            config = criterion::Criterion::default().sample_size(sample_size());
+            targets = $($bench_name),*
+        }
+    };
+}
+
+#[cfg(feature = "bench")]
+fn sample_size() -> usize {
+    std::env::var("SAMPLE_SIZE")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .map_or(10, |s| {
+            s.parse().expect("SAMPLE_SIZE must be an integer or unset")
+        })
+}
+
+#[cfg(feature = "bench")]
+criterion::criterion_main!(benches);
+generate_benches! {
+    // Fits in most L2 caches
+    random_get_4096: 1 << 12,
+    // Hundreds of MB of nodes, several times a large LLC
+    random_get_16777216: 1 << 24,
+}
@@ -0,0 +1,136 @@
+//! Benchmarks of the store itself, rather than the operations of a map or set: building trees on
+//! several threads at once in one `sync-store` store.
+extern crate rand;
+
+use btree_plus_store::{BTreeMap, BTreeStore};
+
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+// region benchmark abstraction / implementation
+trait Bencher {
+    fn black_box<T>(x: T) -> T;
+    fn iter<Return>(&mut self, f: impl FnMut() -> Return);
+}
+
+/// Doesn't actually bench, runs the benchmarks only to test and debug them.
+#[cfg(not(feature = "bench"))]
+struct MockBencher;
+
+#[cfg(feature = "bench")]
+impl<'a, M: criterion::measurement::Measurement> Bencher for criterion::Bencher<'a, M> {
+    fn black_box<T>(x: T) -> T {
+        criterion::black_box(x)
+    }
+
+    fn iter<Return>(&mut self, f: impl FnMut() -> Return) {
+        self.iter(f)
+    }
+}
+
+#[cfg(not(feature = "bench"))]
+impl Bencher for MockBencher {
+    fn black_box<T>(x: T) -> T {
+        x
+    }
+
+    fn iter<Return>(&mut self, mut f: impl FnMut() -> Return) {
+        f();
+    }
+}
+// endregion
+
+/// Each of `n_threads` threads builds `n_maps` maps of `n_inserts` random entries, then drops them.
+/// With `shared`, every map is in one store, otherwise each thread has its own store, which is the
+/// best the shared store can do (no contention).
+fn bench_parallel_build<B: Bencher>(
+    b: &mut B,
+    shared: bool,
+    n_threads: usize,
+    n_maps: usize,
+    n_inserts: usize,
+) {
+    let shared_store = BTreeStore::<usize, usize>::new();
+    let own_stores = (0..n_threads)
+        .map(|_| BTreeStore::<usize, usize>::new())
+        .collect::<Vec<_>>();
+
+    b.iter(|| {
+        std::thread::scope(|scope| {
+            for (thread, own_store) in own_stores.iter().enumerate() {
+                let store = match shared {
+                    false => own_store,
+                    true => &shared_store,
+                };
+                scope.spawn(move || {
+                    let mut rng = SmallRng::seed_from_u64(thread as u64);
+                    let mut maps = (0..n_maps)
+                        .map(|_| BTreeMap::new_in(store))
+                        .collect::<Vec<_>>();
+                    for map in &mut maps {
+                        for _ in 0..n_inserts {
+                            B::black_box(map.insert(rng.gen(), rng.gen()));
+                        }
+                    }
+                    B::black_box(maps);
+                });
+            }
+        })
+    });
+}
+
+macro_rules! generate_benches {
+    ($($bench_name:ident: ($n_threads:literal, $n_maps:literal, $n_inserts:literal)),* $(,)?) => {
+        $(
+            #[cfg(feature = "bench")]
+            fn $bench_name(c: &mut criterion::Criterion) {
+                let mut group = c.benchmark_group(stringify!($bench_name));
+                group.bench_function("shared_store", |b| {
+                    bench_parallel_build(b, true, $n_threads, $n_maps, $n_inserts)
+                });
+                group.bench_function("store_per_thread", |b| {
+                    bench_parallel_build(b, false, $n_threads, $n_maps, $n_inserts)
+                });
+                group.finish();
+            }
+
+            #[cfg(not(feature = "bench"))]
+            mod $bench_name {
+                use super::*;
+
+                #[test]
+                fn shared_store() {
+                    bench_parallel_build(&mut MockBencher, true, $n_threads, $n_maps, $n_inserts);
+                }
+
+                #[test]
+                fn store_per_thread() {
+                    bench_parallel_build(&mut MockBencher, false, $n_threads, $n_maps, $n_inserts);
+                }
+            }
+        )*
+
+        #[cfg(feature = "bench")]
+        criterion::criterion_group! {
+            name = benches;
+            config = criterion::Criterion::default().sample_size(sample_size());
+            targets = $($bench_name),*
+        }
+    };
+}
+
+#[cfg(feature = "bench")]
+fn sample_size() -> usize {
+    std::env::var("SAMPLE_SIZE")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .map_or(10, |s| {
+            s.parse().expect("SAMPLE_SIZE must be an integer or unset")
+        })
+}
+
+#[cfg(feature = "bench")]
+criterion::criterion_main!(benches);
+generate_benches! {
+    parallel_build_4_threads_10_maps_10000_inserts: (4, 10, 10000),
+    parallel_build_8_threads_100_maps_1000_inserts: (8, 100, 1000),
+}
@@ -0,0 +1,108 @@
+use icu_collator::Collator;
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display, Formatter};
+
+/// Key wrapper which orders strings by a [Collator], i.e. per a locale's alphabetical rules rather
+/// than by bytes, so that ranges in [BTreeMap](crate::BTreeMap) and [BTreeSet](crate::BTreeSet)
+/// are locale-correct.
+///
+/// Every key (and range bound) in the same map must use the same collator. Strings which the
+/// collator considers equal (e.g. differing only in case, if the strength ignores case) are the
+/// same key.
+///
+/// # Examples
+///
+/// ```
+/// use btree_plus_store::{BTreeSet, BTreeStore, Collated};
+/// use icu_collator::{Collator, CollatorOptions};
+/// let collator = Collator::try_new(&Default::default(), CollatorOptions::new()).unwrap();
+/// let store = BTreeStore::new();
+/// let mut set = BTreeSet::new_in(&store);
+/// for name in ["zebra", "Émile", "apple", "Ethan", "banana"] {
+///     set.insert(Collated::new(&collator, name));
+/// }
+/// assert_eq!(
+///     set.iter().map(|name| *name.get()).collect::<Vec<_>>(),
+///     ["apple", "banana", "Émile", "Ethan", "zebra"]
+/// );
+/// let (start, end) = (Collated::new(&collator, "b"), Collated::new(&collator, "f"));
+/// assert_eq!(
+///     set.try_range(start..end).unwrap().map(|name| *name.get()).collect::<Vec<_>>(),
+///     ["banana", "Émile", "Ethan"]
+/// );
+/// ```
+#[derive(Clone, Copy)]
+pub struct Collated<'c, S> {
+    collator: &'c Collator,
+    value: S,
+}
+
+impl<'c, S: AsRef<str>> Collated<'c, S> {
+    /// Wraps `value` to be ordered by `collator`
+    #[inline]
+    pub fn new(collator: &'c Collator, value: S) -> Self {
+        Self { collator, value }
+    }
+}
+
+impl<'c, S> Collated<'c, S> {
+    /// Returns the wrapped string
+    #[inline]
+    pub fn get(&self) -> &S {
+        &self.value
+    }
+
+    /// Returns the collator which orders the string
+    #[inline]
+    pub fn collator(&self) -> &'c Collator {
+        self.collator
+    }
+
+    /// Returns the wrapped string
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.value
+    }
+}
+
+impl<'c, S: AsRef<str>> PartialEq for Collated<'c, S> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<'c, S: AsRef<str>> Eq for Collated<'c, S> {}
+
+impl<'c, S: AsRef<str>> PartialOrd for Collated<'c, S> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'c, S: AsRef<str>> Ord for Collated<'c, S> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        debug_assert!(
+            std::ptr::eq(self.collator, other.collator),
+            "compared strings collated by different collators"
+        );
+        self.collator
+            .compare(self.value.as_ref(), other.value.as_ref())
+    }
+}
+
+impl<'c, S: Debug> Debug for Collated<'c, S> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl<'c, S: Display> Display for Collated<'c, S> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.value.fmt(f)
+    }
+}
@@ -0,0 +1,55 @@
+#[cfg(feature = "set")]
+use crate::BTreeSet;
+use crate::{BTreeMap, BTreeStore};
+
+/// Collections which can be built from an iterator in a store, since they can't implement
+/// [FromIterator] without one.
+pub trait FromIteratorIn<A>: Sized {
+    /// What the collection is allocated in, e.g. `&'store BTreeStore<K, V>`
+    type Store;
+
+    /// Creates the collection in `store` from the iterator's items.
+    fn from_iter_in<I: IntoIterator<Item = A>>(iter: I, store: Self::Store) -> Self;
+}
+
+/// Adds [collect_in](CollectIn::collect_in) to iterators, the equivalent of [Iterator::collect] for
+/// collections which need a store.
+///
+/// # Examples
+///
+/// ```
+/// use btree_plus_store::{BTreeMap, BTreeStore, CollectIn};
+/// let store = BTreeStore::new();
+/// let map = (0..10).map(|i| (i, i * i)).collect_in::<BTreeMap<_, _>>(&store);
+/// assert_eq!(map.get(&3), Some(&9));
+/// ```
+pub trait CollectIn: Iterator + Sized {
+    /// Collects the items into a collection allocated in `store`.
+    #[inline]
+    fn collect_in<C: FromIteratorIn<Self::Item>>(self, store: C::Store) -> C {
+        C::from_iter_in(self, store)
+    }
+}
+
+impl<I: Iterator> CollectIn for I {}
+
+impl<'store, K: Ord + Clone, V, const B: usize> FromIteratorIn<(K, V)>
+    for BTreeMap<'store, K, V, B>
+{
+    type Store = &'store BTreeStore<K, V, B>;
+
+    #[inline]
+    fn from_iter_in<I: IntoIterator<Item = (K, V)>>(iter: I, store: Self::Store) -> Self {
+        BTreeMap::from_iter_in(iter, store)
+    }
+}
+
+#[cfg(feature = "set")]
+impl<'store, T: Ord + Clone, const B: usize> FromIteratorIn<T> for BTreeSet<'store, T, B> {
+    type Store = &'store BTreeStore<T, (), B>;
+
+    #[inline]
+    fn from_iter_in<I: IntoIterator<Item = T>>(iter: I, store: Self::Store) -> Self {
+        BTreeSet::from_iter_in(iter, store)
+    }
+}
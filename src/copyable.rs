@@ -1,8 +1,10 @@
+pub use crate::tree::BTree;
 pub use map::BTreeMap;
+#[cfg(feature = "set")]
 pub use set::BTreeSet;
-pub use store::{BTree, BTreeStoreExt};
+pub use store::BTreeStoreExt;
 
 pub mod map;
-pub(crate) mod sealed;
+#[cfg(feature = "set")]
 pub mod set;
 mod store;
@@ -1,42 +1,47 @@
-use crate::BTreeStore;
+use crate::{BTreeStore, Error};
 use std::borrow::Borrow;
 use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
-use std::hash::{Hash, Hasher};
+use std::hash::{BuildHasher, Hash, Hasher};
 use std::marker::PhantomData;
 use std::mem::{size_of, transmute, MaybeUninit};
 use std::ops::{Deref, RangeBounds};
 
 /// A copyable, immutable b-tree map, which doesn't drop its contents.
-pub struct BTreeMap<'store, K, V> {
-    inner: RawBTreeMap<'store, K, V>,
+pub struct BTreeMap<'store, K, V, const B: usize = 8> {
+    inner: RawBTreeMap<'store, K, V, B>,
 }
 
-pub type Iter<'a, K, V> = crate::map::Iter<'a, K, V>;
-pub type Keys<'a, K, V> = crate::map::Keys<'a, K, V>;
-pub type Values<'a, K, V> = crate::map::Values<'a, K, V>;
-pub type Range<'a, K, V> = crate::map::Range<'a, K, V>;
+pub type Iter<'a, K, V, const B: usize = 8> = crate::map::Iter<'a, K, V, B>;
+pub type Keys<'a, K, V, const B: usize = 8> = crate::map::Keys<'a, K, V, B>;
+pub type Values<'a, K, V, const B: usize = 8> = crate::map::Values<'a, K, V, B>;
+pub type Range<'a, K, V, const B: usize = 8> = crate::map::Range<'a, K, V, B>;
+pub type Nodes<'a, K, V, const B: usize = 8> = crate::map::Nodes<'a, K, V, B>;
+pub type Diff<'a, K, V, const B: usize = 8> = crate::map::Diff<'a, K, V, B>;
 
-impl<'store, K, V> From<crate::BTreeMap<'store, K, V>> for BTreeMap<'store, K, V> {
+impl<'store, K, V, const B: usize> From<crate::BTreeMap<'store, K, V, B>>
+    for BTreeMap<'store, K, V, B>
+{
     /// Creates a copyable map from a non-copyable map. Afterwards, the map is no longer mutable and
     /// will no longer drop its contents.
     #[inline]
-    fn from(inner: crate::BTreeMap<'store, K, V>) -> Self {
+    fn from(inner: crate::BTreeMap<'store, K, V, B>) -> Self {
         Self {
             inner: RawBTreeMap::from(inner),
         }
     }
 }
 
-impl<'store, K, V> BTreeMap<'store, K, V> {
+impl<'store, K, V, const B: usize> BTreeMap<'store, K, V, B> {
     /// Helper function to construct a copyable b-tree map by constructing a mutable one and then
     /// immediately wrapping it.
     ///
     /// This literally just creates the mutable map, runs the inner function, and then wraps it.
     #[inline]
     pub fn build(
-        store: &'store BTreeStore<K, V>,
-        f: impl FnOnce(&mut crate::BTreeMap<'store, K, V>),
+        store: &'store BTreeStore<K, V, B>,
+        f: impl FnOnce(&mut crate::BTreeMap<'store, K, V, B>),
     ) -> Self {
         let mut map = crate::BTreeMap::new_in(store);
         f(&mut map);
@@ -109,6 +114,36 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
     pub fn last_key_value(&self) -> Option<(&K, &V)> {
         self.inner.last_key_value()
     }
+
+    /// Whether both maps have the same keys, ignoring values. Copies of the same map are compared
+    /// without reading their keys.
+    #[inline]
+    pub fn keys_eq<V2>(&self, other: &BTreeMap<'_, K, V2, B>) -> bool
+    where
+        K: PartialEq,
+    {
+        self.inner.keys_eq(&other.inner)
+    }
+
+    /// Compares the maps' key sequences lexicographically, ignoring values.
+    #[inline]
+    pub fn keys_cmp<V2>(&self, other: &BTreeMap<'_, K, V2, B>) -> Ordering
+    where
+        K: Ord,
+    {
+        self.inner.keys_cmp(&other.inner)
+    }
+
+    /// Iterates over the differences from this map to `other` in key order. See
+    /// [crate::BTreeMap::diff_with].
+    #[inline]
+    pub fn diff_with<'a>(&'a self, other: &'a BTreeMap<'_, K, V, B>) -> Diff<'a, K, V, B>
+    where
+        K: Ord,
+        V: PartialEq,
+    {
+        self.inner.diff_with(&other.inner)
+    }
     // endregion
 
     // region advanced
@@ -125,46 +160,84 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
         self.inner.validate()
     }
 
-    /// Prints the b-tree in ascii
+    /// Prints the b-tree in ascii to `f`, which can be a [Formatter], a [String], or any other
+    /// [std::fmt::Write].
     #[inline]
-    pub fn print(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+    pub fn print(&self, f: &mut (impl std::fmt::Write + ?Sized)) -> std::fmt::Result
     where
         K: Debug,
         V: Debug,
     {
         self.inner.print(f)
     }
+
+    /// Prints the b-tree in ascii to an [std::io::Write], e.g. a file or stderr.
+    #[inline]
+    pub fn print_to(&self, w: impl std::io::Write) -> std::io::Result<()>
+    where
+        K: Debug,
+        V: Debug,
+    {
+        self.inner.print_to(w)
+    }
+
+    /// Prints the b-tree in ascii to a new string.
+    #[inline]
+    pub fn print_to_string(&self) -> String
+    where
+        K: Debug,
+        V: Debug,
+    {
+        self.inner.print_to_string()
+    }
     // endregion
 
     // region iteration
     /// Iterates over the map's key-value pairs in order.
     #[inline]
-    pub fn iter(&self) -> Iter<'_, K, V> {
+    pub fn iter(&self) -> Iter<'_, K, V, B> {
         self.inner.iter()
     }
 
     /// Iterates over the map's keys in order.
     #[inline]
-    pub fn keys(&self) -> Keys<'_, K, V> {
+    pub fn keys(&self) -> Keys<'_, K, V, B> {
         self.inner.keys()
     }
 
     /// Iterates over the map's values in order.
     #[inline]
-    pub fn values(&self) -> Values<'_, K, V> {
+    pub fn values(&self) -> Values<'_, K, V, B> {
         self.inner.values()
     }
 
     /// Iterates over the map's key-value pairs in order, within the given range.
+    ///
+    /// *Panics* if the range's start is after its end, or they are equal and both excluded.
+    #[track_caller]
     #[inline]
-    pub fn range<Q: Ord + ?Sized>(&self, bounds: impl RangeBounds<Q>) -> Range<'_, K, V>
+    pub fn range<Q: Ord + ?Sized>(&self, bounds: impl RangeBounds<Q>) -> Range<'_, K, V, B>
     where
         K: Borrow<Q>,
     {
         self.inner.range(bounds)
     }
 
+    /// Iterates over the map's key-value pairs in order, within the given range, or returns an error
+    /// if the range's start is after its end.
+    #[inline]
+    pub fn try_range<Q: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<Q>,
+    ) -> Result<Range<'_, K, V, B>, Error>
+    where
+        K: Borrow<Q>,
+    {
+        self.inner.try_range(bounds)
+    }
+
     /// Iterates over the map's keys in order, within the given range.
+    #[track_caller]
     #[inline]
     pub fn range_keys<Q: Ord + ?Sized>(
         &self,
@@ -177,6 +250,7 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
     }
 
     /// Iterates over the map's values in order, within the given range.
+    #[track_caller]
     #[inline]
     pub fn range_values<Q: Ord + ?Sized>(
         &self,
@@ -187,17 +261,24 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
     {
         self.inner.range_values(bounds)
     }
+
+    /// Iterates over summaries of the map's nodes (*not* entries) in pre-order, e.g. to analyze or
+    /// serialize the tree's structure.
+    #[inline]
+    pub fn nodes(&self) -> Nodes<'_, K, V, B> {
+        self.inner.nodes()
+    }
 }
 
 // region common trait impls
-impl<'store, K: Debug, V: Debug> Debug for BTreeMap<'store, K, V> {
+impl<'store, K: Debug, V: Debug, const B: usize> Debug for BTreeMap<'store, K, V, B> {
     #[inline]
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         self.inner.fmt(f)
     }
 }
 
-impl<'store, K, V> Clone for BTreeMap<'store, K, V> {
+impl<'store, K, V, const B: usize> Clone for BTreeMap<'store, K, V, B> {
     #[inline]
     fn clone(&self) -> Self {
         // SAFETY: This is copy-able because:
@@ -210,9 +291,9 @@ impl<'store, K, V> Clone for BTreeMap<'store, K, V> {
     }
 }
 
-impl<'store, K, V> Copy for BTreeMap<'store, K, V> {}
+impl<'store, K, V, const B: usize> Copy for BTreeMap<'store, K, V, B> {}
 
-impl<'store, K: PartialEq, V: PartialEq> PartialEq for BTreeMap<'store, K, V> {
+impl<'store, K: PartialEq, V: PartialEq, const B: usize> PartialEq for BTreeMap<'store, K, V, B> {
     #[inline]
     fn eq(&self, other: &Self) -> bool {
         &*self.inner == &*other.inner
@@ -224,77 +305,126 @@ impl<'store, K: PartialEq, V: PartialEq> PartialEq for BTreeMap<'store, K, V> {
     }
 }
 
-impl<'store, K: Eq, V: Eq> Eq for BTreeMap<'store, K, V> {}
+impl<'store, K: Eq, V: Eq, const B: usize> Eq for BTreeMap<'store, K, V, B> {}
+
+impl<'store, K: Eq + Hash, V: PartialEq, S: BuildHasher, const B: usize> PartialEq<HashMap<K, V, S>>
+    for BTreeMap<'store, K, V, B>
+{
+    #[inline]
+    fn eq(&self, other: &HashMap<K, V, S>) -> bool {
+        &*self.inner == other
+    }
+}
+
+impl<'store, K: Eq + Hash, V: PartialEq, S: BuildHasher, const B: usize>
+    PartialEq<BTreeMap<'store, K, V, B>> for HashMap<K, V, S>
+{
+    #[inline]
+    fn eq(&self, other: &BTreeMap<'store, K, V, B>) -> bool {
+        other == self
+    }
+}
 
-impl<'store, K: PartialOrd, V: PartialOrd> PartialOrd for BTreeMap<'store, K, V> {
+impl<'store, K: PartialOrd, V: PartialOrd, const B: usize> PartialOrd
+    for BTreeMap<'store, K, V, B>
+{
     #[inline]
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.inner.partial_cmp(&other.inner)
+        (*self.inner).partial_cmp(&*other.inner)
     }
 }
 
-impl<'store, K: Ord, V: Ord> Ord for BTreeMap<'store, K, V> {
+impl<'store, K: Ord, V: Ord, const B: usize> Ord for BTreeMap<'store, K, V, B> {
     #[inline]
     fn cmp(&self, other: &Self) -> Ordering {
         self.inner.cmp(&other.inner)
     }
 }
 
-impl<'store, K: Hash, V: Hash> Hash for BTreeMap<'store, K, V> {
+impl<'store, K: Hash, V: Hash, const B: usize> Hash for BTreeMap<'store, K, V, B> {
     #[inline]
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.inner.hash(state)
     }
 }
+
+/// *Panics* if the key isn't in the map. See [BTreeMap::get] for a non-panicking version.
+impl<'store, K: Borrow<Q>, V, Q: Ord + ?Sized, const B: usize> std::ops::Index<&Q>
+    for BTreeMap<'store, K, V, B>
+{
+    type Output = V;
+
+    #[track_caller]
+    #[inline]
+    fn index(&self, key: &Q) -> &V {
+        match self.get(key) {
+            Some(value) => value,
+            None => panic!("BTreeMap::index: key not found"),
+        }
+    }
+}
 // endregion
 
 // region RawBTreeMap
 /// [crate::BTreeMap] but as raw data so it can be [Copy]'d. Also doesn't run drop code.
-struct RawBTreeMap<'store, K, V> {
+struct RawBTreeMap<'store, K, V, const B: usize> {
     // generic parameters may not be used in const operations
     // But fortunately [crate::BTreeMap]'s size doesn't depend on its generics, because everything
     // is under an indirect pointer, and `K` and `V` are [Sized]
     data: [MaybeUninit<u8>; size_of::<crate::BTreeMap<'static, (), ()>>()],
     _p: PhantomData<(&'store K, &'store V)>,
+    /// The root's generation when the tree became copyable, to detect its nodes being freed
+    #[cfg(feature = "generations")]
+    generation: u64,
 }
 
-impl<'store, K, V> From<crate::BTreeMap<'store, K, V>> for RawBTreeMap<'store, K, V> {
+impl<'store, K, V, const B: usize> From<crate::BTreeMap<'store, K, V, B>>
+    for RawBTreeMap<'store, K, V, B>
+{
     #[inline]
-    fn from(inner: crate::BTreeMap<'store, K, V>) -> Self {
+    fn from(inner: crate::BTreeMap<'store, K, V, B>) -> Self {
         Self {
+            #[cfg(feature = "generations")]
+            generation: inner.root_generation(),
             data: unsafe { transmute(inner) },
             _p: PhantomData,
         }
     }
 }
 
-impl<'store, K, V> Deref for RawBTreeMap<'store, K, V> {
-    type Target = crate::BTreeMap<'store, K, V>;
+/// Under the `generations` feature, every dereference looks up the root's generation in the store's
+/// hash map.
+impl<'store, K, V, const B: usize> Deref for RawBTreeMap<'store, K, V, B> {
+    type Target = crate::BTreeMap<'store, K, V, B>;
 
     #[inline]
     fn deref(&self) -> &Self::Target {
-        unsafe { &*(self.data.as_ptr() as *const crate::BTreeMap<'store, K, V>) }
+        let tree = unsafe { &*(self.data.as_ptr() as *const crate::BTreeMap<'store, K, V, B>) };
+        #[cfg(feature = "generations")]
+        assert_eq!(
+            tree.root_generation(),
+            self.generation,
+            "copyable b-tree's nodes were freed (e.g. by `tracing_gc` without it)"
+        );
+        tree
     }
 }
 
-impl<'store, K, V> Clone for RawBTreeMap<'store, K, V> {
+impl<'store, K, V, const B: usize> Clone for RawBTreeMap<'store, K, V, B> {
     #[inline]
     fn clone(&self) -> Self {
-        Self {
-            data: self.data,
-            _p: PhantomData,
-        }
+        *self
     }
 }
 
-impl<'store, K, V> Copy for RawBTreeMap<'store, K, V> {}
+impl<'store, K, V, const B: usize> Copy for RawBTreeMap<'store, K, V, B> {}
 // endregion
 
 //noinspection DuplicatedCode
 // region iterator impls
-impl<'store: 'a, 'a, K, V> IntoIterator for &'a BTreeMap<'store, K, V> {
+impl<'store: 'a, 'a, K, V, const B: usize> IntoIterator for &'a BTreeMap<'store, K, V, B> {
     type Item = (&'a K, &'a V);
-    type IntoIter = Iter<'a, K, V>;
+    type IntoIter = Iter<'a, K, V, B>;
 
     #[inline]
     fn into_iter(self) -> Self::IntoIter {
@@ -303,14 +433,16 @@ impl<'store: 'a, 'a, K, V> IntoIterator for &'a BTreeMap<'store, K, V> {
 }
 // endregion
 
-impl<'store, K, V> crate::copyable::sealed::BTree<'store, K, V> for BTreeMap<'store, K, V> {
+impl<'store, K, V, const B: usize> crate::tree::sealed::BTree<'store, K, V, B>
+    for BTreeMap<'store, K, V, B>
+{
     #[inline]
-    fn assert_store(&self, store: &BTreeStore<K, V>) {
-        self.inner.assert_store(store)
+    fn is_from_store(&self, store: &BTreeStore<K, V, B>) -> bool {
+        self.inner.is_from_store(store)
     }
 
     #[inline]
-    fn nodes(&self) -> crate::copyable::sealed::NodeIter<'store, K, V> {
-        self.inner.nodes()
+    fn node_ptrs(&self) -> crate::node_iter::NodeIter<'store, K, V, B> {
+        self.inner.node_ptrs()
     }
 }
@@ -1,40 +1,42 @@
-use crate::BTreeStore;
+use crate::{BTreeStore, Error};
 use std::borrow::Borrow;
 use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::fmt::{Debug, Formatter};
-use std::hash::{Hash, Hasher};
+use std::hash::{BuildHasher, Hash, Hasher};
 use std::marker::PhantomData;
 use std::mem::{size_of, transmute, MaybeUninit};
 use std::ops::{Deref, RangeBounds};
 
 /// A copyable, immutable b-tree set, which doesn't drop its contents.
-pub struct BTreeSet<'store, T> {
-    inner: RawBTreeSet<'store, T>,
+pub struct BTreeSet<'store, T, const B: usize = 8> {
+    inner: RawBTreeSet<'store, T, B>,
 }
 
-pub type Iter<'a, T> = crate::set::Iter<'a, T>;
-pub type Range<'a, T> = crate::set::Range<'a, T>;
+pub type Iter<'a, T, const B: usize = 8> = crate::set::Iter<'a, T, B>;
+pub type Range<'a, T, const B: usize = 8> = crate::set::Range<'a, T, B>;
+pub type Nodes<'a, T, const B: usize = 8> = crate::set::Nodes<'a, T, B>;
 
-impl<'store, T> From<crate::BTreeSet<'store, T>> for BTreeSet<'store, T> {
+impl<'store, T, const B: usize> From<crate::BTreeSet<'store, T, B>> for BTreeSet<'store, T, B> {
     /// Creates a copyable set from a non-copyable set. Afterwards, the set is no longer mutable and
     /// will no longer drop its contents.
     #[inline]
-    fn from(inner: crate::BTreeSet<'store, T>) -> Self {
+    fn from(inner: crate::BTreeSet<'store, T, B>) -> Self {
         Self {
             inner: RawBTreeSet::from(inner),
         }
     }
 }
 
-impl<'store, T> BTreeSet<'store, T> {
+impl<'store, T, const B: usize> BTreeSet<'store, T, B> {
     /// Helper function to construct a copyable b-tree set by constructing a mutable one and then
     /// immediately wrapping it.
     ///
     /// This literally just creates the mutable set, runs the inner function, and then wraps it.
     #[inline]
     pub fn build(
-        store: &'store BTreeStore<T, ()>,
-        f: impl FnOnce(&mut crate::BTreeSet<'store, T>),
+        store: &'store BTreeStore<T, (), B>,
+        f: impl FnOnce(&mut crate::BTreeSet<'store, T, B>),
     ) -> Self {
         let mut set = crate::BTreeSet::new_in(store);
         f(&mut set);
@@ -98,40 +100,82 @@ impl<'store, T> BTreeSet<'store, T> {
         self.inner.validate()
     }
 
-    /// Prints the b-tree in ascii
+    /// Prints the b-tree in ascii to `f`, which can be a [Formatter], a [String], or any other
+    /// [std::fmt::Write].
     #[inline]
-    pub fn print(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+    pub fn print(&self, f: &mut (impl std::fmt::Write + ?Sized)) -> std::fmt::Result
     where
         T: Debug,
     {
         self.inner.print(f)
     }
 
+    /// Prints the b-tree in ascii to an [std::io::Write], e.g. a file or stderr.
+    #[inline]
+    pub fn print_to(&self, w: impl std::io::Write) -> std::io::Result<()>
+    where
+        T: Debug,
+    {
+        self.inner.print_to(w)
+    }
+
+    /// Prints the b-tree in ascii to a new string.
+    #[inline]
+    pub fn print_to_string(&self) -> String
+    where
+        T: Debug,
+    {
+        self.inner.print_to_string()
+    }
+
     /// Returns an iterator over the set.
     #[inline]
-    pub fn iter(&self) -> Iter<'_, T> {
+    pub fn iter(&self) -> Iter<'_, T, B> {
         self.inner.iter()
     }
 
     /// Returns an iterator over the set within the given bounds
+    ///
+    /// *Panics* if the range's start is after its end, or they are equal and both excluded.
+    #[track_caller]
     #[inline]
-    pub fn range<U: Ord + ?Sized>(&self, bounds: impl RangeBounds<U>) -> Range<T>
+    pub fn range<U: Ord + ?Sized>(&self, bounds: impl RangeBounds<U>) -> Range<T, B>
     where
         T: Borrow<U>,
     {
         self.inner.range(bounds)
     }
+
+    /// Returns an iterator over the set within the given bounds, or an error if the range's start is
+    /// after its end
+    #[inline]
+    pub fn try_range<U: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<U>,
+    ) -> Result<Range<'_, T, B>, Error>
+    where
+        T: Borrow<U>,
+    {
+        self.inner.try_range(bounds)
+    }
+
+    /// Iterates over summaries of the set's nodes (*not* elements) in pre-order, e.g. to analyze or
+    /// serialize the tree's structure.
+    #[inline]
+    pub fn nodes(&self) -> Nodes<'_, T, B> {
+        self.inner.nodes()
+    }
 }
 
 // region common trait impls
-impl<'store, T: Debug> Debug for BTreeSet<'store, T> {
+impl<'store, T: Debug, const B: usize> Debug for BTreeSet<'store, T, B> {
     #[inline]
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         self.inner.fmt(f)
     }
 }
 
-impl<'store, T> Clone for BTreeSet<'store, T> {
+impl<'store, T, const B: usize> Clone for BTreeSet<'store, T, B> {
     #[inline]
     fn clone(&self) -> Self {
         // SAFETY: This is copy-able because:
@@ -144,9 +188,9 @@ impl<'store, T> Clone for BTreeSet<'store, T> {
     }
 }
 
-impl<'store, T> Copy for BTreeSet<'store, T> {}
+impl<'store, T, const B: usize> Copy for BTreeSet<'store, T, B> {}
 
-impl<'store, T: PartialEq> PartialEq for BTreeSet<'store, T> {
+impl<'store, T: PartialEq, const B: usize> PartialEq for BTreeSet<'store, T, B> {
     #[inline]
     fn eq(&self, other: &Self) -> bool {
         &*self.inner == &*other.inner
@@ -158,23 +202,41 @@ impl<'store, T: PartialEq> PartialEq for BTreeSet<'store, T> {
     }
 }
 
-impl<'store, T: Eq> Eq for BTreeSet<'store, T> {}
+impl<'store, T: Eq, const B: usize> Eq for BTreeSet<'store, T, B> {}
+
+impl<'store, T: Eq + Hash, S: BuildHasher, const B: usize> PartialEq<HashSet<T, S>>
+    for BTreeSet<'store, T, B>
+{
+    #[inline]
+    fn eq(&self, other: &HashSet<T, S>) -> bool {
+        &*self.inner == other
+    }
+}
+
+impl<'store, T: Eq + Hash, S: BuildHasher, const B: usize> PartialEq<BTreeSet<'store, T, B>>
+    for HashSet<T, S>
+{
+    #[inline]
+    fn eq(&self, other: &BTreeSet<'store, T, B>) -> bool {
+        other == self
+    }
+}
 
-impl<'store, T: PartialOrd> PartialOrd for BTreeSet<'store, T> {
+impl<'store, T: PartialOrd, const B: usize> PartialOrd for BTreeSet<'store, T, B> {
     #[inline]
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.inner.partial_cmp(&other.inner)
+        (*self.inner).partial_cmp(&*other.inner)
     }
 }
 
-impl<'store, T: Ord> Ord for BTreeSet<'store, T> {
+impl<'store, T: Ord, const B: usize> Ord for BTreeSet<'store, T, B> {
     #[inline]
     fn cmp(&self, other: &Self) -> Ordering {
         self.inner.cmp(&other.inner)
     }
 }
 
-impl<'store, T: Hash> Hash for BTreeSet<'store, T> {
+impl<'store, T: Hash, const B: usize> Hash for BTreeSet<'store, T, B> {
     #[inline]
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.inner.hash(state)
@@ -184,50 +246,61 @@ impl<'store, T: Hash> Hash for BTreeSet<'store, T> {
 
 // region RawBTreeSet
 /// [crate::BTreeSet] but as raw data so it can be [Copy]'d. Also doesn't run drop code.
-struct RawBTreeSet<'store, T> {
+struct RawBTreeSet<'store, T, const B: usize> {
     // generic parameters may not be used in const operations
     // But fortunately [crate::BTreeSet]'s size doesn't depend on its generics, because everything
     // is under an indirect pointer, and `T` is [Sized]
     data: [MaybeUninit<u8>; size_of::<crate::BTreeSet<'static, ()>>()],
     _p: PhantomData<&'store T>,
+    /// The root's generation when the tree became copyable, to detect its nodes being freed
+    #[cfg(feature = "generations")]
+    generation: u64,
 }
 
-impl<'store, T> From<crate::BTreeSet<'store, T>> for RawBTreeSet<'store, T> {
+impl<'store, T, const B: usize> From<crate::BTreeSet<'store, T, B>> for RawBTreeSet<'store, T, B> {
     #[inline]
-    fn from(inner: crate::BTreeSet<'store, T>) -> Self {
+    fn from(inner: crate::BTreeSet<'store, T, B>) -> Self {
         Self {
+            #[cfg(feature = "generations")]
+            generation: inner.0.root_generation(),
             data: unsafe { transmute(inner) },
             _p: PhantomData,
         }
     }
 }
 
-impl<'store, T> Deref for RawBTreeSet<'store, T> {
-    type Target = crate::BTreeSet<'store, T>;
+/// Under the `generations` feature, every dereference looks up the root's generation in the store's
+/// hash map.
+impl<'store, T, const B: usize> Deref for RawBTreeSet<'store, T, B> {
+    type Target = crate::BTreeSet<'store, T, B>;
 
     #[inline]
     fn deref(&self) -> &Self::Target {
-        unsafe { &*(self.data.as_ptr() as *const crate::BTreeSet<'store, T>) }
+        let tree = unsafe { &*(self.data.as_ptr() as *const crate::BTreeSet<'store, T, B>) };
+        #[cfg(feature = "generations")]
+        assert_eq!(
+            tree.0.root_generation(),
+            self.generation,
+            "copyable b-tree's nodes were freed (e.g. by `tracing_gc` without it)"
+        );
+        tree
     }
 }
 
-impl<'store, T> Clone for RawBTreeSet<'store, T> {
+impl<'store, T, const B: usize> Clone for RawBTreeSet<'store, T, B> {
     #[inline]
     fn clone(&self) -> Self {
-        Self {
-            data: self.data,
-            _p: PhantomData,
-        }
+        *self
     }
 }
 
-impl<'store, T> Copy for RawBTreeSet<'store, T> {}
+impl<'store, T, const B: usize> Copy for RawBTreeSet<'store, T, B> {}
 // endregion
 
 //noinspection DuplicatedCode
-impl<'a, 'store: 'a, T> IntoIterator for &'a BTreeSet<'store, T> {
+impl<'a, 'store: 'a, T, const B: usize> IntoIterator for &'a BTreeSet<'store, T, B> {
     type Item = &'a T;
-    type IntoIter = Iter<'a, T>;
+    type IntoIter = Iter<'a, T, B>;
 
     #[inline]
     fn into_iter(self) -> Self::IntoIter {
@@ -235,14 +308,16 @@ impl<'a, 'store: 'a, T> IntoIterator for &'a BTreeSet<'store, T> {
     }
 }
 
-impl<'store, T> crate::copyable::sealed::BTree<'store, T, ()> for BTreeSet<'store, T> {
+impl<'store, T, const B: usize> crate::tree::sealed::BTree<'store, T, (), B>
+    for BTreeSet<'store, T, B>
+{
     #[inline]
-    fn assert_store(&self, store: &BTreeStore<T, ()>) {
-        self.inner.assert_store(store)
+    fn is_from_store(&self, store: &BTreeStore<T, (), B>) -> bool {
+        self.inner.is_from_store(store)
     }
 
     #[inline]
-    fn nodes(&self) -> crate::copyable::sealed::NodeIter<'store, T, ()> {
-        self.inner.nodes()
+    fn node_ptrs(&self) -> crate::node_iter::NodeIter<'store, T, (), B> {
+        self.inner.node_ptrs()
     }
 }
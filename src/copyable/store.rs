@@ -1,17 +1,33 @@
 use std::collections::HashSet;
 
 use crate::node::NodePtr;
-use crate::BTreeStore;
+use crate::{BTree, BTreeStore, Error};
 
 /// Extension to tracing garbage-collect nodes in a store
-pub trait BTreeStoreExt<K, V> {
+pub trait BTreeStoreExt<K, V, const B: usize = 8> {
     /// Remove all allocated nodes which are not reachable through `b_trees` iterator.
     ///
+    /// *Panics* if any of the b-trees aren't from this store (before removing anything).
+    ///
     /// # Safety
     /// `b_trees` *must* return b-trees containing all reachable nodes in the store, AKA there must
     /// not exist a b-tree with this store which is not in `b_trees`. Any nodes not reachable through
     /// `b_trees` will be dropped.
-    unsafe fn tracing_gc<'a>(&self, btrees: impl IntoIterator<Item = impl BTree<'a, K, V>>)
+    #[track_caller]
+    unsafe fn tracing_gc<'a>(&self, btrees: impl IntoIterator<Item = impl BTree<'a, K, V, B>>)
+    where
+        K: 'a,
+        V: 'a;
+
+    /// Remove all allocated nodes which are not reachable through `b_trees` iterator, or return an
+    /// error (before removing anything) if any of the b-trees aren't from this store.
+    ///
+    /// # Safety
+    /// Same as [BTreeStoreExt::tracing_gc]
+    unsafe fn try_tracing_gc<'a>(
+        &self,
+        btrees: impl IntoIterator<Item = impl BTree<'a, K, V, B>>,
+    ) -> Result<(), Error>
     where
         K: 'a,
         V: 'a;
@@ -19,25 +35,36 @@ pub trait BTreeStoreExt<K, V> {
     // TODO: Async or background version which does [tri-color marking](https://en.wikipedia.org/wiki/Tracing_garbage_collection#Tri-color_marking)
 }
 
-/// Generic trait for different b-tree maps and sets, which returns reachable nodes.
-///
-/// This trait is [sealed](https://rust-lang.github.io/api-guidelines/future-proofing.html#sealed-traits-protect-against-downstream-implementations-c-sealed)
-pub trait BTree<'store, K, V>: crate::copyable::sealed::BTree<'store, K, V> {}
+impl<K, V, const B: usize> BTreeStoreExt<K, V, B> for BTreeStore<K, V, B> {
+    #[track_caller]
+    #[inline]
+    unsafe fn tracing_gc<'a>(&self, b_trees: impl IntoIterator<Item = impl BTree<'a, K, V, B>>)
+    where
+        K: 'a,
+        V: 'a,
+    {
+        if let Err(err) = self.try_tracing_gc(b_trees) {
+            err.panic(&format!("BTreeStore::tracing_gc (store {:p})", self))
+        }
+    }
 
-impl<K, V> BTreeStoreExt<K, V> for BTreeStore<K, V> {
     #[inline]
-    unsafe fn tracing_gc<'a>(&self, b_trees: impl IntoIterator<Item = impl BTree<'a, K, V>>)
+    unsafe fn try_tracing_gc<'a>(
+        &self,
+        b_trees: impl IntoIterator<Item = impl BTree<'a, K, V, B>>,
+    ) -> Result<(), Error>
     where
         K: 'a,
         V: 'a,
     {
-        let nodes = b_trees
-            .into_iter()
-            .flat_map(|b_tree| {
-                b_tree.assert_store(self);
-                b_tree.nodes()
-            })
-            .collect::<HashSet<_>>();
+        let mut nodes = HashSet::new();
+        for b_tree in b_trees {
+            if !b_tree.is_from_store(self) {
+                return Err(Error::WrongStore);
+            }
+            nodes.extend(b_tree.node_ptrs().map(|(node, _height)| node));
+        }
         self.retain_shared(|node| nodes.contains(&NodePtr::from_ref(node)));
+        Ok(())
     }
 }
@@ -0,0 +1,82 @@
+use std::collections::TryReserveError;
+use std::fmt::{Display, Formatter};
+
+/// Error returned by the `try_` variants of operations which otherwise *panic*.
+///
+/// New variants may be added in minor versions, so matches need a wildcard arm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The range's start is after its end, or they are equal and both excluded
+    InvalidRange,
+    /// Tried to advance an iterator which has no more elements
+    IterationDone,
+    /// The b-tree's nodes weren't allocated in the given store
+    WrongStore,
+    /// A batch operation failed or its precondition was rejected, so the batch was rolled back
+    BatchRejected { index: usize },
+    /// The quantile isn't between 0 and 1
+    InvalidQuantile,
+    /// The step is 0
+    InvalidStep,
+    /// The key inserted at a cursor isn't between the keys before and after it, or keys which
+    /// should be sorted aren't strictly increasing
+    UnorderedKey,
+    /// The store's slab would be larger than `isize::MAX` bytes
+    CapacityOverflow,
+    /// The store's backend (e.g. a [FixedBackend](crate::FixedBackend)) may not have enough free
+    /// nodes for the operation
+    StoreFull,
+    /// The allocator failed to grow the store's slab
+    OutOfMemory(TryReserveError),
+    /// The arrow columns can't form a map (the message explains why). Only returned with the
+    /// `arrow` feature, but always present so that features stay additive
+    InvalidColumns(&'static str),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidRange => write!(f, "range start is greater than range end"),
+            Error::IterationDone => write!(f, "iteration is done"),
+            Error::WrongStore => write!(f, "b-tree is not from this store"),
+            Error::BatchRejected { index } => write!(f, "batch operation {} was rejected", index),
+            Error::InvalidQuantile => write!(f, "quantile is not between 0 and 1"),
+            Error::InvalidStep => write!(f, "step is 0"),
+            Error::UnorderedKey => write!(f, "key is out of order"),
+            Error::CapacityOverflow => write!(f, "capacity overflow"),
+            Error::StoreFull => write!(f, "store is full"),
+            Error::OutOfMemory(err) => write!(f, "{}", err),
+            Error::InvalidColumns(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    /// Panics with this error, prefixed by the operation which failed (e.g. `BTreeMap::range`).
+    #[cold]
+    #[track_caller]
+    pub(crate) fn panic(self, op: &str) -> ! {
+        panic!("{}: {}", op, self)
+    }
+}
+
+/// Converts the `try_` variant's result into the panicking variant's, reporting the panic at
+/// the caller's location.
+pub(crate) trait OrPanic<T> {
+    #[track_caller]
+    fn or_panic(self, op: &str) -> T;
+}
+
+impl<T> OrPanic<T> for Result<T, Error> {
+    #[inline]
+    #[track_caller]
+    fn or_panic(self, op: &str) -> T {
+        match self {
+            Ok(value) => value,
+            Err(err) => err.panic(op),
+        }
+    }
+}
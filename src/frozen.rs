@@ -0,0 +1,308 @@
+use crate::BTreeMap;
+#[cfg(feature = "set")]
+use crate::BTreeSet;
+use std::borrow::Borrow;
+use std::fmt::{Debug, Formatter};
+use std::ops::Deref;
+
+pub use export::{FrozenExport, FrozenExportHeader};
+
+mod export;
+
+/// A b-tree map which can no longer be modified, so it can be read from many threads at once.
+///
+/// [BTreeMap] isn't [Sync], because trees in the same [BTreeStore](crate::BTreeStore) allocate
+/// and deallocate nodes through it, which isn't thread-safe. But reading a tree never touches the
+/// store, so a frozen map (which only exposes reads, through [Deref]) is [Sync] if its keys and
+/// values are. The store can even keep being used by other trees on the owning thread.
+///
+/// It isn't [Send], because dropping it deallocates its nodes in the store. Share it with
+/// [std::thread::scope] instead.
+///
+/// # Examples
+///
+/// ```
+/// use btree_plus_store::{BTreeMap, BTreeStore, FrozenMap};
+/// let store = BTreeStore::new();
+/// let mut map = BTreeMap::new_in(&store);
+/// for i in 0..1000 {
+///     map.insert(i, i * 2);
+/// }
+/// let map = FrozenMap::new(map);
+/// std::thread::scope(|scope| {
+///     for t in 0..4 {
+///         let map = &map;
+///         scope.spawn(move || assert_eq!(map.get(&(t * 100)), Some(&(t * 200))));
+///     }
+/// });
+/// ```
+///
+/// With [FrozenLayout::Eytzinger], point lookups (`get`, `get_key_value`, `contains_key`) search
+/// a copy of the keys in [Eytzinger](https://algorithmica.org/en/eytzinger) order instead of the
+/// tree:
+///
+/// ```
+/// use btree_plus_store::{BTreeMap, BTreeStore, FrozenLayout, FrozenMap};
+/// let store = BTreeStore::new();
+/// let mut map = BTreeMap::new_in(&store);
+/// map.extend((0..1000).map(|i| (i * 3, i)));
+/// let map = FrozenMap::with_layout(map, FrozenLayout::Eytzinger);
+/// assert_eq!(map.get(&300), Some(&100));
+/// assert_eq!(map.get(&301), None);
+/// ```
+pub struct FrozenMap<'store, K, V, const B: usize = 8> {
+    map: BTreeMap<'store, K, V, B>,
+    eytzinger: Option<Eytzinger<K, V>>,
+}
+
+/// How a [FrozenMap] lays out its keys for point lookups.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FrozenLayout {
+    /// Search the tree, like [BTreeMap]
+    #[default]
+    Tree,
+    /// Also copy the keys into one array in Eytzinger (breadth-first) order, and search that.
+    ///
+    /// The search is branchless and its next few probes are adjacent in memory, so it's faster
+    /// than searching nodes for small, cheaply-compared keys like integers. It costs a clone of
+    /// every key and a pointer per value.
+    Eytzinger,
+}
+
+/// Keys in Eytzinger order: the children of the key at (1-based) position `k` are at `2k` and
+/// `2k + 1`, so the array is a complete binary search tree stored breadth-first.
+struct Eytzinger<K, V> {
+    keys: Box<[K]>,
+    /// Value of each key, pointing into the tree's nodes
+    vals: Box<[*const V]>,
+}
+
+/// A b-tree set which can no longer be modified, so it can be read from many threads at once.
+///
+/// See [FrozenMap].
+#[cfg(feature = "set")]
+pub struct FrozenSet<'store, T, const B: usize = 8>(BTreeSet<'store, T, B>);
+
+// SAFETY: the only access to the tree is through `&BTreeMap`, whose methods only read nodes and
+// never allocate or deallocate in the store. Nodes of other trees are disjoint from this tree's.
+// The Eytzinger layout only has owned keys and pointers to the tree's values.
+unsafe impl<'store, K: Sync, V: Sync, const B: usize> Sync for FrozenMap<'store, K, V, B> {}
+
+// SAFETY: same as `FrozenMap`
+#[cfg(feature = "set")]
+unsafe impl<'store, T: Sync, const B: usize> Sync for FrozenSet<'store, T, B> {}
+
+impl<'store, K, V, const B: usize> FrozenMap<'store, K, V, B> {
+    /// Freezes the map
+    #[inline]
+    pub fn new(map: BTreeMap<'store, K, V, B>) -> Self {
+        Self {
+            map,
+            eytzinger: None,
+        }
+    }
+
+    /// Freezes the map, laying out its keys for lookups in `layout`
+    #[inline]
+    pub fn with_layout(map: BTreeMap<'store, K, V, B>, layout: FrozenLayout) -> Self
+    where
+        K: Clone,
+    {
+        let eytzinger = match layout {
+            FrozenLayout::Tree => None,
+            FrozenLayout::Eytzinger => Some(Eytzinger::new(&map)),
+        };
+        Self { map, eytzinger }
+    }
+
+    /// The layout of keys for lookups
+    #[inline]
+    pub fn layout(&self) -> FrozenLayout {
+        match self.eytzinger {
+            None => FrozenLayout::Tree,
+            Some(_) => FrozenLayout::Eytzinger,
+        }
+    }
+
+    /// Unfreezes the map, so it can be modified again (but no longer shared across threads)
+    #[inline]
+    pub fn into_inner(self) -> BTreeMap<'store, K, V, B> {
+        self.map
+    }
+
+    /// Copies the entries into a buffer with a C-compatible layout, for readers in other
+    /// languages. See [FrozenExport].
+    #[inline]
+    pub fn export(&self) -> FrozenExport<K, V>
+    where
+        K: Copy,
+        V: Copy,
+    {
+        FrozenExport::new(&self.map)
+    }
+
+    /// Whether the map contains the key. Uses the map's [FrozenLayout].
+    #[inline]
+    pub fn contains_key<Q: Ord + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.get_key_value(key).is_some()
+    }
+
+    /// Returns the value corresponding to the key. Uses the map's [FrozenLayout].
+    #[inline]
+    pub fn get<Q: Ord + ?Sized>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        self.get_key_value(key).map(|(_, v)| v)
+    }
+
+    /// Returns the key-value pair corresponding to the key. Uses the map's [FrozenLayout].
+    ///
+    /// With [FrozenLayout::Eytzinger], the returned key is the layout's copy.
+    #[inline]
+    pub fn get_key_value<Q: Ord + ?Sized>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+    {
+        match &self.eytzinger {
+            None => self.map.get_key_value(key),
+            Some(eytzinger) => eytzinger.get_key_value(key),
+        }
+    }
+}
+
+#[cfg(feature = "set")]
+impl<'store, T, const B: usize> FrozenSet<'store, T, B> {
+    /// Freezes the set
+    #[inline]
+    pub fn new(set: BTreeSet<'store, T, B>) -> Self {
+        Self(set)
+    }
+
+    /// Unfreezes the set, so it can be modified again (but no longer shared across threads)
+    #[inline]
+    pub fn into_inner(self) -> BTreeSet<'store, T, B> {
+        self.0
+    }
+
+    /// Copies the elements into a buffer with a C-compatible layout, for readers in other
+    /// languages. The values array is empty (`value_size` is 0). See [FrozenExport].
+    #[inline]
+    pub fn export(&self) -> FrozenExport<T, ()>
+    where
+        T: Copy,
+    {
+        FrozenExport::new(&self.0 .0)
+    }
+}
+
+impl<'store, K, V, const B: usize> From<BTreeMap<'store, K, V, B>> for FrozenMap<'store, K, V, B> {
+    #[inline]
+    fn from(map: BTreeMap<'store, K, V, B>) -> Self {
+        Self::new(map)
+    }
+}
+
+#[cfg(feature = "set")]
+impl<'store, T, const B: usize> From<BTreeSet<'store, T, B>> for FrozenSet<'store, T, B> {
+    #[inline]
+    fn from(set: BTreeSet<'store, T, B>) -> Self {
+        Self(set)
+    }
+}
+
+impl<'store, K, V, const B: usize> Deref for FrozenMap<'store, K, V, B> {
+    type Target = BTreeMap<'store, K, V, B>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.map
+    }
+}
+
+#[cfg(feature = "set")]
+impl<'store, T, const B: usize> Deref for FrozenSet<'store, T, B> {
+    type Target = BTreeSet<'store, T, B>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'store, K: Debug, V: Debug, const B: usize> Debug for FrozenMap<'store, K, V, B> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.map.fmt(f)
+    }
+}
+
+#[cfg(feature = "set")]
+impl<'store, T: Debug, const B: usize> Debug for FrozenSet<'store, T, B> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<K: Clone, V> Eytzinger<K, V> {
+    fn new<const B: usize>(map: &BTreeMap<'_, K, V, B>) -> Self {
+        let entries = map.iter().collect::<Vec<_>>();
+        let mut order = vec![0; entries.len()];
+        Self::in_order(1, &mut order, &mut 0);
+        let mut keys = Vec::with_capacity(entries.len());
+        let mut vals = Vec::with_capacity(entries.len());
+        // `order[k - 1]` is the sorted index of the entry at position `k`
+        for sorted_index in order {
+            let (key, val) = entries[sorted_index];
+            keys.push(key.clone());
+            vals.push(val as *const V);
+        }
+        Self {
+            keys: keys.into_boxed_slice(),
+            vals: vals.into_boxed_slice(),
+        }
+    }
+
+    /// Visits the subtree at position `k` in order, assigning each position the next sorted
+    /// index. Recurses at most `log2(len)` deep.
+    fn in_order(k: usize, order: &mut [usize], next: &mut usize) {
+        if k > order.len() {
+            return;
+        }
+        Self::in_order(2 * k, order, next);
+        order[k - 1] = *next;
+        *next += 1;
+        Self::in_order(2 * k + 1, order, next);
+    }
+}
+
+impl<K, V> Eytzinger<K, V> {
+    #[inline]
+    fn get_key_value<Q: Ord + ?Sized>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+    {
+        let len = self.keys.len();
+        // Descend to past a leaf, going right when the key at `k` is less than `key`...
+        let mut k = 1;
+        while k <= len {
+            k = 2 * k + (self.keys[k - 1].borrow() < key) as usize;
+        }
+        // ...then undo the trailing right turns and the last left turn, which lands on the first
+        // key not less than `key` (or 0 if there isn't one)
+        k >>= k.trailing_ones() + 1;
+        if k == 0 {
+            return None;
+        }
+        let found = &self.keys[k - 1];
+        if found.borrow() != key {
+            return None;
+        }
+        // SAFETY: the frozen map owns the tree and never modifies it, so values stay put
+        Some((found, unsafe { &*self.vals[k - 1] }))
+    }
+}
@@ -0,0 +1,239 @@
+use crate::BTreeMap;
+use std::alloc::{alloc_zeroed, dealloc, handle_alloc_error, Layout};
+use std::borrow::Borrow;
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+use std::mem::{align_of, size_of};
+use std::ptr::NonNull;
+use std::slice;
+
+/// A frozen map copied into one contiguous buffer with a documented, C-compatible layout, so code
+/// in other languages (in the same process, or after copying the buffer) can look up keys.
+///
+/// # Layout
+///
+/// The buffer starts with a [FrozenExportHeader], aligned to the largest of its, `K`'s and `V`'s
+/// alignments. At `keys_offset` bytes from the start is an array of `len` keys in ascending order,
+/// and at `values_offset` is an array of `len` values, where `values[i]` is the value of
+/// `keys[i]`. Both arrays are aligned for their element type, and every offset and size is in
+/// bytes. In C:
+///
+/// ```c
+/// struct btree_frozen_header {
+///     uint8_t magic[8];       /* "BTPSFRZ\0" */
+///     uint32_t version;       /* 1 */
+///     uint32_t header_size;   /* sizeof(struct btree_frozen_header) */
+///     uint64_t len;
+///     uint64_t key_size;      /* stride of the keys array */
+///     uint64_t key_align;
+///     uint64_t value_size;    /* stride of the values array (0 for sets) */
+///     uint64_t value_align;
+///     uint64_t keys_offset;
+///     uint64_t values_offset;
+///     uint64_t total_size;
+/// };
+///
+/// const int64_t *btree_frozen_get(const struct btree_frozen_header *h, int64_t key) {
+///     const int64_t *keys = (const int64_t *)((const char *)h + h->keys_offset);
+///     const int64_t *values = (const int64_t *)((const char *)h + h->values_offset);
+///     uint64_t lo = 0, hi = h->len;
+///     while (lo < hi) {
+///         uint64_t mid = lo + (hi - lo) / 2;
+///         if (keys[mid] < key) lo = mid + 1; else hi = mid;
+///     }
+///     return lo < h->len && keys[lo] == key ? &values[lo] : NULL;
+/// }
+/// ```
+///
+/// Readers should compare keys the same way `K`'s [Ord] does. Keys and values are copied
+/// bitwise, so for the buffer to be meaningful outside of Rust they should be `#[repr(C)]` (or
+/// primitives), and to be meaningful outside of this process they shouldn't contain pointers.
+///
+/// # Examples
+///
+/// ```
+/// use btree_plus_store::{BTreeMap, BTreeStore, FrozenMap};
+/// let store = BTreeStore::new();
+/// let mut map = BTreeMap::new_in(&store);
+/// map.extend((0..100i64).map(|i| (i * 2, i)));
+/// let export = FrozenMap::new(map).export();
+/// assert_eq!(export.header().len, 100);
+/// assert_eq!(export.get(&42), Some(&21));
+/// // Pass `export.as_ptr()` and `export.size()` to the reader
+/// ```
+pub struct FrozenExport<K, V> {
+    ptr: NonNull<u8>,
+    layout: Layout,
+    _p: PhantomData<(K, V)>,
+}
+
+/// The header at the start of a [FrozenExport]'s buffer. See [FrozenExport] for the layout.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FrozenExportHeader {
+    /// [FrozenExportHeader::MAGIC]
+    pub magic: [u8; 8],
+    /// [FrozenExportHeader::VERSION]
+    pub version: u32,
+    /// Size of this header, so readers can skip fields added in later versions
+    pub header_size: u32,
+    /// Number of entries
+    pub len: u64,
+    /// Size of (and stride between) each key
+    pub key_size: u64,
+    /// Alignment of each key
+    pub key_align: u64,
+    /// Size of (and stride between) each value
+    pub value_size: u64,
+    /// Alignment of each value
+    pub value_align: u64,
+    /// Offset of the keys array from the start of the buffer
+    pub keys_offset: u64,
+    /// Offset of the values array from the start of the buffer
+    pub values_offset: u64,
+    /// Size of the whole buffer
+    pub total_size: u64,
+}
+
+impl FrozenExportHeader {
+    /// Identifies a buffer as a frozen export
+    pub const MAGIC: [u8; 8] = *b"BTPSFRZ\0";
+    /// Version of the layout, incremented on incompatible changes
+    pub const VERSION: u32 = 1;
+}
+
+// SAFETY: the buffer only contains owned copies of keys and values
+unsafe impl<K: Send, V: Send> Send for FrozenExport<K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for FrozenExport<K, V> {}
+
+impl<K: Copy, V: Copy> FrozenExport<K, V> {
+    pub(super) fn new<const B: usize>(map: &BTreeMap<'_, K, V, B>) -> Self {
+        let len = map.len();
+        let keys_layout = Layout::array::<K>(len).expect("export too large");
+        let values_layout = Layout::array::<V>(len).expect("export too large");
+        let (layout, keys_offset) = Layout::new::<FrozenExportHeader>()
+            .extend(keys_layout)
+            .expect("export too large");
+        let (layout, values_offset) = layout.extend(values_layout).expect("export too large");
+        let layout = layout.pad_to_align();
+
+        // Zeroed so that padding between the header and arrays is initialized
+        let ptr = NonNull::new(unsafe { alloc_zeroed(layout) })
+            .unwrap_or_else(|| handle_alloc_error(layout));
+        let header = FrozenExportHeader {
+            magic: FrozenExportHeader::MAGIC,
+            version: FrozenExportHeader::VERSION,
+            header_size: size_of::<FrozenExportHeader>() as u32,
+            len: len as u64,
+            key_size: size_of::<K>() as u64,
+            key_align: align_of::<K>() as u64,
+            value_size: size_of::<V>() as u64,
+            value_align: align_of::<V>() as u64,
+            keys_offset: keys_offset as u64,
+            values_offset: values_offset as u64,
+            total_size: layout.size() as u64,
+        };
+        // SAFETY: the layout has room for the header and both arrays at their offsets, each
+        // aligned, and the tree iterates exactly `len` entries
+        unsafe {
+            ptr.as_ptr().cast::<FrozenExportHeader>().write(header);
+            let keys = ptr.as_ptr().add(keys_offset).cast::<K>();
+            let values = ptr.as_ptr().add(values_offset).cast::<V>();
+            for (i, (key, value)) in map.iter().enumerate() {
+                keys.add(i).write(*key);
+                values.add(i).write(*value);
+            }
+        }
+        Self {
+            ptr,
+            layout,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<K, V> FrozenExport<K, V> {
+    /// The buffer's header
+    #[inline]
+    pub fn header(&self) -> &FrozenExportHeader {
+        // SAFETY: the buffer starts with an initialized, aligned header
+        unsafe { &*self.ptr.as_ptr().cast::<FrozenExportHeader>() }
+    }
+
+    /// Pointer to the start of the buffer, to pass to the reader. It's valid for [Self::size]
+    /// bytes as long as `self` is alive.
+    #[inline]
+    pub fn as_ptr(&self) -> *const u8 {
+        self.ptr.as_ptr()
+    }
+
+    /// Size of the buffer in bytes
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.layout.size()
+    }
+
+    /// Number of entries
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.header().len as usize
+    }
+
+    /// Whether there are no entries
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The keys array, in ascending order
+    #[inline]
+    pub fn keys(&self) -> &[K] {
+        let header = self.header();
+        // SAFETY: the buffer has `len` initialized keys at `keys_offset`
+        unsafe {
+            slice::from_raw_parts(
+                self.ptr.as_ptr().add(header.keys_offset as usize).cast(),
+                header.len as usize,
+            )
+        }
+    }
+
+    /// The values array, where each value corresponds to the key at the same index
+    #[inline]
+    pub fn values(&self) -> &[V] {
+        let header = self.header();
+        // SAFETY: the buffer has `len` initialized values at `values_offset`
+        unsafe {
+            slice::from_raw_parts(
+                self.ptr.as_ptr().add(header.values_offset as usize).cast(),
+                header.len as usize,
+            )
+        }
+    }
+
+    /// Returns the value corresponding to the key, by binary search (like a C reader would)
+    #[inline]
+    pub fn get<Q: Ord + ?Sized>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        let index = self.keys().binary_search_by(|k| k.borrow().cmp(key)).ok()?;
+        Some(&self.values()[index])
+    }
+}
+
+impl<K, V> Drop for FrozenExport<K, V> {
+    #[inline]
+    fn drop(&mut self) {
+        // Keys and values are `Copy`, so they don't need to be dropped
+        unsafe { dealloc(self.ptr.as_ptr(), self.layout) }
+    }
+}
+
+impl<K: Debug, V: Debug> Debug for FrozenExport<K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.keys().iter().zip(self.values()))
+            .finish()
+    }
+}
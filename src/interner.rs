@@ -0,0 +1,202 @@
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::ptr;
+
+/// Arena which stores each distinct key once, so that b-trees (e.g. in the same
+/// [BTreeStore](crate::BTreeStore)) with many overlapping keys can share them.
+///
+/// Trees use [Interned] keys, which are only a reference into the interner; comparisons and lookups
+/// still use the underlying key, so `map.get("foo")` works on a `BTreeMap<Interned<str>, V>`.
+///
+/// Keys are never freed until the interner is dropped or [Interner::clear]ed.
+///
+/// # Examples
+///
+/// ```
+/// use btree_plus_store::{BTreeMap, BTreeStore, Interner};
+/// let interner = Interner::<str>::new();
+/// let store = BTreeStore::new();
+/// let mut a = BTreeMap::new_in(&store);
+/// let mut b = BTreeMap::new_in(&store);
+/// a.insert(interner.intern("shared key"), 1);
+/// b.insert(interner.intern("shared key"), 2);
+/// assert_eq!(interner.len(), 1);
+/// assert_eq!(a.get("shared key"), Some(&1));
+/// ```
+pub struct Interner<T: ?Sized> {
+    keys: RefCell<HashSet<Box<T>>>,
+}
+
+/// A key stored in an [Interner]. This is a reference, so it's small and [Copy], but it compares,
+/// hashes, and formats like the underlying key.
+pub struct Interned<'i, T: ?Sized>(&'i T);
+
+impl<T: ?Sized> Interner<T> {
+    /// Creates an empty interner.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            keys: RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// Returns the number of distinct keys.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys.borrow().len()
+    }
+
+    /// Returns `true` if no keys have been interned.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys.borrow().is_empty()
+    }
+
+    /// Frees all keys. This requires `&mut`, so there must be no more [Interned] keys (or trees with
+    /// them) alive.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.keys.get_mut().clear()
+    }
+}
+
+impl<T: ?Sized + Eq + Hash> Interner<T> {
+    /// Returns the interned equivalent of `key`, storing a copy if there isn't one yet.
+    #[inline]
+    pub fn intern(&self, key: &T) -> Interned<'_, T>
+    where
+        Box<T>: for<'a> From<&'a T>,
+    {
+        if let Some(interned) = self.get(key) {
+            return interned;
+        }
+        self.insert(Box::from(key))
+    }
+
+    /// Returns the interned equivalent of `key`, storing it if there isn't one yet.
+    #[inline]
+    pub fn intern_boxed(&self, key: Box<T>) -> Interned<'_, T> {
+        if let Some(interned) = self.get(&*key) {
+            return interned;
+        }
+        self.insert(key)
+    }
+
+    /// Returns the interned equivalent of `key` if there is one, without storing it.
+    #[inline]
+    pub fn get(&self, key: &T) -> Option<Interned<'_, T>> {
+        let keys = self.keys.borrow();
+        let key = keys.get(key)?;
+        // SAFETY: The key is boxed so its address is stable, and it's only freed by `clear`, which
+        // takes `&mut self` so there can't be any outstanding `Interned`s.
+        Some(Interned(unsafe { &*(&**key as *const T) }))
+    }
+
+    #[inline]
+    fn insert(&self, key: Box<T>) -> Interned<'_, T> {
+        // SAFETY: See `get`
+        let interned = Interned(unsafe { &*(&*key as *const T) });
+        self.keys.borrow_mut().insert(key);
+        interned
+    }
+}
+
+impl<T: ?Sized> Default for Interner<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: ?Sized + Debug> Debug for Interner<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.keys.borrow().iter()).finish()
+    }
+}
+
+impl<'i, T: ?Sized> Interned<'i, T> {
+    /// Returns the underlying key, which lives as long as the interner.
+    #[inline]
+    pub fn get(self) -> &'i T {
+        self.0
+    }
+}
+
+impl<'i, T: ?Sized> Clone for Interned<'i, T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'i, T: ?Sized> Copy for Interned<'i, T> {}
+
+impl<'i, T: ?Sized> Deref for Interned<'i, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl<'i, T: ?Sized> Borrow<T> for Interned<'i, T> {
+    #[inline]
+    fn borrow(&self) -> &T {
+        self.0
+    }
+}
+
+impl<'i, T: ?Sized + PartialEq> PartialEq for Interned<'i, T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        // Keys from the same interner are equal iff they're the same key
+        ptr::eq(self.0, other.0) || self.0 == other.0
+    }
+}
+
+impl<'i, T: ?Sized + Eq> Eq for Interned<'i, T> {}
+
+impl<'i, T: ?Sized + PartialOrd> PartialOrd for Interned<'i, T> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.0.partial_cmp(other.0)
+    }
+}
+
+impl<'i, T: ?Sized + Ord> Ord for Interned<'i, T> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        if ptr::eq(self.0, other.0) {
+            Ordering::Equal
+        } else {
+            self.0.cmp(other.0)
+        }
+    }
+}
+
+impl<'i, T: ?Sized + Hash> Hash for Interned<'i, T> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl<'i, T: ?Sized + Debug> Debug for Interned<'i, T> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<'i, T: ?Sized + Display> Display for Interned<'i, T> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
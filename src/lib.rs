@@ -1,18 +1,60 @@
 #![doc = include_str!("../README.md")]
+#![cfg_attr(feature = "nightly", allow(incomplete_features))]
+#![cfg_attr(
+    feature = "nightly",
+    feature(allocator_api, specialization, trusted_len)
+)]
 
+#[cfg(feature = "icu")]
+pub use collation::Collated;
+pub use collect::{CollectIn, FromIteratorIn};
+pub use error::Error;
+#[cfg(feature = "set")]
+pub use frozen::FrozenSet;
+pub use frozen::{FrozenExport, FrozenExportHeader, FrozenLayout, FrozenMap};
+pub use interner::{Interned, Interner};
 pub use map::BTreeMap;
+#[cfg(feature = "set")]
+pub use owned::OwnedBTreeSet;
+pub use owned::{OwnedBTreeMap, StoreRef};
+pub use prefix::{prefix_range, Prefix, PrefixRange};
+#[cfg(feature = "set")]
 pub use set::BTreeSet;
-pub use store::BTreeStore;
+pub use small::SmallBTreeMap;
+#[cfg(feature = "set")]
+pub use small::SmallBTreeSet;
+#[cfg(feature = "nightly")]
+pub use store::AllocatorBackend;
+pub use store::{
+    BTreeStore, BTreeStoreBuilder, FixedBackend, GrowthPolicy, LeakReport, RebalancePolicy,
+    SearchStrategy, SlabBackend, StoreAllocObserver, StoreStats, UnderflowStrategy,
+};
+pub use total_ord::{TotalOrd, TotalOrdFloat};
+pub use tree::BTree;
 
+#[cfg(feature = "icu")]
+mod collation;
+mod collect;
 /// Immutable map and set which implement [Copy] but don't drop or deallocate its contents; instead,
 /// the store has a new helper which performs a special variant of
 /// [tracing garbage collection](https://en.wikipedia.org/wiki/Tracing_garbage_collection)
 #[cfg(feature = "copyable")]
 pub mod copyable;
 mod cursor;
+mod error;
+mod frozen;
+mod interner;
+mod macros;
 pub mod map;
 mod node;
+mod node_iter;
+mod owned;
+mod prefix;
+#[cfg(feature = "set")]
 pub mod set;
+pub mod small;
 mod store;
+mod total_ord;
+mod tree;
 /// Misc utility functions
 mod utils;
@@ -0,0 +1,48 @@
+/// Creates a [BTreeMap](crate::BTreeMap) in a store from `key => value` pairs.
+///
+/// The store is borrowed, so it can be a [BTreeStore](crate::BTreeStore) or a reference to one.
+/// The entries are built with [BTreeMap::from_iter_in](crate::BTreeMap::from_iter_in), so they're
+/// bulk-loaded without searching, and sorting them is linear if they're already in order. If a key
+/// is repeated, the last entry is kept.
+///
+/// # Examples
+///
+/// ```
+/// use btree_plus_store::{btreemap_in, BTreeStore};
+/// let store = BTreeStore::new();
+/// let map = btreemap_in!(store; "a" => 1, "b" => 2);
+/// assert_eq!(map.get("b"), Some(&2));
+/// let empty = btreemap_in!(store);
+/// assert_ne!(map, empty);
+/// ```
+#[macro_export]
+macro_rules! btreemap_in {
+    ($store:expr; $($key:expr => $val:expr),+ $(,)?) => {
+        $crate::BTreeMap::from_iter_in([$(($key, $val)),+], &$store)
+    };
+    ($store:expr $(;)?) => {
+        $crate::BTreeMap::new_in(&$store)
+    };
+}
+
+/// Creates a [BTreeSet](crate::BTreeSet) in a store from values. See
+/// [btreemap_in!](crate::btreemap_in).
+///
+/// # Examples
+///
+/// ```
+/// use btree_plus_store::{btreeset_in, BTreeStore};
+/// let store = BTreeStore::new();
+/// let set = btreeset_in!(store; 1, 2, 3);
+/// assert!(set.contains(&2));
+/// ```
+#[cfg(feature = "set")]
+#[macro_export]
+macro_rules! btreeset_in {
+    ($store:expr; $($value:expr),+ $(,)?) => {
+        $crate::BTreeSet::from_iter_in([$($value),+], &$store)
+    };
+    ($store:expr $(;)?) => {
+        $crate::BTreeSet::new_in(&$store)
+    };
+}
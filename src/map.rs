@@ -1,63 +1,212 @@
 use std::borrow::Borrow;
 use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::collections::Bound;
+use std::collections::{Bound, HashMap};
 use std::fmt::{Debug, Formatter};
-use std::hash::{Hash, Hasher};
-use std::iter::FusedIterator;
+use std::hash::{BuildHasher, Hash, Hasher};
+#[cfg(feature = "nightly")]
+use std::iter::TrustedLen;
+use std::iter::{FusedIterator, Rev};
 use std::marker::PhantomData;
-use std::mem::{forget, MaybeUninit};
-use std::ops::RangeBounds;
+use std::mem::{forget, replace, size_of, swap, ManuallyDrop};
+use std::ops::{Deref, RangeBounds};
 use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
 use std::ptr::{drop_in_place, NonNull};
 use std::thread::panicking;
 
-use crate::cursor::Cursor;
-use crate::node::{address_after, address_before, normalize_address, Node, NodePtr, M};
-use crate::utils::PtrEq;
-use crate::BTreeStore;
+use crate::cursor::LeafCursor;
+use crate::error::OrPanic;
+use crate::node::{
+    address_after, address_backward, address_before, address_forward, adjust_ancestor_sizes,
+    normalize_address, recompute_size, Node, NodePtr,
+};
+use crate::node_iter::NodeIter;
+use crate::prefix::{prefix_range, Prefix};
+use crate::utils::{maybe_uninit_array, IoFmtWriter, PtrEq};
+#[cfg(feature = "set")]
+use crate::BTreeSet;
+use crate::{BTreeStore, Error, UnderflowStrategy};
+
+#[cfg(feature = "arrow")]
+mod arrow;
+#[cfg(feature = "rayon")]
+mod par;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "rayon")]
+pub use par::{ParIter, ParIterMut};
+#[cfg(feature = "serde")]
+pub use serialize::{MapSeed, SerdeForm, SerializeAs};
 
 /// A b-tree map.
 ///
 /// See [std::collections::BTreeMap] for more info.
-// TODO: impl Clone
-pub struct BTreeMap<'store, K, V> {
-    store: &'store BTreeStore<K, V>,
-    root: Option<NodePtr<K, V>>,
+pub struct BTreeMap<'store, K, V, const B: usize = 8> {
+    store: &'store BTreeStore<K, V, B>,
+    root: Option<NodePtr<K, V, B>>,
     length: usize,
     height: usize,
+    /// Cached first leaf, so that accessing the first entry doesn't descend the tree
+    first: Option<NodePtr<K, V, B>>,
+    /// Cached last leaf, so that accessing the last entry doesn't descend the tree
+    last: Option<NodePtr<K, V, B>>,
+    /// Leaf of the last mutable access. Searches for nearby keys start from here instead of the
+    /// root, walking up only as far as needed.
+    finger: Option<NodePtr<K, V, B>>,
+    /// [BTreeStore::id] of the store the map was created in
+    #[cfg(feature = "generations")]
+    store_id: u64,
     /// For dropck; the `Box` avoids making the `Unpin` impl more strict than before
     _p: PhantomData<Box<(K, V)>>,
 }
 
+// SAFETY: under `sync-store`, the store can be shared between threads, and the map's nodes are
+// only accessed through the map. Moving the map moves its entries.
+#[cfg(feature = "sync-store")]
+unsafe impl<'store, K: Send, V: Send, const B: usize> Send for BTreeMap<'store, K, V, B> {}
+
+// SAFETY: `&BTreeMap` methods only read the map's nodes, besides allocating (e.g. `reserve`) in
+// the store, which is `Sync` under `sync-store`.
+#[cfg(feature = "sync-store")]
+unsafe impl<'store, K: Send + Sync, V: Send + Sync, const B: usize> Sync
+    for BTreeMap<'store, K, V, B>
+{
+}
+
 /// The result of looking up an address to retrieve or insert an entry
-enum Find<K, V> {
+enum Find<K, V, const B: usize> {
     /// The tree is empty
     NoRoot,
     /// The entry would be before this address
-    Before { node: NodePtr<K, V>, idx: u16 },
+    Before { node: NodePtr<K, V, B>, idx: u16 },
     /// The entry is at this address
-    At { node: NodePtr<K, V>, idx: u16 },
+    At { node: NodePtr<K, V, B>, idx: u16 },
 }
 
 /// Pointer and index to the start and end entry for a range within a tree.
 ///
 /// These bounds are always inclusive. Use `Option<NodeBounds<'a, K, V>>` to represent a
 /// potentially-empty range.
-struct NodeBounds<K, V> {
+struct NodeBounds<K, V, const B: usize> {
     /// Start node (inclusive)
-    start_node: NodePtr<K, V>,
+    start_node: NodePtr<K, V, B>,
     /// End node (inclusive)
-    end_node: NodePtr<K, V>,
+    end_node: NodePtr<K, V, B>,
     /// Index in start node (inclusive)
     start_index: u16,
     /// Index in end node (inclusive)
     end_index: u16,
 }
 
-impl<'store, K, V> BTreeMap<'store, K, V> {
+/// An operation in [BTreeMap::apply_batch].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchOp<K, V> {
+    /// Insert the entry, replacing the value if the key is present
+    Insert(K, V),
+    /// Replace the value at the key, failing if the key isn't present
+    Update(K, V),
+    /// Remove the key, failing if the key isn't present
+    Remove(K),
+}
+
+/// A difference between two maps, from [BTreeMap::diff_with].
+#[derive(Debug, PartialEq, Eq)]
+pub enum DiffItem<'a, K, V> {
+    /// The key is only in the other map
+    Added(&'a K, &'a V),
+    /// The key is only in this map
+    Removed(&'a K, &'a V),
+    /// The key is in both maps with different values: this map's, then the other map's
+    Changed(&'a K, &'a V, &'a V),
+}
+
+/// Reverts an applied [BatchOp] or [Checkpoint] edit.
+enum Undo<K, V> {
+    /// The key was inserted, so remove it
+    Remove(K),
+    /// The key was replaced or removed, so put back the old value
+    Restore(K, V),
+}
+
+/// Speculative edits to a map, which are rolled back when this is dropped unless they're
+/// [committed](Checkpoint::commit). From [BTreeMap::checkpoint].
+///
+/// Derefs to the map, to read it with the edits applied.
+pub struct Checkpoint<'a, 'store, K: Clone + Ord, V, const B: usize = 8> {
+    map: &'a mut BTreeMap<'store, K, V, B>,
+    undo_log: Vec<Undo<K, V>>,
+}
+
+impl<'a, 'store, K: Clone + Ord, V, const B: usize> Checkpoint<'a, 'store, K, V, B> {
+    /// Inserts the entry, and returns the value it replaced. The undo log keeps a clone of the
+    /// replaced value to restore on rollback.
+    #[inline]
+    pub fn insert(&mut self, key: K, val: V) -> Option<V>
+    where
+        V: Clone,
+    {
+        match self.map.insert(key.clone(), val) {
+            None => {
+                self.undo_log.push(Undo::Remove(key));
+                None
+            }
+            Some(old_val) => {
+                self.undo_log.push(Undo::Restore(key, old_val.clone()));
+                Some(old_val)
+            }
+        }
+    }
+
+    /// Removes the key, and returns its value. The undo log keeps a clone of the value to restore
+    /// on rollback.
+    #[inline]
+    pub fn remove<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        V: Clone,
+    {
+        let (key, val) = self.map.remove_entry(key)?;
+        self.undo_log.push(Undo::Restore(key, val.clone()));
+        Some(val)
+    }
+
+    /// Keeps the edits.
+    #[inline]
+    pub fn commit(mut self) {
+        self.undo_log.clear()
+    }
+
+    /// Reverts the edits, like dropping the checkpoint.
+    #[inline]
+    pub fn rollback(self) {}
+}
+
+impl<'a, 'store, K: Clone + Ord, V, const B: usize> Deref for Checkpoint<'a, 'store, K, V, B> {
+    type Target = BTreeMap<'store, K, V, B>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &*self.map
+    }
+}
+
+impl<'a, 'store, K: Clone + Ord, V, const B: usize> Drop for Checkpoint<'a, 'store, K, V, B> {
+    #[inline]
+    fn drop(&mut self) {
+        if panicking() {
+            // The map may be inconsistent, so it's left as is (see `BTreeMap::drop`)
+            return;
+        }
+        self.map.rollback(std::mem::take(&mut self.undo_log));
+    }
+}
+
+impl<'store, K, V, const B: usize> BTreeMap<'store, K, V, B> {
     /// Creates an empty `BTreeMap`.
     ///
+    /// This doesn't allocate: the map's first node is allocated from the store on the first
+    /// insert, and an emptied map frees all of its nodes.
+    ///
     /// # Examples
     ///
     /// ```
@@ -66,16 +215,176 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
     /// let mut map = BTreeMap::new_in(&store);
     /// ```
     #[inline]
-    pub const fn new_in(store: &'store BTreeStore<K, V>) -> Self {
+    pub const fn new_in(store: &'store BTreeStore<K, V, B>) -> Self {
         Self {
             store,
             root: None,
             length: 0,
             height: 0,
+            first: None,
+            last: None,
+            finger: None,
+            #[cfg(feature = "generations")]
+            store_id: store.id(),
             _p: PhantomData,
         }
     }
 
+    /// Creates a map in `store` from the entries. If a key is repeated, the last entry is kept.
+    ///
+    /// The entries are sorted and then bulk-loaded, so the nodes are full. See also
+    /// [CollectIn::collect_in](crate::CollectIn::collect_in).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use btree_plus_store::{BTreeMap, BTreeStore};
+    /// let store = BTreeStore::new();
+    /// let map = BTreeMap::from_iter_in([(2, "b"), (1, "a"), (2, "c")], &store);
+    /// assert!(map.into_iter().eq([(1, "a"), (2, "c")]));
+    /// ```
+    pub fn from_iter_in(
+        entries: impl IntoIterator<Item = (K, V)>,
+        store: &'store BTreeStore<K, V, B>,
+    ) -> Self
+    where
+        K: Ord + Clone,
+    {
+        let mut entries = entries.into_iter().collect::<Vec<_>>();
+        // Stable, so the last entry of each repeated key stays last
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut entries = entries.into_iter().peekable();
+        let deduped = std::iter::from_fn(move || loop {
+            let entry = entries.next()?;
+            match entries.peek() {
+                Some((next_key, _)) if *next_key == entry.0 => {}
+                _ => return Some(entry),
+            }
+        });
+        Self::bulk_load_in(store, deduped)
+    }
+
+    /// Creates a map in `store` from an array of entries, like std's `From<[(K, V); N]>`. See
+    /// [BTreeMap::from_iter_in].
+    #[inline]
+    pub fn from_array_in<const N: usize>(
+        entries: [(K, V); N],
+        store: &'store BTreeStore<K, V, B>,
+    ) -> Self
+    where
+        K: Ord + Clone,
+    {
+        Self::from_iter_in(entries, store)
+    }
+
+    /// Creates a map in `store` from an array of entries whose keys are strictly increasing,
+    /// without sorting or searching.
+    ///
+    /// *Panics* if the keys aren't strictly increasing.
+    #[track_caller]
+    #[inline]
+    pub fn from_sorted_array_in<const N: usize>(
+        entries: [(K, V); N],
+        store: &'store BTreeStore<K, V, B>,
+    ) -> Self
+    where
+        K: Ord + Clone,
+    {
+        Self::try_from_sorted_array_in(entries, store).or_panic("BTreeMap::from_sorted_array_in")
+    }
+
+    /// Creates a map in `store` from an array of entries whose keys are strictly increasing,
+    /// without sorting or searching, or returns [Error::UnorderedKey] if they aren't.
+    pub fn try_from_sorted_array_in<const N: usize>(
+        entries: [(K, V); N],
+        store: &'store BTreeStore<K, V, B>,
+    ) -> Result<Self, Error>
+    where
+        K: Ord + Clone,
+    {
+        if !entries.windows(2).all(|pair| pair[0].0 < pair[1].0) {
+            return Err(Error::UnorderedKey);
+        }
+        Ok(Self::bulk_load_in(store, entries))
+    }
+
+    /// Creates a map in `store` from entries whose keys are strictly increasing, in O(n): nodes
+    /// are filled bottom-up without sorting or searching, so they're packed full.
+    ///
+    /// *Panics* if the keys aren't strictly increasing.
+    #[track_caller]
+    #[inline]
+    pub fn from_sorted_iter_in(
+        entries: impl IntoIterator<Item = (K, V)>,
+        store: &'store BTreeStore<K, V, B>,
+    ) -> Self
+    where
+        K: Ord + Clone,
+    {
+        Self::try_from_sorted_iter_in(entries, store).or_panic("BTreeMap::from_sorted_iter_in")
+    }
+
+    /// Creates a map in `store` from entries whose keys are strictly increasing, in O(n), or
+    /// returns [Error::UnorderedKey] if they aren't. The entries are consumed until the first
+    /// out-of-order key, and the nodes built from them are freed.
+    pub fn try_from_sorted_iter_in(
+        entries: impl IntoIterator<Item = (K, V)>,
+        store: &'store BTreeStore<K, V, B>,
+    ) -> Result<Self, Error>
+    where
+        K: Ord + Clone,
+    {
+        let mut entries = entries.into_iter();
+        // Each entry is held back until the next one is checked, so keys aren't cloned
+        let mut pending = entries.next();
+        let mut unordered = false;
+        let checked = std::iter::from_fn(|| {
+            let entry = pending.take()?;
+            pending = entries.next();
+            if matches!(&pending, Some((next_key, _)) if *next_key <= entry.0) {
+                unordered = true;
+                pending = None;
+                return None;
+            }
+            Some(entry)
+        });
+        let map = Self::bulk_load_in(store, checked);
+        if unordered {
+            return Err(Error::UnorderedKey);
+        }
+        Ok(map)
+    }
+
+    /// Creates a map in `store` from a [std::collections::BTreeMap]'s entries. They're already
+    /// sorted, so they're bulk-loaded without searching.
+    #[inline]
+    pub fn from_std_in(
+        map: std::collections::BTreeMap<K, V>,
+        store: &'store BTreeStore<K, V, B>,
+    ) -> Self
+    where
+        K: Clone,
+    {
+        Self::bulk_load_in(store, map)
+    }
+
+    /// Clones the entries into a [std::collections::BTreeMap].
+    #[inline]
+    pub fn to_std(&self) -> std::collections::BTreeMap<K, V>
+    where
+        K: Ord + Clone,
+        V: Clone,
+    {
+        self.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+
+    /// The store the map's nodes are allocated in
+    #[cfg(feature = "set")]
+    #[inline]
+    pub(crate) fn store(&self) -> &'store BTreeStore<K, V, B> {
+        self.store
+    }
+
     // region length
     /// Returns the number of elements in the map.
     #[inline]
@@ -88,6 +397,43 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
     pub fn is_empty(&self) -> bool {
         self.root.is_none()
     }
+
+    /// Reserves slots in the store for the nodes needed to insert `additional` more entries, so
+    /// inserting them doesn't grow the store's slab partway through. See [BTreeStore::reserve].
+    ///
+    /// The estimate assumes nodes are only half full (as after sequential inserts), so it's
+    /// usually more than enough.
+    #[inline]
+    pub fn reserve(&self, additional: usize) {
+        let needed = max_nodes::<B>(self.length + additional) - max_nodes::<B>(self.length);
+        self.store.reserve(needed);
+    }
+
+    /// Like [BTreeMap::reserve], but returns [Error::CapacityOverflow] instead of panicking if the
+    /// store's slab would be too large, or [Error::OutOfMemory] instead of aborting if the
+    /// allocator can't grow it. See [BTreeStore::try_reserve].
+    #[inline]
+    pub fn try_reserve(&self, additional: usize) -> Result<(), Error> {
+        let length = self
+            .length
+            .checked_add(additional)
+            .ok_or(Error::CapacityOverflow)?;
+        self.store
+            .try_reserve(max_nodes::<B>(length) - max_nodes::<B>(self.length))
+    }
+
+    /// Like [Clone::clone], but returns an error (before allocating) if the store's backend may
+    /// not have enough free nodes for the copy, or its slab would be too large or can't grow. See
+    /// [BTreeStore::try_reserve].
+    #[inline]
+    pub fn try_clone(&self) -> Result<Self, Error>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.store.try_reserve(max_nodes::<B>(self.length))?;
+        Ok(self.clone())
+    }
     // endregion
 
     // region retrieval
@@ -118,7 +464,7 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
     where
         K: Borrow<Q>,
     {
-        match self.find(key) {
+        match self.find_mut(key) {
             Find::At { mut node, idx } => unsafe { Some(node.as_mut().val_mut(idx)) },
             _ => None,
         }
@@ -160,12 +506,70 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
     where
         K: Borrow<Q>,
     {
-        match self.find(key) {
+        match self.find_mut(key) {
             Find::At { mut node, idx } => unsafe { Some(node.as_mut().key_val_mut(idx)) },
             _ => None,
         }
     }
 
+    /// Returns the values corresponding to each key, in order, with `None` for missing keys.
+    ///
+    /// Each search starts from the previous key's leaf and walks up only as far as needed, so when
+    /// the keys are sorted, adjacent keys share most of their descent (and keys in the same leaf
+    /// don't descend at all). Unsorted keys still get the right values, without the sharing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use btree_plus_store::{BTreeMap, BTreeStore};
+    /// let store = BTreeStore::new();
+    /// let map = BTreeMap::from_iter_in((0..100).map(|i| (i * 2, i)), &store);
+    /// assert!(map.get_batch(&[4, 5, 6, 300]).eq([Some(&2), None, Some(&3), None]));
+    /// ```
+    #[inline]
+    pub fn get_batch<'a, Q: Ord>(&'a self, sorted_keys: &'a [Q]) -> GetBatch<'a, K, V, Q, B>
+    where
+        K: Borrow<Q>,
+    {
+        GetBatch {
+            map: self,
+            keys: sorted_keys.iter(),
+            leaf: None,
+        }
+    }
+
+    /// Returns mutable references to the values corresponding to each key, or `None` if any key
+    /// is missing or two keys are equivalent (since they'd alias the same value).
+    ///
+    /// Checks every pair of keys, so it's meant for small `N`.
+    #[inline]
+    pub fn get_many_mut<Q: Ord + ?Sized, const N: usize>(
+        &mut self,
+        keys: [&Q; N],
+    ) -> Option<[&mut V; N]>
+    where
+        K: Borrow<Q>,
+    {
+        let addresses = keys.map(|key| match self.find_mut(key) {
+            Find::At { node, idx } => Some((node, idx)),
+            Find::NoRoot | Find::Before { .. } => None,
+        });
+        for (i, address) in addresses.iter().enumerate() {
+            let (node, idx) = (*address)?;
+            if addresses[..i]
+                .iter()
+                .any(|other| matches!(other, Some((n, j)) if n.ptr_eq(&node) && *j == idx))
+            {
+                return None;
+            }
+        }
+        // SAFETY: every address is of a distinct entry, so the references don't alias
+        Some(addresses.map(|address| {
+            let (mut node, idx) = address.unwrap();
+            unsafe { node.as_mut().val_mut(idx) }
+        }))
+    }
+
     /// Returns the first key and value
     #[inline]
     pub fn first_key_value(&self) -> Option<(&K, &V)> {
@@ -193,6 +597,136 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
         self.last_leaf()
             .map(|mut node| unsafe { node.as_mut().last_key_value_mut() })
     }
+
+    /// Returns the entry with the largest key less than `key`, which needn't be in the map.
+    #[inline]
+    pub fn next_before<Q: Ord + ?Sized>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+    {
+        self.lower_bound(Bound::Included(key)).peek_prev()
+    }
+
+    /// Returns the entry with the smallest key greater than `key`, which needn't be in the map.
+    #[inline]
+    pub fn next_after<Q: Ord + ?Sized>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+    {
+        self.upper_bound(Bound::Included(key)).peek_next()
+    }
+
+    /// Returns the first entry, to inspect, modify, or remove without searching again
+    #[inline]
+    pub fn first_entry(&mut self) -> Option<OccupiedEntry<'_, 'store, K, V, B>> {
+        self.first_leaf().map(|node| OccupiedEntry {
+            map: self,
+            node,
+            idx: 0,
+        })
+    }
+
+    /// Returns the last entry, to inspect, modify, or remove without searching again
+    #[inline]
+    pub fn last_entry(&mut self) -> Option<OccupiedEntry<'_, 'store, K, V, B>> {
+        self.last_leaf().map(|node| OccupiedEntry {
+            map: self,
+            node,
+            idx: unsafe { node.as_ref().len - 1 },
+        })
+    }
+
+    /// Whether both maps have the same keys, ignoring values (which may even be different types).
+    ///
+    /// Compares whole runs of keys at a time, and stops early at the first difference or once both
+    /// maps reach the same leaf (e.g. in copies of a [copyable](crate::copyable) map), since the
+    /// keys after are the same.
+    #[inline]
+    pub fn keys_eq<V2>(&self, other: &BTreeMap<K, V2, B>) -> bool
+    where
+        K: PartialEq,
+    {
+        // With equal lengths, neither map runs out of keys first
+        self.length == other.length
+            && self.cmp_key_runs(other, |a, b| match a == b {
+                true => Ordering::Equal,
+                false => Ordering::Less,
+            }) == Ordering::Equal
+    }
+
+    /// Compares the maps' key sequences lexicographically, ignoring values (which may even be
+    /// different types). See [Self::keys_eq].
+    #[inline]
+    pub fn keys_cmp<V2>(&self, other: &BTreeMap<K, V2, B>) -> Ordering
+    where
+        K: Ord,
+    {
+        self.cmp_key_runs(other, |a, b| a.cmp(b))
+    }
+
+    /// Whether the map's entries equal the iterator's, in order. The iterator can yield entries
+    /// by value or reference, e.g. from a [Vec] or [std::collections::BTreeMap], so the other side
+    /// doesn't need to be collected into a map.
+    pub fn eq_iter<Q: Borrow<K>, W: Borrow<V>>(
+        &self,
+        other: impl IntoIterator<Item = (Q, W)>,
+    ) -> bool
+    where
+        K: PartialEq,
+        V: PartialEq,
+    {
+        let mut other = other.into_iter();
+        self.iter().all(|(k, v)| {
+            other
+                .next()
+                .is_some_and(|(q, w)| k == q.borrow() && v == w.borrow())
+        }) && other.next().is_none()
+    }
+
+    /// Compares the map's entries with the iterator's lexicographically, like [Ord] does between
+    /// maps. See [BTreeMap::eq_iter].
+    pub fn cmp_with<Q: Borrow<K>, W: Borrow<V>>(
+        &self,
+        other: impl IntoIterator<Item = (Q, W)>,
+    ) -> Ordering
+    where
+        K: Ord,
+        V: Ord,
+    {
+        let mut other = other.into_iter();
+        for (k, v) in self.iter() {
+            let Some((q, w)) = other.next() else {
+                return Ordering::Greater;
+            };
+            match k.cmp(q.borrow()).then_with(|| v.cmp(w.borrow())) {
+                Ordering::Equal => {}
+                ordering => return ordering,
+            }
+        }
+        match other.next() {
+            Some(_) => Ordering::Less,
+            None => Ordering::Equal,
+        }
+    }
+
+    /// Iterates over the differences from this map to `other` in key order: keys only in `other`
+    /// are [added](DiffItem::Added), keys only in this map are [removed](DiffItem::Removed), and
+    /// keys in both with unequal values are [changed](DiffItem::Changed).
+    ///
+    /// Walks both maps together, so it takes `O(n + m)`. The walk stops early once both maps reach
+    /// the same leaf (e.g. in copies of a [copyable](crate::copyable) map), since the entries after
+    /// are the same.
+    #[inline]
+    pub fn diff_with<'a>(&'a self, other: &'a BTreeMap<K, V, B>) -> Diff<'a, K, V, B>
+    where
+        K: Ord,
+        V: PartialEq,
+    {
+        Diff {
+            old: self.iter(),
+            new: other.iter(),
+        }
+    }
     // endregion
 
     // region insertion and removal
@@ -202,7 +736,7 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
     where
         K: Clone + Ord,
     {
-        match self.find(&key) {
+        match self.find_mut(&key) {
             Find::NoRoot => {
                 self.insert_root(key, val);
                 None
@@ -215,6 +749,70 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
         }
     }
 
+    /// Like [BTreeMap::insert], but returns an error (without inserting) if the store may not be
+    /// able to allocate a node for every node up to the root to split into:
+    /// [Error::OutOfMemory] if the allocator can't grow the store's slab, or [Error::StoreFull] if
+    /// the store has a fixed-size backend without enough free nodes. Replacing a present key's
+    /// value never allocates, so it always succeeds. See [BTreeStore::try_reserve].
+    #[inline]
+    pub fn try_insert(&mut self, key: K, val: V) -> Result<Option<V>, Error>
+    where
+        K: Clone + Ord,
+    {
+        match self.find_mut(&key) {
+            Find::NoRoot => {
+                self.store.ensure_available(1)?;
+                self.insert_root(key, val);
+                Ok(None)
+            }
+            Find::Before { node, idx } => unsafe {
+                // Every node on the path may split, and then the root needs a new parent
+                self.store.ensure_available(self.height + 2)?;
+                self.insert_before(key, val, node, idx);
+                Ok(None)
+            },
+            Find::At { mut node, idx } => unsafe { Ok(Some(node.as_mut().replace_val(idx, val))) },
+        }
+    }
+
+    /// Like [Extend::extend], but stops at the first entry which [BTreeMap::try_insert] fails to
+    /// insert, and returns its error. Entries before it stay inserted; it and the rest of the
+    /// iterator are dropped.
+    pub fn try_extend(&mut self, iter: impl IntoIterator<Item = (K, V)>) -> Result<(), Error>
+    where
+        K: Clone + Ord,
+    {
+        for (key, val) in iter {
+            self.try_insert(key, val)?;
+        }
+        Ok(())
+    }
+
+    /// Inserts the key and value, or if an equal key is present, replaces both it and its value
+    /// and returns the old ones.
+    #[cfg(feature = "set")]
+    #[inline]
+    pub(crate) fn replace_entry(&mut self, key: K, val: V) -> Option<(K, V)>
+    where
+        K: Clone + Ord,
+    {
+        match self.find_mut(&key) {
+            Find::NoRoot => {
+                self.insert_root(key, val);
+                None
+            }
+            Find::Before { node, idx } => unsafe {
+                self.insert_before(key, val, node, idx);
+                None
+            },
+            // Separators in internal nodes are equal to the new key, so they can stay
+            Find::At { mut node, idx } => unsafe {
+                let old_key = node.as_mut().replace_key(idx, key);
+                Some((old_key, node.as_mut().replace_val(idx, val)))
+            },
+        }
+    }
+
     /// Get a reference to the value at the given key, or insert a new value if the key is not
     /// present.
     #[inline]
@@ -222,27 +820,57 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
     where
         K: Clone + Ord,
     {
-        match self.find(&key) {
+        self.get_or_insert_key_value(key, val).1
+    }
+
+    /// [BTreeMap::get_or_insert], but also returns the stored key.
+    #[inline]
+    pub(crate) fn get_or_insert_key_value(&mut self, key: K, val: V) -> (&K, &mut V)
+    where
+        K: Clone + Ord,
+    {
+        let (mut node, idx) = match self.find_mut(&key) {
+            Find::NoRoot => unsafe { self.insert_at(key, val, None) },
+            Find::Before { node, idx } => unsafe { self.insert_at(key, val, Some((node, idx))) },
+            Find::At { node, idx } => (node, idx),
+        };
+        unsafe { node.as_mut().key_val_mut(idx) }
+    }
+
+    /// Returns the stored key and value equivalent to `key`, or if not present, inserts and returns
+    /// the entry `f` makes from it, which must have an equal key (otherwise the map will be invalid,
+    /// but not memory unsafe).
+    #[cfg(feature = "set")]
+    #[inline]
+    pub(crate) fn get_or_insert_key_value_with<Q: Ord + ?Sized>(
+        &mut self,
+        key: &Q,
+        f: impl FnOnce(&Q) -> (K, V),
+    ) -> (&K, &mut V)
+    where
+        K: Clone + Borrow<Q>,
+    {
+        let (mut node, idx) = match self.find_mut(key) {
             Find::NoRoot => unsafe {
-                self.insert_root(key, val);
-                self.root.unwrap().as_mut().val_mut(0)
+                let (key, val) = f(key);
+                self.insert_at(key, val, None)
             },
             Find::Before { node, idx } => unsafe {
-                // Maybe could optimize into a single lookup...
-                self.insert_before(key.clone(), val, node, idx);
-                self.get_mut(&key).unwrap()
+                let (key, val) = f(key);
+                self.insert_at(key, val, Some((node, idx)))
             },
-            Find::At { mut node, idx } => unsafe { node.as_mut().val_mut(idx) },
-        }
+            Find::At { node, idx } => (node, idx),
+        };
+        unsafe { node.as_mut().key_val_mut(idx) }
     }
 
-    /// Removes the equivalent key and returns the actual key and value, if present.
+    /// Removes the equivalent key and returns the stored key and value, if present.
     #[inline]
-    pub fn remove_key_value<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<(K, V)>
+    pub fn remove_entry<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<(K, V)>
     where
         K: Clone + Borrow<Q>,
     {
-        match self.find(key) {
+        match self.find_mut(key) {
             Find::NoRoot | Find::Before { .. } => None,
             Find::At { mut node, idx } => unsafe {
                 let (key, val) = node.as_mut().remove_val(idx);
@@ -252,18 +880,28 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
         }
     }
 
-    /// Removes the equivalent key and returns the value if present.
+    /// Removes the equivalent key and returns the actual key and value, if present. Same as
+    /// [BTreeMap::remove_entry].
     #[inline]
-    pub fn remove<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<V>
+    pub fn remove_key_value<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<(K, V)>
     where
         K: Clone + Borrow<Q>,
     {
-        self.remove_key_value(key).map(|(_, val)| val)
+        self.remove_entry(key)
     }
 
-    /// Removes the first key and value as long as the map isn't empty
+    /// Removes the equivalent key and returns the value if present.
     #[inline]
-    pub fn pop_first(&mut self) -> Option<(K, V)>
+    pub fn remove<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Clone + Borrow<Q>,
+    {
+        self.remove_entry(key).map(|(_, val)| val)
+    }
+
+    /// Removes the first key and value as long as the map isn't empty
+    #[inline]
+    pub fn pop_first(&mut self) -> Option<(K, V)>
     where
         K: Clone,
     {
@@ -288,6 +926,51 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
         })
     }
 
+    /// Lazily removes and yields the entries within the range for which `pred` returns `true`, in
+    /// order. Entries which aren't visited (e.g. because the iterator is dropped early) are kept.
+    ///
+    /// *Panics* if the range's start is after its end, or they are equal and both excluded.
+    #[track_caller]
+    #[inline]
+    pub fn extract_if<Q: Ord + ?Sized, R: RangeBounds<Q>, F: FnMut(&K, &mut V) -> bool>(
+        &mut self,
+        bounds: R,
+        pred: F,
+    ) -> ExtractIf<'_, 'store, K, V, Q, R, F, B>
+    where
+        K: Clone + Borrow<Q>,
+    {
+        self.try_extract_if(bounds, pred)
+            .or_panic("BTreeMap::extract_if")
+    }
+
+    /// Lazily removes and yields the entries within the range for which `pred` returns `true`, in
+    /// order, or returns an error if the range's start is after its end.
+    ///
+    /// The iterator walks the leaves once. Removing an entry only has to search for the next one
+    /// again when it rebalances the entry's leaf.
+    #[inline]
+    pub fn try_extract_if<Q: Ord + ?Sized, R: RangeBounds<Q>, F: FnMut(&K, &mut V) -> bool>(
+        &mut self,
+        bounds: R,
+        pred: F,
+    ) -> Result<ExtractIf<'_, 'store, K, V, Q, R, F, B>, Error>
+    where
+        K: Clone + Borrow<Q>,
+    {
+        check_range_bounds(&bounds)?;
+        let position = self
+            .node_bounds((bounds.start_bound(), bounds.end_bound()))
+            .map(|bounds| bounds.start());
+        Ok(ExtractIf {
+            map: self,
+            position,
+            bounds,
+            pred,
+            _p: PhantomData,
+        })
+    }
+
     /// Clears the map, removing all key-value pairs.
     #[inline]
     pub fn clear(&mut self) {
@@ -296,7 +979,568 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
                 drop_node_ptr(root, self.height, &mut |n| self.store.dealloc(n));
             }
         }
+        self.first = None;
+        self.last = None;
+        self.finger = None;
+        self.length = 0;
+        self.height = 0;
+    }
+    // endregion
+
+    // region order statistics
+    /// Inserts a key-value pair into the map, and returns the index the key now sits at (its #
+    /// of smaller keys) along with the old value if there was one.
+    #[cfg(feature = "order-statistic")]
+    #[inline]
+    pub fn insert_full(&mut self, key: K, val: V) -> (usize, Option<V>)
+    where
+        K: Clone + Ord,
+    {
+        let (find, index) = self.find_indexed(&key);
+        match find {
+            Find::NoRoot => {
+                self.insert_root(key, val);
+                (index, None)
+            }
+            Find::Before { node, idx } => unsafe {
+                self.insert_before(key, val, node, idx);
+                (index, None)
+            },
+            Find::At { mut node, idx } => unsafe {
+                (index, Some(node.as_mut().replace_val(idx, val)))
+            },
+        }
+    }
+
+    /// Returns the index of the equivalent key (its # of smaller keys), if present.
+    #[cfg(feature = "order-statistic")]
+    #[inline]
+    pub fn index_of<Q: Ord + ?Sized>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+    {
+        match self.find_indexed(key) {
+            (Find::At { .. }, index) => Some(index),
+            (Find::NoRoot | Find::Before { .. }, _) => None,
+        }
+    }
+
+    /// Returns the entry at the given index (the entry with `index` smaller keys), if the map is
+    /// large enough.
+    #[cfg(feature = "order-statistic")]
+    #[inline]
+    pub fn get_index(&self, index: usize) -> Option<(&K, &V)> {
+        if index >= self.length {
+            return None;
+        }
+        Some(unsafe { key_val_at_index(self.root?, self.height, index) })
+    }
+
+    /// Returns the entry at the `q` quantile, where `0.0` is the first entry and `1.0` is the last,
+    /// if the map isn't empty. This is the entry at index `floor(q * (len - 1))`, so between two
+    /// entries it picks the lower one.
+    ///
+    /// *Panics* if `q` isn't between `0.0` and `1.0`.
+    #[cfg(feature = "order-statistic")]
+    #[track_caller]
+    #[inline]
+    pub fn quantile(&self, q: f64) -> Option<(&K, &V)> {
+        self.try_quantile(q).or_panic("BTreeMap::quantile")
+    }
+
+    /// Returns the entry at the `q` quantile (see [Self::quantile]) if the map isn't empty, or an
+    /// error if `q` isn't between `0.0` and `1.0`.
+    #[cfg(feature = "order-statistic")]
+    #[inline]
+    pub fn try_quantile(&self, q: f64) -> Result<Option<(&K, &V)>, Error> {
+        if !(0.0..=1.0).contains(&q) {
+            return Err(Error::InvalidQuantile);
+        }
+        let Some(last_index) = self.length.checked_sub(1) else {
+            return Ok(None)
+        };
+        Ok(self.get_index((q * last_index as f64) as usize))
+    }
+
+    /// Returns the median entry if the map isn't empty. If the map has an even length, this is the
+    /// lower of the 2 middle entries.
+    #[cfg(feature = "order-statistic")]
+    #[inline]
+    pub fn median(&self) -> Option<(&K, &V)> {
+        self.get_index(self.length.saturating_sub(1) / 2)
+    }
+    // endregion
+
+    // region batch
+    /// Applies all of the operations, or none of them: if an operation fails (an [BatchOp::Update]
+    /// or [BatchOp::Remove] of a missing key), the ones before it are rolled back and this returns
+    /// [Error::BatchRejected] with its index.
+    #[inline]
+    pub fn apply_batch(&mut self, ops: impl IntoIterator<Item = BatchOp<K, V>>) -> Result<(), Error>
+    where
+        K: Clone + Ord,
+    {
+        self.apply_batch_if(ops, |_, _| true)
+    }
+
+    /// [Self::apply_batch], but also rolls back if `precondition` returns `false` for an operation.
+    /// `precondition` is called before applying each operation, with the key's current value.
+    ///
+    /// If `precondition` *panic*s, the operations before it are not rolled back.
+    #[inline]
+    pub fn apply_batch_if(
+        &mut self,
+        ops: impl IntoIterator<Item = BatchOp<K, V>>,
+        mut precondition: impl FnMut(&BatchOp<K, V>, Option<&V>) -> bool,
+    ) -> Result<(), Error>
+    where
+        K: Clone + Ord,
+    {
+        let mut undo_log = Vec::new();
+        for (index, op) in ops.into_iter().enumerate() {
+            let (BatchOp::Insert(key, _) | BatchOp::Update(key, _) | BatchOp::Remove(key)) = &op;
+            let current = self.get(key);
+            let valid = matches!(op, BatchOp::Insert(..)) || current.is_some();
+            if !valid || !precondition(&op, current) {
+                self.rollback(undo_log);
+                return Err(Error::BatchRejected { index });
+            }
+            match op {
+                BatchOp::Insert(key, val) | BatchOp::Update(key, val) => {
+                    undo_log.push(match self.insert(key.clone(), val) {
+                        None => Undo::Remove(key),
+                        Some(old_val) => Undo::Restore(key, old_val),
+                    })
+                }
+                BatchOp::Remove(key) => {
+                    // Always present, since we checked above
+                    if let Some((key, val)) = self.remove_entry(&key) {
+                        undo_log.push(Undo::Restore(key, val))
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts speculative edits: inserts and removals through the returned [Checkpoint] are
+    /// rolled back when it's dropped, unless it's [committed](Checkpoint::commit).
+    ///
+    /// The map isn't cloned; rolling back replays an undo log, so it costs as much as the edits.
+    /// This is a per-map undo log, not a store-wide checkpoint: nodes are edited in place and their
+    /// slots are reused by other trees, so there's no marker to roll the store back to in O(1).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use btree_plus_store::{BTreeMap, BTreeStore};
+    /// let store = BTreeStore::new();
+    /// let mut map = BTreeMap::new_in(&store);
+    /// map.insert(1, "a");
+    ///
+    /// let mut checkpoint = map.checkpoint();
+    /// checkpoint.insert(2, "b");
+    /// assert_eq!(checkpoint.remove(&1), Some("a"));
+    /// assert_eq!(checkpoint.len(), 1);
+    /// checkpoint.rollback();
+    /// assert_eq!(map.get(&1), Some(&"a"));
+    /// assert_eq!(map.get(&2), None);
+    ///
+    /// let mut checkpoint = map.checkpoint();
+    /// checkpoint.insert(2, "b");
+    /// checkpoint.commit();
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    #[inline]
+    pub fn checkpoint(&mut self) -> Checkpoint<'_, 'store, K, V, B>
+    where
+        K: Clone + Ord,
+    {
+        Checkpoint {
+            map: self,
+            undo_log: Vec::new(),
+        }
+    }
+
+    /// Reverts applied batch operations, most recent last in `undo_log`.
+    #[inline]
+    fn rollback(&mut self, undo_log: Vec<Undo<K, V>>)
+    where
+        K: Clone + Ord,
+    {
+        for undo in undo_log.into_iter().rev() {
+            match undo {
+                Undo::Remove(key) => {
+                    self.remove(&key);
+                }
+                Undo::Restore(key, val) => {
+                    self.insert(key, val);
+                }
+            }
+        }
+    }
+    // endregion
+
+    // region transfer
+    /// Moves the entries within the range into `dest`, replacing entries in `dest` with the same
+    /// keys.
+    ///
+    /// *Panics* if the range's start is after its end, or they are equal and both excluded, or if
+    /// `dest` isn't in the same store.
+    #[track_caller]
+    #[inline]
+    pub fn transfer_range<Q: Ord + ?Sized>(&mut self, bounds: impl RangeBounds<Q>, dest: &mut Self)
+    where
+        K: Clone + Borrow<Q> + Ord,
+    {
+        if !self.same_store(dest) {
+            panic!(
+                "BTreeMap::transfer_range: dest is in store {:p}, but this map is in store {:p}",
+                dest.store, self.store
+            )
+        }
+        self.try_transfer_range(bounds, dest)
+            .or_panic("BTreeMap::transfer_range")
+    }
+
+    /// Moves the entries within the range into `dest`, replacing entries in `dest` with the same
+    /// keys, or returns an error if the range's start is after its end or `dest` isn't in the same
+    /// store.
+    ///
+    /// Instead of removing and inserting each entry, the range's subtrees are split off and
+    /// relinked, so this takes O(log n) plus a walk over the range's leaves to count its entries.
+    /// The exception is if `dest` has keys between the range's first and last keys, in which case
+    /// the entries must be inserted one by one.
+    pub fn try_transfer_range<Q: Ord + ?Sized>(
+        &mut self,
+        bounds: impl RangeBounds<Q>,
+        dest: &mut Self,
+    ) -> Result<(), Error>
+    where
+        K: Clone + Borrow<Q> + Ord,
+    {
+        check_range_bounds(&bounds)?;
+        if !self.same_store(dest) {
+            return Err(Error::WrongStore);
+        }
+        let Some(bounds) = self.node_bounds(bounds) else {
+            return Ok(())
+        };
+        let mut moved = unsafe { self.split_off_range(bounds) };
+
+        let moved_before_dest = match (moved.last_key_value(), dest.first_key_value()) {
+            (Some((moved_last, _)), Some((dest_first, _))) => moved_last < dest_first,
+            _ => true,
+        };
+        let moved_after_dest = match (dest.last_key_value(), moved.first_key_value()) {
+            (Some((dest_last, _)), Some((moved_first, _))) => dest_last < moved_first,
+            _ => true,
+        };
+        if moved_after_dest {
+            unsafe { dest.concat_unchecked(moved) };
+        } else if moved_before_dest {
+            swap(dest, &mut moved);
+            unsafe { dest.concat_unchecked(moved) };
+        } else {
+            for (key, val) in moved {
+                dest.insert(key, val);
+            }
+        }
+        Ok(())
+    }
+
+    /// Splits the map in two at the key: the map keeps the keys less than `key`, and the returned
+    /// map (in the same store) has the rest.
+    ///
+    /// Like [BTreeMap::transfer_range], the nodes are split along the path to the key in
+    /// O(log n), plus a walk over the returned map's leaves to count its entries (which isn't
+    /// needed under `order-statistic`), instead of moving each entry.
+    #[inline]
+    pub fn split_off<Q: Ord + ?Sized>(&mut self, key: &Q) -> Self
+    where
+        K: Clone + Borrow<Q>,
+    {
+        let (leaf, idx) = match self.find(key) {
+            Find::NoRoot => return Self::new_in(self.store),
+            Find::Before { node, idx } | Find::At { node, idx } => (node, idx),
+        };
+        unsafe {
+            #[cfg(not(feature = "order-statistic"))]
+            let split_len = {
+                let mut len = (leaf.as_ref().len - idx) as usize;
+                let mut next = leaf.as_ref().next();
+                while let Some(node) = next {
+                    len += node.as_ref().len as usize;
+                    next = node.as_ref().next();
+                }
+                len
+            };
+            let mut split = self.split_off_at(leaf, idx);
+            // Splitting recomputes the sizes on both sides
+            #[cfg(feature = "order-statistic")]
+            let split_len = split
+                .root
+                .map_or(0, |root| root.as_ref().subtree_len(split.height));
+            split.length = split_len;
+            self.length -= split_len;
+            split.fix_border(false);
+            self.fix_border(true);
+            split
+        }
+    }
+
+    /// Splits the map along the pivots into `pivots.len() + 1` maps in the same store, in key
+    /// order: the first has the keys less than the smallest pivot, each next one has the keys from
+    /// a pivot up to (excluding) the next larger pivot, and the last has the keys from the largest
+    /// pivot on. The pivots needn't be sorted or distinct.
+    ///
+    /// Each map is [split off](BTreeMap::split_off) in O(log n) plus a walk over its leaves (which
+    /// isn't needed under `order-statistic`), instead of moving each entry.
+    pub fn split_by_pivots<Q: Ord>(mut self, pivots: &[Q]) -> Vec<Self>
+    where
+        K: Clone + Borrow<Q>,
+    {
+        let mut pivots = pivots.iter().collect::<Vec<_>>();
+        pivots.sort_unstable();
+
+        // Split off from the back, so each map's leaves are only walked once
+        let mut pieces = Vec::with_capacity(pivots.len() + 1);
+        for pivot in pivots.into_iter().rev() {
+            pieces.push(self.split_off(pivot));
+        }
+        pieces.push(self);
+        pieces.reverse();
+        pieces
+    }
+
+    /// Moves all entries from `other` into the map, leaving `other` empty. Values in `other`
+    /// replace values in the map with the same keys.
+    ///
+    /// If both maps are in the same store, this is [BTreeMap::transfer_range] of `other`'s whole
+    /// range, so if the keys don't interleave, `other`'s nodes are relinked into the map in
+    /// O(log n). Otherwise, both maps' entries are merged in order into new full nodes in O(n + m).
+    #[inline]
+    pub fn append(&mut self, other: &mut Self)
+    where
+        K: Clone + Ord,
+    {
+        if self.same_store(other) {
+            match other.try_transfer_range::<K>(.., self) {
+                Ok(()) => {}
+                Err(err) => unreachable!("transfer of the whole range failed: {}", err),
+            }
+            return;
+        }
+
+        let other = replace(other, Self::new_in(other.store));
+        let this = replace(self, Self::new_in(self.store));
+        let mut this = this.into_iter().peekable();
+        let mut other = other.into_iter().peekable();
+        let merged = std::iter::from_fn(|| match (this.peek(), other.peek()) {
+            (Some((a, _)), Some((b, _))) => match a.cmp(b) {
+                Ordering::Less => this.next(),
+                Ordering::Equal => {
+                    this.next();
+                    other.next()
+                }
+                Ordering::Greater => other.next(),
+            },
+            (Some(_), None) => this.next(),
+            (None, _) => other.next(),
+        });
+        *self = Self::bulk_load_in(self.store, merged);
+    }
+
+    /// Removes the entries within the range and returns how many there were. Instead of removing
+    /// each entry (and rebalancing after each), the range's subtrees are split off and the nodes
+    /// around it rejoined, so this takes O(log n) plus the nodes freed, e.g. to expire old entries
+    /// of time-windowed data.
+    ///
+    /// *Panics* if the range's start is after its end, or they are equal and both excluded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use btree_plus_store::{BTreeMap, BTreeStore};
+    /// let store = BTreeStore::new();
+    /// let mut map = BTreeMap::from_iter_in((0..1000).map(|i| (i, i)), &store);
+    /// assert_eq!(map.remove_range(100..900), 800);
+    /// assert!(map.keys().copied().eq((0..100).chain(900..1000)));
+    /// ```
+    #[track_caller]
+    #[inline]
+    pub fn remove_range<Q: Ord + ?Sized>(&mut self, bounds: impl RangeBounds<Q>) -> usize
+    where
+        K: Clone + Borrow<Q> + Ord,
+    {
+        check_range_bounds(&bounds).or_panic("BTreeMap::remove_range");
+        self.remove_checked_range(bounds)
+    }
+
+    /// Removes the entries within the range and returns how many there were, or returns an error
+    /// (without removing any) if the range's start is after its end, or if the store may not be
+    /// able to allocate the nodes which splitting the tree around the range needs, like
+    /// [BTreeMap::try_insert]. See [BTreeMap::remove_range].
+    pub fn try_remove_range<Q: Ord + ?Sized>(
+        &mut self,
+        bounds: impl RangeBounds<Q>,
+    ) -> Result<usize, Error>
+    where
+        K: Clone + Borrow<Q> + Ord,
+    {
+        check_range_bounds(&bounds)?;
+        // Both ends of the range may split every node up to the root, and joining the rest back
+        // together may split every ancestor of the join and add a root
+        self.store.ensure_available(3 * self.height + 4)?;
+        Ok(self.remove_checked_range(bounds))
+    }
+
+    /// Removes the entries within the range, whose bounds were checked, and returns how many there
+    /// were
+    #[inline]
+    fn remove_checked_range<Q: Ord + ?Sized>(&mut self, bounds: impl RangeBounds<Q>) -> usize
+    where
+        K: Clone + Borrow<Q> + Ord,
+    {
+        let Some(bounds) = self.node_bounds(bounds) else {
+            return 0;
+        };
+        // Freeing the split-off map's nodes doesn't rebalance. They're freed here instead of when
+        // it's dropped, which first checks whether the thread is panicking, a call which may unwind
+        let mut removed = unsafe { self.split_off_range(bounds) };
+        if let Some(root) = removed.root.take() {
+            unsafe { drop_node_ptr(root, removed.height, &mut |n| self.store.dealloc(n)) };
+        }
+        removed.length
+    }
+
+    /// Splits off the entries within the bounds into a new map in O(log n) plus a walk over the
+    /// bounds' leaves, and joins the entries before and after them back together.
+    ///
+    /// # Safety
+    /// `bounds` must be in this map.
+    unsafe fn split_off_range(&mut self, bounds: NodeBounds<K, V, B>) -> Self
+    where
+        K: Clone,
+    {
+        let moved_len = bounds.len();
+        let remaining_len = self.length - moved_len;
+        let (start_node, start_idx) = bounds.start();
+        let (end_node, end_idx) = bounds.end();
+
+        // Splitting only deallocates nodes when fixing borders, so both addresses stay valid
+        // until then
+        let mut after = self.split_off_at(end_node, end_idx + 1);
+        let mut moved = self.split_off_at(start_node, start_idx);
+        moved.length = moved_len;
+        moved.fix_border(false);
+        moved.fix_border(true);
+        // Only the total length of the entries before and after the range is known, which is
+        // all that matters after concatenating them
         self.length = 0;
+        after.length = remaining_len;
+        self.fix_border(true);
+        after.fix_border(false);
+        self.concat_unchecked(after);
+        moved
+    }
+
+    /// Joins two maps in the same store, where every key in `self` is less than every key in
+    /// `other`, by grafting the shorter tree's root into the taller tree in O(log n), instead of
+    /// moving each entry.
+    ///
+    /// *Panics* if the maps are in different stores, or their keys overlap or interleave.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use btree_plus_store::{BTreeMap, BTreeStore};
+    /// let store = BTreeStore::new();
+    /// let low = BTreeMap::from_iter_in((0..100).map(|i| (i, i)), &store);
+    /// let high = BTreeMap::from_iter_in((100..1000).map(|i| (i, i)), &store);
+    /// let map = low.concat(high);
+    /// assert!(map.keys().copied().eq(0..1000));
+    /// ```
+    #[track_caller]
+    #[inline]
+    pub fn concat(mut self, other: Self) -> Self
+    where
+        K: Clone + Ord,
+    {
+        self.check_concat(&other).or_panic("BTreeMap::concat");
+        unsafe { self.concat_unchecked(other) };
+        self
+    }
+
+    /// Moves every entry of `other` after the entries of the map in O(log n), leaving `other`
+    /// empty, or returns an error (without changing either map) if they're in different stores,
+    /// any key in `other` isn't greater than every key in the map, or the store may not be able to
+    /// allocate the nodes which grafting `other` needs, like [BTreeMap::try_insert]. See
+    /// [BTreeMap::concat].
+    pub fn try_concat(&mut self, other: &mut Self) -> Result<(), Error>
+    where
+        K: Clone + Ord,
+    {
+        self.check_concat(other)?;
+        if self.root.is_some() && other.root.is_some() {
+            // Grafting may split every ancestor of the graft and add a root
+            self.store
+                .ensure_available(self.height.max(other.height) + 2)?;
+        }
+        let other = replace(other, Self::new_in(self.store));
+        unsafe { self.concat_unchecked(other) };
+        Ok(())
+    }
+
+    /// Checks that `other` can be concatenated after the map
+    #[inline]
+    fn check_concat(&self, other: &Self) -> Result<(), Error>
+    where
+        K: Ord,
+    {
+        if !self.same_store(other) {
+            return Err(Error::WrongStore);
+        }
+        if let (Some((last, _)), Some((first, _))) =
+            (self.last_key_value(), other.first_key_value())
+        {
+            if last >= first {
+                return Err(Error::UnorderedKey);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the entries within the range and iterates over them in order. The entries are
+    /// removed up-front, so any which aren't iterated are dropped along with the iterator, and
+    /// their nodes are returned to the store as it goes.
+    ///
+    /// *Panics* if the range's start is after its end, or they are equal and both excluded.
+    #[track_caller]
+    #[inline]
+    pub fn drain<Q: Ord + ?Sized>(&mut self, bounds: impl RangeBounds<Q>) -> Drain<'store, K, V, B>
+    where
+        K: Clone + Borrow<Q> + Ord,
+    {
+        self.try_drain(bounds).or_panic("BTreeMap::drain")
+    }
+
+    /// Removes the entries within the range and iterates over them in order, or returns an error if
+    /// the range's start is after its end. See [BTreeMap::drain].
+    ///
+    /// The range is split off like in [BTreeMap::transfer_range], so this takes O(log n) plus a
+    /// walk over the range's leaves, instead of removing each entry.
+    #[inline]
+    pub fn try_drain<Q: Ord + ?Sized>(
+        &mut self,
+        bounds: impl RangeBounds<Q>,
+    ) -> Result<Drain<'store, K, V, B>, Error>
+    where
+        K: Clone + Borrow<Q> + Ord,
+    {
+        let mut drained = Self::new_in(self.store);
+        self.try_transfer_range(bounds, &mut drained)?;
+        Ok(Drain(drained.into_iter()))
     }
     // endregion
 
@@ -316,7 +1560,7 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
     where
         K: Clone + Ord,
     {
-        match self.find(&key) {
+        match self.find_mut(&key) {
             Find::NoRoot => match update(None) {
                 (None, r) => r,
                 (Some(val), r) => {
@@ -371,25 +1615,283 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
         self.update_and_return(key, |val| (update(val), ()))
     }
 
-    /// Validates the map, *panic*ing if it is invalid. Specifically, we check that the number of
-    /// entries in each node is within the b-tree invariant bounds, and that the keys are in order.
+    /// Transforms every value with `f`, moving the entries into a new map in `store`.
     ///
-    /// Ideally, this should always be a no-op.
+    /// The new map has exactly the same shape as this one: keys are moved node-by-node and the
+    /// tree is never searched or rebalanced, so this is O(n).
     #[inline]
-    pub fn validate(&self)
-    where
-        K: Debug + Ord,
-        V: Debug,
-    {
-        unsafe fn validate_node<K: Debug + Ord, V: Debug>(
-            errors: &mut Vec<String>,
-            node: NodePtr<K, V>,
-            parent: Option<(NodePtr<K, V>, u16)>,
-            height: usize,
-            (mut prev_key, mut prev_leaf): (Option<NonNull<K>>, Option<NodePtr<K, V>>),
-        ) -> (usize, (NonNull<K>, NodePtr<K, V>)) {
+    pub fn map_values<'new_store, W>(
+        mut self,
+        mut f: impl FnMut(V) -> W,
+        store: &'new_store BTreeStore<K, W, B>,
+    ) -> BTreeMap<'new_store, K, W, B> {
+        let mut mapped = BTreeMap::new_in(store);
+        if let Some(root) = self.root.take() {
+            let mut prev_leaf = None;
+            mapped.root = Some(unsafe {
+                map_node_values(
+                    root,
+                    self.height,
+                    None,
+                    &mut prev_leaf,
+                    &mut f,
+                    self.store,
+                    store,
+                )
+            });
+            mapped.length = self.length;
+            mapped.height = self.height;
+            mapped.cache_first_last();
+            self.length = 0;
+        }
+        mapped
+    }
+
+    /// Consumes the map in O(1), without deallocating its nodes or dropping its entries.
+    ///
+    /// The nodes stay allocated until the store is [reset](BTreeStore::reset) (which frees them
+    /// all at once) or dropped, and the entries are leaked. So this is meant for keys and values
+    /// without drop glue, when the whole store will be thrown away.
+    #[inline]
+    pub fn abandon(self) {
+        forget(self)
+    }
+
+    /// Moves the entries into a new map in `store`, freeing this map's nodes.
+    ///
+    /// To defragment a store in place after large trees in it were cleared or shrunk, use
+    /// [BTreeStore::compact]. This instead moves the tree into a new store (which allocates its
+    /// nodes together), e.g. to split a store's trees between stores, after which the old store
+    /// can be dropped or [shrunk](BTreeStore::shrink_to_fit). Like [BTreeMap::map_values], this is
+    /// O(n).
+    #[inline]
+    pub fn compact_in<'new_store>(
+        self,
+        store: &'new_store BTreeStore<K, V, B>,
+    ) -> BTreeMap<'new_store, K, V, B> {
+        self.map_values(|val| val, store)
+    }
+
+    /// Points the map at the new slots of its nodes which the store moved (see
+    /// [BTreeStore::compact]).
+    ///
+    /// # Safety
+    /// `moved` maps each of the map's nodes which were moved to its new slot, and the map wasn't
+    /// accessed since they were moved.
+    pub(crate) unsafe fn relocate(&mut self, moved: &HashMap<NodePtr<K, V, B>, NodePtr<K, V, B>>) {
+        let relocate = |node: &mut NodePtr<K, V, B>| {
+            if let Some(&to) = moved.get(node) {
+                *node = to;
+            }
+        };
+        for node in [
+            &mut self.root,
+            &mut self.first,
+            &mut self.last,
+            &mut self.finger,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            relocate(node);
+        }
+        let Some(root) = self.root else { return };
+        let mut stack = vec![(root, self.height)];
+        while let Some((mut node, height)) = stack.pop() {
+            let node = node.as_mut();
+            if let Some(parent) = &mut node.parent {
+                relocate(parent);
+            }
+            if height == 0 {
+                let leaf = node.d.leaf_mut();
+                for sibling in [&mut leaf.prev, &mut leaf.next].into_iter().flatten() {
+                    relocate(sibling);
+                }
+            } else {
+                for edge in node.edges_mut() {
+                    relocate(edge);
+                    stack.push((*edge, height - 1));
+                }
+            }
+        }
+    }
+
+    /// Removes the entries for which `f` returns `false`.
+    ///
+    /// Like [BTreeMap::partition], the entries are visited in order and the kept ones are pushed
+    /// onto the end of a new tree, so this is a single O(n) traversal that never searches or
+    /// allocates beyond the new tree's nodes. The old nodes are freed as they are emptied. If `f`
+    /// panics, the map keeps the entries kept so far.
+    #[inline]
+    pub fn retain(&mut self, mut f: impl FnMut(&K, &mut V) -> bool)
+    where
+        K: Clone,
+    {
+        /// Fixes the kept tree's border even if `f` panics
+        struct FixBorder<'m, 'store, K: Clone, V, const B: usize>(
+            &'m mut BTreeMap<'store, K, V, B>,
+        );
+
+        impl<'m, 'store, K: Clone, V, const B: usize> Drop for FixBorder<'m, 'store, K, V, B> {
+            fn drop(&mut self) {
+                unsafe { self.0.fix_border(true) }
+            }
+        }
+
+        let old = replace(self, Self::new_in(self.store));
+        let kept = FixBorder(self);
+        for (key, mut val) in old {
+            if f(&key, &mut val) {
+                unsafe { kept.0.push_last(key, val) };
+            }
+        }
+    }
+
+    /// Splits the map into the entries for which `pred` returns `true` and the rest, both in the
+    /// same store.
+    ///
+    /// The entries are visited in order and pushed onto the end of either map, so this is a
+    /// single O(n) traversal that never searches. The old nodes are freed as they are emptied.
+    #[inline]
+    pub fn partition(self, mut pred: impl FnMut(&K, &V) -> bool) -> (Self, Self)
+    where
+        K: Clone,
+    {
+        let mut matching = Self::new_in(self.store);
+        let mut rest = Self::new_in(self.store);
+        for (key, val) in self {
+            let dest = match pred(&key, &val) {
+                true => &mut matching,
+                false => &mut rest,
+            };
+            unsafe { dest.push_last(key, val) };
+        }
+        unsafe {
+            matching.fix_border(true);
+            rest.fix_border(true);
+        }
+        (matching, rest)
+    }
+
+    /// Removes the entries whose keys aren't in `keys`, which can be in any store.
+    ///
+    /// Walks the map and the set together instead of searching the set for each key, so this is
+    /// O(n + m). Like [BTreeMap::partition], kept entries are pushed onto the end of a new tree and
+    /// the old nodes are freed as they are emptied.
+    #[cfg(feature = "set")]
+    #[inline]
+    pub fn retain_keys_in(&mut self, keys: &BTreeSet<'_, K, B>)
+    where
+        K: Clone + Ord,
+    {
+        let old = replace(self, Self::new_in(self.store));
+        let mut keys = keys.iter().peekable();
+        for (key, val) in old {
+            match skip_to(&mut keys, &key) {
+                Some(true) => unsafe { self.push_last(key, val) },
+                Some(false) => {}
+                // The remaining entries are dropped with `old`
+                None => break,
+            }
+        }
+        unsafe { self.fix_border(true) };
+    }
+
+    /// Returns a new map (in the same store) with clones of the entries whose keys are in `keys`,
+    /// which can be in any store.
+    ///
+    /// Walks the map and the set together like [BTreeMap::retain_keys_in], and builds the new
+    /// map's nodes full without searching.
+    #[cfg(feature = "set")]
+    #[inline]
+    pub fn filter_by_keys(&self, keys: &BTreeSet<'_, K, B>) -> Self
+    where
+        K: Clone + Ord,
+        V: Clone,
+    {
+        let mut keys = keys.iter().peekable();
+        let mut entries = self.iter();
+        let filtered = std::iter::from_fn(|| loop {
+            let (key, val) = entries.next()?;
+            if skip_to(&mut keys, key)? {
+                return Some((key.clone(), val.clone()));
+            }
+        });
+        Self::bulk_load_in(self.store, filtered)
+    }
+
+    /// Rebuilds the tree so that every node is full, e.g. after many removals have left nodes
+    /// half-empty.
+    ///
+    /// The entries are moved into fresh nodes in order (without searching or rebalancing), and the
+    /// old nodes are freed as they are emptied, so this is O(n) and reuses the old nodes' memory.
+    #[inline]
+    pub fn optimize(&mut self)
+    where
+        K: Clone,
+    {
+        let old = replace(self, Self::new_in(self.store));
+        *self = Self::bulk_load_in(old.store, old);
+    }
+
+    /// Replaces the map's entries with clones of `other`'s, reusing the map's nodes instead of
+    /// freeing them and allocating new ones. `other` can be in a different store; the map stays in
+    /// its own.
+    ///
+    /// Like [BTreeMap::optimize], the copy's nodes are full, so it needs at most as many nodes as
+    /// `other`. Only nodes beyond what the map already has are allocated, and leftover nodes are
+    /// freed.
+    ///
+    /// If cloning a key or value panics, the map is left empty, and the nodes already holding
+    /// clones are leaked.
+    pub fn copy_from(&mut self, other: &BTreeMap<K, V, B>)
+    where
+        K: Clone,
+        V: Clone,
+    {
+        /// Frees the old nodes which weren't reused, even if cloning panics
+        struct FreeUnused<'store, K, V, const B: usize>(
+            &'store BTreeStore<K, V, B>,
+            Vec<NodePtr<K, V, B>>,
+        );
+
+        impl<'store, K, V, const B: usize> Drop for FreeUnused<'store, K, V, B> {
+            fn drop(&mut self) {
+                for node in self.1.drain(..) {
+                    self.0.dealloc(node);
+                }
+            }
+        }
+
+        let mut old = replace(self, Self::new_in(self.store));
+        let mut free = FreeUnused(self.store, Vec::new());
+        if let Some(root) = old.root.take() {
+            unsafe { drop_node_ptr(root, old.height, &mut |node| free.1.push(node)) };
+        }
+        let entries = other.iter().map(|(k, v)| (k.clone(), v.clone()));
+        *self = Self::bulk_load_reusing(self.store, entries, &mut free.1);
+    }
+
+    /// Validates the map, *panic*ing if it is invalid. Specifically, we check that the number of
+    /// entries in each node is within the b-tree invariant bounds, and that the keys are in order.
+    ///
+    /// Ideally, this should always be a no-op.
+    #[inline]
+    pub fn validate(&self)
+    where
+        K: Debug + Ord,
+        V: Debug,
+    {
+        unsafe fn validate_node<K: Debug + Ord, V: Debug, const B: usize>(
+            errors: &mut Vec<String>,
+            node: NodePtr<K, V, B>,
+            parent: Option<(NodePtr<K, V, B>, u16)>,
+            height: usize,
+            (mut prev_key, mut prev_leaf): (Option<NonNull<K>>, Option<NodePtr<K, V, B>>),
+            min_non_root_len: usize,
+        ) -> (usize, (NonNull<K>, NodePtr<K, V, B>)) {
             let errors = RefCell::new(errors);
-            let assert2 = |node: NodePtr<K, V>, cond: bool, msg: &str| {
+            let assert2 = |node: NodePtr<K, V, B>, cond: bool, msg: &str| {
                 if !cond {
                     (*errors.borrow_mut()).push(format!("{:X?} {}", node.as_ptr(), msg))
                 }
@@ -415,9 +1917,9 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
 
             let min_len = match parent {
                 None => 1,
-                Some(_) => M / 2,
+                Some(_) => min_non_root_len,
             } as u16;
-            let max_len = M as u16;
+            let max_len = B as u16;
             assert(node.len >= min_len, "has too few entries");
             assert(node.len <= max_len, "has too many entries");
 
@@ -479,18 +1981,23 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
                         Some((node_ptr, i)),
                         height - 1,
                         (prev_key, prev_leaf),
+                        min_non_root_len,
                     );
                     len += child_len;
                     prev_key = Some(last_key);
                     prev_leaf = Some(last_leaf);
                 }
+                #[cfg(feature = "order-statistic")]
+                assert(node.d.internal().size == len, "subtree size is incorrect");
                 (len, (prev_key.unwrap(), prev_leaf.unwrap()))
             }
         }
         let mut errors = Vec::new();
         if let Some(root) = self.root {
-            let (len, (_last_key, last_leaf)) =
-                unsafe { validate_node(&mut errors, root, None, self.height, (None, None)) };
+            let min_len = self.store.rebalance.relaxed_min_len(B);
+            let (len, (_last_key, last_leaf)) = unsafe {
+                validate_node(&mut errors, root, None, self.height, (None, None), min_len)
+            };
             if len != self.length {
                 errors.push(String::from("tree length isn't correct"))
             };
@@ -499,22 +2006,57 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
                     last_leaf.as_ptr()
                 }))
             }
+            if !self.last.ptr_eq(&Some(last_leaf)) {
+                errors.push(String::from("cached last leaf is incorrect"))
+            }
+            let mut first_leaf = root;
+            for _ in 0..self.height {
+                first_leaf = unsafe { first_leaf.as_ref().edge(0) };
+            }
+            if !self.first.ptr_eq(&Some(first_leaf)) {
+                errors.push(String::from("cached first leaf is incorrect"))
+            }
+            if let Some(finger) = self.finger {
+                let mut node = finger;
+                let mut depth = 0;
+                while let Some(parent) = unsafe { node.as_ref().parent } {
+                    node = parent;
+                    depth += 1;
+                }
+                if !node.ptr_eq(&root) || depth != self.height {
+                    errors.push(format!("{:X?} finger isn't a leaf in the tree", unsafe {
+                        finger.as_ptr()
+                    }))
+                }
+            }
+        } else {
+            if self.first.is_some() || self.last.is_some() || self.finger.is_some() {
+                errors.push(String::from(
+                    "cached first, last, or finger leaf is set in an empty tree",
+                ))
+            }
+            if self.length != 0 || self.height != 0 {
+                errors.push(String::from(
+                    "tree has no root but nonzero length or height",
+                ))
+            }
         }
         if !errors.is_empty() {
             panic!("invalid b-tree:\n{:?}\n- {}", self, errors.join("\n- "));
         }
     }
 
-    /// Prints the b-tree in ascii
+    /// Prints the b-tree in ascii to `f`, which can be a [Formatter], a [String], or any other
+    /// [std::fmt::Write].
     #[inline]
-    pub fn print(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+    pub fn print(&self, f: &mut (impl std::fmt::Write + ?Sized)) -> std::fmt::Result
     where
         K: Debug,
         V: Debug,
     {
-        unsafe fn print_node<K: Debug, V: Debug>(
-            f: &mut Formatter<'_>,
-            node: NodePtr<K, V>,
+        unsafe fn print_node<K: Debug, V: Debug, const B: usize>(
+            f: &mut (impl std::fmt::Write + ?Sized),
+            node: NodePtr<K, V, B>,
             max_height: usize,
             height: usize,
         ) -> std::fmt::Result {
@@ -568,182 +2110,1031 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
             writeln!(f, "empty")
         }
     }
+
+    /// Prints the b-tree in ascii to an [std::io::Write], e.g. a file or stderr.
+    #[inline]
+    pub fn print_to(&self, w: impl std::io::Write) -> std::io::Result<()>
+    where
+        K: Debug,
+        V: Debug,
+    {
+        let mut w = IoFmtWriter::new(w);
+        let result = self.print(&mut w);
+        w.finish(result)
+    }
+
+    /// Prints the b-tree in ascii to a new string.
+    #[inline]
+    pub fn print_to_string(&self) -> String
+    where
+        K: Debug,
+        V: Debug,
+    {
+        let mut string = String::new();
+        self.print(&mut string)
+            .expect("writing to a String doesn't fail");
+        string
+    }
     // endregion
 
     // region iteration
     /// Iterates over the map's key-value pairs in order.
     #[inline]
-    pub fn iter(&self) -> Iter<'_, K, V> {
+    pub fn iter(&self) -> Iter<'_, K, V, B> {
         Iter::new(self)
     }
 
+    /// Iterates over the map's key-value pairs in order, starting at the first key `>= key` and
+    /// running to the end of the map.
+    ///
+    /// Unlike `range(key..)`, this returns the same [Iter] as [Self::iter]. With the
+    /// `order-statistic` feature this is O(log n), otherwise it walks the leaves after `key` to
+    /// count the remaining entries.
+    #[inline]
+    pub fn iter_from<Q: Ord + ?Sized>(&self, key: &Q) -> Iter<'_, K, V, B>
+    where
+        K: Borrow<Q>,
+    {
+        let bounds = self.node_bounds((Bound::Included(key), Bound::Unbounded));
+        #[cfg(feature = "order-statistic")]
+        let length = self.length - self.find_indexed(key).1;
+        #[cfg(not(feature = "order-statistic"))]
+        let length = bounds.as_ref().map_or(0, |bounds| unsafe { bounds.len() });
+        Iter::new_within(bounds, length)
+    }
+
+    /// Iterates over the map's key-value pairs in order, from the start of the map up to and
+    /// including the last key `<= key`, e.g. to iterate backwards from `key`.
+    ///
+    /// See [Self::iter_from].
+    #[inline]
+    pub fn iter_from_back<Q: Ord + ?Sized>(&self, key: &Q) -> Iter<'_, K, V, B>
+    where
+        K: Borrow<Q>,
+    {
+        let bounds = self.node_bounds((Bound::Unbounded, Bound::Included(key)));
+        #[cfg(feature = "order-statistic")]
+        let length = match self.find_indexed(key) {
+            (Find::At { .. }, index) => index + 1,
+            (_, index) => index,
+        };
+        #[cfg(not(feature = "order-statistic"))]
+        let length = bounds.as_ref().map_or(0, |bounds| unsafe { bounds.len() });
+        Iter::new_within(bounds, length)
+    }
+
+    /// Iterates over the map's key-value pairs in order, along with a handle to each, which can be
+    /// kept to access the entry again (or iterate from it) in O(1) instead of searching for its key.
+    ///
+    /// Handles borrow the map, since modifying it may move entries between nodes.
+    #[inline]
+    pub fn entries_with_handles(&self) -> EntriesWithHandles<'_, K, V, B> {
+        EntriesWithHandles {
+            iter: self.iter(),
+            last: self.last_leaf(),
+        }
+    }
+
+    /// Iterates over the `k` entries with the largest keys (or every entry if there are fewer),
+    /// largest first. The same as `iter().rev().take(k)`, but the iterator is exactly `k` long.
+    ///
+    /// Finding the smallest of the `k` entries walks `k / B` leaves back from the end.
+    #[inline]
+    pub fn top_k(&self, k: usize) -> Rev<Iter<'_, K, V, B>> {
+        self.top_k_ascending(k).rev()
+    }
+
+    /// [Self::top_k] but smallest first
+    #[inline]
+    pub(crate) fn top_k_ascending(&self, k: usize) -> Iter<'_, K, V, B> {
+        let k = k.min(self.length);
+        let bounds = match (self.first_leaf(), self.last_leaf()) {
+            (Some(first), Some(last)) if k > 0 => unsafe {
+                let end_index = last.as_ref().len - 1;
+                let (start_node, start_index) =
+                    address_backward(last, end_index, k - 1, (first, 0));
+                Some(NodeBounds {
+                    start_node,
+                    end_node: last,
+                    start_index,
+                    end_index,
+                })
+            },
+            _ => None,
+        };
+        Iter::new_within(bounds, k)
+    }
+
+    /// Iterates over the `k` entries with the smallest keys (or every entry if there are fewer),
+    /// smallest first. The same as `iter().take(k)`, but the iterator is exactly `k` long.
+    ///
+    /// Finding the largest of the `k` entries walks `k / B` leaves from the start.
+    #[inline]
+    pub fn bottom_k(&self, k: usize) -> Iter<'_, K, V, B> {
+        let k = k.min(self.length);
+        let bounds = match (self.first_leaf(), self.last_leaf()) {
+            (Some(first), Some(last)) if k > 0 => unsafe {
+                let limit = (last, last.as_ref().len - 1);
+                let (end_node, end_index) = address_forward(first, 0, k - 1, limit);
+                Some(NodeBounds {
+                    start_node: first,
+                    end_node,
+                    start_index: 0,
+                    end_index,
+                })
+            },
+            _ => None,
+        };
+        Iter::new_within(bounds, k)
+    }
+
+    /// Iterates over the (up to) `k` entries with the largest keys within the given range, largest
+    /// first.
+    ///
+    /// *Panics* if the range's start is after its end, or they are equal and both excluded.
+    #[track_caller]
+    #[inline]
+    pub fn top_k_in<Q: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<Q>,
+        k: usize,
+    ) -> Rev<Range<'_, K, V, B>>
+    where
+        K: Borrow<Q>,
+    {
+        self.try_top_k_in(bounds, k).or_panic("BTreeMap::top_k_in")
+    }
+
+    /// Iterates over the (up to) `k` entries with the largest keys within the given range, largest
+    /// first, or returns an error if the range's start is after its end.
+    #[inline]
+    pub fn try_top_k_in<Q: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<Q>,
+        k: usize,
+    ) -> Result<Rev<Range<'_, K, V, B>>, Error>
+    where
+        K: Borrow<Q>,
+    {
+        self.try_top_k_in_ascending(bounds, k).map(Iterator::rev)
+    }
+
+    /// [Self::try_top_k_in] but smallest first
+    #[inline]
+    pub(crate) fn try_top_k_in_ascending<Q: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<Q>,
+        k: usize,
+    ) -> Result<Range<'_, K, V, B>, Error>
+    where
+        K: Borrow<Q>,
+    {
+        check_range_bounds(&bounds)?;
+        let bounds = self.node_bounds(bounds).filter(|_| k > 0).map(|bounds| {
+            let (end_node, end_index) = bounds.end();
+            let (start_node, start_index) =
+                unsafe { address_backward(end_node, end_index, k - 1, bounds.start()) };
+            NodeBounds {
+                start_node,
+                start_index,
+                ..bounds
+            }
+        });
+        Ok(Range::new_within(bounds))
+    }
+
+    /// Iterates over the (up to) `k` entries with the smallest keys within the given range,
+    /// smallest first.
+    ///
+    /// *Panics* if the range's start is after its end, or they are equal and both excluded.
+    #[track_caller]
+    #[inline]
+    pub fn bottom_k_in<Q: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<Q>,
+        k: usize,
+    ) -> Range<'_, K, V, B>
+    where
+        K: Borrow<Q>,
+    {
+        self.try_bottom_k_in(bounds, k)
+            .or_panic("BTreeMap::bottom_k_in")
+    }
+
+    /// Iterates over the (up to) `k` entries with the smallest keys within the given range,
+    /// smallest first, or returns an error if the range's start is after its end.
+    #[inline]
+    pub fn try_bottom_k_in<Q: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<Q>,
+        k: usize,
+    ) -> Result<Range<'_, K, V, B>, Error>
+    where
+        K: Borrow<Q>,
+    {
+        check_range_bounds(&bounds)?;
+        let bounds = self.node_bounds(bounds).filter(|_| k > 0).map(|bounds| {
+            let (start_node, start_index) = bounds.start();
+            let (end_node, end_index) =
+                unsafe { address_forward(start_node, start_index, k - 1, bounds.end()) };
+            NodeBounds {
+                end_node,
+                end_index,
+                ..bounds
+            }
+        });
+        Ok(Range::new_within(bounds))
+    }
+
     /// Iterates over the map's key-value pairs in order. Values are mutable
     #[inline]
-    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V, B> {
         IterMut::new(self)
     }
 
     /// Iterates over the map's keys in order.
     #[inline]
-    pub fn keys(&self) -> Keys<'_, K, V> {
+    pub fn keys(&self) -> Keys<'_, K, V, B> {
         Keys(self.iter())
     }
 
     /// Iterates over the map's values in order.
     #[inline]
-    pub fn values(&self) -> Values<'_, K, V> {
+    pub fn values(&self) -> Values<'_, K, V, B> {
         Values(self.iter())
     }
 
     /// Iterates over the map's values in order. Values are mutable
     #[inline]
-    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V, B> {
         ValuesMut(self.iter_mut())
     }
 
+    /// Consumes the map, iterating over its keys in order.
+    #[inline]
+    pub fn into_keys(self) -> IntoKeys<'store, K, V, B> {
+        IntoKeys(self.into_iter())
+    }
+
+    /// Consumes the map, iterating over its values in order.
+    #[inline]
+    pub fn into_values(self) -> IntoValues<'store, K, V, B> {
+        IntoValues(self.into_iter())
+    }
+
     /// Iterates over the map's key-value pairs in order, within the given range.
+    ///
+    /// *Panics* if the range's start is after its end, or they are equal and both excluded.
+    #[track_caller]
     #[inline]
-    pub fn range<Q: Ord + ?Sized>(&self, bounds: impl RangeBounds<Q>) -> Range<'_, K, V>
+    pub fn range<Q: Ord + ?Sized>(&self, bounds: impl RangeBounds<Q>) -> Range<'_, K, V, B>
     where
         K: Borrow<Q>,
     {
-        Range::new(self, bounds)
+        self.try_range(bounds).or_panic("BTreeMap::range")
     }
 
-    /// Iterates over the map's key-value pairs in order, within the given range.. Values are mutable
+    /// Iterates over the map's key-value pairs in order, within the given range, or returns an error
+    /// if the range's start is after its end.
     #[inline]
-    pub fn range_mut<Q: Ord + ?Sized>(&mut self, bounds: impl RangeBounds<Q>) -> RangeMut<'_, K, V>
+    pub fn try_range<Q: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<Q>,
+    ) -> Result<Range<'_, K, V, B>, Error>
     where
         K: Borrow<Q>,
     {
-        RangeMut::new(self, bounds)
+        check_range_bounds(&bounds)?;
+        Ok(Range::new(self, bounds))
     }
 
-    /// Iterates over the map's keys in order, within the given range.
+    /// Iterates over every `step`-th key-value pair within the given range, starting with the first.
+    /// The same as `range(bounds).step_by(step)`, but each pair is found by descending the tree by
+    /// rank, so it takes `O(log n)` per pair instead of `O(step)`.
+    ///
+    /// *Panics* if the range's start is after its end, or they are equal and both excluded, or if
+    /// `step` is 0.
+    #[cfg(feature = "order-statistic")]
+    #[track_caller]
     #[inline]
-    pub fn range_keys<Q: Ord + ?Sized>(
+    pub fn range_step<Q: Ord + ?Sized>(
         &self,
         bounds: impl RangeBounds<Q>,
-    ) -> impl Iterator<Item = &K> + '_
+        step: usize,
+    ) -> RangeStep<'_, K, V, B>
     where
         K: Borrow<Q>,
     {
-        self.range(bounds).map(|(k, _)| k)
+        self.try_range_step(bounds, step)
+            .or_panic("BTreeMap::range_step")
     }
 
-    /// Iterates over the map's values in order, within the given range.
+    /// Iterates over every `step`-th key-value pair within the given range (see
+    /// [Self::range_step]), or returns an error if the range's start is after its end or `step` is
+    /// 0.
+    #[cfg(feature = "order-statistic")]
     #[inline]
-    pub fn range_values<Q: Ord + ?Sized>(
+    pub fn try_range_step<Q: Ord + ?Sized>(
         &self,
         bounds: impl RangeBounds<Q>,
-    ) -> impl Iterator<Item = &V> + '_
+        step: usize,
+    ) -> Result<RangeStep<'_, K, V, B>, Error>
     where
         K: Borrow<Q>,
     {
-        self.range(bounds).map(|(_, v)| v)
+        check_range_bounds(&bounds)?;
+        if step == 0 {
+            return Err(Error::InvalidStep);
+        }
+        let start = self.bound_index(bounds.start_bound(), false);
+        let end = self.bound_index(bounds.end_bound(), true);
+        Ok(RangeStep::new(self, start, end.max(start), step))
     }
 
-    /// Iterates over the map's values in order, within the given range. Values are mutable
+    /// Iterates over the map's key-value pairs in order, within the given range.. Values are mutable
+    ///
+    /// *Panics* if the range's start is after its end, or they are equal and both excluded.
+    #[track_caller]
     #[inline]
-    pub fn range_values_mut<Q: Ord + ?Sized>(
+    pub fn range_mut<Q: Ord + ?Sized>(
         &mut self,
         bounds: impl RangeBounds<Q>,
-    ) -> impl Iterator<Item = &mut V> + '_
+    ) -> RangeMut<'_, K, V, B>
     where
         K: Borrow<Q>,
     {
-        self.range_mut(bounds).map(|(_, v)| v)
+        self.try_range_mut(bounds).or_panic("BTreeMap::range_mut")
     }
 
-    // /// Drains elements.
-    // #[inline]
-    // pub fn drain(&mut self) -> Drain<'_, K, V> {
-    //     Drain::new(self)
-    // }
-
-    // /// Removes elements which don't pass the predicate
-    // #[inline]
-    // pub fn retain<F: FnMut(&K, &mut V) -> bool>(&mut self, mut f: F) {
-    //     self.drain_filter(|k, v| !f(k, v));
-    // }
-
-    // /// Drains elements according to the filter.
-    // #[inline]
-    // pub fn drain_filter<F: FnMut(&K, &mut V) -> bool>(&mut self, filter: F) -> DrainFilter<'_, K, V, F> {
-    //     DrainFilter::new(self, filter)
-    // }
-
-    // /// Drains elements within the given range
-    // #[inline]
-    // pub fn drain_range<Q: Ord + ?Sized>(&mut self, bounds: impl RangeBounds<Q>) -> DrainRange<'_, K, V> where K: Borrow<Q> {
-    //     DrainRange::new(self, bounds)
-    // }
-
-    // /// Removes elements within the range which don't pass the predicate
-    // #[inline]
-    // pub fn retain_range<Q: Ord, F: FnMut(&K, &mut V) -> bool>(&mut self, bounds: impl RangeBounds<Q>, mut f: F) where K: Borrow<Q> {
-    //     self.drain_filter_range(bounds, |k, v| !f(k, v));
-    // }
-
-    // /// Drains elements within the given range according to the filter
-    // #[inline]
-    // pub fn drain_filter_range<Q: Ord, F: FnMut(&K, &mut V) -> bool>(&mut self, bounds: impl RangeBounds<Q>, mut filter: F) -> DrainFilterRange<'_, K, V, F> where K: Borrow<Q> {
-    //     DrainFilterRange::new(self, bounds, filter)
-    // }
-    // endregion
+    /// Iterates over the map's key-value pairs in order, within the given range, or returns an error
+    /// if the range's start is after its end. Values are mutable
+    #[inline]
+    pub fn try_range_mut<Q: Ord + ?Sized>(
+        &mut self,
+        bounds: impl RangeBounds<Q>,
+    ) -> Result<RangeMut<'_, K, V, B>, Error>
+    where
+        K: Borrow<Q>,
+    {
+        check_range_bounds(&bounds)?;
+        Ok(RangeMut::new(self, bounds))
+    }
 
-    // region b-tree misc
+    /// Returns a cursor at the gap before the first key above the bound: at or after an
+    /// [included](Bound::Included) key, after an [excluded](Bound::Excluded) key, or before the
+    /// first key if [unbounded](Bound::Unbounded).
     #[inline]
-    fn first_leaf(&self) -> Option<NodePtr<K, V>> {
-        let mut node = self.root?;
-        for _ in 0..self.height {
-            node = unsafe { node.as_ref().edge(0) };
-        }
-        Some(node)
+    pub fn lower_bound<Q: Ord + ?Sized>(&self, bound: Bound<&Q>) -> Cursor<'_, K, V, B>
+    where
+        K: Borrow<Q>,
+    {
+        Cursor::new(self.lower_bound_gap(bound))
     }
 
+    /// Returns a mutable cursor at the gap before the first key above the bound. See
+    /// [BTreeMap::lower_bound].
     #[inline]
-    fn last_leaf(&self) -> Option<NodePtr<K, V>> {
-        let mut node = self.root?;
-        for _ in 0..self.height {
-            node = unsafe { node.as_ref().edge(node.as_ref().len) };
-        }
-        Some(node)
+    pub fn lower_bound_mut<Q: Ord + ?Sized>(
+        &mut self,
+        bound: Bound<&Q>,
+    ) -> CursorMut<'_, 'store, K, V, B>
+    where
+        K: Borrow<Q>,
+    {
+        let gap = self.lower_bound_gap(bound);
+        CursorMut { map: self, gap }
     }
 
+    /// Returns a cursor at the gap after the last key below the bound: at or before an
+    /// [included](Bound::Included) key, before an [excluded](Bound::Excluded) key, or after the
+    /// last key if [unbounded](Bound::Unbounded).
     #[inline]
-    fn find<Q: Ord + ?Sized>(&self, key: &Q) -> Find<K, V>
+    pub fn upper_bound<Q: Ord + ?Sized>(&self, bound: Bound<&Q>) -> Cursor<'_, K, V, B>
     where
         K: Borrow<Q>,
     {
-        let Some(mut node) = self.root else {
-            return Find::NoRoot
-        };
-        let mut height = self.height;
-        loop {
-            match unsafe { node.as_ref().keys() }.binary_search_by(|k| k.borrow().cmp(key)) {
-                Ok(idx) => {
-                    let idx = idx as u16;
-                    if height == 0 {
-                        break Find::At { node, idx };
+        Cursor::new(self.upper_bound_gap(bound))
+    }
+
+    /// Returns a mutable cursor at the gap after the last key below the bound. See
+    /// [BTreeMap::upper_bound].
+    #[inline]
+    pub fn upper_bound_mut<Q: Ord + ?Sized>(
+        &mut self,
+        bound: Bound<&Q>,
+    ) -> CursorMut<'_, 'store, K, V, B>
+    where
+        K: Borrow<Q>,
+    {
+        let gap = self.upper_bound_gap(bound);
+        CursorMut { map: self, gap }
+    }
+
+    /// Iterates over the map's keys in order, within the given range.
+    #[track_caller]
+    #[inline]
+    pub fn range_keys<Q: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<Q>,
+    ) -> impl Iterator<Item = &K> + '_
+    where
+        K: Borrow<Q>,
+    {
+        self.range(bounds).map(|(k, _)| k)
+    }
+
+    /// Iterates over the map's values in order, within the given range.
+    #[track_caller]
+    #[inline]
+    pub fn range_values<Q: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<Q>,
+    ) -> impl Iterator<Item = &V> + '_
+    where
+        K: Borrow<Q>,
+    {
+        self.range(bounds).map(|(_, v)| v)
+    }
+
+    /// Iterates over the map's values in order, within the given range. Values are mutable
+    #[track_caller]
+    #[inline]
+    pub fn range_values_mut<Q: Ord + ?Sized>(
+        &mut self,
+        bounds: impl RangeBounds<Q>,
+    ) -> impl Iterator<Item = &mut V> + '_
+    where
+        K: Borrow<Q>,
+    {
+        self.range_mut(bounds).map(|(_, v)| v)
+    }
+
+    /// Iterates over the map's key-value pairs in order, whose keys start with `prefix`. See
+    /// [prefix_range].
+    #[inline]
+    pub fn iter_prefix<P: Prefix + ?Sized>(&self, prefix: &P) -> Range<'_, K, V, B>
+    where
+        K: Borrow<P>,
+    {
+        // A prefix range's start is never after its end
+        Range::new(self, prefix_range(prefix))
+    }
+
+    /// Iterates over the map's key-value pairs in order, whose keys start with `prefix`. Values are
+    /// mutable
+    #[inline]
+    pub fn iter_prefix_mut<P: Prefix + ?Sized>(&mut self, prefix: &P) -> RangeMut<'_, K, V, B>
+    where
+        K: Borrow<P>,
+    {
+        RangeMut::new(self, prefix_range(prefix))
+    }
+
+    /// Iterates over the map's entries in groups of consecutive keys with the same `prefix(key)`
+    /// (e.g. the date of a timestamp, or a tuple's first field), yielding each prefix and the range
+    /// of its entries.
+    ///
+    /// `prefix` must be monotonic (`a <= b` implies `prefix(a) <= prefix(b)`), so that each group's
+    /// end can be found by searching its first leaf or descending the tree, calling `prefix`
+    /// `O(log n)` times per group instead of once per entry. Otherwise groups may be split or
+    /// merged, but every entry is still in exactly one group.
+    #[inline]
+    pub fn group_by_key_prefix<P: Ord, F: FnMut(&K) -> P>(
+        &self,
+        prefix: F,
+    ) -> GroupBy<'_, K, V, F, B>
+    where
+        K: Ord,
+    {
+        GroupBy::new(self, prefix)
+    }
+
+    /// Rebalances every underfull node, in one pass over the tree.
+    ///
+    /// Only needed if the store's [RebalancePolicy](crate::RebalancePolicy) is
+    /// [relaxed](crate::RebalancePolicy::relaxed), so that removals leave underfull nodes. Otherwise
+    /// the tree is always balanced, so this only walks it.
+    #[inline]
+    pub fn repair(&mut self)
+    where
+        K: Clone,
+    {
+        let min_len = self.store.rebalance.min_len(B);
+        let mut height = 0;
+        unsafe {
+            // Fix each level from the bottom up, since merging nodes removes keys from their parents
+            'levels: while height < self.height {
+                let mut node = self.root.unwrap();
+                for _ in height..self.height {
+                    node = node.as_ref().edge(0);
+                }
+                let mut next = Some(node);
+                while let Some(node) = next {
+                    if (node.as_ref().len as usize) < min_len && node.as_ref().parent().is_some() {
+                        // Check the node again afterwards, since it may have merged with a sibling
+                        // which was also underfull, or (if it had no sibling) become the root
+                        match node.as_ref().parent() {
+                            Some((parent, _)) if parent.as_ref().len == 0 => {
+                                let old_height = self.height;
+                                self.ensure_sibling(node, height, min_len);
+                                if self.height < old_height {
+                                    // The root collapsed, which frees the node too if it has no
+                                    // keys, so start the level over
+                                    continue 'levels;
+                                }
+                            }
+                            _ => {
+                                self.rebalance_underfull(node, height, min_len);
+                            }
+                        }
+                        continue;
+                    }
+                    next = next_at_height(node);
+                }
+                height += 1;
+            }
+            self.collapse_root();
+        }
+    }
+
+    /// Generation of the root node, which changes if it's freed, or 0 if the map is empty. Doesn't
+    /// read the root, so it may be freed.
+    #[cfg(feature = "generations")]
+    #[inline]
+    pub(crate) fn root_generation(&self) -> u64 {
+        self.root.map_or(0, |root| self.store.generation(root))
+    }
+
+    /// Iterates over summaries of the map's nodes (*not* entries) in pre-order, e.g. to analyze or
+    /// serialize the tree's structure.
+    #[inline]
+    pub fn nodes(&self) -> Nodes<'_, K, V, B> {
+        Nodes(NodeIter::new(self.root, self.height))
+    }
+
+    /// Estimated bytes used by the map: its nodes in the store, plus the map itself. Doesn't
+    /// include heap memory owned by keys and values, or free slots in the store (see
+    /// [BTreeStore::stats]).
+    ///
+    /// Counts the nodes, so this is O(n / [BTreeStore::NODE_CAPACITY]).
+    #[inline]
+    pub fn memory_usage(&self) -> usize {
+        size_of::<Self>() + self.nodes().count() * BTreeStore::<K, V, B>::NODE_SIZE
+    }
+    // endregion
+
+    // region b-tree misc
+    /// Builds a map from entries whose keys are strictly increasing, without searching or
+    /// rebalancing. Nodes are filled completely, except the last node on each level may share
+    /// entries with its predecessor so that both stay above the minimum.
+    ///
+    /// If the keys aren't strictly increasing, the map will be invalid (but not memory unsafe).
+    #[inline]
+    pub(crate) fn bulk_load_in(
+        store: &'store BTreeStore<K, V, B>,
+        entries: impl IntoIterator<Item = (K, V)>,
+    ) -> Self
+    where
+        K: Clone,
+    {
+        Self::bulk_load_reusing(store, entries, &mut Vec::new())
+    }
+
+    /// [BTreeMap::bulk_load_in], but takes nodes from `free` (whose contents must already be
+    /// dropped) before allocating new ones.
+    fn bulk_load_reusing(
+        store: &'store BTreeStore<K, V, B>,
+        entries: impl IntoIterator<Item = (K, V)>,
+        free: &mut Vec<NodePtr<K, V, B>>,
+    ) -> Self
+    where
+        K: Clone,
+    {
+        let mut alloc = |node| match free.pop() {
+            None => store.alloc(node),
+            Some(mut reused) => {
+                // Nodes don't drop their contents, so this only overwrites them
+                unsafe { *reused.as_mut() = node };
+                #[cfg(feature = "generations")]
+                store.restamp(reused);
+                reused
+            }
+        };
+        let mut map = Self::new_in(store);
+
+        // Fill leaves
+        let mut level = Vec::<NodePtr<K, V, B>>::new();
+        for (key, val) in entries {
+            let mut leaf = match level.last() {
+                Some(&leaf) if (unsafe { leaf.as_ref() }.len as usize) < B => leaf,
+                prev => unsafe {
+                    let mut leaf = alloc(Node::leaf());
+                    if let Some(&(mut prev)) = prev {
+                        prev.as_mut().set_next(Some(leaf));
+                        leaf.as_mut().set_prev(Some(prev));
                     }
-                    height -= 1;
-                    node = unsafe { node.as_ref().edge(idx + 1) }
+                    level.push(leaf);
+                    leaf
+                },
+            };
+            unsafe {
+                let len = leaf.as_ref().len;
+                leaf.as_mut().insert_val(len, key, val);
+            }
+            map.length += 1;
+        }
+        // The last leaf may be underfull, but the one before is full, so we can move entries
+        if let [.., mut prev, mut last] = level[..] {
+            unsafe {
+                while (last.as_ref().len as usize) < B / 2 {
+                    let (key, val) = prev.as_mut().remove_val(prev.as_ref().len - 1);
+                    last.as_mut().insert_val(0, key, val);
+                }
+            }
+        }
+
+        map.build_internal_levels(level, &mut alloc);
+        map
+    }
+
+    /// Builds internal levels above `level`, the map's leaves in order, until there's only a root.
+    fn build_internal_levels(
+        &mut self,
+        mut level: Vec<NodePtr<K, V, B>>,
+        alloc: &mut impl FnMut(Node<K, V, B>) -> NodePtr<K, V, B>,
+    ) where
+        K: Clone,
+    {
+        while level.len() > 1 {
+            let mut parents = Vec::new();
+            let mut start = 0;
+            while start < level.len() {
+                let mut end = (start + B + 1).min(level.len());
+                // If a full node would leave the last node underfull, split the rest evenly
+                let remaining = level.len() - end;
+                if remaining > 0 && remaining < B / 2 + 1 {
+                    end = start + (level.len() - start) / 2;
                 }
-                Err(idx) => {
+
+                let mut parent = alloc(Node::internal());
+                for (idx, &(mut child)) in level[start..end].iter().enumerate() {
                     let idx = idx as u16;
-                    if height == 0 {
-                        break Find::Before { node, idx };
+                    unsafe {
+                        child.as_mut().set_parent(parent, idx);
+                        match idx.checked_sub(1) {
+                            None => {
+                                parent.as_mut().d.internal_mut().edges[0].write(child);
+                            }
+                            Some(key_idx) => {
+                                let key = first_key(child, self.height).clone();
+                                parent.as_mut().insert_edge(key_idx, true, key, child);
+                            }
+                        }
                     }
-                    height -= 1;
-                    node = unsafe { node.as_ref().edge(idx) }
                 }
+                unsafe { recompute_size(parent, self.height + 1) };
+                parents.push(parent);
+                start = end;
+            }
+            level = parents;
+            self.height += 1;
+        }
+        self.root = level.pop();
+        self.cache_first_last();
+    }
+
+    /// Compares the key sequences of `self` and `other` lexicographically, passing `cmp` runs of
+    /// keys of the same length which are each in one leaf. Stops once both are at the same entry
+    /// of the same leaf, after which the keys are the same.
+    #[inline]
+    fn cmp_key_runs<V2>(
+        &self,
+        other: &BTreeMap<K, V2, B>,
+        mut cmp: impl FnMut(&[K], &[K]) -> Ordering,
+    ) -> Ordering {
+        let mut a = self.first_leaf().map(|node| (node, 0));
+        let mut b = other.first_leaf().map(|node| (node, 0));
+        loop {
+            let ((a_node, a_idx), (b_node, b_idx)) = match (a, b) {
+                (None, None) => break Ordering::Equal,
+                (None, Some(_)) => break Ordering::Less,
+                (Some(_), None) => break Ordering::Greater,
+                (Some(a), Some(b)) => (a, b),
+            };
+            unsafe {
+                let shared = a_node.as_ptr().cast::<()>() == b_node.as_ptr().cast::<()>();
+                if shared && a_idx == b_idx {
+                    break Ordering::Equal;
+                }
+                let a_keys = &a_node.as_ref().keys()[a_idx as usize..];
+                let b_keys = &b_node.as_ref().keys()[b_idx as usize..];
+                let len = a_keys.len().min(b_keys.len());
+                match cmp(&a_keys[..len], &b_keys[..len]) {
+                    Ordering::Equal => {}
+                    ordering => break ordering,
+                }
+                a = normalize_address(a_node, a_idx + len as u16);
+                b = normalize_address(b_node, b_idx + len as u16);
+            }
+        }
+    }
+
+    #[inline]
+    #[track_caller]
+    fn first_leaf(&self) -> Option<NodePtr<K, V, B>> {
+        self.first.inspect(|&node| self.check_node(node))
+    }
+
+    #[inline]
+    #[track_caller]
+    fn last_leaf(&self) -> Option<NodePtr<K, V, B>> {
+        self.last.inspect(|&node| self.check_node(node))
+    }
+
+    /// Under the `generations` feature, panics if the map is used with a store other than the one
+    /// it was created in, or `node` isn't one of its store's live nodes (e.g. it was freed by
+    /// another tree's `clear`), instead of reading it. Lookups and iterators check the node they
+    /// start from.
+    #[inline]
+    #[track_caller]
+    #[allow(unused_variables)]
+    fn check_node(&self, node: NodePtr<K, V, B>) {
+        #[cfg(feature = "generations")]
+        {
+            assert_eq!(
+                self.store_id,
+                self.store.id(),
+                "b-tree used with a store other than its own"
+            );
+            assert_ne!(
+                self.store.generation(node),
+                0,
+                "b-tree's nodes were freed (e.g. by another tree's `clear`) or are in another store"
+            );
+        }
+    }
+
+    /// Whether both maps are in the same store. Under the `generations` feature, compares the
+    /// stores' IDs, which unlike their addresses aren't reused by later stores.
+    #[inline]
+    fn same_store(&self, other: &Self) -> bool {
+        #[cfg(feature = "generations")]
+        {
+            self.store_id == other.store_id
+        }
+        #[cfg(not(feature = "generations"))]
+        {
+            std::ptr::eq(self.store, other.store)
+        }
+    }
+
+    /// Sets the cached first and last leaves by descending the tree. Only needed when the tree is
+    /// built directly; insertion and removal keep the cache up-to-date themselves.
+    #[inline]
+    fn cache_first_last(&mut self) {
+        self.first = self.root.map(|mut node| {
+            for _ in 0..self.height {
+                node = unsafe { node.as_ref().edge(0) };
+            }
+            node
+        });
+        self.last = self.root.map(|mut node| {
+            for _ in 0..self.height {
+                node = unsafe { node.as_ref().edge(node.as_ref().len) };
+            }
+            node
+        });
+    }
+
+    #[inline]
+    fn find<Q: Ord + ?Sized>(&self, key: &Q) -> Find<K, V, B>
+    where
+        K: Borrow<Q>,
+    {
+        let Some((node, height)) = self.search_start(key) else {
+            return Find::NoRoot
+        };
+        self.descend(node, height, key)
+    }
+
+    /// Searches for `key` in the subtree of `node`, which is at `height`.
+    #[inline]
+    fn descend<Q: Ord + ?Sized>(
+        &self,
+        mut node: NodePtr<K, V, B>,
+        mut height: usize,
+        key: &Q,
+    ) -> Find<K, V, B>
+    where
+        K: Borrow<Q>,
+    {
+        loop {
+            if height > 0 {
+                // Loads while the keys are searched, so reading the child's edge doesn't stall
+                unsafe { node.as_ref().prefetch_edges() };
+            }
+            let found = unsafe { node.as_ref().search(key, self.store.search) };
+            if height == 0 {
+                break match found {
+                    Ok(idx) => Find::At {
+                        node,
+                        idx: idx as u16,
+                    },
+                    Err(idx) => Find::Before {
+                        node,
+                        idx: idx as u16,
+                    },
+                };
+            }
+            let edge_idx = match found {
+                Ok(idx) => idx as u16 + 1,
+                Err(idx) => idx as u16,
+            };
+            node = unsafe { node.as_ref().edge(edge_idx) };
+            // Issued as soon as the child is known, so its keys' misses overlap each other
+            unsafe { node.as_ref() }.prefetch_keys();
+            height -= 1;
+        }
+    }
+
+    /// [Self::find], and moves the finger to the found leaf.
+    #[inline]
+    fn find_mut<Q: Ord + ?Sized>(&mut self, key: &Q) -> Find<K, V, B>
+    where
+        K: Borrow<Q>,
+    {
+        let find = self.find(key);
+        if let Find::Before { node, .. } | Find::At { node, .. } = find {
+            self.finger = Some(node);
+        }
+        find
+    }
+
+    /// The gap before the first key above the bound, as the address of the entry after it (which
+    /// may be one past the end of its leaf)
+    #[inline]
+    fn lower_bound_gap<Q: Ord + ?Sized>(&self, bound: Bound<&Q>) -> Option<(NodePtr<K, V, B>, u16)>
+    where
+        K: Borrow<Q>,
+    {
+        let (key, excluded) = match bound {
+            Bound::Included(key) => (key, false),
+            Bound::Excluded(key) => (key, true),
+            Bound::Unbounded => return self.first_leaf().map(|node| (node, 0)),
+        };
+        match self.find(key) {
+            Find::NoRoot => None,
+            Find::Before { node, idx } => Some((node, idx)),
+            Find::At { node, idx } => Some((node, idx + excluded as u16)),
+        }
+    }
+
+    /// The gap after the last key below the bound, as the address of the entry after it (which
+    /// may be one past the end of its leaf)
+    #[inline]
+    fn upper_bound_gap<Q: Ord + ?Sized>(&self, bound: Bound<&Q>) -> Option<(NodePtr<K, V, B>, u16)>
+    where
+        K: Borrow<Q>,
+    {
+        let (key, excluded) = match bound {
+            Bound::Included(key) => (key, false),
+            Bound::Excluded(key) => (key, true),
+            Bound::Unbounded => {
+                return self
+                    .last_leaf()
+                    .map(|node| (node, unsafe { node.as_ref().len }))
+            }
+        };
+        match self.find(key) {
+            Find::NoRoot => None,
+            Find::Before { node, idx } => Some((node, idx)),
+            Find::At { node, idx } => Some((node, idx + !excluded as u16)),
+        }
+    }
+
+    /// Returns the lowest node (and its height) whose subtree would contain `key`, starting from the
+    /// finger and walking up until `key` is between the separators around the node. Returns the
+    /// root if there is no finger, and `None` if the tree is empty.
+    ///
+    /// Keys at or after the last leaf's first key start at the last leaf, so appending in order
+    /// (e.g. timestamps or increasing IDs) doesn't descend the tree. The finger can't catch these,
+    /// since the last leaf has no separator after it, so its walk would reach the root.
+    #[inline]
+    fn search_start<Q: Ord + ?Sized>(&self, key: &Q) -> Option<(NodePtr<K, V, B>, usize)>
+    where
+        K: Borrow<Q>,
+    {
+        let root = self.root?;
+        if let Some(last) = self.last {
+            self.check_node(last);
+            let last_ref = unsafe { last.as_ref() };
+            if last_ref.len > 0 && unsafe { last_ref.key(0) }.borrow() <= key {
+                return Some((last, 0));
+            }
+        }
+        let Some(finger) = self.finger else {
+            self.check_node(root);
+            return Some((root, self.height))
+        };
+        self.check_node(finger);
+        Some(Self::walk_up(finger, key))
+    }
+
+    /// Returns the lowest ancestor of `leaf` (or `leaf` itself) whose subtree would contain `key`,
+    /// and its height.
+    #[inline]
+    fn walk_up<Q: Ord + ?Sized>(leaf: NodePtr<K, V, B>, key: &Q) -> (NodePtr<K, V, B>, usize)
+    where
+        K: Borrow<Q>,
+    {
+        let mut node = leaf;
+        let mut height = 0;
+        // Whether `key` is known to be after the separator before `node` / before the separator
+        // after `node`. Once known these stay known as we walk up, since each ancestor's bounds are
+        // looser than its child's.
+        let mut after_start = false;
+        let mut before_end = false;
+        while let Some((parent, idx)) = unsafe { node.as_ref().parent() } {
+            let parent_ref = unsafe { parent.as_ref() };
+            if !after_start && idx > 0 {
+                after_start = unsafe { parent_ref.key(idx - 1) }.borrow() <= key;
+                // Otherwise `key` is before `node`, so it's before the end of `parent`
+                before_end |= !after_start;
+            }
+            if !before_end && idx < parent_ref.len {
+                before_end = key < unsafe { parent_ref.key(idx) }.borrow();
+                after_start |= !before_end;
+            }
+            if after_start && before_end {
+                break;
+            }
+            node = parent;
+            height += 1;
+        }
+        (node, height)
+    }
+
+    /// [Self::find], but also returns the # of entries before the found address.
+    #[cfg(feature = "order-statistic")]
+    #[inline]
+    fn find_indexed<Q: Ord + ?Sized>(&self, key: &Q) -> (Find<K, V, B>, usize)
+    where
+        K: Borrow<Q>,
+    {
+        let Some(mut node) = self.root else {
+            return (Find::NoRoot, 0)
+        };
+        self.check_node(node);
+        let mut height = self.height;
+        let mut index = 0;
+        loop {
+            let node_ref = unsafe { node.as_ref() };
+            let (idx, found) = match unsafe { node_ref.search(key, self.store.search) } {
+                Ok(idx) => (idx as u16, true),
+                Err(idx) => (idx as u16, false),
+            };
+            if height == 0 {
+                index += idx as usize;
+                break match found {
+                    true => (Find::At { node, idx }, index),
+                    false => (Find::Before { node, idx }, index),
+                };
+            }
+            // Equal keys are the first key of the right subtree
+            let edge_idx = idx + found as u16;
+            height -= 1;
+            index += unsafe { node_ref.edges() }[..edge_idx as usize]
+                .iter()
+                .map(|child| unsafe { child.as_ref().subtree_len(height) })
+                .sum::<usize>();
+            node = unsafe { node_ref.edge(edge_idx) };
+        }
+    }
+
+    /// Number of keys before `bound`, as a range's start (`is_end == false`) or end
+    #[cfg(feature = "order-statistic")]
+    #[inline]
+    fn bound_index<Q: Ord + ?Sized>(&self, bound: Bound<&Q>, is_end: bool) -> usize
+    where
+        K: Borrow<Q>,
+    {
+        match bound {
+            Bound::Unbounded if is_end => self.length,
+            Bound::Unbounded => 0,
+            Bound::Included(key) | Bound::Excluded(key) => {
+                let (find, index) = self.find_indexed(key);
+                // An equal key comes before an included end or excluded start
+                let equal_before = is_end == matches!(bound, Bound::Included(_));
+                index + (matches!(find, Find::At { .. }) && equal_before) as usize
             }
         }
     }
 
     #[inline]
-    fn node_bounds<Q: Ord + ?Sized>(&self, bounds: impl RangeBounds<Q>) -> Option<NodeBounds<K, V>>
+    fn node_bounds<Q: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<Q>,
+    ) -> Option<NodeBounds<K, V, B>>
     where
         K: Borrow<Q>,
     {
@@ -803,20 +3194,49 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
             root.insert_val(0, key, val);
         }
         self.root = Some(self.store.alloc(root));
+        self.first = self.root;
+        self.last = self.root;
         self.length += 1;
     }
 
+    /// Inserts the entry before the address (or as the root if `None`) from [Self::find], and
+    /// returns where it ends up, without searching again.
     #[inline]
-    unsafe fn insert_before(&mut self, mut key: K, val: V, mut node: NodePtr<K, V>, idx: u16)
+    unsafe fn insert_at(
+        &mut self,
+        key: K,
+        val: V,
+        address: Option<(NodePtr<K, V, B>, u16)>,
+    ) -> (NodePtr<K, V, B>, u16)
     where
         K: Clone,
     {
-        if (node.as_ref().len as usize) < M {
-            node.as_mut().insert_val(idx, key, val);
-        } else {
-            // Rebalance (overflow)
-
-            // First split
+        let Some((node, idx)) = address else {
+            self.insert_root(key, val);
+            return (self.root.unwrap(), 0);
+        };
+        self.insert_before(key, val, node, idx);
+        // If the leaf split, it kept the entries before its new length and the next leaf has the
+        // rest
+        let len = node.as_ref().len;
+        match idx < len {
+            true => (node, idx),
+            false => (node.as_ref().next().unwrap(), idx - len),
+        }
+    }
+
+    #[inline]
+    unsafe fn insert_before(&mut self, mut key: K, val: V, mut node: NodePtr<K, V, B>, idx: u16)
+    where
+        K: Clone,
+    {
+        if (node.as_ref().len as usize) < B {
+            node.as_mut().insert_val(idx, key, val);
+            adjust_ancestor_sizes(node, 1);
+        } else {
+            // Rebalance (overflow)
+
+            // First split
             // `key` gets replaced with the "split" (median) key, and `node` gets replaced with the
             // left node
             let mut right = self
@@ -824,88 +3244,189 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
                 .alloc(node.as_mut().split_leaf(idx, &mut key, val));
             node.as_mut().set_next(Some(right));
             right.as_mut().set_prev(Some(node));
-            if let Some(mut right_next) = right.as_ref().next() {
-                right_next.as_mut().set_prev(Some(right));
-            }
-
-            loop {
-                let Some((mut parent, idx)) = node.as_ref().parent() else {
-                    // At root: create a new root with the split key, left, and right nodes
-                    self.height += 1;
-                    let mut left = node;
-                    let mut root = self.store.alloc(Node::internal());
-                    left.as_mut().set_parent(root, 0);
-                    // Has to be before insert_edge, otherwise we try to modify a deallocated edge,
-                    // because the tree has 0 edges but insert_edge always expects at least 1.
-                    // Furthermore, we need correct parent_idx, which is why we set both to 0.
-                    right.as_mut().set_parent(root, 0);
-                    root.as_mut().set_last_edge(right);
-                    root.as_mut().insert_edge(0, false, key, left);
-                    self.root = Some(root);
-                    break
-                };
-
-                // Insert split key and right into parent. left is already in parent at idx, so
-                // insert key at idx and right at idx + 1. We must handle the case where the parent
-                // overflows too...
-                right.as_mut().set_parent(parent, idx + 1);
-                if (parent.as_ref().len as usize) < M {
-                    // The parent won't overflow, actually insert into parent
-                    parent.as_mut().insert_edge(idx, true, key, right);
-                    break;
-                }
-                // The parent will overflow too, so we split the parent when inserting idx/key/right
-                // split_internal will replace key with the split key and node with the left node,
-                // and we re-assign right to the right node (we don't just pass as a &mut like we do
-                // with key because it must be allocated). Then insert the new internal parent-right
-                // node in its parent, and so on, until we either find a suitable parent or reach
-                // the root.
-                node = parent;
-                right = self
-                    .store
-                    .alloc(node.as_mut().split_internal(idx, &mut key, right));
-                for right_child in right.as_mut().edges_mut() {
-                    right_child.as_mut().parent = Some(right);
-                }
+            match right.as_ref().next() {
+                None => self.last = Some(right),
+                Some(mut right_next) => right_next.as_mut().set_prev(Some(right)),
             }
+
+            self.insert_edge_after(node, key, right, 0, 1);
         }
         self.length += 1;
     }
 
+    /// Inserts an entry whose key is greater than every key in the map, without searching. The
+    /// last leaf may be left underfull, so call [Self::fix_border] on the right once done pushing.
     #[inline]
-    unsafe fn post_removal(&mut self, mut node: NodePtr<K, V>)
+    unsafe fn push_last(&mut self, key: K, val: V)
     where
         K: Clone,
     {
-        self.length -= 1;
+        let Some(mut last) = self.last else {
+            return self.insert_root(key, val)
+        };
+        if (last.as_ref().len as usize) < B {
+            let len = last.as_ref().len;
+            last.as_mut().insert_val(len, key, val);
+            adjust_ancestor_sizes(last, 1);
+        } else {
+            let mut leaf = self.store.alloc(Node::leaf());
+            let split_key = key.clone();
+            leaf.as_mut().insert_val(0, key, val);
+            last.as_mut().set_next(Some(leaf));
+            leaf.as_mut().set_prev(Some(last));
+            self.last = Some(leaf);
+            self.insert_edge_after(last, split_key, leaf, 0, 1);
+        }
+        self.length += 1;
+    }
 
-        // Rebalance (underflow)
-        let mut is_leaf = true;
-        while (node.as_ref().len as usize) < M / 2 {
+    /// Inserts `right` after `node` in `node`'s parent, with `key` separating them, splitting
+    /// ancestors which overflow (or creating a new root if `node` is the root). `node` and `right`
+    /// have the given height, and `added` is the # of entries the insertion adds to the ancestors.
+    ///
+    /// Doesn't update the length or leaf links.
+    #[inline]
+    unsafe fn insert_edge_after(
+        &mut self,
+        mut node: NodePtr<K, V, B>,
+        mut key: K,
+        mut right: NodePtr<K, V, B>,
+        mut height: usize,
+        added: usize,
+    ) {
+        loop {
             let Some((mut parent, idx)) = node.as_ref().parent() else {
-                // Node is root. Root node can have less than M < 2 children
-                if is_leaf {
+                // At root: create a new root with the split key, left, and right nodes
+                self.height = height + 1;
+                let mut left = node;
+                let mut root = self.store.alloc(Node::internal());
+                left.as_mut().set_parent(root, 0);
+                // Has to be before insert_edge, otherwise we try to modify a deallocated edge,
+                // because the tree has 0 edges but insert_edge always expects at least 1.
+                // Furthermore, we need correct parent_idx, which is why we set both to 0.
+                right.as_mut().set_parent(root, 0);
+                root.as_mut().set_last_edge(right);
+                root.as_mut().insert_edge(0, false, key, left);
+                recompute_size(root, self.height);
+                self.root = Some(root);
+                break
+            };
+
+            // Insert split key and right into parent. left is already in parent at idx, so
+            // insert key at idx and right at idx + 1. We must handle the case where the parent
+            // overflows too...
+            right.as_mut().set_parent(parent, idx + 1);
+            if (parent.as_ref().len as usize) < B {
+                // The parent won't overflow, actually insert into parent
+                parent.as_mut().insert_edge(idx, true, key, right);
+                adjust_ancestor_sizes(right, added as isize);
+                break;
+            }
+            // The parent will overflow too, so we split the parent when inserting idx/key/right
+            // split_internal will replace key with the split key and node with the left node,
+            // and we re-assign right to the right node (we don't just pass as a &mut like we do
+            // with key because it must be allocated). Then insert the new internal parent-right
+            // node in its parent, and so on, until we either find a suitable parent or reach
+            // the root.
+            node = parent;
+            right = self
+                .store
+                .alloc(node.as_mut().split_internal(idx, &mut key, right));
+            for right_child in right.as_mut().edges_mut() {
+                right_child.as_mut().parent = Some(right);
+            }
+            height += 1;
+            recompute_size(node, height);
+            recompute_size(right, height);
+        }
+    }
+
+    #[inline]
+    unsafe fn post_removal(&mut self, node: NodePtr<K, V, B>)
+    where
+        K: Clone,
+    {
+        self.length -= 1;
+        adjust_ancestor_sizes(node, -1);
+        self.rebalance_from(node, 0);
+    }
+
+    /// Rebalances `node`, which has the given height, and then its ancestors, until one isn't
+    /// underfull.
+    #[inline]
+    unsafe fn rebalance_from(&mut self, mut node: NodePtr<K, V, B>, mut height: usize)
+    where
+        K: Clone,
+    {
+        let min_len = self.store.rebalance.relaxed_min_len(B);
+        while (node.as_ref().len as usize) < min_len {
+            let Some((parent, _)) = node.as_ref().parent() else {
+                // Node is root. Root node can have less than B < 2 children
+                if height == 0 {
                     // If the root is a leaf, it can have min 1 child. Otherwise, the tree
                     // is empty.
                     if node.as_ref().len == 0 {
+                        self.store.dealloc(node);
                         self.root = None;
+                        self.first = None;
+                        self.last = None;
+                        self.finger = None;
                     }
                 } else if node.as_ref().len < 1 {
                     // If the root is internal, it can have min 1 child (= 2 edges). Otherwise, the
                     // remaining edge becomes the new root.
                     self.height -= 1;
-                    self.root = Some(node.as_ref().edge(0));
+                    let mut root = node.as_ref().edge(0);
+                    root.as_mut().clear_parent();
+                    self.root = Some(root);
                     self.store.dealloc(node);
-                    self.root.as_mut().unwrap().as_mut().clear_parent();
                 }
                 break
             };
 
-            // Try to redistribute with prev sibling
-            if idx > 0 {
-                let mut prev = parent.as_ref().edge(idx - 1);
-                if (prev.as_ref().len as usize) > M / 2 {
-                    if is_leaf {
+            if !self.rebalance_underfull(node, height, min_len) {
+                break;
+            }
+
+            // Since we merged, we may now have to redistribute or merge the parent since it
+            // has 1 less child
+            node = parent;
+            height += 1;
+        }
+    }
+
+    /// Fixes `node`, which has the given height and has less than `min_len` entries (by any
+    /// amount), by moving entries from a sibling or merging with one. Returns `true` if it merged,
+    /// in which case the parent has 1 less entry and may be underfull.
+    #[inline]
+    unsafe fn rebalance_underfull(
+        &mut self,
+        mut node: NodePtr<K, V, B>,
+        height: usize,
+        min_len: usize,
+    ) -> bool
+    where
+        K: Clone,
+    {
+        let Some((mut parent, idx)) = node.as_ref().parent() else {
+            return false
+        };
+        let policy = self.store.rebalance;
+        let deficit = min_len - node.as_ref().len as usize;
+
+        // Skip redistributing if we'd rather merge and can. Merging with an internal node also
+        // moves the separator key down
+        let sibling_idx = if idx > 0 { idx - 1 } else { idx + 1 };
+        let sibling_len = parent.as_ref().edge(sibling_idx).as_ref().len as usize;
+        let merged_len = node.as_ref().len as usize + sibling_len + (height > 0) as usize;
+        let steal = policy.underflow_strategy() == UnderflowStrategy::Steal || merged_len > B;
+
+        // Try to redistribute with prev sibling
+        if steal && idx > 0 {
+            let mut prev = parent.as_ref().edge(idx - 1);
+            if (prev.as_ref().len as usize) >= min_len + deficit {
+                for _ in 0..deficit {
+                    if height == 0 {
                         let (key, val) = prev.as_mut().remove_val(prev.as_ref().len - 1);
                         node.as_mut().insert_val(0, key.clone(), val);
                         parent.as_mut().replace_key(idx - 1, key);
@@ -915,15 +3436,21 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
                         edge.as_mut().set_parent(node, 0);
                         node.as_mut().insert_edge(0, false, key, edge);
                     }
-                    break;
                 }
+                if height > 0 {
+                    recompute_size(prev, height);
+                    recompute_size(node, height);
+                }
+                return false;
             }
+        }
 
-            // Try to redistribute with next sibling
-            if idx < parent.as_ref().len {
-                let mut next = parent.as_ref().edge(idx + 1);
-                if (next.as_ref().len as usize) > M / 2 {
-                    if is_leaf {
+        // Try to redistribute with next sibling
+        if steal && idx < parent.as_ref().len {
+            let mut next = parent.as_ref().edge(idx + 1);
+            if (next.as_ref().len as usize) >= min_len + deficit {
+                for _ in 0..deficit {
+                    if height == 0 {
                         parent
                             .as_mut()
                             .replace_key(idx, next.as_ref().key(1).clone());
@@ -936,103 +3463,565 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
                         edge.as_mut().set_parent(node, len + 1);
                         node.as_mut().insert_edge(len, true, key, edge);
                     }
-                    break;
                 }
+                if height > 0 {
+                    recompute_size(next, height);
+                    recompute_size(node, height);
+                }
+                return false;
             }
+        }
 
-            // Merge with prev sibling or next sibling. We prioritize prev just because, but
-            // must choose next if idx == 0
-            if idx > 0 {
-                let mut prev = parent.as_mut().edge(idx - 1);
-                if is_leaf {
-                    node.as_mut().merge_prev_leaf(prev.as_mut());
-                    if let Some(mut new_prev) = node.as_ref().prev() {
-                        new_prev.as_mut().set_next(Some(node));
-                    }
-                } else {
-                    let key = parent.as_ref().key(idx - 1).clone();
-                    for child in prev.as_mut().edges_mut() {
-                        child.as_mut().parent = Some(node);
-                    }
-                    node.as_mut().merge_prev_internal(key, prev.as_mut());
+        // Merge with prev sibling or next sibling. We prioritize prev just because, but
+        // must choose next if idx == 0
+        if idx > 0 {
+            let mut prev = parent.as_mut().edge(idx - 1);
+            if height == 0 {
+                node.as_mut().merge_prev_leaf(prev.as_mut());
+                match node.as_ref().prev() {
+                    None => self.first = Some(node),
+                    Some(mut new_prev) => new_prev.as_mut().set_next(Some(node)),
+                }
+            } else {
+                let key = parent.as_ref().key(idx - 1).clone();
+                for child in prev.as_mut().edges_mut() {
+                    child.as_mut().parent = Some(node);
                 }
+                node.as_mut().merge_prev_internal(key, prev.as_mut());
+                recompute_size(node, height);
+            }
 
-                // Dealloc and remove absorbed (empty) node and fix indices of the nodes
-                // after
-                let (_key, edge) = parent.as_mut().remove_edge(idx - 1, false);
-                debug_assert!(edge.ptr_eq(&prev));
-                self.store.dealloc(prev);
+            // Dealloc and remove absorbed (empty) node and fix indices of the nodes
+            // after
+            let (_key, edge) = parent.as_mut().remove_edge(idx - 1, false);
+            debug_assert!(edge.ptr_eq(&prev));
+            if self.finger.ptr_eq(&Some(prev)) {
+                self.finger = Some(node);
+            }
+            self.store.dealloc(prev);
+        } else {
+            let mut next = parent.as_mut().edge(idx + 1);
+            if height == 0 {
+                node.as_mut().merge_next_leaf(next.as_mut());
+                match node.as_ref().next() {
+                    None => self.last = Some(node),
+                    Some(mut new_next) => new_next.as_mut().set_prev(Some(node)),
+                }
             } else {
-                let mut next = parent.as_mut().edge(idx + 1);
-                if is_leaf {
-                    node.as_mut().merge_next_leaf(next.as_mut());
-                    if let Some(mut new_next) = node.as_ref().next() {
-                        new_next.as_mut().set_prev(Some(node));
-                    }
-                } else {
-                    let key = parent.as_ref().key(idx).clone();
-                    for child in next.as_mut().edges_mut() {
-                        child.as_mut().parent = Some(node);
-                    }
-                    node.as_mut().merge_next_internal(key, next.as_mut());
+                let key = parent.as_ref().key(idx).clone();
+                for child in next.as_mut().edges_mut() {
+                    child.as_mut().parent = Some(node);
                 }
+                node.as_mut().merge_next_internal(key, next.as_mut());
+                recompute_size(node, height);
+            }
 
-                // Dealloc and remove absorbed (empty) node and fix indices of the nodes
-                // after
-                let (_key, edge) = parent.as_mut().remove_edge(idx, true);
-                debug_assert!(edge.ptr_eq(&next));
-                self.store.dealloc(next);
+            // Dealloc and remove absorbed (empty) node and fix indices of the nodes
+            // after
+            let (_key, edge) = parent.as_mut().remove_edge(idx, true);
+            debug_assert!(edge.ptr_eq(&next));
+            if self.finger.ptr_eq(&Some(next)) {
+                self.finger = Some(node);
             }
+            self.store.dealloc(next);
+        }
+        true
+    }
 
-            // Since we merged, we may now have to redistribute or merge the parent since it
-            // has 1 less child
+    /// Splits the tree so that the entry at `idx` in `leaf` and every entry after it are moved into
+    /// the returned tree. `idx` may be `leaf.len`, to split after `leaf`.
+    ///
+    /// Only the nodes on the path from `leaf` to the root are split, so nodes on the new borders
+    /// may be underfull: call [Self::fix_border] on each side once done splitting. The lengths
+    /// are also left for the caller to set, since they aren't known without walking the leaves.
+    #[inline]
+    unsafe fn split_off_at(&mut self, mut leaf: NodePtr<K, V, B>, idx: u16) -> Self {
+        self.finger = None;
+        let mut right = Self::new_in(self.store);
+        right.height = self.height;
+
+        // Split the leaf, where the left side (if any) stays in place
+        let prev = leaf.as_ref().prev();
+        let next = leaf.as_ref().next();
+        let (mut left_node, mut right_node) = if idx == 0 {
+            (None, Some(leaf))
+        } else if idx == leaf.as_ref().len {
+            (Some(leaf), None)
+        } else {
+            let mut new = self.store.alloc(leaf.as_mut().split_off_leaf(idx));
+            new.as_mut().set_next(next);
+            if let Some(mut next) = next {
+                next.as_mut().set_prev(Some(new));
+            }
+            (Some(leaf), Some(new))
+        };
+        // Unlink the leaves on either side of the split
+        if let Some(mut left_last) = left_node.or(prev) {
+            left_last.as_mut().set_next(None);
+        }
+        if let Some(mut right_first) = right_node.or(next) {
+            right_first.as_mut().set_prev(None);
+        }
+
+        // Split the ancestors around the split child
+        let mut node = leaf;
+        let mut height = 0;
+        while let Some((mut parent, idx)) = node.as_ref().parent() {
+            height += 1;
+            let mut new = None;
+            let (new_left, new_right) = match (left_node, right_node) {
+                (Some(_), Some(child)) => {
+                    let new = new.insert(self.store.alloc(parent.as_mut().split_off_internal(idx)));
+                    new.as_mut().d.internal_mut().edges[0].write(child);
+                    (Some(parent), Some(*new))
+                }
+                (Some(_), None) if idx == parent.as_ref().len => (Some(parent), None),
+                (Some(_), None) => {
+                    // The key after the split child no longer separates anything
+                    let new = new.insert(
+                        self.store
+                            .alloc(parent.as_mut().split_off_internal(idx + 1)),
+                    );
+                    let (_key, edge) = parent.as_mut().remove_last_edge();
+                    new.as_mut().d.internal_mut().edges[0].write(edge);
+                    (Some(parent), Some(*new))
+                }
+                (None, Some(_)) if idx == 0 => (None, Some(parent)),
+                (None, Some(child)) => {
+                    // The key before the split child no longer separates anything
+                    let new = new.insert(self.store.alloc(parent.as_mut().split_off_internal(idx)));
+                    let (_key, _child) = parent.as_mut().remove_last_edge();
+                    new.as_mut().d.internal_mut().edges[0].write(child);
+                    (Some(parent), Some(*new))
+                }
+                // SAFETY: the split child has entries on at least one side of the split
+                (None, None) => std::hint::unreachable_unchecked(),
+            };
+            if let Some(mut new) = new {
+                for (idx, child) in new.as_mut().edges_mut().iter_mut().enumerate() {
+                    child.as_mut().set_parent(new, idx as u16);
+                }
+            }
+            for half in [new_left, new_right].into_iter().flatten() {
+                recompute_size(half, height);
+            }
+            (left_node, right_node) = (new_left, new_right);
             node = parent;
-            is_leaf = false;
+        }
+        self.root = left_node;
+        right.root = right_node;
+        right
+    }
+
+    /// Fixes the underfull nodes on the right border (or left border if `!right`) after
+    /// [Self::split_off_at], then caches the first and last leaves.
+    ///
+    /// Each step fixes the topmost underfull node, whose parent is never underfull, so it always
+    /// has a sibling to take entries from or merge with. Merging may make the parent underfull
+    /// again, which the next step fixes.
+    #[inline]
+    unsafe fn fix_border(&mut self, right: bool)
+    where
+        K: Clone,
+    {
+        let min_len = self.store.rebalance.relaxed_min_len(B);
+        loop {
+            self.collapse_root();
+            let Some(mut node) = self.root else {
+                self.height = 0;
+                break
+            };
+
+            let mut height = self.height;
+            let mut underfull = None;
+            while height > 0 {
+                node = node.as_ref().edge(match right {
+                    false => 0,
+                    true => node.as_ref().len,
+                });
+                height -= 1;
+                if (node.as_ref().len as usize) < min_len {
+                    underfull = Some((node, height));
+                    break;
+                }
+            }
+            let Some((node, height)) = underfull else {
+                break
+            };
+            self.rebalance_underfull(node, height, min_len);
+        }
+        self.cache_first_last();
+    }
+
+    /// Removes roots with only 1 edge, making the edge the root.
+    #[inline]
+    unsafe fn collapse_root(&mut self) {
+        while let Some(root) = self.root {
+            if self.height == 0 || root.as_ref().len > 0 {
+                break;
+            }
+            let mut child = root.as_ref().edge(0);
+            child.as_mut().clear_parent();
+            self.store.dealloc(root);
+            self.root = Some(child);
+            self.height -= 1;
+        }
+    }
+
+    /// Makes `node`, which has the given height, have a sibling, by rebalancing its ancestors
+    /// which are only children, or collapsing the root if it has only 1 edge. Collapsing the root
+    /// frees `node` too if it has no keys, so if the tree's height changes, `node` mustn't be used.
+    #[inline]
+    unsafe fn ensure_sibling(&mut self, node: NodePtr<K, V, B>, height: usize, min_len: usize)
+    where
+        K: Clone,
+    {
+        let Some((parent, _)) = node.as_ref().parent() else {
+            return;
+        };
+        if parent.as_ref().len > 0 {
+            return;
+        }
+        if parent.as_ref().parent().is_none() {
+            self.collapse_root();
+            return;
+        }
+        // The parent has no keys so it's underfull, and taking entries from or merging with its
+        // sibling gives it more edges
+        let old_height = self.height;
+        self.ensure_sibling(parent, height + 1, min_len);
+        if self.height < old_height {
+            // The root collapsed into the parent, and since it has no keys, past it
+            return;
+        }
+        self.rebalance_underfull(parent, height + 1, min_len);
+    }
+
+    /// Moves every entry of `other` after the entries of `self`. Every key in `self` must be less
+    /// than every key in `other`, and both must be in the same store. The new length is the sum of
+    /// both lengths, even if one tree is empty.
+    ///
+    /// The shorter tree's root is inserted into the taller tree at the same height, which splits
+    /// at most one node per level, so this is O(log n).
+    #[inline]
+    unsafe fn concat_unchecked(&mut self, other: Self)
+    where
+        K: Clone,
+    {
+        // `other` ends up without nodes, so it isn't dropped, which would check whether the thread
+        // is panicking (a call which may unwind)
+        let mut other = ManuallyDrop::new(other);
+        let (left_len, right_len) = (self.length, other.length);
+        let Some(right_root) = other.root.take() else {
+            self.length = left_len + right_len;
+            return
+        };
+        let Some(left_root) = self.root else {
+            other.root = Some(right_root);
+            swap(self, &mut *other);
+            self.length = left_len + right_len;
+            return
+        };
+        let (left_height, right_height) = (self.height, other.height);
+        let key = first_key(right_root, right_height).clone();
+        // Only the total length may be known, so ancestors' sizes are adjusted by the subtree sizes
+        #[cfg(feature = "order-statistic")]
+        let (left_size, right_size) = (
+            left_root.as_ref().subtree_len(left_height),
+            right_root.as_ref().subtree_len(right_height),
+        );
+        #[cfg(not(feature = "order-statistic"))]
+        let (left_size, right_size) = (0, 0);
+
+        // Link the leaves
+        if let (Some(mut left_last), Some(mut right_first)) = (self.last, other.first) {
+            left_last.as_mut().set_next(Some(right_first));
+            right_first.as_mut().set_prev(Some(left_last));
+        }
+        self.last = other.last;
+        self.length = left_len + right_len;
+        // `other` is now empty
+        other.length = 0;
+        other.first = None;
+        other.last = None;
+        other.finger = None;
+
+        let (inserted, sibling, height) = if left_height >= right_height {
+            // Insert the right root after the left tree's last node at the same height
+            let mut node = left_root;
+            for _ in right_height..left_height {
+                node = node.as_ref().edge(node.as_ref().len);
+            }
+            self.insert_edge_after(node, key, right_root, right_height, right_size);
+            (right_root, node, right_height)
+        } else {
+            // Put the left root before the right tree's first node at the same height, by
+            // replacing that node and then inserting it after
+            let mut node = right_root;
+            for _ in left_height..right_height {
+                node = node.as_ref().edge(0);
+            }
+            // The right tree is taller, so `node` isn't its root
+            let (mut parent, _) = node.as_ref().parent().unwrap_unchecked();
+            let mut left_root = left_root;
+            left_root.as_mut().set_parent(parent, 0);
+            *parent.as_mut().edge_mut(0) = left_root;
+            self.root = Some(right_root);
+            self.height = right_height;
+            self.insert_edge_after(left_root, key, node, left_height, left_size);
+            (left_root, node, left_height)
+        };
+
+        // The inserted root may be underfull, and if both roots had the same height, so may be
+        // the other root
+        let min_len = self.store.rebalance.relaxed_min_len(B);
+        if (inserted.as_ref().len as usize) < min_len {
+            self.rebalance_from(inserted, height);
+        } else if (sibling.as_ref().len as usize) < min_len {
+            self.rebalance_from(sibling, height);
         }
     }
     // endregion
 }
 
-impl<K, V> NodeBounds<K, V> {
+impl<K, V, const B: usize> NodeBounds<K, V, B> {
     #[inline]
-    fn start(&self) -> (NodePtr<K, V>, u16) {
+    fn start(&self) -> (NodePtr<K, V, B>, u16) {
         (self.start_node, self.start_index)
     }
 
     #[inline]
-    fn end(&self) -> (NodePtr<K, V>, u16) {
+    fn end(&self) -> (NodePtr<K, V, B>, u16) {
         (self.end_node, self.end_index)
     }
+
+    /// \# of entries within the bounds, found by walking the leaves between them.
+    #[inline]
+    unsafe fn len(&self) -> usize {
+        let mut len = 0;
+        let mut node = self.start_node;
+        while !node.ptr_eq(&self.end_node) {
+            len += node.as_ref().len as usize;
+            let Some(next) = node.as_ref().next() else {
+                break
+            };
+            node = next;
+        }
+        len + self.end_index as usize + 1 - self.start_index as usize
+    }
+}
+
+/// Returns an error if the range's start is after its end, or they are equal and both excluded
+/// (same as the cases where [std::collections::BTreeMap::range] panics).
+#[inline]
+fn check_range_bounds<Q: Ord + ?Sized>(bounds: &impl RangeBounds<Q>) -> Result<(), Error> {
+    match (bounds.start_bound(), bounds.end_bound()) {
+        (Bound::Excluded(start), Bound::Excluded(end)) if start == end => Err(Error::InvalidRange),
+        (
+            Bound::Included(start) | Bound::Excluded(start),
+            Bound::Included(end) | Bound::Excluded(end),
+        ) if start > end => Err(Error::InvalidRange),
+        _ => Ok(()),
+    }
+}
+
+/// Entry at `index` in the subtree at `node`, which has the given `height`. `index` must be less
+/// than the subtree's size.
+#[cfg(feature = "order-statistic")]
+unsafe fn key_val_at_index<'a, K: 'a, V: 'a, const B: usize>(
+    mut node: NodePtr<K, V, B>,
+    mut height: usize,
+    mut index: usize,
+) -> (&'a K, &'a V) {
+    while height > 0 {
+        height -= 1;
+        node = *node
+            .as_ref()
+            .edges()
+            .iter()
+            .find(|child| {
+                let child_len = child.as_ref().subtree_len(height);
+                match index.checked_sub(child_len) {
+                    None => true,
+                    Some(rest) => {
+                        index = rest;
+                        false
+                    }
+                }
+            })
+            .expect("subtree sizes are inconsistent with the map's length");
+    }
+    node.as_ref().key_val(index as u16)
+}
+
+/// Next node after `node` with the same height, if any.
+unsafe fn next_at_height<K, V, const B: usize>(
+    mut node: NodePtr<K, V, B>,
+) -> Option<NodePtr<K, V, B>> {
+    let mut depth = 0;
+    loop {
+        let (parent, idx) = node.as_ref().parent()?;
+        if idx < parent.as_ref().len {
+            node = parent.as_ref().edge(idx + 1);
+            break;
+        }
+        node = parent;
+        depth += 1;
+    }
+    for _ in 0..depth {
+        node = node.as_ref().edge(0);
+    }
+    Some(node)
+}
+
+/// First (smallest) key in the subtree at `node`, which has the given `height`.
+unsafe fn first_key<'a, K: 'a, V: 'a, const B: usize>(
+    mut node: NodePtr<K, V, B>,
+    height: usize,
+) -> &'a K {
+    for _ in 0..height {
+        node = node.as_ref().edge(0);
+    }
+    node.as_ref().key(0)
+}
+
+/// Moves `node` and its descendants from `old_store` into `new_store`, transforming each value with
+/// `f`. Leaves are linked in order, `prev_leaf` being the last leaf created so far.
+///
+/// Returns the new node. Deallocates the old nodes but doesn't drop their keys or values (they are
+/// moved).
+unsafe fn map_node_values<K, V, W, const B: usize>(
+    node: NodePtr<K, V, B>,
+    height: usize,
+    parent: Option<(NodePtr<K, W, B>, u16)>,
+    prev_leaf: &mut Option<NodePtr<K, W, B>>,
+    f: &mut impl FnMut(V) -> W,
+    old_store: &BTreeStore<K, V, B>,
+    new_store: &BTreeStore<K, W, B>,
+) -> NodePtr<K, W, B> {
+    let mut old = old_store.dealloc_and_return(node);
+    let mut new = match height {
+        0 => Node::leaf(),
+        _ => Node::internal(),
+    };
+    new.len = old.len;
+    new.keys = replace(&mut old.keys, maybe_uninit_array());
+    if let Some((parent, parent_idx)) = parent {
+        new.set_parent(parent, parent_idx);
+    }
+    let mut new_ptr = new_store.alloc(new);
+
+    if height == 0 {
+        for idx in 0..old.len {
+            new_ptr.as_mut().write_val(idx, f(old.read_val(idx)));
+        }
+        new_ptr.as_mut().set_prev(*prev_leaf);
+        if let Some(mut prev) = *prev_leaf {
+            prev.as_mut().set_next(Some(new_ptr));
+        }
+        *prev_leaf = Some(new_ptr);
+    } else {
+        for idx in 0..old.len + 1 {
+            let child = map_node_values(
+                old.edge(idx),
+                height - 1,
+                Some((new_ptr, idx)),
+                prev_leaf,
+                f,
+                old_store,
+                new_store,
+            );
+            new_ptr.as_mut().d.internal_mut().edges[idx as usize].write(child);
+        }
+        recompute_size(new_ptr, height);
+    }
+    new_ptr
 }
 
 // region common trait impls
-impl<'store, K: Debug, V: Debug> Debug for BTreeMap<'store, K, V> {
+impl<'store, K: Debug, V: Debug, const B: usize> Debug for BTreeMap<'store, K, V, B> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         self.print(f)
     }
 }
 
-impl<'store, K: PartialEq, V: PartialEq> PartialEq for BTreeMap<'store, K, V> {
+impl<'store, K: PartialEq, V: PartialEq, const B: usize> PartialEq for BTreeMap<'store, K, V, B> {
     fn eq(&self, other: &Self) -> bool {
         self.iter().eq(other.iter())
     }
 }
 
-impl<'store, K: Eq, V: Eq> Eq for BTreeMap<'store, K, V> {}
+impl<'store, K: Eq, V: Eq, const B: usize> Eq for BTreeMap<'store, K, V, B> {}
+
+/// Compares contents, by looking up each of the map's keys in the hash map.
+impl<'store, K: Eq + Hash, V: PartialEq, S: BuildHasher, const B: usize> PartialEq<HashMap<K, V, S>>
+    for BTreeMap<'store, K, V, B>
+{
+    fn eq(&self, other: &HashMap<K, V, S>) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl<'store, K: Eq + Hash, V: PartialEq, S: BuildHasher, const B: usize>
+    PartialEq<BTreeMap<'store, K, V, B>> for HashMap<K, V, S>
+{
+    #[inline]
+    fn eq(&self, other: &BTreeMap<'store, K, V, B>) -> bool {
+        other == self
+    }
+}
+
+impl<'store, K: PartialEq, V: PartialEq, const B: usize> PartialEq<std::collections::BTreeMap<K, V>>
+    for BTreeMap<'store, K, V, B>
+{
+    #[inline]
+    fn eq(&self, other: &std::collections::BTreeMap<K, V>) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<'store, K: PartialEq, V: PartialEq, const B: usize> PartialEq<BTreeMap<'store, K, V, B>>
+    for std::collections::BTreeMap<K, V>
+{
+    #[inline]
+    fn eq(&self, other: &BTreeMap<'store, K, V, B>) -> bool {
+        other == self
+    }
+}
 
-impl<'store, K: PartialOrd, V: PartialOrd> PartialOrd for BTreeMap<'store, K, V> {
+impl<'store, K: PartialOrd, V: PartialOrd, const B: usize> PartialOrd
+    for BTreeMap<'store, K, V, B>
+{
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         self.iter().partial_cmp(other.iter())
     }
 }
 
-impl<'store, K: Ord, V: Ord> Ord for BTreeMap<'store, K, V> {
+impl<'store, K: PartialOrd, V: PartialOrd, const B: usize>
+    PartialOrd<std::collections::BTreeMap<K, V>> for BTreeMap<'store, K, V, B>
+{
+    #[inline]
+    fn partial_cmp(&self, other: &std::collections::BTreeMap<K, V>) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<'store, K: PartialOrd, V: PartialOrd, const B: usize> PartialOrd<BTreeMap<'store, K, V, B>>
+    for std::collections::BTreeMap<K, V>
+{
+    #[inline]
+    fn partial_cmp(&self, other: &BTreeMap<'store, K, V, B>) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<'store, K: Ord, V: Ord, const B: usize> Ord for BTreeMap<'store, K, V, B> {
     fn cmp(&self, other: &Self) -> Ordering {
         self.iter().cmp(other.iter())
     }
 }
 
-impl<'store, K: Hash, V: Hash> Hash for BTreeMap<'store, K, V> {
+impl<'store, K: Hash, V: Hash, const B: usize> Hash for BTreeMap<'store, K, V, B> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         for (k, v) in self.iter() {
             k.hash(state);
@@ -1041,34 +4030,92 @@ impl<'store, K: Hash, V: Hash> Hash for BTreeMap<'store, K, V> {
     }
 }
 
-impl<'store, K: Ord + Clone, V> Extend<(K, V)> for BTreeMap<'store, K, V> {
+/// *Panics* if the key isn't in the map. See [BTreeMap::get] for a non-panicking version.
+impl<'store, K: Borrow<Q>, V, Q: Ord + ?Sized, const B: usize> std::ops::Index<&Q>
+    for BTreeMap<'store, K, V, B>
+{
+    type Output = V;
+
+    #[track_caller]
+    #[inline]
+    fn index(&self, key: &Q) -> &V {
+        match self.get(key) {
+            Some(value) => value,
+            None => panic!("BTreeMap::index: key not found"),
+        }
+    }
+}
+
+impl<'store, K: Clone, V: Clone, const B: usize> Clone for BTreeMap<'store, K, V, B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self::bulk_load_in(self.store, self.iter().map(|(k, v)| (k.clone(), v.clone())))
+    }
+
+    /// Reuses `self`'s nodes, and keeps `self` in its own store (see [BTreeMap::copy_from]).
+    #[inline]
+    fn clone_from(&mut self, source: &Self) {
+        self.copy_from(source)
+    }
+}
+
+impl<'store, K: Ord + Clone, V, const B: usize> Extend<(K, V)> for BTreeMap<'store, K, V, B> {
     fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
         for (k, v) in iter {
             self.insert(k, v);
         }
     }
 }
+
+#[cfg(feature = "thread-local-store")]
+impl<K: 'static, V: 'static> BTreeMap<'static, K, V> {
+    /// Creates an empty map in this thread's [default store](BTreeStore::thread_local).
+    #[inline]
+    pub fn new() -> Self {
+        Self::new_in(BTreeStore::thread_local())
+    }
+}
+
+/// Creates an empty map in this thread's [default store](BTreeStore::thread_local).
+#[cfg(feature = "thread-local-store")]
+impl<K: 'static, V: 'static> Default for BTreeMap<'static, K, V> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builds a map in this thread's [default store](BTreeStore::thread_local). See
+/// [BTreeMap::from_iter_in].
+#[cfg(feature = "thread-local-store")]
+impl<K: Ord + Clone + 'static, V: 'static> FromIterator<(K, V)> for BTreeMap<'static, K, V> {
+    #[inline]
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        Self::from_iter_in(iter, BTreeStore::thread_local())
+    }
+}
 // endregion
 
 // region drop and dealloc
-impl<'store, K, V> Drop for BTreeMap<'store, K, V> {
+impl<'store, K, V, const B: usize> Drop for BTreeMap<'store, K, V, B> {
     #[inline]
     fn drop(&mut self) {
+        let Some(root) = self.root.take() else {
+            return;
+        };
         if panicking() {
             // TODO: Drop when panicking without causing UB (need to reorder some operations)
             return;
         }
 
-        if let Some(root) = self.root.take() {
-            unsafe { drop_node_ptr(root, self.height, &mut |n| self.store.dealloc(n)) }
-        }
+        unsafe { drop_node_ptr(root, self.height, &mut |n| self.store.dealloc(n)) }
     }
 }
 
-unsafe fn drop_node_ptr<K, V>(
-    mut node: NodePtr<K, V>,
+unsafe fn drop_node_ptr<K, V, const B: usize>(
+    mut node: NodePtr<K, V, B>,
     height: usize,
-    dealloc: &mut impl FnMut(NodePtr<K, V>),
+    dealloc: &mut impl FnMut(NodePtr<K, V, B>),
 ) {
     let node_ref = node.as_mut();
 
@@ -1088,13 +4135,44 @@ unsafe fn drop_node_ptr<K, V>(
     dealloc(node);
 }
 
-/// If this address is at the start of the node, deallocates the node, then checks if it's at the
-/// start of its parent, if so deallocates its parent, and so on.
-///
+/// Advances `keys` past the keys less than `key`, then returns whether the next key is `key`, or
+/// `None` if there are no keys left.
+#[cfg(feature = "set")]
+#[inline]
+fn skip_to<'a, K: Ord + 'a>(
+    keys: &mut std::iter::Peekable<impl Iterator<Item = &'a K>>,
+    key: &K,
+) -> Option<bool> {
+    loop {
+        match (*keys.peek()?).cmp(key) {
+            Ordering::Less => {
+                keys.next();
+            }
+            Ordering::Equal => return Some(true),
+            Ordering::Greater => return Some(false),
+        }
+    }
+}
+
+/// Upper bound of the # of nodes in a tree with `len` entries whose nodes are at least half full
+#[inline]
+fn max_nodes<const B: usize>(len: usize) -> usize {
+    let mut level = len.div_ceil(B / 2);
+    let mut nodes = level;
+    while level > 1 {
+        level = level.div_ceil(B / 2);
+        nodes += level;
+    }
+    nodes
+}
+
+/// If this address is at the start of the node, deallocates the node, then checks if it's at the
+/// start of its parent, if so deallocates its parent, and so on.
+///
 /// Doesn't drop any of the nodes' contents
-unsafe fn dealloc_up_firsts<K, V>(
-    mut address: (NodePtr<K, V>, u16),
-    mut dealloc: impl FnMut(NodePtr<K, V>),
+unsafe fn dealloc_up_firsts<K, V, const B: usize>(
+    mut address: (NodePtr<K, V, B>, u16),
+    mut dealloc: impl FnMut(NodePtr<K, V, B>),
 ) {
     loop {
         let (node, idx) = address;
@@ -1122,9 +4200,9 @@ unsafe fn dealloc_up_firsts<K, V>(
 ///
 /// Doesn't drop any of the nodes' contents
 #[inline]
-unsafe fn dealloc_up_lasts<K, V>(
-    (mut node, mut idx): (NodePtr<K, V>, u16),
-    mut dealloc: impl FnMut(NodePtr<K, V>),
+unsafe fn dealloc_up_lasts<K, V, const B: usize>(
+    (mut node, mut idx): (NodePtr<K, V, B>, u16),
+    mut dealloc: impl FnMut(NodePtr<K, V, B>),
 ) {
     debug_assert!(
         idx < node.as_ref().len,
@@ -1151,153 +4229,1325 @@ unsafe fn dealloc_up_lasts<K, V>(
         }
     }
 }
+
+/// Deallocates the node and all of its ancestors.
+///
+/// Doesn't drop any of the nodes' contents
+#[inline]
+unsafe fn dealloc_up_all<K, V, const B: usize>(
+    mut node: NodePtr<K, V, B>,
+    mut dealloc: impl FnMut(NodePtr<K, V, B>),
+) {
+    while let Some(parent) = {
+        let parent = node.as_ref().parent();
+        dealloc(node);
+        parent
+    } {
+        node = parent.0;
+    }
+}
+// endregion
+
+// region iterators (almost all boilerplate)
+//noinspection DuplicatedCode
+// region iterator impls
+impl<'store: 'a, 'a, K, V, const B: usize> IntoIterator for &'a BTreeMap<'store, K, V, B> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V, B>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'store: 'a, 'a, K, V, const B: usize> IntoIterator for &'a mut BTreeMap<'store, K, V, B> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V, B>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<'store, K, V, const B: usize> IntoIterator for BTreeMap<'store, K, V, B> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<'store, K, V, B>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter::new(self)
+    }
+}
+// endregion
+
+// region Iter
+pub struct Iter<'a, K, V, const B: usize = 8> {
+    cursor: LeafCursor<'a, K, V, B>,
+    back_cursor: LeafCursor<'a, K, V, B>,
+    length: usize,
+    _p: PhantomData<(&'a K, &'a V)>,
+}
+
+//noinspection DuplicatedCode
+impl<'a, K, V, const B: usize> Iter<'a, K, V, B> {
+    #[inline]
+    fn new(tree: &'a BTreeMap<K, V, B>) -> Self {
+        Self {
+            cursor: unsafe { LeafCursor::new(tree.first_leaf(), 0) },
+            back_cursor: unsafe { LeafCursor::new_at_end(tree.last_leaf()) },
+            length: tree.length,
+            _p: PhantomData,
+        }
+    }
+
+    /// Iterates over the `length` entries within `bounds`
+    #[inline]
+    fn new_within(bounds: Option<NodeBounds<K, V, B>>, length: usize) -> Self {
+        let (cursor, back_cursor) = match bounds {
+            None => (LeafCursor::new_detached(), LeafCursor::new_detached()),
+            Some(bounds) => unsafe {
+                let (start_node, start_idx) = bounds.start();
+                let (end_node, end_idx) = bounds.end();
+                (
+                    LeafCursor::new(Some(start_node), start_idx),
+                    LeafCursor::new(Some(end_node), end_idx),
+                )
+            },
+        };
+        Self {
+            cursor,
+            back_cursor,
+            length,
+            _p: PhantomData,
+        }
+    }
+
+    /// Get the next element without advancing the iterator
+    #[inline]
+    pub fn peek(&self) -> Option<(&'a K, &'a V)> {
+        if self.length == 0 {
+            return None;
+        }
+        self.cursor.key_value()
+    }
+
+    /// Get the next back element without advancing the back iterator
+    #[inline]
+    pub fn peek_back(&self) -> Option<(&'a K, &'a V)> {
+        if self.length == 0 {
+            return None;
+        }
+        self.back_cursor.key_value()
+    }
+
+    /// Equivalent to `next` except *panics* if iteration is done.
+    #[track_caller]
+    #[inline]
+    pub fn advance(&mut self) {
+        self.try_advance().or_panic("Iter::advance")
+    }
+
+    /// Equivalent to `next_back` except *panics* if iteration is done.
+    #[track_caller]
+    #[inline]
+    pub fn advance_back(&mut self) {
+        self.try_advance_back().or_panic("Iter::advance_back")
+    }
+
+    /// Equivalent to `next` except returns an error if iteration is done.
+    #[inline]
+    pub fn try_advance(&mut self) -> Result<(), Error> {
+        if self.length == 0 {
+            return Err(Error::IterationDone);
+        }
+        self.advance_unchecked();
+        Ok(())
+    }
+
+    /// Equivalent to `next_back` except returns an error if iteration is done.
+    #[inline]
+    pub fn try_advance_back(&mut self) -> Result<(), Error> {
+        if self.length == 0 {
+            return Err(Error::IterationDone);
+        }
+        self.advance_back_unchecked();
+        Ok(())
+    }
+
+    /// Iteration must not be done
+    #[inline]
+    fn advance_unchecked(&mut self) {
+        self.cursor.advance();
+        self.length -= 1;
+    }
+
+    /// Iteration must not be done
+    #[inline]
+    fn advance_back_unchecked(&mut self) {
+        self.back_cursor.advance_back();
+        self.length -= 1;
+    }
+}
+
+impl<'a, K, V, const B: usize> Iterator for Iter<'a, K, V, B> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let key_value = self.peek()?;
+        self.advance_unchecked();
+        Some(key_value)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.length, Some(self.length))
+    }
+}
+
+impl<'a, K, V, const B: usize> DoubleEndedIterator for Iter<'a, K, V, B> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let key_value = self.peek_back()?;
+        self.advance_back_unchecked();
+        Some(key_value)
+    }
+}
+
+impl<'a, K, V, const B: usize> ExactSizeIterator for Iter<'a, K, V, B> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.length
+    }
+}
+
+impl<'a, K, V, const B: usize> FusedIterator for Iter<'a, K, V, B> {}
+
+impl<'a, K, V, const B: usize> Clone for Iter<'a, K, V, B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            cursor: self.cursor.clone(),
+            back_cursor: self.back_cursor.clone(),
+            length: self.length,
+            _p: PhantomData,
+        }
+    }
+}
+
+// SAFETY: `size_hint` is exact
+#[cfg(feature = "nightly")]
+unsafe impl<'a, K, V, const B: usize> TrustedLen for Iter<'a, K, V, B> {}
+// endregion
+
+// region IterMut
+pub struct IterMut<'a, K, V, const B: usize = 8> {
+    cursor: LeafCursor<'a, K, V, B>,
+    back_cursor: LeafCursor<'a, K, V, B>,
+    length: usize,
+    /// Unlike in [LeafCursor], reference to `V` is mutable
+    _p: PhantomData<(&'a K, &'a mut V)>,
+}
+
+//noinspection DuplicatedCode
+impl<'a, K, V, const B: usize> IterMut<'a, K, V, B> {
+    #[inline]
+    fn new(tree: &'a BTreeMap<K, V, B>) -> Self {
+        Self {
+            cursor: unsafe { LeafCursor::new(tree.first_leaf(), 0) },
+            back_cursor: unsafe { LeafCursor::new_at_end(tree.last_leaf()) },
+            length: tree.length,
+            _p: PhantomData,
+        }
+    }
+
+    /// Get the next element without advancing the iterator
+    #[inline]
+    pub fn peek(&self) -> Option<(&'a K, &'a V)> {
+        if self.length == 0 {
+            return None;
+        }
+        self.cursor.key_value()
+    }
+
+    /// Get the next back element without advancing the back iterator
+    #[inline]
+    pub fn peek_back(&self) -> Option<(&'a K, &'a V)> {
+        if self.length == 0 {
+            return None;
+        }
+        self.back_cursor.key_value()
+    }
+
+    /// Get the next element without advancing the iterator
+    #[inline]
+    pub fn peek_mut(&mut self) -> Option<(&'a K, &'a mut V)> {
+        if self.length == 0 {
+            return None;
+        }
+        unsafe { self.cursor.key_value_mut() }
+    }
+
+    /// Get the next back element without advancing the back iterator
+    #[inline]
+    pub fn peek_back_mut(&mut self) -> Option<(&'a K, &'a mut V)> {
+        if self.length == 0 {
+            return None;
+        }
+        unsafe { self.back_cursor.key_value_mut() }
+    }
+
+    /// Equivalent to `next` except *panics* if iteration is done.
+    #[track_caller]
+    #[inline]
+    pub fn advance(&mut self) {
+        self.try_advance().or_panic("IterMut::advance")
+    }
+
+    /// Equivalent to `next_back` except *panics* if iteration is done.
+    #[track_caller]
+    #[inline]
+    pub fn advance_back(&mut self) {
+        self.try_advance_back().or_panic("IterMut::advance_back")
+    }
+
+    /// Equivalent to `next` except returns an error if iteration is done.
+    #[inline]
+    pub fn try_advance(&mut self) -> Result<(), Error> {
+        if self.length == 0 {
+            return Err(Error::IterationDone);
+        }
+        self.advance_unchecked();
+        Ok(())
+    }
+
+    /// Equivalent to `next_back` except returns an error if iteration is done.
+    #[inline]
+    pub fn try_advance_back(&mut self) -> Result<(), Error> {
+        if self.length == 0 {
+            return Err(Error::IterationDone);
+        }
+        self.advance_back_unchecked();
+        Ok(())
+    }
+
+    /// Iteration must not be done
+    #[inline]
+    fn advance_unchecked(&mut self) {
+        self.cursor.advance();
+        self.length -= 1;
+    }
+
+    /// Iteration must not be done
+    #[inline]
+    fn advance_back_unchecked(&mut self) {
+        self.back_cursor.advance_back();
+        self.length -= 1;
+    }
+}
+
+impl<'a, K, V, const B: usize> Iterator for IterMut<'a, K, V, B> {
+    type Item = (&'a K, &'a mut V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let key_value = self.peek_mut()?;
+        self.advance_unchecked();
+        Some(key_value)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.length, Some(self.length))
+    }
+}
+
+impl<'a, K, V, const B: usize> DoubleEndedIterator for IterMut<'a, K, V, B> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let key_value = self.peek_back_mut()?;
+        self.advance_back_unchecked();
+        Some(key_value)
+    }
+}
+
+impl<'a, K, V, const B: usize> ExactSizeIterator for IterMut<'a, K, V, B> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.length
+    }
+}
+
+impl<'a, K, V, const B: usize> FusedIterator for IterMut<'a, K, V, B> {}
+
+// SAFETY: `size_hint` is exact
+#[cfg(feature = "nightly")]
+unsafe impl<'a, K, V, const B: usize> TrustedLen for IterMut<'a, K, V, B> {}
+// endregion
+
+// region IntoIter
+pub struct IntoIter<'store, K, V, const B: usize = 8> {
+    store: &'store BTreeStore<K, V, B>,
+    cursor: LeafCursor<'store, K, V, B>,
+    back_cursor: LeafCursor<'store, K, V, B>,
+    length: usize,
+    /// Unlike in [LeafCursor], `K` and `V` are owned
+    _p: PhantomData<(K, V)>,
+}
+
+impl<'store, K, V, const B: usize> IntoIter<'store, K, V, B> {
+    #[inline]
+    fn new(tree: BTreeMap<'store, K, V, B>) -> Self {
+        let result = Self {
+            store: tree.store,
+            cursor: unsafe { LeafCursor::new(tree.first_leaf(), 0) },
+            back_cursor: unsafe { LeafCursor::new_at_end(tree.last_leaf()) },
+            length: tree.length,
+            _p: PhantomData,
+        };
+        // We drop the tree's nodes, so prevent it drop dropping when it drops itself.
+        // This should be equivalent to `tree.root = None`
+        forget(tree);
+        result
+    }
+}
+
+impl<'store, K, V, const B: usize> Iterator for IntoIter<'store, K, V, B> {
+    type Item = (K, V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.length == 0 {
+            return None;
+        }
+        unsafe {
+            let key_value = self.cursor.read_key_value().unwrap();
+            let address = self.cursor.address().unwrap();
+            self.length -= 1;
+            if self.length == 0 {
+                // The cursors met, so the next leaf may be freed by the back. Nodes around where
+                // they met aren't past either end, so free them now
+                dealloc_up_all(address.0, |n| self.store.dealloc(n));
+            } else {
+                self.cursor.advance();
+                dealloc_up_lasts(address, |n| self.store.dealloc(n));
+            }
+            Some(key_value)
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.length, Some(self.length))
+    }
+}
+
+impl<'a, K, V, const B: usize> DoubleEndedIterator for IntoIter<'a, K, V, B> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.length == 0 {
+            return None;
+        }
+        unsafe {
+            let key_value = self.back_cursor.read_key_value().unwrap();
+            let address = self.back_cursor.address().unwrap();
+            self.length -= 1;
+            if self.length == 0 {
+                dealloc_up_all(address.0, |n| self.store.dealloc(n));
+            } else {
+                self.back_cursor.advance_back();
+                dealloc_up_firsts(address, |n| self.store.dealloc(n));
+            }
+            Some(key_value)
+        }
+    }
+}
+
+impl<'store, K, V, const B: usize> Drop for IntoIter<'store, K, V, B> {
+    /// Drops the remaining entries and deallocates their nodes
+    #[inline]
+    fn drop(&mut self) {
+        self.for_each(drop);
+    }
+}
+
+impl<'store, K, V, const B: usize> ExactSizeIterator for IntoIter<'store, K, V, B> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.length
+    }
+}
+
+impl<'store, K, V, const B: usize> FusedIterator for IntoIter<'store, K, V, B> {}
+
+// SAFETY: `size_hint` is exact
+#[cfg(feature = "nightly")]
+unsafe impl<'store, K, V, const B: usize> TrustedLen for IntoIter<'store, K, V, B> {}
+// endregion
+
+// region Keys
+pub struct Keys<'a, K, V, const B: usize = 8>(Iter<'a, K, V, B>);
+
+impl<'a, K, V, const B: usize> Iterator for Keys<'a, K, V, B> {
+    type Item = &'a K;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, _)| k)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, K, V, const B: usize> DoubleEndedIterator for Keys<'a, K, V, B> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(k, _)| k)
+    }
+}
+
+impl<'a, K, V, const B: usize> ExactSizeIterator for Keys<'a, K, V, B> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<'a, K, V, const B: usize> FusedIterator for Keys<'a, K, V, B> {}
+
+impl<'a, K, V, const B: usize> Clone for Keys<'a, K, V, B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+// SAFETY: `size_hint` is exact
+#[cfg(feature = "nightly")]
+unsafe impl<'a, K, V, const B: usize> TrustedLen for Keys<'a, K, V, B> {}
+// endregion
+
+// region Values
+pub struct Values<'a, K, V, const B: usize = 8>(Iter<'a, K, V, B>);
+
+impl<'a, K, V, const B: usize> Iterator for Values<'a, K, V, B> {
+    type Item = &'a V;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, v)| v)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, K, V, const B: usize> DoubleEndedIterator for Values<'a, K, V, B> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(_, v)| v)
+    }
+}
+
+impl<'a, K, V, const B: usize> ExactSizeIterator for Values<'a, K, V, B> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<'a, K, V, const B: usize> FusedIterator for Values<'a, K, V, B> {}
+
+impl<'a, K, V, const B: usize> Clone for Values<'a, K, V, B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+// SAFETY: `size_hint` is exact
+#[cfg(feature = "nightly")]
+unsafe impl<'a, K, V, const B: usize> TrustedLen for Values<'a, K, V, B> {}
+// endregion
+
+// region ValuesMut
+pub struct ValuesMut<'a, K, V, const B: usize = 8>(IterMut<'a, K, V, B>);
+
+impl<'a, K, V, const B: usize> Iterator for ValuesMut<'a, K, V, B> {
+    type Item = &'a mut V;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, v)| v)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, K, V, const B: usize> DoubleEndedIterator for ValuesMut<'a, K, V, B> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(_, v)| v)
+    }
+}
+
+impl<'a, K, V, const B: usize> ExactSizeIterator for ValuesMut<'a, K, V, B> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<'a, K, V, const B: usize> FusedIterator for ValuesMut<'a, K, V, B> {}
+
+// SAFETY: `size_hint` is exact
+#[cfg(feature = "nightly")]
+unsafe impl<'a, K, V, const B: usize> TrustedLen for ValuesMut<'a, K, V, B> {}
+// endregion
+
+// region IntoKeys
+pub struct IntoKeys<'store, K, V, const B: usize = 8>(IntoIter<'store, K, V, B>);
+
+impl<'store, K, V, const B: usize> Iterator for IntoKeys<'store, K, V, B> {
+    type Item = K;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, _)| k)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'store, K, V, const B: usize> DoubleEndedIterator for IntoKeys<'store, K, V, B> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(k, _)| k)
+    }
+}
+
+impl<'store, K, V, const B: usize> ExactSizeIterator for IntoKeys<'store, K, V, B> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<'store, K, V, const B: usize> FusedIterator for IntoKeys<'store, K, V, B> {}
+
+// SAFETY: `size_hint` is exact
+#[cfg(feature = "nightly")]
+unsafe impl<'store, K, V, const B: usize> TrustedLen for IntoKeys<'store, K, V, B> {}
+// endregion
+
+// region IntoValues
+pub struct IntoValues<'store, K, V, const B: usize = 8>(IntoIter<'store, K, V, B>);
+
+impl<'store, K, V, const B: usize> Iterator for IntoValues<'store, K, V, B> {
+    type Item = V;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, v)| v)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'store, K, V, const B: usize> DoubleEndedIterator for IntoValues<'store, K, V, B> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(_, v)| v)
+    }
+}
+
+impl<'store, K, V, const B: usize> ExactSizeIterator for IntoValues<'store, K, V, B> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<'store, K, V, const B: usize> FusedIterator for IntoValues<'store, K, V, B> {}
+
+// SAFETY: `size_hint` is exact
+#[cfg(feature = "nightly")]
+unsafe impl<'store, K, V, const B: usize> TrustedLen for IntoValues<'store, K, V, B> {}
+// endregion
+
+// region Drain
+pub struct Drain<'store, K, V, const B: usize = 8>(IntoIter<'store, K, V, B>);
+
+impl<'store, K, V, const B: usize> Iterator for Drain<'store, K, V, B> {
+    type Item = (K, V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'store, K, V, const B: usize> DoubleEndedIterator for Drain<'store, K, V, B> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back()
+    }
+}
+
+impl<'store, K, V, const B: usize> ExactSizeIterator for Drain<'store, K, V, B> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<'store, K, V, const B: usize> FusedIterator for Drain<'store, K, V, B> {}
+
+// SAFETY: `size_hint` is exact
+#[cfg(feature = "nightly")]
+unsafe impl<'store, K, V, const B: usize> TrustedLen for Drain<'store, K, V, B> {}
+// endregion
+
+// region Range
+pub struct Range<'a, K, V, const B: usize = 8> {
+    cursor: LeafCursor<'a, K, V, B>,
+    back_cursor: LeafCursor<'a, K, V, B>,
+    _p: PhantomData<(&'a K, &'a V)>,
+}
+
+//noinspection DuplicatedCode
+impl<'a, K, V, const B: usize> Range<'a, K, V, B> {
+    #[inline]
+    fn new<Q: Ord + ?Sized>(tree: &'a BTreeMap<K, V, B>, bounds: impl RangeBounds<Q>) -> Self
+    where
+        K: Borrow<Q>,
+    {
+        Self::new_within(tree.node_bounds(bounds))
+    }
+
+    /// Iterates over the entries within `bounds`
+    #[inline]
+    fn new_within(bounds: Option<NodeBounds<K, V, B>>) -> Self {
+        let cursor = match bounds.as_ref().map(|b| b.start()) {
+            None => LeafCursor::new_detached(),
+            Some((start_node, start_idx)) => unsafe {
+                LeafCursor::new(Some(start_node), start_idx)
+            },
+        };
+        let back_cursor = match bounds.as_ref().map(|b| b.end()) {
+            None => LeafCursor::new_detached(),
+            Some((end_node, end_idx)) => unsafe { LeafCursor::new(Some(end_node), end_idx) },
+        };
+        Self {
+            cursor,
+            back_cursor,
+            _p: PhantomData,
+        }
+    }
+
+    /// Get the next element without advancing the iterator
+    #[inline]
+    pub fn peek(&self) -> Option<(&'a K, &'a V)> {
+        self.cursor.key_value()
+    }
+
+    /// Get the next back element without advancing the back iterator
+    #[inline]
+    pub fn peek_back(&self) -> Option<(&'a K, &'a V)> {
+        self.back_cursor.key_value()
+    }
+
+    /// Equivalent to `next` except *panics* if iteration is done.
+    #[track_caller]
+    #[inline]
+    pub fn advance(&mut self) {
+        self.try_advance().or_panic("Range::advance")
+    }
+
+    /// Equivalent to `next_back` except *panics* if iteration is done.
+    #[track_caller]
+    #[inline]
+    pub fn advance_back(&mut self) {
+        self.try_advance_back().or_panic("Range::advance_back")
+    }
+
+    /// Equivalent to `next` except returns an error if iteration is done.
+    #[inline]
+    pub fn try_advance(&mut self) -> Result<(), Error> {
+        if !self.cursor.is_attached() {
+            return Err(Error::IterationDone);
+        }
+        self.advance_unchecked();
+        Ok(())
+    }
+
+    /// Equivalent to `next_back` except returns an error if iteration is done.
+    #[inline]
+    pub fn try_advance_back(&mut self) -> Result<(), Error> {
+        if !self.back_cursor.is_attached() {
+            return Err(Error::IterationDone);
+        }
+        self.advance_back_unchecked();
+        Ok(())
+    }
+
+    /// Iteration must not be done
+    #[inline]
+    fn advance_unchecked(&mut self) {
+        // The cursors meet at the last entry left
+        if self.cursor.address().ptr_eq(&self.back_cursor.address()) {
+            self.cursor.detach();
+            self.back_cursor.detach()
+        } else {
+            self.cursor.advance();
+            if !self.cursor.is_attached() {
+                self.back_cursor.detach();
+            }
+        }
+    }
+
+    /// Iteration must not be done
+    #[inline]
+    fn advance_back_unchecked(&mut self) {
+        // The cursors meet at the last entry left
+        if self.back_cursor.address().ptr_eq(&self.cursor.address()) {
+            self.cursor.detach();
+            self.back_cursor.detach()
+        } else {
+            self.back_cursor.advance_back();
+            if !self.back_cursor.is_attached() {
+                self.cursor.detach();
+            }
+        }
+    }
+}
+
+impl<'a, K, V, const B: usize> Iterator for Range<'a, K, V, B> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let key_value = self.peek()?;
+        self.advance_unchecked();
+        Some(key_value)
+    }
+}
+
+impl<'a, K, V, const B: usize> DoubleEndedIterator for Range<'a, K, V, B> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let key_value = self.peek_back()?;
+        self.advance_back_unchecked();
+        Some(key_value)
+    }
+}
+
+impl<'a, K, V, const B: usize> FusedIterator for Range<'a, K, V, B> {}
+
+impl<'a, K, V, const B: usize> Clone for Range<'a, K, V, B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            cursor: self.cursor.clone(),
+            back_cursor: self.back_cursor.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+// endregion
+
+// region RangeStep
+#[cfg(feature = "order-statistic")]
+pub struct RangeStep<'a, K, V, const B: usize = 8> {
+    root: Option<NodePtr<K, V, B>>,
+    height: usize,
+    /// Index of the next entry
+    start: usize,
+    /// Index after the last entry, which isn't necessarily a multiple of `step` after `start`
+    end: usize,
+    step: usize,
+    _p: PhantomData<(&'a K, &'a V)>,
+}
+
+#[cfg(feature = "order-statistic")]
+impl<'a, K, V, const B: usize> RangeStep<'a, K, V, B> {
+    #[inline]
+    fn new(tree: &'a BTreeMap<K, V, B>, start: usize, end: usize, step: usize) -> Self {
+        Self {
+            root: tree.root,
+            height: tree.height,
+            start,
+            end,
+            step,
+            _p: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "order-statistic")]
+impl<'a, K, V, const B: usize> Iterator for RangeStep<'a, K, V, B> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.start >= self.end {
+            return None;
+        }
+        let index = self.start;
+        self.start = self.start.saturating_add(self.step);
+        Some(unsafe { key_val_at_index(self.root?, self.height, index) })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+
+#[cfg(feature = "order-statistic")]
+impl<'a, K, V, const B: usize> DoubleEndedIterator for RangeStep<'a, K, V, B> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let index = self.start + (self.len().checked_sub(1)? * self.step);
+        self.end = index;
+        Some(unsafe { key_val_at_index(self.root?, self.height, index) })
+    }
+}
+
+#[cfg(feature = "order-statistic")]
+impl<'a, K, V, const B: usize> ExactSizeIterator for RangeStep<'a, K, V, B> {
+    #[inline]
+    fn len(&self) -> usize {
+        match self.end.checked_sub(self.start) {
+            None | Some(0) => 0,
+            Some(len) => (len - 1) / self.step + 1,
+        }
+    }
+}
+
+#[cfg(feature = "order-statistic")]
+impl<'a, K, V, const B: usize> FusedIterator for RangeStep<'a, K, V, B> {}
+
+#[cfg(feature = "order-statistic")]
+impl<'a, K, V, const B: usize> Clone for RangeStep<'a, K, V, B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root,
+            height: self.height,
+            start: self.start,
+            end: self.end,
+            step: self.step,
+            _p: PhantomData,
+        }
+    }
+}
+
+// SAFETY: `size_hint` is exact
+#[cfg(all(feature = "order-statistic", feature = "nightly"))]
+unsafe impl<'a, K, V, const B: usize> TrustedLen for RangeStep<'a, K, V, B> {}
 // endregion
 
-// region iterators (almost all boilerplate)
-//noinspection DuplicatedCode
-// region iterator impls
-impl<'store: 'a, 'a, K, V> IntoIterator for &'a BTreeMap<'store, K, V> {
-    type Item = (&'a K, &'a V);
-    type IntoIter = Iter<'a, K, V>;
+// region GetBatch
+pub struct GetBatch<'a, K, V, Q, const B: usize = 8> {
+    map: &'a BTreeMap<'a, K, V, B>,
+    keys: std::slice::Iter<'a, Q>,
+    /// Leaf of the previous key, where the next search starts
+    leaf: Option<NodePtr<K, V, B>>,
+}
+
+impl<'a, K: Borrow<Q>, V, Q: Ord, const B: usize> Iterator for GetBatch<'a, K, V, Q, B> {
+    type Item = Option<&'a V>;
 
     #[inline]
-    fn into_iter(self) -> Self::IntoIter {
-        self.iter()
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = self.keys.next()?;
+        let start = match self.leaf {
+            Some(leaf) => Some(BTreeMap::walk_up(leaf, key)),
+            None => self.map.search_start(key),
+        };
+        let Some((node, height)) = start else {
+            return Some(None);
+        };
+        Some(match self.map.descend(node, height, key) {
+            Find::At { node, idx } => {
+                self.leaf = Some(node);
+                Some(unsafe { node.as_ref().val(idx) })
+            }
+            Find::Before { node, .. } => {
+                self.leaf = Some(node);
+                None
+            }
+            Find::NoRoot => None,
+        })
     }
-}
-
-impl<'store: 'a, 'a, K, V> IntoIterator for &'a mut BTreeMap<'store, K, V> {
-    type Item = (&'a K, &'a mut V);
-    type IntoIter = IterMut<'a, K, V>;
 
     #[inline]
-    fn into_iter(self) -> Self::IntoIter {
-        self.iter_mut()
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
     }
 }
 
-impl<'store, K, V> IntoIterator for BTreeMap<'store, K, V> {
-    type Item = (K, V);
-    type IntoIter = IntoIter<'store, K, V>;
+impl<'a, K: Borrow<Q>, V, Q: Ord, const B: usize> ExactSizeIterator for GetBatch<'a, K, V, Q, B> {}
+
+impl<'a, K: Borrow<Q>, V, Q: Ord, const B: usize> FusedIterator for GetBatch<'a, K, V, Q, B> {}
 
+impl<'a, K, V, Q, const B: usize> Clone for GetBatch<'a, K, V, Q, B> {
     #[inline]
-    fn into_iter(self) -> Self::IntoIter {
-        IntoIter::new(self)
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map,
+            keys: self.keys.clone(),
+            leaf: self.leaf,
+        }
     }
 }
 // endregion
 
-// region Iter
-pub struct Iter<'a, K, V> {
-    cursor: Cursor<'a, K, V>,
-    back_cursor: Cursor<'a, K, V>,
-    length: usize,
+// region GroupBy
+pub struct GroupBy<'a, K, V, F, const B: usize = 8> {
+    root: Option<NodePtr<K, V, B>>,
+    height: usize,
+    next: Option<(NodePtr<K, V, B>, u16)>,
+    prefix: F,
     _p: PhantomData<(&'a K, &'a V)>,
 }
 
-//noinspection DuplicatedCode
-impl<'a, K, V> Iter<'a, K, V> {
+impl<'a, K: Ord, V, F, const B: usize> GroupBy<'a, K, V, F, B> {
     #[inline]
-    fn new(tree: &'a BTreeMap<K, V>) -> Self {
+    fn new(tree: &'a BTreeMap<K, V, B>, prefix: F) -> Self {
         Self {
-            cursor: unsafe { Cursor::new(tree.first_leaf(), 0) },
-            back_cursor: unsafe { Cursor::new_at_end(tree.last_leaf()) },
-            length: tree.length,
+            root: tree.root,
+            height: tree.height,
+            next: tree.first_leaf().map(|node| (node, 0)),
+            prefix,
             _p: PhantomData,
         }
     }
 
-    /// Get the next element without advancing the iterator
+    /// Address of the last entry whose prefix is `<= prefix`, or `start` if it's before `start`
+    /// (only possible if the prefix function isn't monotonic).
     #[inline]
-    pub fn peek(&self) -> Option<(&'a K, &'a V)> {
-        if self.length == 0 {
-            return None;
+    unsafe fn group_end<P: Ord>(
+        &mut self,
+        start_node: NodePtr<K, V, B>,
+        start_idx: u16,
+        prefix: &P,
+    ) -> (NodePtr<K, V, B>, u16)
+    where
+        F: FnMut(&K) -> P,
+    {
+        let f = &mut self.prefix;
+        let mut in_group = |key: &K| f(key) <= *prefix;
+
+        // Small groups usually end in their first leaf
+        let keys = start_node.as_ref().keys();
+        if !in_group(&keys[keys.len() - 1]) {
+            let end_idx = keys[start_idx as usize + 1..].partition_point(&mut in_group) as u16;
+            return (start_node, start_idx + end_idx);
         }
-        self.cursor.key_value()
-    }
 
-    /// Get the next back element without advancing the back iterator
-    #[inline]
-    pub fn peek_back(&self) -> Option<(&'a K, &'a V)> {
-        if self.length == 0 {
-            return None;
+        // Otherwise descend to the first entry after the group, which is in the edge before the
+        // first separator after the group
+        let mut node = self.root.unwrap_unchecked();
+        for _ in 0..self.height {
+            let idx = node.as_ref().keys().partition_point(&mut in_group) as u16;
+            node = node.as_ref().edge(idx);
+        }
+        let idx = node.as_ref().keys().partition_point(&mut in_group) as u16;
+        match address_before(node, idx) {
+            Some((end_node, end_idx))
+                if end_node.as_ref().key(end_idx) >= start_node.as_ref().key(start_idx) =>
+            {
+                (end_node, end_idx)
+            }
+            _ => (start_node, start_idx),
         }
-        self.back_cursor.key_value()
     }
+}
+
+impl<'a, K: Ord, V, P: Ord, F: FnMut(&K) -> P, const B: usize> Iterator
+    for GroupBy<'a, K, V, F, B>
+{
+    type Item = (P, Range<'a, K, V, B>);
 
-    /// Equivalent to `next` except *panics* if iteration is done.
     #[inline]
-    pub fn advance(&mut self) {
-        if self.length == 0 {
-            panic!("iteration is done");
+    fn next(&mut self) -> Option<Self::Item> {
+        let (start_node, start_idx) = self.next?;
+        unsafe {
+            let prefix = (self.prefix)(start_node.as_ref().key(start_idx));
+            let (end_node, end_idx) = self.group_end(start_node, start_idx, &prefix);
+            self.next = address_after(end_node, end_idx);
+            let bounds = NodeBounds {
+                start_node,
+                end_node,
+                start_index: start_idx,
+                end_index: end_idx,
+            };
+            Some((prefix, Range::new_within(Some(bounds))))
         }
-        self.cursor.advance();
-        self.length -= 1;
     }
+}
 
-    /// Equivalent to `next_back` except *panics* if iteration is done.
+impl<'a, K: Ord, V, P: Ord, F: FnMut(&K) -> P, const B: usize> FusedIterator
+    for GroupBy<'a, K, V, F, B>
+{
+}
+// endregion
+
+// region EntriesWithHandles
+pub struct EntriesWithHandles<'a, K, V, const B: usize = 8> {
+    iter: Iter<'a, K, V, B>,
+    last: Option<NodePtr<K, V, B>>,
+}
+
+/// Position of an entry in a [BTreeMap], from [BTreeMap::entries_with_handles]. The map is
+/// borrowed for `'a`, so the entry can't move and accessing it is O(1).
+pub struct EntryHandle<'a, K, V, const B: usize = 8> {
+    node: NodePtr<K, V, B>,
+    idx: u16,
+    /// The tree's last leaf, so iteration can resume from the entry
+    last: NodePtr<K, V, B>,
+    _p: PhantomData<(&'a K, &'a V)>,
+}
+
+impl<'a, K, V, const B: usize> EntriesWithHandles<'a, K, V, B> {
+    /// # Safety
+    /// `address` must be an entry in the iterated map
     #[inline]
-    pub fn advance_back(&mut self) {
-        if self.length == 0 {
-            panic!("iteration is done");
+    unsafe fn handle(&self, (node, idx): (NodePtr<K, V, B>, u16)) -> EntryHandle<'a, K, V, B> {
+        EntryHandle {
+            node,
+            idx,
+            last: self.last.unwrap_unchecked(),
+            _p: PhantomData,
         }
-        self.back_cursor.advance_back();
-        self.length -= 1;
     }
 }
 
-impl<'a, K, V> Iterator for Iter<'a, K, V> {
-    type Item = (&'a K, &'a V);
+impl<'a, K, V, const B: usize> Iterator for EntriesWithHandles<'a, K, V, B> {
+    type Item = (EntryHandle<'a, K, V, B>, &'a K, &'a V);
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        let key_value = self.peek()?;
-        self.advance();
-        Some(key_value)
+        let address = self.iter.cursor.address();
+        let (key, val) = self.iter.next()?;
+        Some((unsafe { self.handle(address.unwrap_unchecked()) }, key, val))
     }
 
     #[inline]
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.length, Some(self.length))
+        self.iter.size_hint()
     }
 }
 
-impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+impl<'a, K, V, const B: usize> DoubleEndedIterator for EntriesWithHandles<'a, K, V, B> {
     #[inline]
     fn next_back(&mut self) -> Option<Self::Item> {
-        let key_value = self.peek_back()?;
-        self.advance_back();
-        Some(key_value)
+        let address = self.iter.back_cursor.address();
+        let (key, val) = self.iter.next_back()?;
+        Some((unsafe { self.handle(address.unwrap_unchecked()) }, key, val))
     }
 }
 
-impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {
+impl<'a, K, V, const B: usize> ExactSizeIterator for EntriesWithHandles<'a, K, V, B> {
     #[inline]
     fn len(&self) -> usize {
-        self.length
+        self.iter.len()
+    }
+}
+
+impl<'a, K, V, const B: usize> FusedIterator for EntriesWithHandles<'a, K, V, B> {}
+
+// SAFETY: `size_hint` is exact
+#[cfg(feature = "nightly")]
+unsafe impl<'a, K, V, const B: usize> TrustedLen for EntriesWithHandles<'a, K, V, B> {}
+
+impl<'a, K, V, const B: usize> EntryHandle<'a, K, V, B> {
+    /// The entry's key
+    #[inline]
+    pub fn key(&self) -> &'a K {
+        self.key_value().0
+    }
+
+    /// The entry's value
+    #[inline]
+    pub fn value(&self) -> &'a V {
+        self.key_value().1
+    }
+
+    /// The entry's key and value
+    #[inline]
+    pub fn key_value(&self) -> (&'a K, &'a V) {
+        unsafe { self.node.as_ref().key_val(self.idx) }
+    }
+
+    /// Iterates over the map's key-value pairs in order, from this entry to the end of the map
+    #[inline]
+    pub fn iter_from(&self) -> Range<'a, K, V, B> {
+        Range::new_within(Some(NodeBounds {
+            start_node: self.node,
+            end_node: self.last,
+            start_index: self.idx,
+            end_index: unsafe { self.last.as_ref().len - 1 },
+        }))
+    }
+}
+
+impl<'a, K, V, const B: usize> Clone for EntryHandle<'a, K, V, B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, K, V, const B: usize> Copy for EntryHandle<'a, K, V, B> {}
+
+impl<'a, K, V, const B: usize> PartialEq for EntryHandle<'a, K, V, B> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.node.ptr_eq(&other.node) && self.idx == other.idx
     }
 }
 
-impl<'a, K, V> FusedIterator for Iter<'a, K, V> {}
+impl<'a, K, V, const B: usize> Eq for EntryHandle<'a, K, V, B> {}
+
+impl<'a, K: Debug, V: Debug, const B: usize> Debug for EntryHandle<'a, K, V, B> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("EntryHandle")
+            .field(&self.key_value())
+            .finish()
+    }
+}
 // endregion
 
-// region IterMut
-pub struct IterMut<'a, K, V> {
-    cursor: Cursor<'a, K, V>,
-    back_cursor: Cursor<'a, K, V>,
-    length: usize,
-    /// Unlike in [Cursor], reference to `V` is mutable
+// region Diff
+pub struct Diff<'a, K, V, const B: usize = 8> {
+    old: Iter<'a, K, V, B>,
+    new: Iter<'a, K, V, B>,
+}
+
+impl<'a, K: Ord, V: PartialEq, const B: usize> Iterator for Diff<'a, K, V, B> {
+    type Item = DiffItem<'a, K, V>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let (Some((old_node, old_idx)), Some((new_node, new_idx))) =
+                (self.old.cursor.address(), self.new.cursor.address())
+            {
+                if old_node.ptr_eq(&new_node) && old_idx == new_idx {
+                    // The rest of both maps is the same leaves
+                    self.old = Iter::new_within(None, 0);
+                    self.new = Iter::new_within(None, 0);
+                    return None;
+                }
+            }
+            let item = match (self.old.peek(), self.new.peek()) {
+                (None, None) => return None,
+                (Some((key, val)), None) => {
+                    self.old.next();
+                    DiffItem::Removed(key, val)
+                }
+                (None, Some((key, val))) => {
+                    self.new.next();
+                    DiffItem::Added(key, val)
+                }
+                (Some((old_key, old_val)), Some((new_key, new_val))) => {
+                    match old_key.cmp(new_key) {
+                        Ordering::Less => {
+                            self.old.next();
+                            DiffItem::Removed(old_key, old_val)
+                        }
+                        Ordering::Greater => {
+                            self.new.next();
+                            DiffItem::Added(new_key, new_val)
+                        }
+                        Ordering::Equal => {
+                            self.old.next();
+                            self.new.next();
+                            if old_val == new_val {
+                                continue;
+                            }
+                            DiffItem::Changed(old_key, old_val, new_val)
+                        }
+                    }
+                }
+            };
+            return Some(item);
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.old.len() + self.new.len()))
+    }
+}
+
+impl<'a, K: Ord, V: PartialEq, const B: usize> FusedIterator for Diff<'a, K, V, B> {}
+
+impl<'a, K, V> Clone for DiffItem<'a, K, V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, K, V> Copy for DiffItem<'a, K, V> {}
+// endregion
+
+// region RangeMut
+pub struct RangeMut<'a, K, V, const B: usize = 8> {
+    cursor: LeafCursor<'a, K, V, B>,
+    back_cursor: LeafCursor<'a, K, V, B>,
+    /// Unlike [LeafCursor], the reference to `V` is mutable
     _p: PhantomData<(&'a K, &'a mut V)>,
 }
 
 //noinspection DuplicatedCode
-impl<'a, K, V> IterMut<'a, K, V> {
+impl<'a, K, V, const B: usize> RangeMut<'a, K, V, B> {
     #[inline]
-    fn new(tree: &'a BTreeMap<K, V>) -> Self {
+    fn new<Q: Ord + ?Sized>(tree: &'a BTreeMap<K, V, B>, bounds: impl RangeBounds<Q>) -> Self
+    where
+        K: Borrow<Q>,
+    {
+        let bounds = tree.node_bounds(bounds);
+        let cursor = match bounds.as_ref().map(|b| b.start()) {
+            None => LeafCursor::new_detached(),
+            Some((start_node, start_idx)) => unsafe {
+                LeafCursor::new(Some(start_node), start_idx)
+            },
+        };
+        let back_cursor = match bounds.as_ref().map(|b| b.end()) {
+            None => LeafCursor::new_detached(),
+            Some((end_node, end_idx)) => unsafe { LeafCursor::new(Some(end_node), end_idx) },
+        };
         Self {
-            cursor: unsafe { Cursor::new(tree.first_leaf(), 0) },
-            back_cursor: unsafe { Cursor::new_at_end(tree.last_leaf()) },
-            length: tree.length,
+            cursor,
+            back_cursor,
             _p: PhantomData,
         }
     }
@@ -1305,516 +5555,646 @@ impl<'a, K, V> IterMut<'a, K, V> {
     /// Get the next element without advancing the iterator
     #[inline]
     pub fn peek(&self) -> Option<(&'a K, &'a V)> {
-        if self.length == 0 {
-            return None;
-        }
         self.cursor.key_value()
     }
 
     /// Get the next back element without advancing the back iterator
     #[inline]
     pub fn peek_back(&self) -> Option<(&'a K, &'a V)> {
-        if self.length == 0 {
-            return None;
-        }
         self.back_cursor.key_value()
     }
 
-    /// Get the next element without advancing the iterator
+    /// Get the next element without advancing the iterator, with the value reference mutable
     #[inline]
     pub fn peek_mut(&mut self) -> Option<(&'a K, &'a mut V)> {
-        if self.length == 0 {
-            return None;
-        }
         unsafe { self.cursor.key_value_mut() }
     }
 
-    /// Get the next back element without advancing the back iterator
+    /// Get the next back element without advancing the back iterator, with the value reference
+    /// mutable
     #[inline]
     pub fn peek_back_mut(&mut self) -> Option<(&'a K, &'a mut V)> {
-        if self.length == 0 {
-            return None;
-        }
         unsafe { self.back_cursor.key_value_mut() }
     }
 
     /// Equivalent to `next` except *panics* if iteration is done.
+    #[track_caller]
     #[inline]
     pub fn advance(&mut self) {
-        if self.length == 0 {
-            panic!("iteration is done");
-        }
-        self.cursor.advance();
-        self.length -= 1;
+        self.try_advance().or_panic("RangeMut::advance")
     }
 
     /// Equivalent to `next_back` except *panics* if iteration is done.
+    #[track_caller]
     #[inline]
     pub fn advance_back(&mut self) {
-        if self.length == 0 {
-            panic!("iteration is done");
+        self.try_advance_back().or_panic("RangeMut::advance_back")
+    }
+
+    /// Equivalent to `next` except returns an error if iteration is done.
+    #[inline]
+    pub fn try_advance(&mut self) -> Result<(), Error> {
+        if !self.cursor.is_attached() {
+            return Err(Error::IterationDone);
         }
-        self.back_cursor.advance_back();
-        self.length -= 1;
+        self.advance_unchecked();
+        Ok(())
     }
-}
 
-impl<'a, K, V> Iterator for IterMut<'a, K, V> {
-    type Item = (&'a K, &'a mut V);
+    /// Equivalent to `next_back` except returns an error if iteration is done.
+    #[inline]
+    pub fn try_advance_back(&mut self) -> Result<(), Error> {
+        if !self.back_cursor.is_attached() {
+            return Err(Error::IterationDone);
+        }
+        self.advance_back_unchecked();
+        Ok(())
+    }
 
+    /// Iteration must not be done
     #[inline]
-    fn next(&mut self) -> Option<Self::Item> {
-        let key_value = self.peek_mut()?;
-        self.advance();
-        Some(key_value)
+    fn advance_unchecked(&mut self) {
+        // The cursors meet at the last entry left
+        if self.cursor.address().ptr_eq(&self.back_cursor.address()) {
+            self.cursor.detach();
+            self.back_cursor.detach()
+        } else {
+            self.cursor.advance();
+            if !self.cursor.is_attached() {
+                self.back_cursor.detach();
+            }
+        }
     }
 
+    /// Iteration must not be done
     #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.length, Some(self.length))
+    fn advance_back_unchecked(&mut self) {
+        // The cursors meet at the last entry left
+        if self.back_cursor.address().ptr_eq(&self.cursor.address()) {
+            self.cursor.detach();
+            self.back_cursor.detach()
+        } else {
+            self.back_cursor.advance_back();
+            if !self.back_cursor.is_attached() {
+                self.cursor.detach();
+            }
+        }
     }
 }
 
-impl<'a, K, V> DoubleEndedIterator for IterMut<'a, K, V> {
+impl<'a, K, V, const B: usize> Iterator for RangeMut<'a, K, V, B> {
+    type Item = (&'a K, &'a mut V);
+
     #[inline]
-    fn next_back(&mut self) -> Option<Self::Item> {
-        let key_value = self.peek_back_mut()?;
-        self.advance_back();
+    fn next(&mut self) -> Option<Self::Item> {
+        let key_value = self.peek_mut()?;
+        self.advance_unchecked();
         Some(key_value)
     }
 }
 
-impl<'a, K, V> ExactSizeIterator for IterMut<'a, K, V> {
+impl<'a, K, V, const B: usize> DoubleEndedIterator for RangeMut<'a, K, V, B> {
     #[inline]
-    fn len(&self) -> usize {
-        self.length
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let key_value = self.peek_back_mut()?;
+        self.advance_back_unchecked();
+        Some(key_value)
     }
 }
 
-impl<'a, K, V> FusedIterator for IterMut<'a, K, V> {}
+impl<'a, K, V, const B: usize> FusedIterator for RangeMut<'a, K, V, B> {}
 // endregion
 
-// region IntoIter
-pub struct IntoIter<'store, K, V> {
-    store: &'store BTreeStore<K, V>,
-    cursor: Cursor<'store, K, V>,
-    back_cursor: Cursor<'store, K, V>,
-    length: usize,
-    /// Unlike in [Cursor], `K` and `V` are owned
-    _p: PhantomData<(K, V)>,
+// region Cursor
+/// A position between two entries of a map (or before the first or after the last), which can
+/// move in either direction. Returned by [BTreeMap::lower_bound] and [BTreeMap::upper_bound].
+pub struct Cursor<'a, K, V, const B: usize = 8> {
+    /// Address of the entry after the gap, which may be one past the end of its leaf, or `None` if
+    /// the map is empty
+    gap: Option<(NodePtr<K, V, B>, u16)>,
+    _p: PhantomData<(&'a K, &'a V)>,
 }
 
-impl<'store, K, V> IntoIter<'store, K, V> {
+impl<'a, K, V, const B: usize> Cursor<'a, K, V, B> {
     #[inline]
-    fn new(tree: BTreeMap<'store, K, V>) -> Self {
-        let result = Self {
-            store: tree.store,
-            cursor: unsafe { Cursor::new(tree.first_leaf(), 0) },
-            back_cursor: unsafe { Cursor::new_at_end(tree.last_leaf()) },
-            length: tree.length,
+    fn new(gap: Option<(NodePtr<K, V, B>, u16)>) -> Self {
+        Self {
+            gap,
             _p: PhantomData,
-        };
-        // We drop the tree's nodes, so prevent it drop dropping when it drops itself.
-        // This should be equivalent to `tree.root = None`
-        forget(tree);
-        result
+        }
     }
-}
 
-impl<'store, K, V> Iterator for IntoIter<'store, K, V> {
-    type Item = (K, V);
+    /// Moves past the next entry and returns it, or returns `None` at the end of the map
+    // Named like std's cursors, which aren't iterators since they move in both directions
+    #[allow(clippy::should_implement_trait)]
+    #[inline]
+    pub fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        let (node, idx) = unsafe { address_after_gap(self.gap) }?;
+        self.gap = Some((node, idx + 1));
+        Some(unsafe { node.as_ref().key_val(idx) })
+    }
 
+    /// Moves before the previous entry and returns it, or returns `None` at the start of the map
     #[inline]
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.length == 0 {
-            return None;
-        }
-        unsafe {
-            let key_value = self.cursor.read_key_value().unwrap();
-            let address = self.cursor.address().unwrap();
-            self.cursor.advance();
-            dealloc_up_lasts(address, |n| self.store.dealloc(n));
-            self.length -= 1;
-            Some(key_value)
-        }
+    pub fn prev(&mut self) -> Option<(&'a K, &'a V)> {
+        let (node, idx) = unsafe { address_before_gap(self.gap) }?;
+        self.gap = Some((node, idx));
+        Some(unsafe { node.as_ref().key_val(idx) })
+    }
+
+    /// Returns the next entry without moving
+    #[inline]
+    pub fn peek_next(&self) -> Option<(&'a K, &'a V)> {
+        let (node, idx) = unsafe { address_after_gap(self.gap) }?;
+        Some(unsafe { node.as_ref().key_val(idx) })
     }
 
+    /// Returns the previous entry without moving
     #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.length, Some(self.length))
+    pub fn peek_prev(&self) -> Option<(&'a K, &'a V)> {
+        let (node, idx) = unsafe { address_before_gap(self.gap) }?;
+        Some(unsafe { node.as_ref().key_val(idx) })
     }
 }
 
-impl<'a, K, V> DoubleEndedIterator for IntoIter<'a, K, V> {
+impl<'a, K, V, const B: usize> Clone for Cursor<'a, K, V, B> {
     #[inline]
-    fn next_back(&mut self) -> Option<Self::Item> {
-        if self.length == 0 {
-            return None;
-        }
-        unsafe {
-            let key_value = self.back_cursor.read_key_value().unwrap();
-            let address = self.back_cursor.address().unwrap();
-            self.back_cursor.advance_back();
-            dealloc_up_firsts(address, |n| self.store.dealloc(n));
-            self.length -= 1;
-            Some(key_value)
-        }
+    fn clone(&self) -> Self {
+        Self::new(self.gap)
     }
 }
 
-impl<'store, K, V> ExactSizeIterator for IntoIter<'store, K, V> {
+impl<'a, K: Debug, V: Debug, const B: usize> Debug for Cursor<'a, K, V, B> {
     #[inline]
-    fn len(&self) -> usize {
-        self.length
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cursor")
+            .field("prev", &self.peek_prev())
+            .field("next", &self.peek_next())
+            .finish()
     }
 }
 
-impl<'store, K, V> FusedIterator for IntoIter<'store, K, V> {}
-// endregion
+/// A [Cursor] which can also modify the map: mutate values, and insert or remove entries around
+/// the gap. Returned by [BTreeMap::lower_bound_mut] and [BTreeMap::upper_bound_mut].
+pub struct CursorMut<'a, 'store, K, V, const B: usize = 8> {
+    map: &'a mut BTreeMap<'store, K, V, B>,
+    /// See [Cursor::gap]
+    gap: Option<(NodePtr<K, V, B>, u16)>,
+}
 
-// region Keys
-pub struct Keys<'a, K, V>(Iter<'a, K, V>);
+impl<'a, 'store, K, V, const B: usize> CursorMut<'a, 'store, K, V, B> {
+    /// Moves past the next entry and returns it, or returns `None` at the end of the map
+    // Named like std's cursors, which aren't iterators since they move in both directions
+    #[allow(clippy::should_implement_trait)]
+    #[inline]
+    pub fn next(&mut self) -> Option<(&K, &mut V)> {
+        let (mut node, idx) = unsafe { address_after_gap(self.gap) }?;
+        self.gap = Some((node, idx + 1));
+        Some(unsafe { node.as_mut().key_val_mut(idx) })
+    }
 
-impl<'a, K, V> Iterator for Keys<'a, K, V> {
-    type Item = &'a K;
+    /// Moves before the previous entry and returns it, or returns `None` at the start of the map
+    #[inline]
+    pub fn prev(&mut self) -> Option<(&K, &mut V)> {
+        let (mut node, idx) = unsafe { address_before_gap(self.gap) }?;
+        self.gap = Some((node, idx));
+        Some(unsafe { node.as_mut().key_val_mut(idx) })
+    }
 
+    /// Returns the next entry without moving
     #[inline]
-    fn next(&mut self) -> Option<Self::Item> {
-        self.0.next().map(|(k, _)| k)
+    pub fn peek_next(&mut self) -> Option<(&K, &mut V)> {
+        let (mut node, idx) = unsafe { address_after_gap(self.gap) }?;
+        Some(unsafe { node.as_mut().key_val_mut(idx) })
     }
 
+    /// Returns the previous entry without moving
     #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.0.size_hint()
+    pub fn peek_prev(&mut self) -> Option<(&K, &mut V)> {
+        let (mut node, idx) = unsafe { address_before_gap(self.gap) }?;
+        Some(unsafe { node.as_mut().key_val_mut(idx) })
     }
-}
 
-impl<'a, K, V> DoubleEndedIterator for Keys<'a, K, V> {
+    /// Returns a read-only cursor at the same gap
     #[inline]
-    fn next_back(&mut self) -> Option<Self::Item> {
-        self.0.next_back().map(|(k, _)| k)
+    pub fn as_cursor(&self) -> Cursor<'_, K, V, B> {
+        Cursor::new(self.gap)
     }
-}
 
-impl<'a, K, V> ExactSizeIterator for Keys<'a, K, V> {
+    /// Inserts the entry into the gap, after which the cursor is before it, or returns an error if
+    /// the key isn't between the previous and next keys.
     #[inline]
-    fn len(&self) -> usize {
-        self.0.len()
+    pub fn insert_after(&mut self, key: K, value: V) -> Result<(), Error>
+    where
+        K: Clone + Ord,
+    {
+        self.insert(key, value, false)
     }
-}
 
-impl<'a, K, V> FusedIterator for Keys<'a, K, V> {}
-// endregion
+    /// Inserts the entry into the gap, after which the cursor is after it, or returns an error if
+    /// the key isn't between the previous and next keys.
+    #[inline]
+    pub fn insert_before(&mut self, key: K, value: V) -> Result<(), Error>
+    where
+        K: Clone + Ord,
+    {
+        self.insert(key, value, true)
+    }
 
-// region Values
-pub struct Values<'a, K, V>(Iter<'a, K, V>);
+    /// Inserts the entry like [BTreeMap::insert], but searches for its position starting from the
+    /// cursor instead of the root, then moves the cursor after it. Returns the replaced value if
+    /// the key was present.
+    ///
+    /// Unlike [CursorMut::insert_before], the key can be anywhere in the map, but the closer it is
+    /// to the cursor, the less of the tree is searched: inserting a mostly-sorted or clustered
+    /// stream this way is amortized O(1) per entry, like C++'s `map::insert(hint, value)`.
+    #[inline]
+    pub fn insert_hint(&mut self, key: K, value: V) -> Option<V>
+    where
+        K: Clone + Ord,
+    {
+        self.insert_near(key, value, true)
+    }
 
-impl<'a, K, V> Iterator for Values<'a, K, V> {
-    type Item = &'a V;
+    #[inline]
+    fn insert(&mut self, key: K, value: V, move_past: bool) -> Result<(), Error>
+    where
+        K: Clone + Ord,
+    {
+        let cursor = self.as_cursor();
+        if cursor.peek_prev().is_some_and(|(prev, _)| *prev >= key)
+            || cursor.peek_next().is_some_and(|(next, _)| *next <= key)
+        {
+            return Err(Error::UnorderedKey);
+        }
+        self.insert_near(key, value, move_past);
+        Ok(())
+    }
 
+    /// Inserts the entry, searching from the cursor's leaf, and moves the cursor before or after it
     #[inline]
-    fn next(&mut self) -> Option<Self::Item> {
-        self.0.next().map(|(_, v)| v)
+    fn insert_near(&mut self, key: K, value: V, move_past: bool) -> Option<V>
+    where
+        K: Clone + Ord,
+    {
+        if let Some((node, _)) = self.gap {
+            self.map.finger = Some(node);
+        }
+        let old_value = self.map.insert(key.clone(), value);
+        // Inserting may split the leaf, so find the entry again (from the finger, which is nearby)
+        self.gap = match self.map.find(&key) {
+            Find::At { node, idx } => Some((node, idx + move_past as u16)),
+            Find::NoRoot | Find::Before { .. } => unreachable!("inserted entry not found"),
+        };
+        old_value
     }
 
+    /// Removes and returns the next entry, or returns `None` at the end of the map
     #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.0.size_hint()
+    pub fn remove_next(&mut self) -> Option<(K, V)>
+    where
+        K: Clone + Ord,
+    {
+        let address = unsafe { address_after_gap(self.gap) }?;
+        Some(self.remove(address))
     }
-}
 
-impl<'a, K, V> DoubleEndedIterator for Values<'a, K, V> {
+    /// Removes and returns the previous entry, or returns `None` at the start of the map
     #[inline]
-    fn next_back(&mut self) -> Option<Self::Item> {
-        self.0.next_back().map(|(_, v)| v)
+    pub fn remove_prev(&mut self) -> Option<(K, V)>
+    where
+        K: Clone + Ord,
+    {
+        let address = unsafe { address_before_gap(self.gap) }?;
+        Some(self.remove(address))
+    }
+
+    /// Removes the entry at the address, which is next to the gap, and moves the gap to where it
+    /// was
+    #[inline]
+    fn remove(&mut self, (mut node, idx): (NodePtr<K, V, B>, u16)) -> (K, V)
+    where
+        K: Clone + Ord,
+    {
+        unsafe {
+            let entry = node.as_mut().remove_val(idx);
+            let min_len = self.map.store.rebalance.relaxed_min_len(B);
+            if (node.as_ref().len as usize) < min_len {
+                // Rebalancing moves entries between leaves, so find the gap again
+                self.map.post_removal(node);
+                self.gap = match self.map.find(&entry.0) {
+                    Find::NoRoot => None,
+                    Find::Before { node, idx } => Some((node, idx)),
+                    Find::At { .. } => unreachable!("removed entry found"),
+                };
+            } else {
+                self.map.post_removal(node);
+                self.gap = Some((node, idx));
+            }
+            entry
+        }
     }
 }
 
-impl<'a, K, V> ExactSizeIterator for Values<'a, K, V> {
+impl<'a, 'store, K: Debug, V: Debug, const B: usize> Debug for CursorMut<'a, 'store, K, V, B> {
     #[inline]
-    fn len(&self) -> usize {
-        self.0.len()
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("CursorMut").field(&self.as_cursor()).finish()
     }
 }
 
-impl<'a, K, V> FusedIterator for Values<'a, K, V> {}
+/// Address of the entry after the gap, which is in the next leaf if the gap is at the end of one
+#[inline]
+unsafe fn address_after_gap<K, V, const B: usize>(
+    gap: Option<(NodePtr<K, V, B>, u16)>,
+) -> Option<(NodePtr<K, V, B>, u16)> {
+    let (node, idx) = gap?;
+    normalize_address(node, idx)
+}
+
+/// Address of the entry before the gap
+#[inline]
+unsafe fn address_before_gap<K, V, const B: usize>(
+    gap: Option<(NodePtr<K, V, B>, u16)>,
+) -> Option<(NodePtr<K, V, B>, u16)> {
+    let (node, idx) = gap?;
+    address_before(node, idx)
+}
 // endregion
 
-// region ValuesMut
-pub struct ValuesMut<'a, K, V>(IterMut<'a, K, V>);
+// region ExtractIf
+pub struct ExtractIf<'a, 'store, K, V, Q: ?Sized, R, F, const B: usize = 8> {
+    map: &'a mut BTreeMap<'store, K, V, B>,
+    /// Address of the next entry to visit, or `None` once done
+    position: Option<(NodePtr<K, V, B>, u16)>,
+    /// Only the end bound is checked, since iteration starts at the start bound
+    bounds: R,
+    pred: F,
+    _p: PhantomData<fn(&Q)>,
+}
 
-impl<'a, K, V> Iterator for ValuesMut<'a, K, V> {
-    type Item = &'a mut V;
+impl<
+        'a,
+        'store,
+        K,
+        V,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+        F: FnMut(&K, &mut V) -> bool,
+        const B: usize,
+    > Iterator for ExtractIf<'a, 'store, K, V, Q, R, F, B>
+where
+    K: Clone + Borrow<Q>,
+{
+    type Item = (K, V);
 
-    #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        self.0.next().map(|(_, v)| v)
-    }
+        loop {
+            let (mut node, idx) = self.position?;
+            let (key, val) = unsafe { node.as_mut().key_val_mut(idx) };
+            let in_bounds = match self.bounds.end_bound() {
+                Bound::Included(end) => key.borrow() <= end,
+                Bound::Excluded(end) => key.borrow() < end,
+                Bound::Unbounded => true,
+            };
+            if !in_bounds {
+                self.position = None;
+                return None;
+            }
+            if !(self.pred)(key, val) {
+                self.position = unsafe { address_after(node, idx) };
+                continue;
+            }
 
-    #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.0.size_hint()
+            return Some(unsafe {
+                let entry = node.as_mut().remove_val(idx);
+                // The next entry shifted into `idx`, unless it's in the next leaf
+                let next = match idx < node.as_ref().len {
+                    true => Some((node, idx)),
+                    false => node.as_ref().next().map(|next| (next, 0)),
+                };
+                let min_len = self.map.store.rebalance.relaxed_min_len(B);
+                if (node.as_ref().len as usize) < min_len {
+                    // Rebalancing moves entries between leaves, so find the next one again
+                    let next_key = next.map(|(node, idx)| node.as_ref().key(idx).clone());
+                    self.map.post_removal(node);
+                    self.position =
+                        next_key.and_then(|key| match self.map.find::<Q>(key.borrow()) {
+                            Find::At { node, idx } => Some((node, idx)),
+                            Find::NoRoot | Find::Before { .. } => None,
+                        });
+                } else {
+                    self.map.post_removal(node);
+                    self.position = next;
+                }
+                entry
+            });
+        }
     }
-}
 
-impl<'a, K, V> DoubleEndedIterator for ValuesMut<'a, K, V> {
     #[inline]
-    fn next_back(&mut self) -> Option<Self::Item> {
-        self.0.next_back().map(|(_, v)| v)
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.position {
+            None => (0, Some(0)),
+            Some(_) => (0, Some(self.map.length)),
+        }
     }
 }
 
-impl<'a, K, V> ExactSizeIterator for ValuesMut<'a, K, V> {
-    #[inline]
-    fn len(&self) -> usize {
-        self.0.len()
-    }
+impl<
+        'a,
+        'store,
+        K,
+        V,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+        F: FnMut(&K, &mut V) -> bool,
+        const B: usize,
+    > FusedIterator for ExtractIf<'a, 'store, K, V, Q, R, F, B>
+where
+    K: Clone + Borrow<Q>,
+{
 }
-
-impl<'a, K, V> FusedIterator for ValuesMut<'a, K, V> {}
 // endregion
 
-// region Range
-pub struct Range<'a, K, V> {
-    cursor: Cursor<'a, K, V>,
-    back_cursor: Cursor<'a, K, V>,
-    bounds: MaybeUninit<NodeBounds<K, V>>,
-    _p: PhantomData<(&'a K, &'a V)>,
+// region OccupiedEntry
+/// An entry in the map, returned by [BTreeMap::first_entry] and [BTreeMap::last_entry].
+pub struct OccupiedEntry<'a, 'store, K, V, const B: usize = 8> {
+    map: &'a mut BTreeMap<'store, K, V, B>,
+    node: NodePtr<K, V, B>,
+    idx: u16,
 }
 
-//noinspection DuplicatedCode
-impl<'a, K, V> Range<'a, K, V> {
+impl<'a, 'store, K, V, const B: usize> OccupiedEntry<'a, 'store, K, V, B> {
+    /// The entry's key
     #[inline]
-    fn new<Q: Ord + ?Sized>(tree: &'a BTreeMap<K, V>, bounds: impl RangeBounds<Q>) -> Self
-    where
-        K: Borrow<Q>,
-    {
-        let bounds = tree.node_bounds(bounds);
-        let cursor = match bounds.as_ref().map(|b| b.start()) {
-            None => Cursor::new_detached(),
-            Some((start_node, start_idx)) => unsafe { Cursor::new(Some(start_node), start_idx) },
-        };
-        let back_cursor = match bounds.as_ref().map(|b| b.end()) {
-            None => Cursor::new_detached(),
-            Some((end_node, end_idx)) => unsafe { Cursor::new(Some(end_node), end_idx) },
-        };
-        let bounds = match bounds {
-            None => MaybeUninit::uninit(),
-            Some(bounds) => MaybeUninit::new(bounds),
-        };
-        Self {
-            cursor,
-            back_cursor,
-            bounds,
-            _p: PhantomData,
-        }
+    pub fn key(&self) -> &K {
+        unsafe { self.node.as_ref().key(self.idx) }
     }
 
-    /// Get the next element without advancing the iterator
+    /// The entry's value
     #[inline]
-    pub fn peek(&self) -> Option<(&'a K, &'a V)> {
-        self.cursor.key_value()
+    pub fn get(&self) -> &V {
+        unsafe { self.node.as_ref().key_val(self.idx).1 }
     }
 
-    /// Get the next back element without advancing the back iterator
+    /// The entry's mutable value
     #[inline]
-    pub fn peek_back(&self) -> Option<(&'a K, &'a V)> {
-        self.back_cursor.key_value()
+    pub fn get_mut(&mut self) -> &mut V {
+        unsafe { self.node.as_mut().key_val_mut(self.idx).1 }
     }
 
-    /// Equivalent to `next` except *panics* if iteration is done.
+    /// Converts into the entry's mutable value, borrowed for the map's lifetime
     #[inline]
-    pub fn advance(&mut self) {
-        self.cursor.advance();
-        if !self.cursor.is_attached() {
-            self.back_cursor.detach();
-        } else if self
-            .cursor
-            .address()
-            .ptr_eq(&Some(unsafe { self.bounds.assume_init_ref() }.end()))
-        {
-            self.cursor.detach();
-            self.back_cursor.detach()
-        }
+    pub fn into_mut(mut self) -> &'a mut V {
+        unsafe { self.node.as_mut().key_val_mut(self.idx).1 }
     }
 
-    /// Equivalent to `next_back` except *panics* if iteration is done.
+    /// Replaces the entry's value and returns the old one
     #[inline]
-    pub fn advance_back(&mut self) {
-        self.back_cursor.advance_back();
-        if !self.back_cursor.is_attached() {
-            self.cursor.detach();
-        } else if self
-            .back_cursor
-            .address()
-            .ptr_eq(&Some(unsafe { self.bounds.assume_init_ref() }.start()))
-        {
-            self.cursor.detach();
-            self.back_cursor.detach()
-        }
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
     }
-}
 
-impl<'a, K, V> Iterator for Range<'a, K, V> {
-    type Item = (&'a K, &'a V);
+    /// Removes the entry from the map and returns its key and value
+    #[inline]
+    pub fn remove_entry(mut self) -> (K, V)
+    where
+        K: Clone,
+    {
+        unsafe {
+            let entry = self.node.as_mut().remove_val(self.idx);
+            self.map.post_removal(self.node);
+            entry
+        }
+    }
 
+    /// Removes the entry from the map and returns its value
     #[inline]
-    fn next(&mut self) -> Option<Self::Item> {
-        let key_value = self.peek()?;
-        self.advance();
-        Some(key_value)
+    pub fn remove(self) -> V
+    where
+        K: Clone,
+    {
+        self.remove_entry().1
     }
 }
 
-impl<'a, K, V> DoubleEndedIterator for Range<'a, K, V> {
+impl<'a, 'store, K: Debug, V: Debug, const B: usize> Debug for OccupiedEntry<'a, 'store, K, V, B> {
     #[inline]
-    fn next_back(&mut self) -> Option<Self::Item> {
-        let key_value = self.peek_back()?;
-        self.advance_back();
-        Some(key_value)
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OccupiedEntry")
+            .field("key", self.key())
+            .field("value", self.get())
+            .finish()
     }
 }
-
-impl<'a, K, V> FusedIterator for Range<'a, K, V> {}
 // endregion
 
-// region RangeMut
-pub struct RangeMut<'a, K, V> {
-    cursor: Cursor<'a, K, V>,
-    back_cursor: Cursor<'a, K, V>,
-    bounds: MaybeUninit<NodeBounds<K, V>>,
-    /// Unlike [Cursor], the reference to `V` is mutable
-    _p: PhantomData<(&'a K, &'a mut V)>,
+// region Nodes
+/// Iterator over summaries of a map's nodes, returned by [BTreeMap::nodes]
+pub struct Nodes<'a, K, V, const B: usize = 8>(NodeIter<'a, K, V, B>);
+
+/// Summary of a single node in the tree, yielded by [BTreeMap::nodes].
+#[derive(Debug)]
+pub struct NodeSummary<'a, K> {
+    depth: usize,
+    is_leaf: bool,
+    keys: &'a [K],
 }
 
-//noinspection DuplicatedCode
-impl<'a, K, V> RangeMut<'a, K, V> {
+impl<'a, K> NodeSummary<'a, K> {
+    /// Distance from the root (the root has depth 0, its children depth 1, etc.)
     #[inline]
-    fn new<Q: Ord + ?Sized>(tree: &'a BTreeMap<K, V>, bounds: impl RangeBounds<Q>) -> Self
-    where
-        K: Borrow<Q>,
-    {
-        let bounds = tree.node_bounds(bounds);
-        let cursor = match bounds.as_ref().map(|b| b.start()) {
-            None => Cursor::new_detached(),
-            Some((start_node, start_idx)) => unsafe { Cursor::new(Some(start_node), start_idx) },
-        };
-        let back_cursor = match bounds.as_ref().map(|b| b.end()) {
-            None => Cursor::new_detached(),
-            Some((end_node, end_idx)) => unsafe { Cursor::new(Some(end_node), end_idx) },
-        };
-        let bounds = match bounds {
-            None => MaybeUninit::uninit(),
-            Some(bounds) => MaybeUninit::new(bounds),
-        };
-        Self {
-            cursor,
-            back_cursor,
-            bounds,
-            _p: PhantomData,
-        }
+    pub fn depth(&self) -> usize {
+        self.depth
     }
 
-    /// Get the next element without advancing the iterator
+    /// Whether the node is a leaf, which contains entries instead of children
     #[inline]
-    pub fn peek(&self) -> Option<(&'a K, &'a V)> {
-        self.cursor.key_value()
+    pub fn is_leaf(&self) -> bool {
+        self.is_leaf
     }
 
-    /// Get the next back element without advancing the back iterator
+    /// Number of keys in the node. For a leaf this is the # of entries, for an internal node this
+    /// is the # of separator keys (1 less than the # of children).
     #[inline]
-    pub fn peek_back(&self) -> Option<(&'a K, &'a V)> {
-        self.back_cursor.key_value()
+    pub fn len(&self) -> usize {
+        self.keys.len()
     }
 
-    /// Get the next element without advancing the iterator, with the value reference mutable
+    /// Returns `true` if the node has no keys (only possible for an empty root, which is never
+    /// yielded, so this is always `false`)
     #[inline]
-    pub fn peek_mut(&mut self) -> Option<(&'a K, &'a mut V)> {
-        unsafe { self.cursor.key_value_mut() }
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
     }
 
-    /// Get the next back element without advancing the back iterator, with the value reference
-    /// mutable
+    /// The node's keys in order. For an internal node these are the separator keys.
     #[inline]
-    pub fn peek_back_mut(&mut self) -> Option<(&'a K, &'a mut V)> {
-        unsafe { self.back_cursor.key_value_mut() }
+    pub fn keys(&self) -> &'a [K] {
+        self.keys
     }
 
-    /// Equivalent to `next` except *panics* if iteration is done.
+    /// The node's first key (lower key bound)
     #[inline]
-    pub fn advance(&mut self) {
-        self.cursor.advance();
-        if !self.cursor.is_attached() {
-            self.back_cursor.detach();
-        } else if self
-            .cursor
-            .address()
-            .ptr_eq(&Some(unsafe { self.bounds.assume_init_ref() }.end()))
-        {
-            self.cursor.detach();
-            self.back_cursor.detach()
-        }
+    pub fn first_key(&self) -> &'a K {
+        &self.keys[0]
     }
 
-    /// Equivalent to `next_back` except *panics* if iteration is done.
+    /// The node's last key (upper key bound)
     #[inline]
-    pub fn advance_back(&mut self) {
-        self.back_cursor.advance_back();
-        if !self.back_cursor.is_attached() {
-            self.cursor.detach();
-        } else if self
-            .back_cursor
-            .address()
-            .ptr_eq(&Some(unsafe { self.bounds.assume_init_ref() }.start()))
-        {
-            self.cursor.detach();
-            self.back_cursor.detach()
-        }
+    pub fn last_key(&self) -> &'a K {
+        &self.keys[self.keys.len() - 1]
     }
 }
 
-impl<'a, K, V> Iterator for RangeMut<'a, K, V> {
-    type Item = (&'a K, &'a mut V);
+impl<'a, K, V, const B: usize> Iterator for Nodes<'a, K, V, B> {
+    type Item = NodeSummary<'a, K>;
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        let key_value = self.peek_mut()?;
-        self.advance();
-        Some(key_value)
-    }
-}
-
-impl<'a, K, V> DoubleEndedIterator for RangeMut<'a, K, V> {
-    #[inline]
-    fn next_back(&mut self) -> Option<Self::Item> {
-        let key_value = self.peek_back_mut()?;
-        self.advance_back();
-        Some(key_value)
+        let (node, height) = self.0.next()?;
+        Some(NodeSummary {
+            depth: self.0.max_height() - height,
+            is_leaf: height == 0,
+            keys: unsafe { node.as_ref().keys() },
+        })
     }
 }
 
-impl<'a, K, V> FusedIterator for RangeMut<'a, K, V> {}
+impl<'a, K, V, const B: usize> FusedIterator for Nodes<'a, K, V, B> {}
 // endregion
 // endregion
 
-#[cfg(feature = "copyable")]
-impl<'store, K, V> crate::copyable::sealed::BTree<'store, K, V> for BTreeMap<'store, K, V> {
+impl<'store, K, V, const B: usize> crate::tree::sealed::BTree<'store, K, V, B>
+    for BTreeMap<'store, K, V, B>
+{
     #[inline]
-    fn assert_store(&self, store: &BTreeStore<K, V>) {
-        assert_eq!(
-            NonNull::from(self.store),
-            NonNull::from(store),
-            "b-tree is not from this store"
-        );
+    fn is_from_store(&self, store: &BTreeStore<K, V, B>) -> bool {
+        #[cfg(feature = "generations")]
+        {
+            self.store_id == store.id()
+        }
+        #[cfg(not(feature = "generations"))]
+        {
+            NonNull::from(self.store) == NonNull::from(store)
+        }
+    }
+
+    #[inline]
+    fn node_ptrs(&self) -> crate::node_iter::NodeIter<'store, K, V, B> {
+        crate::node_iter::NodeIter::new(self.root, self.height)
     }
 
     #[inline]
-    fn nodes(&self) -> crate::copyable::sealed::NodeIter<'store, K, V> {
-        crate::copyable::sealed::NodeIter::new(self.root, self.height)
+    fn as_map_mut(&mut self) -> Option<&mut crate::BTreeMap<'store, K, V, B>> {
+        Some(self)
     }
 }
 
-unsafe fn as_nullable_ptr<K, V>(ptr: Option<NodePtr<K, V>>) -> *const Node<K, V> {
+unsafe fn as_nullable_ptr<K, V, const B: usize>(
+    ptr: Option<NodePtr<K, V, B>>,
+) -> *const Node<K, V, B> {
     match ptr {
         Some(ptr) => ptr.as_ptr().as_ptr(),
         None => std::ptr::null(),
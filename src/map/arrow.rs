@@ -0,0 +1,97 @@
+use arrow_array::types::ArrowPrimitiveType;
+use arrow_array::{Array, ArrowNativeTypeOp, PrimitiveArray};
+
+use crate::error::OrPanic;
+use crate::{BTreeMap, BTreeStore, Error};
+
+impl<'store, K, V, const B: usize> BTreeMap<'store, K, V, B> {
+    /// Creates a map from a sorted key column and a value column.
+    ///
+    /// The map is bulk-loaded (nodes are filled completely without searching or rebalancing), so
+    /// this is O(n).
+    ///
+    /// # Panics
+    /// If the columns have different lengths, either column contains nulls, or the keys aren't
+    /// strictly increasing.
+    #[track_caller]
+    pub fn from_arrow_in<KA, VA>(
+        store: &'store BTreeStore<K, V, B>,
+        keys: &PrimitiveArray<KA>,
+        values: &PrimitiveArray<VA>,
+    ) -> Self
+    where
+        KA: ArrowPrimitiveType<Native = K>,
+        VA: ArrowPrimitiveType<Native = V>,
+        K: ArrowNativeTypeOp + Ord,
+        V: ArrowNativeTypeOp,
+    {
+        Self::try_from_arrow_in(store, keys, values).or_panic("BTreeMap::from_arrow_in")
+    }
+
+    /// Creates a map from a sorted key column and a value column, or returns an error if the columns
+    /// have different lengths, either column contains nulls, or the keys aren't strictly increasing.
+    ///
+    /// See [BTreeMap::from_arrow_in].
+    pub fn try_from_arrow_in<KA, VA>(
+        store: &'store BTreeStore<K, V, B>,
+        keys: &PrimitiveArray<KA>,
+        values: &PrimitiveArray<VA>,
+    ) -> Result<Self, Error>
+    where
+        KA: ArrowPrimitiveType<Native = K>,
+        VA: ArrowPrimitiveType<Native = V>,
+        K: ArrowNativeTypeOp + Ord,
+        V: ArrowNativeTypeOp,
+    {
+        if keys.len() != values.len() {
+            return Err(Error::InvalidColumns(
+                "key and value columns have different lengths",
+            ));
+        }
+        if keys.null_count() != 0 {
+            return Err(Error::InvalidColumns("key column contains nulls"));
+        }
+        if values.null_count() != 0 {
+            return Err(Error::InvalidColumns("value column contains nulls"));
+        }
+        if !keys.values().windows(2).all(|keys| keys[0] < keys[1]) {
+            return Err(Error::InvalidColumns(
+                "key column isn't strictly increasing",
+            ));
+        }
+
+        Ok(Self::bulk_load_in(
+            store,
+            keys.values()
+                .iter()
+                .copied()
+                .zip(values.values().iter().copied()),
+        ))
+    }
+
+    /// Exports the map into a key column and a value column, both in key order.
+    ///
+    /// Each leaf's keys and values are copied as a chunk.
+    pub fn to_arrow<KA, VA>(&self) -> (PrimitiveArray<KA>, PrimitiveArray<VA>)
+    where
+        KA: ArrowPrimitiveType<Native = K>,
+        VA: ArrowPrimitiveType<Native = V>,
+        K: ArrowNativeTypeOp,
+        V: ArrowNativeTypeOp,
+    {
+        let mut keys = Vec::with_capacity(self.length);
+        let mut values = Vec::with_capacity(self.length);
+        let mut leaf = self.first_leaf();
+        while let Some(node) = leaf {
+            unsafe {
+                keys.extend_from_slice(node.as_ref().keys());
+                values.extend_from_slice(node.as_ref().vals());
+                leaf = node.as_ref().next();
+            }
+        }
+        (
+            PrimitiveArray::new(keys.into(), None),
+            PrimitiveArray::new(values.into(), None),
+        )
+    }
+}
@@ -0,0 +1,360 @@
+use rayon::iter::plumbing::{bridge_unindexed, Folder, UnindexedConsumer, UnindexedProducer};
+use rayon::iter::{IntoParallelIterator, ParallelExtend, ParallelIterator};
+use rayon::slice::ParallelSliceMut;
+use std::marker::PhantomData;
+use std::mem::swap;
+
+use crate::node::{Node, NodePtr};
+use crate::{BTreeMap, BTreeStore};
+
+impl<'store, K, V, const B: usize> BTreeMap<'store, K, V, B> {
+    /// Iterates over the map's key-value pairs in parallel.
+    ///
+    /// The tree is split between threads at subtree boundaries, so each thread iterates whole
+    /// leaves. Order-preserving adaptors (e.g. `collect` into a `Vec`) get the entries in order.
+    #[inline]
+    pub fn par_iter(&self) -> ParIter<'_, K, V, B>
+    where
+        K: Sync,
+        V: Sync,
+    {
+        ParIter {
+            subtrees: Subtrees::new(self),
+            _p: PhantomData,
+        }
+    }
+
+    /// Iterates over the map's key-value pairs in parallel, with mutable references to the
+    /// values. See [BTreeMap::par_iter].
+    #[inline]
+    pub fn par_iter_mut(&mut self) -> ParIterMut<'_, K, V, B>
+    where
+        K: Sync,
+        V: Send,
+    {
+        ParIterMut {
+            subtrees: Subtrees::new(self),
+            _p: PhantomData,
+        }
+    }
+
+    /// Creates a map in `store` from the entries, in parallel. If a key is repeated, the last
+    /// entry is kept.
+    ///
+    /// Like [BTreeMap::from_iter_in], the entries are sorted and then bulk-loaded, but the sort and
+    /// filling the leaves are split between threads. Only allocating the nodes and building the
+    /// internal levels (a small fraction of the nodes) happen on the calling thread, since the
+    /// store is only accessed from there.
+    pub fn par_from_iter_in(
+        entries: impl IntoParallelIterator<Item = (K, V)>,
+        store: &'store BTreeStore<K, V, B>,
+    ) -> Self
+    where
+        K: Ord + Clone + Send,
+        V: Send,
+    {
+        let mut entries = entries.into_par_iter().collect::<Vec<_>>();
+        // Stable, so the last entry of each repeated key stays last
+        entries.par_sort_by(|(a, _), (b, _)| a.cmp(b));
+        // `dedup_by` keeps the first of each run, so move the later entry into it
+        entries.dedup_by(|later, kept| {
+            let repeated = later.0 == kept.0;
+            if repeated {
+                swap(later, kept);
+            }
+            repeated
+        });
+        Self::par_bulk_load_in(store, entries)
+    }
+
+    /// [BTreeMap::bulk_load_in] from a `Vec`, filling leaves in parallel.
+    fn par_bulk_load_in(store: &'store BTreeStore<K, V, B>, mut entries: Vec<(K, V)>) -> Self
+    where
+        K: Clone + Send,
+        V: Send,
+    {
+        let len = entries.len();
+        let mut map = Self::new_in(store);
+        map.length = len;
+
+        // Leaves are full, except the last one shares entries with its predecessor if it would be
+        // underfull, like in `bulk_load_in`
+        let mut starts = (0..len).step_by(B).collect::<Vec<_>>();
+        if let [.., _, last_start] = &mut starts[..] {
+            if len - *last_start < B / 2 {
+                *last_start = len - B / 2;
+            }
+        }
+        let mut leaves = Vec::<NodePtr<K, V, B>>::with_capacity(starts.len());
+        for _ in &starts {
+            let mut leaf = store.alloc(Node::leaf());
+            if let Some(&(mut prev)) = leaves.last() {
+                unsafe {
+                    prev.as_mut().set_next(Some(leaf));
+                    leaf.as_mut().set_prev(Some(prev));
+                }
+            }
+            leaves.push(leaf);
+        }
+
+        // SAFETY: the entries are moved out exactly once, each into its own leaf, and nothing in
+        // between can panic. Each leaf is only accessed by one thread.
+        let src = SendPtr(entries.as_mut_ptr());
+        unsafe { entries.set_len(0) };
+        let shared_leaves = SendPtr(leaves.as_mut_ptr());
+        (0..starts.len()).into_par_iter().for_each(|i| unsafe {
+            let src = src.get();
+            let end = starts.get(i + 1).copied().unwrap_or(len);
+            let mut leaf = *shared_leaves.get().add(i);
+            for (idx, entry) in (starts[i]..end).enumerate() {
+                let (key, val) = src.add(entry).read();
+                leaf.as_mut().insert_val(idx as u16, key, val);
+            }
+        });
+        drop(entries);
+
+        map.build_internal_levels(leaves, &mut |node| store.alloc(node));
+        map
+    }
+}
+
+impl<'store, K: Ord + Clone + Send, V: Send, const B: usize> ParallelExtend<(K, V)>
+    for BTreeMap<'store, K, V, B>
+{
+    /// Collects and sorts the entries in parallel. If the map is empty they're bulk-loaded like
+    /// [BTreeMap::par_from_iter_in], otherwise they're inserted in order, so each insert starts
+    /// near the last one.
+    fn par_extend<I: IntoParallelIterator<Item = (K, V)>>(&mut self, par_iter: I) {
+        if self.is_empty() {
+            *self = Self::par_from_iter_in(par_iter, self.store);
+            return;
+        }
+        let mut entries = par_iter.into_par_iter().collect::<Vec<_>>();
+        // Stable, so the last entry of each repeated key is inserted last
+        entries.par_sort_by(|(a, _), (b, _)| a.cmp(b));
+        self.extend(entries);
+    }
+}
+
+impl<'a, 'store, K: Ord + Copy + Send + Sync, V: Copy + Send + Sync, const B: usize>
+    ParallelExtend<(&'a K, &'a V)> for BTreeMap<'store, K, V, B>
+{
+    #[inline]
+    fn par_extend<I: IntoParallelIterator<Item = (&'a K, &'a V)>>(&mut self, par_iter: I) {
+        self.par_extend(par_iter.into_par_iter().map(|(k, v)| (*k, *v)))
+    }
+}
+
+/// Subtrees which together hold a run of a tree's entries, in order, and their heights
+struct Subtrees<K, V, const B: usize>(Vec<(NodePtr<K, V, B>, usize)>);
+
+impl<K, V, const B: usize> Subtrees<K, V, B> {
+    #[inline]
+    fn new(map: &BTreeMap<K, V, B>) -> Self {
+        Self(
+            map.root
+                .map(|root| (root, map.height))
+                .into_iter()
+                .collect(),
+        )
+    }
+
+    /// Splits the subtrees in half, first replacing a lone internal node with its children. Leaves
+    /// aren't split.
+    fn split(mut self) -> (Self, Option<Self>) {
+        if let [(node, height)] = self.0[..] {
+            if height == 0 {
+                return (self, None);
+            }
+            let node = unsafe { node.as_ref() };
+            self.0 = (0..=node.len)
+                .map(|idx| (unsafe { node.edge(idx) }, height - 1))
+                .collect();
+        }
+        if self.0.len() < 2 {
+            return (self, None);
+        }
+        let right = self.0.split_off(self.0.len() / 2);
+        (self, Some(Self(right)))
+    }
+
+    /// Folds `entry(leaf, idx)` for each entry in order, until the folder is full.
+    ///
+    /// # Safety
+    /// The subtrees must be alive, and `entry` must be safe to call on each of their entries.
+    unsafe fn fold<T, F: Folder<T>>(
+        self,
+        mut folder: F,
+        entry: &impl Fn(NodePtr<K, V, B>, u16) -> T,
+    ) -> F {
+        for (node, height) in self.0 {
+            folder = fold_subtree(node, height, folder, entry);
+            if folder.full() {
+                break;
+            }
+        }
+        folder
+    }
+}
+
+/// See [Subtrees::fold]
+unsafe fn fold_subtree<K, V, T, F: Folder<T>, const B: usize>(
+    node: NodePtr<K, V, B>,
+    height: usize,
+    mut folder: F,
+    entry: &impl Fn(NodePtr<K, V, B>, u16) -> T,
+) -> F {
+    let len = node.as_ref().len;
+    if height == 0 {
+        for idx in 0..len {
+            if folder.full() {
+                break;
+            }
+            folder = folder.consume(entry(node, idx));
+        }
+    } else {
+        for idx in 0..=len {
+            if folder.full() {
+                break;
+            }
+            folder = fold_subtree(node.as_ref().edge(idx), height - 1, folder, entry);
+        }
+    }
+    folder
+}
+
+/// Pointer which is sent to other threads, when they access disjoint parts of what it points to
+struct SendPtr<T>(*mut T);
+
+// SAFETY: see the uses
+unsafe impl<T> Send for SendPtr<T> {}
+unsafe impl<T> Sync for SendPtr<T> {}
+
+impl<T> SendPtr<T> {
+    /// A method, so closures capture the whole wrapper instead of the (non-`Sync`) pointer
+    #[inline]
+    fn get(&self) -> *mut T {
+        self.0
+    }
+}
+
+// region ParIter
+/// Parallel iterator over a [BTreeMap]'s entries, from [BTreeMap::par_iter]
+pub struct ParIter<'a, K, V, const B: usize = 8> {
+    subtrees: Subtrees<K, V, B>,
+    _p: PhantomData<(&'a K, &'a V)>,
+}
+
+// SAFETY: it only hands out shared references to the entries
+unsafe impl<'a, K: Sync, V: Sync, const B: usize> Send for ParIter<'a, K, V, B> {}
+
+impl<'a, K: Sync, V: Sync, const B: usize> ParallelIterator for ParIter<'a, K, V, B> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+        bridge_unindexed(self, consumer)
+    }
+}
+
+impl<'a, K: Sync, V: Sync, const B: usize> UnindexedProducer for ParIter<'a, K, V, B> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn split(self) -> (Self, Option<Self>) {
+        let (left, right) = self.subtrees.split();
+        let right = right.map(|subtrees| Self {
+            subtrees,
+            _p: PhantomData,
+        });
+        (
+            Self {
+                subtrees: left,
+                _p: PhantomData,
+            },
+            right,
+        )
+    }
+
+    #[inline]
+    fn fold_with<F: Folder<Self::Item>>(self, folder: F) -> F {
+        unsafe {
+            self.subtrees
+                .fold(folder, &|node, idx| node.as_ref().key_val(idx))
+        }
+    }
+}
+// endregion
+
+// region ParIterMut
+/// Parallel iterator over a [BTreeMap]'s entries with mutable values, from
+/// [BTreeMap::par_iter_mut]
+pub struct ParIterMut<'a, K, V, const B: usize = 8> {
+    subtrees: Subtrees<K, V, B>,
+    _p: PhantomData<(&'a K, &'a mut V)>,
+}
+
+// SAFETY: split iterators cover disjoint subtrees, so each value is only borrowed mutably once
+unsafe impl<'a, K: Sync, V: Send, const B: usize> Send for ParIterMut<'a, K, V, B> {}
+
+impl<'a, K: Sync, V: Send, const B: usize> ParallelIterator for ParIterMut<'a, K, V, B> {
+    type Item = (&'a K, &'a mut V);
+
+    #[inline]
+    fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+        bridge_unindexed(self, consumer)
+    }
+}
+
+impl<'a, K: Sync, V: Send, const B: usize> UnindexedProducer for ParIterMut<'a, K, V, B> {
+    type Item = (&'a K, &'a mut V);
+
+    #[inline]
+    fn split(self) -> (Self, Option<Self>) {
+        let (left, right) = self.subtrees.split();
+        let right = right.map(|subtrees| Self {
+            subtrees,
+            _p: PhantomData,
+        });
+        (
+            Self {
+                subtrees: left,
+                _p: PhantomData,
+            },
+            right,
+        )
+    }
+
+    #[inline]
+    fn fold_with<F: Folder<Self::Item>>(self, folder: F) -> F {
+        unsafe {
+            self.subtrees
+                .fold(folder, &|mut node, idx| node.as_mut().key_val_mut(idx))
+        }
+    }
+}
+// endregion
+
+impl<'a, 'store, K: Sync, V: Sync, const B: usize> IntoParallelIterator
+    for &'a BTreeMap<'store, K, V, B>
+{
+    type Iter = ParIter<'a, K, V, B>;
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter()
+    }
+}
+
+impl<'a, 'store, K: Sync, V: Send, const B: usize> IntoParallelIterator
+    for &'a mut BTreeMap<'store, K, V, B>
+{
+    type Iter = ParIterMut<'a, K, V, B>;
+    type Item = (&'a K, &'a mut V);
+
+    #[inline]
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter_mut()
+    }
+}
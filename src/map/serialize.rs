@@ -0,0 +1,162 @@
+use std::fmt::Formatter;
+
+use serde::de::{DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{BTreeMap, BTreeStore};
+
+/// How a map is serialized and deserialized with serde.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SerdeForm {
+    /// A map, which is an object in JSON (so the keys must serialize as strings or numbers)
+    #[default]
+    Map,
+    /// A sequence of key-value pairs, which is an array of 2-element arrays in JSON
+    Seq,
+}
+
+/// Serializes a map in the given [SerdeForm], from [BTreeMap::serialize_as].
+pub struct SerializeAs<'a, 'store, K, V, const B: usize = 8> {
+    map: &'a BTreeMap<'store, K, V, B>,
+    form: SerdeForm,
+}
+
+/// Deserializes a map into a store, in the given [SerdeForm] (by default [SerdeForm::Map]).
+///
+/// Deserializing needs a store to allocate the map's nodes in, so instead of [Deserialize], maps
+/// implement [DeserializeSeed] via this seed.
+///
+/// # Examples
+///
+/// ```
+/// use btree_plus_store::map::{MapSeed, SerdeForm};
+/// use btree_plus_store::{BTreeMap, BTreeStore};
+/// use serde::de::DeserializeSeed;
+/// let store = BTreeStore::new();
+/// let mut deserializer = serde_json::Deserializer::from_str(r#"{"a": 1, "b": 2}"#);
+/// let map: BTreeMap<String, i32> = MapSeed::new(&store).deserialize(&mut deserializer).unwrap();
+/// assert_eq!(map.get("b"), Some(&2));
+/// assert_eq!(serde_json::to_string(&map).unwrap(), r#"{"a":1,"b":2}"#);
+/// assert_eq!(
+///     serde_json::to_string(&map.serialize_as(SerdeForm::Seq)).unwrap(),
+///     r#"[["a",1],["b",2]]"#
+/// );
+/// ```
+pub struct MapSeed<'store, K, V, const B: usize = 8> {
+    store: &'store BTreeStore<K, V, B>,
+    form: SerdeForm,
+}
+
+struct MapVisitor<'store, K, V, const B: usize>(&'store BTreeStore<K, V, B>);
+
+impl<'store, K, V, const B: usize> BTreeMap<'store, K, V, B> {
+    /// Serializes the map in the given [SerdeForm]. Serializing the map itself uses
+    /// [SerdeForm::Map].
+    #[inline]
+    pub fn serialize_as(&self, form: SerdeForm) -> SerializeAs<'_, 'store, K, V, B> {
+        SerializeAs { map: self, form }
+    }
+}
+
+impl<'store, K: Serialize, V: Serialize, const B: usize> Serialize for BTreeMap<'store, K, V, B> {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.serialize_as(SerdeForm::Map).serialize(serializer)
+    }
+}
+
+impl<'a, 'store, K: Serialize, V: Serialize, const B: usize> Serialize
+    for SerializeAs<'a, 'store, K, V, B>
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.form {
+            SerdeForm::Map => {
+                let mut map = serializer.serialize_map(Some(self.map.len()))?;
+                for (key, val) in self.map.iter() {
+                    map.serialize_entry(key, val)?;
+                }
+                map.end()
+            }
+            SerdeForm::Seq => {
+                let mut seq = serializer.serialize_seq(Some(self.map.len()))?;
+                for entry in self.map.iter() {
+                    seq.serialize_element(&entry)?;
+                }
+                seq.end()
+            }
+        }
+    }
+}
+
+impl<'store, K, V, const B: usize> MapSeed<'store, K, V, B> {
+    /// Deserializes a map into `store`
+    #[inline]
+    pub fn new(store: &'store BTreeStore<K, V, B>) -> Self {
+        Self {
+            store,
+            form: SerdeForm::default(),
+        }
+    }
+
+    /// Which form to expect, which must be the one the map was serialized in
+    #[inline]
+    pub fn form(mut self, form: SerdeForm) -> Self {
+        self.form = form;
+        self
+    }
+}
+
+impl<'de, 'store, K, V, const B: usize> DeserializeSeed<'de> for MapSeed<'store, K, V, B>
+where
+    K: Deserialize<'de> + Ord + Clone,
+    V: Deserialize<'de>,
+{
+    type Value = BTreeMap<'store, K, V, B>;
+
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let visitor = MapVisitor(self.store);
+        match self.form {
+            SerdeForm::Map => deserializer.deserialize_map(visitor),
+            SerdeForm::Seq => deserializer.deserialize_seq(visitor),
+        }
+    }
+}
+
+impl<'de, 'store, K, V, const B: usize> Visitor<'de> for MapVisitor<'store, K, V, B>
+where
+    K: Deserialize<'de> + Ord + Clone,
+    V: Deserialize<'de>,
+{
+    type Value = BTreeMap<'store, K, V, B>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        write!(formatter, "a map, or a sequence of key-value pairs")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut map = BTreeMap::new_in(self.0);
+        while let Some((key, val)) = access.next_entry()? {
+            map.insert(key, val);
+        }
+        Ok(map)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut map = BTreeMap::new_in(self.0);
+        while let Some((key, val)) = access.next_element()? {
+            map.insert(key, val);
+        }
+        Ok(map)
+    }
+}
+
+impl<'store, K, V, const B: usize> Clone for MapSeed<'store, K, V, B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'store, K, V, const B: usize> Copy for MapSeed<'store, K, V, B> {}
@@ -1,20 +1,21 @@
+use std::borrow::Borrow;
 use std::cmp::Ordering;
-use std::mem::{swap, ManuallyDrop, MaybeUninit};
+use std::mem::{offset_of, size_of, swap, ManuallyDrop, MaybeUninit};
+#[cfg(feature = "nightly")]
 use std::ops::{Bound, RangeBounds};
-use std::ptr::{copy, copy_nonoverlapping};
-
-use rustc_arena_modified::slab_arena::UnsafeRef;
+use std::ops::{Deref, DerefMut, Range};
+use std::ptr::copy;
+#[cfg(feature = "nightly")]
+use std::ptr::copy_nonoverlapping;
 
+use crate::store::{SearchStrategy, UnsafeRef};
 use crate::utils::{maybe_uninit_array, PtrEq};
 
-/// \# of keys and values in a leaf node
-pub const M: usize = 8;
-
 /// A node in the b+tree. This can be either leaf node or internal node depending on the implicit
-/// height.
-pub struct Node<K, V> {
+/// height. It holds up to `B` keys, and values or `B + 1` children.
+pub struct Node<K, V, const B: usize> {
     /// Parent node. We use [NonNull] in part because [LeafNode] must be covariant in `K` and `V`.
-    pub parent: Option<NodePtr<K, V>>,
+    pub parent: Option<NodePtr<K, V, B>>,
     /// This node's index into the parent node's `edges` array.
     /// `*node.parent.d.internal().edges[node.parent_idx]` should be the same thing as `node`.
     /// This is only guaranteed to be initialized when `parent` is non-null.
@@ -22,43 +23,91 @@ pub struct Node<K, V> {
     /// Total # Of keys and values, not including children.
     pub len: u16,
     /// Keys storage. The first `len` are initialized.
-    pub keys: [MaybeUninit<K>; M],
+    pub keys: [MaybeUninit<K>; B],
     /// Values or children depending on the implicit height.
-    pub d: NodeData<K, V>,
+    pub d: NodeData<K, V, B>,
 }
 
 /// Contains leaf/internal-specific data. An untagged union, whether it contains leaf or internal
 /// node data is determined by the implicit height.
-pub union NodeData<K, V> {
+pub union NodeData<K, V, const B: usize> {
     /// Leaf data. Only exists if the implicit height is 0.
-    pub leaf: ManuallyDrop<LeafData<K, V>>,
+    pub leaf: ManuallyDrop<LeafData<K, V, B>>,
     /// Internal data. Only exists if the implicit height is positive.
-    pub internal: ManuallyDrop<InternalData<K, V>>,
+    pub internal: ManuallyDrop<InternalData<K, V, B>>,
 }
 
 /// Leaf data. Only exists if the implicit height is 0.
-pub struct LeafData<K, V> {
+pub struct LeafData<K, V, const B: usize> {
     /// Vals storage. The first `len` are initialized.
-    pub vals: [MaybeUninit<V>; M],
+    pub vals: [MaybeUninit<V>; B],
     /// Previous leaf node in the linked list.
-    pub prev: Option<NodePtr<K, V>>,
+    pub prev: Option<NodePtr<K, V, B>>,
     /// Next leaf node in the linked list.
-    pub next: Option<NodePtr<K, V>>,
+    pub next: Option<NodePtr<K, V, B>>,
 }
 
 /// Internal data. Only exists if the implicit height is positive.
-pub struct InternalData<K, V> {
+pub struct InternalData<K, V, const B: usize> {
     /// Pointers to the node's children. `edges[i]` is the child whose keys are between
     /// `keys[i - 1]` and `keys[i]` (if either doesn't exist, just before or after the other). The
     /// first `len + 1` are initialized.
-    pub edges: [MaybeUninit<NodePtr<K, V>>; M + 1],
+    pub edges: Edges<K, V, B>,
+    /// Total # of entries in this node's subtree (the sum of its leaves' `len`s).
+    #[cfg(feature = "order-statistic")]
+    pub size: usize,
+}
+
+/// `B + 1` edges, which can't be an array type until const generic expressions are stable. The
+/// last is kept separately, and `repr(C)` puts it directly after the others, so they deref to one
+/// slice.
+#[repr(C)]
+pub struct Edges<K, V, const B: usize> {
+    first: [MaybeUninit<NodePtr<K, V, B>>; B],
+    last: MaybeUninit<NodePtr<K, V, B>>,
 }
 
 /// A managed, non-null pointer to a node. This is either a pointer to a leaf node or internal node,
 /// depending on the implicit height.
-pub type NodePtr<K, V> = UnsafeRef<Node<K, V>>;
+pub type NodePtr<K, V, const B: usize> = UnsafeRef<Node<K, V, B>>;
+
+impl<K, V, const B: usize> Edges<K, V, B> {
+    #[inline]
+    fn uninit() -> Self {
+        Edges {
+            first: maybe_uninit_array(),
+            last: MaybeUninit::uninit(),
+        }
+    }
+}
+
+impl<K, V, const B: usize> Deref for Edges<K, V, B> {
+    type Target = [MaybeUninit<NodePtr<K, V, B>>];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: `first` and `last` are laid out like an array of `B + 1` edges. The pointer is
+        // to all of `self`, not just `first`, so it may be used to read `last`
+        unsafe { std::slice::from_raw_parts(self as *const Self as *const _, B + 1) }
+    }
+}
+
+impl<K, V, const B: usize> DerefMut for Edges<K, V, B> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: see `deref`
+        unsafe { std::slice::from_raw_parts_mut(self as *mut Self as *mut _, B + 1) }
+    }
+}
+
+impl<K, V, const B: usize> Node<K, V, B> {
+    /// Fails to compile for capacities which trees can't use: a full node must split into two
+    /// with at least 2 entries each, and the # of edges must fit in a `u16`.
+    pub const VALID_CAPACITY: () = assert!(
+        B >= 4 && B < u16::MAX as usize,
+        "node capacity must be at least 4 and less than 65535"
+    );
 
-impl<K, V> Node<K, V> {
     #[inline]
     pub fn leaf() -> Self {
         Node {
@@ -85,14 +134,16 @@ impl<K, V> Node<K, V> {
             keys: maybe_uninit_array(),
             d: NodeData {
                 internal: ManuallyDrop::new(InternalData {
-                    edges: maybe_uninit_array(),
+                    edges: Edges::uninit(),
+                    #[cfg(feature = "order-statistic")]
+                    size: 0,
                 }),
             },
         }
     }
 
     #[inline]
-    pub fn parent(&self) -> Option<(NodePtr<K, V>, u16)> {
+    pub fn parent(&self) -> Option<(NodePtr<K, V, B>, u16)> {
         self.parent
             .map(|p| (p, unsafe { self.parent_idx.assume_init() }))
     }
@@ -104,7 +155,7 @@ impl<K, V> Node<K, V> {
     }
 
     #[inline]
-    pub fn set_parent(&mut self, parent: NodePtr<K, V>, parent_idx: u16) {
+    pub fn set_parent(&mut self, parent: NodePtr<K, V, B>, parent_idx: u16) {
         self.parent = Some(parent);
         self.parent_idx.write(parent_idx);
     }
@@ -116,22 +167,22 @@ impl<K, V> Node<K, V> {
     }
 
     #[inline]
-    pub unsafe fn prev(&self) -> Option<NodePtr<K, V>> {
+    pub unsafe fn prev(&self) -> Option<NodePtr<K, V, B>> {
         self.d.leaf().prev
     }
 
     #[inline]
-    pub unsafe fn set_prev(&mut self, prev: Option<NodePtr<K, V>>) {
+    pub unsafe fn set_prev(&mut self, prev: Option<NodePtr<K, V, B>>) {
         self.d.leaf_mut().prev = prev;
     }
 
     #[inline]
-    pub unsafe fn next(&self) -> Option<NodePtr<K, V>> {
+    pub unsafe fn next(&self) -> Option<NodePtr<K, V, B>> {
         self.d.leaf().next
     }
 
     #[inline]
-    pub unsafe fn set_next(&mut self, next: Option<NodePtr<K, V>>) {
+    pub unsafe fn set_next(&mut self, next: Option<NodePtr<K, V, B>>) {
         self.d.leaf_mut().next = next;
     }
 
@@ -227,8 +278,34 @@ impl<K, V> Node<K, V> {
         )
     }
 
+    /// Under the `prefetch` feature (on x86 and x86_64), hints the CPU to load the node's length
+    /// and keys into cache before they're searched. A node's keys span several cache lines, so
+    /// the misses then overlap instead of the search stalling on each in turn.
+    ///
+    /// Only computes addresses, so the node isn't read.
+    #[inline(always)]
+    pub fn prefetch_keys(&self) {
+        prefetch(std::ptr::addr_of!(self.len), size_of::<u16>());
+        prefetch(
+            std::ptr::addr_of!(self.keys),
+            size_of::<[MaybeUninit<K>; B]>(),
+        );
+    }
+
+    /// Under the `prefetch` feature (on x86 and x86_64), hints the CPU to load the internal node's
+    /// edges into cache, so that they load while its keys are searched, instead of after.
+    ///
+    /// Only computes addresses, so the node isn't read.
+    #[inline(always)]
+    pub unsafe fn prefetch_edges(&self) {
+        prefetch(
+            std::ptr::addr_of!(self.d.internal.edges),
+            size_of::<Edges<K, V, B>>(),
+        );
+    }
+
     #[inline]
-    pub unsafe fn edge(&self, idx: u16) -> NodePtr<K, V> {
+    pub unsafe fn edge(&self, idx: u16) -> NodePtr<K, V, B> {
         debug_assert!(idx < self.len + 1);
         self.d
             .internal()
@@ -238,7 +315,7 @@ impl<K, V> Node<K, V> {
     }
 
     #[inline]
-    pub unsafe fn edge_mut(&mut self, idx: u16) -> &mut NodePtr<K, V> {
+    pub unsafe fn edge_mut(&mut self, idx: u16) -> &mut NodePtr<K, V, B> {
         debug_assert!(idx < self.len + 1);
         self.d
             .internal_mut()
@@ -249,37 +326,95 @@ impl<K, V> Node<K, V> {
 
     #[inline]
     pub unsafe fn keys(&self) -> &[K] {
-        &*(&self.keys[..self.len as usize] as *const [MaybeUninit<K>] as *const [K])
+        &*(self.keys.get_unchecked(..self.len as usize) as *const [MaybeUninit<K>] as *const [K])
+    }
+
+    /// Searches the node's keys for `key`, returning the same thing as [slice::binary_search].
+    #[inline]
+    pub unsafe fn search<Q: Ord + ?Sized>(
+        &self,
+        key: &Q,
+        strategy: SearchStrategy,
+    ) -> Result<usize, usize>
+    where
+        K: Borrow<Q>,
+    {
+        let keys = self.keys();
+        match strategy {
+            SearchStrategy::Binary => keys.binary_search_by(|k| k.borrow().cmp(key)),
+            SearchStrategy::Linear => {
+                for (idx, k) in keys.iter().enumerate() {
+                    match k.borrow().cmp(key) {
+                        Ordering::Less => {}
+                        Ordering::Equal => return Ok(idx),
+                        Ordering::Greater => return Err(idx),
+                    }
+                }
+                Err(keys.len())
+            }
+            SearchStrategy::Branchless => {
+                // `count` over a `bool` compiles to a compare-and-add per key, without branching
+                let idx = keys.iter().filter(|k| (*k).borrow() < key).count();
+                match keys.get(idx) {
+                    Some(k) if k.borrow() == key => Ok(idx),
+                    _ => Err(idx),
+                }
+            }
+        }
     }
 
     #[inline]
     pub unsafe fn keys_mut(&mut self) -> &mut [K] {
-        &mut *(&mut self.keys[..self.len as usize] as *mut [MaybeUninit<K>] as *mut [K])
+        &mut *(self.keys.get_unchecked_mut(..self.len as usize) as *mut [MaybeUninit<K>]
+            as *mut [K])
     }
 
     #[allow(unused)]
     #[inline]
     pub unsafe fn vals(&self) -> &[V] {
-        &*(&self.d.leaf().vals[..self.len as usize] as *const [MaybeUninit<V>] as *const [V])
+        &*(self.d.leaf().vals.get_unchecked(..self.len as usize) as *const [MaybeUninit<V>]
+            as *const [V])
     }
 
     #[inline]
     pub unsafe fn vals_mut(&mut self) -> &mut [V] {
-        &mut *(&mut self.d.leaf_mut().vals[..self.len as usize] as *mut [MaybeUninit<V>]
+        &mut *(self
+            .d
+            .leaf_mut()
+            .vals
+            .get_unchecked_mut(..self.len as usize) as *mut [MaybeUninit<V>]
             as *mut [V])
     }
 
     #[inline]
-    pub unsafe fn edges(&self) -> &[NodePtr<K, V>] {
-        &*(&self.d.internal().edges[..(self.len + 1) as usize]
-            as *const [MaybeUninit<NodePtr<K, V>>] as *const [NodePtr<K, V>])
+    pub unsafe fn edges(&self) -> &[NodePtr<K, V, B>] {
+        &*(self
+            .d
+            .internal()
+            .edges
+            .get_unchecked(..(self.len + 1) as usize)
+            as *const [MaybeUninit<NodePtr<K, V, B>>] as *const [NodePtr<K, V, B>])
     }
 
     #[allow(unused)]
     #[inline]
-    pub unsafe fn edges_mut(&mut self) -> &mut [NodePtr<K, V>] {
-        &mut *(&mut self.d.internal_mut().edges[..(self.len + 1) as usize]
-            as *mut [MaybeUninit<NodePtr<K, V>>] as *mut [NodePtr<K, V>])
+    pub unsafe fn edges_mut(&mut self) -> &mut [NodePtr<K, V, B>] {
+        &mut *(self
+            .d
+            .internal_mut()
+            .edges
+            .get_unchecked_mut(..(self.len + 1) as usize)
+            as *mut [MaybeUninit<NodePtr<K, V, B>>] as *mut [NodePtr<K, V, B>])
+    }
+
+    /// Total # of entries in this node's subtree, where this node has the given height.
+    #[cfg(feature = "order-statistic")]
+    #[inline]
+    pub unsafe fn subtree_len(&self, height: usize) -> usize {
+        match height {
+            0 => self.len as usize,
+            _ => self.d.internal().size,
+        }
     }
 
     #[inline]
@@ -339,35 +474,32 @@ impl<K, V> Node<K, V> {
     #[inline]
     pub unsafe fn insert_val(&mut self, idx: u16, key: K, val: V) {
         debug_assert!(idx <= self.len);
-        debug_assert!((self.len as usize) < M, "LeafNode::insert would overflow");
+        debug_assert!((self.len as usize) < B, "LeafNode::insert would overflow");
 
         // Shift later keys and values
-        if self.len > idx {
-            unsafe_copy_slice_overlapping(
-                &mut self.keys,
-                idx as usize + 1..self.len as usize + 1,
-                idx as usize..self.len as usize,
-            );
-            unsafe_copy_slice_overlapping(
-                &mut self.d.leaf_mut().vals,
-                idx as usize + 1..self.len as usize + 1,
-                idx as usize..self.len as usize,
-            );
-        }
+        Self::LAYOUT.open_leaf_slot(self.erased(), idx);
 
         // Do insert
-        self.keys[idx as usize].write(key);
-        self.d.leaf_mut().vals[idx as usize].write(val);
-
-        self.len += 1;
+        self.keys.get_unchecked_mut(idx as usize).write(key);
+        self.d
+            .leaf_mut()
+            .vals
+            .get_unchecked_mut(idx as usize)
+            .write(val);
     }
 
     /// Doesn't rebalance. You must call `set_parent` on the edge beforehand.
     #[inline]
-    pub unsafe fn insert_edge(&mut self, idx: u16, after_key: bool, key: K, edge: NodePtr<K, V>) {
+    pub unsafe fn insert_edge(
+        &mut self,
+        idx: u16,
+        after_key: bool,
+        key: K,
+        edge: NodePtr<K, V, B>,
+    ) {
         debug_assert!(idx <= self.len);
         debug_assert!(
-            (self.len as usize) < M,
+            (self.len as usize) < B,
             "InternalNode::insert_edge would overflow"
         );
         debug_assert_eq!(
@@ -379,54 +511,36 @@ impl<K, V> Node<K, V> {
             "InternalNode::insert_edge edge's parent_idx must be set before insertion (idx is redundant)"
         );
 
-        // Shift later keys and edges
-        if idx < self.len {
-            unsafe_copy_slice_overlapping(
-                &mut self.keys,
-                idx as usize + 1..self.len as usize + 1,
-                idx as usize..self.len as usize,
-            );
-        }
-        let after_edge_idx = match after_key {
+        let edge_idx = match after_key {
             false => idx,
             true => idx + 1,
         };
-        if after_edge_idx < self.len + 1 {
-            unsafe_copy_slice_overlapping(
-                &mut self.d.internal_mut().edges,
-                after_edge_idx as usize + 1..self.len as usize + 2,
-                after_edge_idx as usize..self.len as usize + 1,
-            );
-            // Update later edge parent idxs
-            for edge in self.d.internal_mut().edges
-                [after_edge_idx as usize + 1..self.len as usize + 2]
-                .iter_mut()
-                .map(|e| e.assume_init_mut())
-            {
-                *edge.as_mut().parent_idx.assume_init_mut() += 1;
-            }
-        }
 
-        // Do insert
-        self.keys[idx as usize].write(key);
-        if after_key {
-            self.d.internal_mut().edges[idx as usize + 1].write(edge);
-        } else {
-            self.d.internal_mut().edges[idx as usize].write(edge);
-        }
+        // Shift later keys and edges, and update the later edges' parent idxs
+        Self::LAYOUT.open_internal_slot(self.erased(), idx, edge_idx);
 
-        self.len += 1;
+        // Do insert
+        self.keys.get_unchecked_mut(idx as usize).write(key);
+        self.d
+            .internal_mut()
+            .edges
+            .get_unchecked_mut(edge_idx as usize)
+            .write(edge);
     }
 
     /// You must call `set_parent` on the edge beforehand.
     #[inline]
-    pub unsafe fn set_last_edge(&mut self, edge: NodePtr<K, V>) {
+    pub unsafe fn set_last_edge(&mut self, edge: NodePtr<K, V, B>) {
         debug_assert_eq!(
             edge.as_ref().parent_idx(),
             Some(self.len),
             "InternalNode::set_last_edge edge's parent_idx must be set before insertion (idx is redundant)"
         );
-        self.d.internal_mut().edges[self.len as usize].write(edge);
+        self.d
+            .internal_mut()
+            .edges
+            .get_unchecked_mut(self.len as usize)
+            .write(edge);
     }
 
     /// Doesn't rebalance
@@ -436,30 +550,22 @@ impl<K, V> Node<K, V> {
         debug_assert!(self.len > 0);
 
         // Read removed key and value (safe because we either overwrite or decrease len past memory)
-        let key = self.keys[idx as usize].assume_init_read();
-        let val = self.d.leaf().vals[idx as usize].assume_init_read();
+        let key = self.keys.get_unchecked(idx as usize).assume_init_read();
+        let val = self
+            .d
+            .leaf()
+            .vals
+            .get_unchecked(idx as usize)
+            .assume_init_read();
 
         // Shift later keys and values
-        if idx + 1 < self.len {
-            unsafe_copy_slice_overlapping(
-                &mut self.keys,
-                idx as usize..self.len as usize - 1,
-                idx as usize + 1..self.len as usize,
-            );
-            unsafe_copy_slice_overlapping(
-                &mut self.d.leaf_mut().vals,
-                idx as usize..self.len as usize - 1,
-                idx as usize + 1..self.len as usize,
-            );
-        }
-
-        self.len -= 1;
+        Self::LAYOUT.close_leaf_slot(self.erased(), idx);
         (key, val)
     }
 
     /// Doesn't rebalance.
     #[inline]
-    pub unsafe fn remove_edge(&mut self, idx: u16, after_key: bool) -> (K, NodePtr<K, V>) {
+    pub unsafe fn remove_edge(&mut self, idx: u16, after_key: bool) -> (K, NodePtr<K, V, B>) {
         debug_assert!(idx < self.len);
         debug_assert!(self.len > 0);
         let edge_idx = match after_key {
@@ -473,39 +579,22 @@ impl<K, V> Node<K, V> {
         );
 
         // Read removed key and edge (safe because we either overwrite or decrease len past memory)
-        let key = self.keys[idx as usize].assume_init_read();
-        let edge = self.d.internal().edges[edge_idx as usize].assume_init();
-
-        // Shift later keys and edges
-        if idx + 1 < self.len {
-            unsafe_copy_slice_overlapping(
-                &mut self.keys,
-                idx as usize..self.len as usize - 1,
-                idx as usize + 1..self.len as usize,
-            );
-        }
-        if edge_idx < self.len {
-            unsafe_copy_slice_overlapping(
-                &mut self.d.internal_mut().edges,
-                edge_idx as usize..self.len as usize,
-                edge_idx as usize + 1..self.len as usize + 1,
-            );
-            // Update later edge parent idxs
-            for edge in self.d.internal_mut().edges[edge_idx as usize..self.len as usize]
-                .iter_mut()
-                .map(|e| e.assume_init_mut())
-            {
-                *edge.as_mut().parent_idx.assume_init_mut() -= 1;
-            }
-        }
+        let key = self.keys.get_unchecked(idx as usize).assume_init_read();
+        let edge = self
+            .d
+            .internal()
+            .edges
+            .get_unchecked(edge_idx as usize)
+            .assume_init();
 
-        self.len -= 1;
+        // Shift later keys and edges, and update the later edges' parent idxs
+        Self::LAYOUT.close_internal_slot(self.erased(), idx, edge_idx);
         (key, edge)
     }
 
     /// Doesn't rebalance, removes edge after key
     #[inline]
-    pub unsafe fn remove_last_edge(&mut self) -> (K, NodePtr<K, V>) {
+    pub unsafe fn remove_last_edge(&mut self) -> (K, NodePtr<K, V, B>) {
         debug_assert!(self.len > 0);
         debug_assert_eq!(
             self.edge(self.len).as_ref().parent_idx(),
@@ -514,8 +603,16 @@ impl<K, V> Node<K, V> {
         );
 
         // Read removed key and edge (safe because we decrease len past memory)
-        let key = self.keys[self.len as usize - 1].assume_init_read();
-        let edge = self.d.internal().edges[self.len as usize].assume_init();
+        let key = self
+            .keys
+            .get_unchecked(self.len as usize - 1)
+            .assume_init_read();
+        let edge = self
+            .d
+            .internal()
+            .edges
+            .get_unchecked(self.len as usize)
+            .assume_init();
 
         self.len -= 1;
         (key, edge)
@@ -525,8 +622,8 @@ impl<K, V> Node<K, V> {
     #[inline]
     pub unsafe fn replace_key(&mut self, idx: u16, key: K) -> K {
         debug_assert!(idx < self.len);
-        let old_key = self.keys[idx as usize].assume_init_read();
-        self.keys[idx as usize].write(key);
+        let old_key = self.keys.get_unchecked(idx as usize).assume_init_read();
+        self.keys.get_unchecked_mut(idx as usize).write(key);
         old_key
     }
 
@@ -534,8 +631,17 @@ impl<K, V> Node<K, V> {
     #[inline]
     pub unsafe fn replace_val(&mut self, idx: u16, val: V) -> V {
         debug_assert!(idx < self.len);
-        let old_val = self.d.leaf().vals[idx as usize].assume_init_read();
-        self.d.leaf_mut().vals[idx as usize].write(val);
+        let old_val = self
+            .d
+            .leaf()
+            .vals
+            .get_unchecked(idx as usize)
+            .assume_init_read();
+        self.d
+            .leaf_mut()
+            .vals
+            .get_unchecked_mut(idx as usize)
+            .write(val);
         old_val
     }
 
@@ -544,14 +650,28 @@ impl<K, V> Node<K, V> {
     ///
     /// `self.d.leaf().prev`, `right.d.leaf().next`, and `self.d.leaf().prev.next` are set, but you need to set
     /// `self.d.leaf().next`, `right.d.leaf().prev`, and `right.d.leaf().next.prev`.
+    ///
+    /// Under the `nightly` feature, splits of [Copy] keys move entries in bulk.
     #[inline]
-    pub unsafe fn split_leaf(&mut self, mut idx: u16, key: &mut K, mut val: V) -> Node<K, V>
+    pub unsafe fn split_leaf(&mut self, idx: u16, key: &mut K, val: V) -> Node<K, V, B>
+    where
+        K: Clone,
+    {
+        #[cfg(feature = "nightly")]
+        return SplitLeaf::split_leaf_impl(self, idx, key, val);
+        #[cfg(not(feature = "nightly"))]
+        return self.split_leaf_by_swaps(idx, key, val);
+    }
+
+    /// [Node::split_leaf] which swaps the new entry into the median position one entry at a time
+    #[inline]
+    unsafe fn split_leaf_by_swaps(&mut self, mut idx: u16, key: &mut K, mut val: V) -> Node<K, V, B>
     where
         K: Clone,
     {
         debug_assert!(idx <= self.len);
         debug_assert!(
-            self.len as usize >= M / 2,
+            self.len as usize >= B / 2,
             "LeafNode::split_leaf would underflow"
         );
 
@@ -571,20 +691,75 @@ impl<K, V> Node<K, V> {
         }
 
         // Now we just split and insert the middle into one of the nodes
-        unsafe_copy_slice_nonoverlapping(
-            &mut right.keys[1..median as usize + 1],
-            &self.keys[median as usize..self.len as usize],
-        );
-        unsafe_copy_slice_nonoverlapping(
-            &mut right.d.leaf_mut().vals[1..median as usize + 1],
-            &self.d.leaf().vals[median as usize..self.len as usize],
-        );
+        Self::LAYOUT.split_leaf(self.erased(), right.erased(), median);
         // Remember: this is a B+ tree, so we copy the key in the leaf node, and write the val
         // instead of propagating it to the internal.
         right.keys[0].write(key.clone());
         right.d.leaf_mut().vals[0].write(val);
-        right.len = self.len - median + 1;
-        self.len = median;
+        right.d.leaf_mut().next = self.d.leaf().next;
+        right
+    }
+
+    /// [Node::split_leaf] which copies each side of the new entry into place in bulk. The split
+    /// key is duplicated bitwise instead of cloned, hence `K: Copy`.
+    #[cfg(feature = "nightly")]
+    #[inline]
+    unsafe fn split_leaf_by_copies(&mut self, idx: u16, key: &mut K, val: V) -> Node<K, V, B>
+    where
+        K: Copy,
+    {
+        debug_assert!(idx <= self.len);
+        debug_assert!(
+            self.len as usize >= B / 2,
+            "LeafNode::split_leaf would underflow"
+        );
+
+        let (idx, len) = (idx as usize, self.len as usize);
+        let median = len / 2;
+        let mut right = Node::leaf();
+
+        if idx < median {
+            // The new entry goes in the left node, which pushes its last entry to the right
+            unsafe_copy_slice_nonoverlapping(
+                &mut right.keys[1..len - median + 1],
+                &self.keys[median..len],
+            );
+            unsafe_copy_slice_nonoverlapping(
+                &mut right.d.leaf_mut().vals[1..len - median + 1],
+                &self.d.leaf().vals[median..len],
+            );
+            right.keys[0] = self.keys[median - 1];
+            right.d.leaf_mut().vals[0].write(self.read_val(median as u16 - 1));
+            unsafe_copy_slice_overlapping(&mut self.keys, idx + 1..median, idx..median - 1);
+            unsafe_copy_slice_overlapping(
+                &mut self.d.leaf_mut().vals,
+                idx + 1..median,
+                idx..median - 1,
+            );
+            self.keys[idx].write(*key);
+            self.d.leaf_mut().vals[idx].write(val);
+        } else {
+            // The new entry goes in the right node, between the entries before and after it
+            let right_idx = idx - median;
+            unsafe_copy_slice_nonoverlapping(&mut right.keys[..right_idx], &self.keys[median..idx]);
+            unsafe_copy_slice_nonoverlapping(
+                &mut right.d.leaf_mut().vals[..right_idx],
+                &self.d.leaf().vals[median..idx],
+            );
+            right.keys[right_idx].write(*key);
+            right.d.leaf_mut().vals[right_idx].write(val);
+            unsafe_copy_slice_nonoverlapping(
+                &mut right.keys[right_idx + 1..len - median + 1],
+                &self.keys[idx..len],
+            );
+            unsafe_copy_slice_nonoverlapping(
+                &mut right.d.leaf_mut().vals[right_idx + 1..len - median + 1],
+                &self.d.leaf().vals[idx..len],
+            );
+        }
+        *key = right.keys[0].assume_init();
+        right.len = (len - median + 1) as u16;
+        self.len = median as u16;
         right.d.leaf_mut().next = self.d.leaf().next;
         right
     }
@@ -599,11 +774,11 @@ impl<K, V> Node<K, V> {
         &mut self,
         mut idx: u16,
         key: &mut K,
-        mut edge: NodePtr<K, V>,
-    ) -> Node<K, V> {
+        mut edge: NodePtr<K, V, B>,
+    ) -> Node<K, V, B> {
         debug_assert!(idx <= self.len);
         debug_assert!(
-            self.len as usize >= M / 2,
+            self.len as usize >= B / 2,
             "InternalNode::split_internal would underflow"
         );
         debug_assert_eq!(
@@ -636,34 +811,38 @@ impl<K, V> Node<K, V> {
             debug_assert_eq!(edge.as_ref().parent_idx(), Some(idx + 1));
         }
 
-        // Now we just split and insert the middle into one of the nodes
-        unsafe_copy_slice_nonoverlapping(
-            &mut right.keys[..median as usize],
-            &self.keys[median as usize..self.len as usize],
-        );
-        unsafe_copy_slice_nonoverlapping(
-            &mut right.d.internal_mut().edges[1..median as usize + 1],
-            &self.d.internal().edges[median as usize + 1..self.len as usize + 1],
-        );
         // Put the edge in index 0 in right, so that it's after the split key
         right.d.internal_mut().edges[0].write(edge);
-        // Update parent_idxs in right (including the edge we just inserted)
-        for (idx, mut edge) in right.d.internal_mut().edges[..median as usize + 1]
-            .iter_mut()
-            .enumerate()
-            .map(|(idx, e)| (idx as u16, e.assume_init()))
-        {
-            *edge.as_mut().parent_idx.assume_init_mut() = idx;
-        }
-        right.len = self.len - median;
-        self.len = median;
+        // Now we just split, and update parent_idxs in right (including the edge we just inserted)
+        Self::LAYOUT.split_internal(self.erased(), right.erased(), median);
+        right
+    }
+
+    /// This keeps the entries before `idx`, and returns a new leaf with the entries from `idx` on.
+    /// The new leaf isn't linked to any other nodes.
+    #[inline]
+    pub unsafe fn split_off_leaf(&mut self, idx: u16) -> Node<K, V, B> {
+        debug_assert!(idx <= self.len);
+        let mut right = Node::leaf();
+        Self::LAYOUT.split_off(self.erased(), right.erased(), idx, true);
+        right
+    }
+
+    /// This keeps the keys before `idx` and edges up to `idx`, and returns a new internal node with
+    /// the keys from `idx` on and the edges after them. The new node's first edge is uninitialized,
+    /// and you must set it and the parents of all of its edges.
+    #[inline]
+    pub unsafe fn split_off_internal(&mut self, idx: u16) -> Node<K, V, B> {
+        debug_assert!(idx <= self.len);
+        let mut right = Node::internal();
+        Self::LAYOUT.split_off(self.erased(), right.erased(), idx, false);
         right
     }
 
     /// Absorbs all of `prev`'s keys and values and also its `prev`. Afterwards `prev` should be
     /// removed from the parent and discarded, and `self.prev.next` should be set to `self`.
     #[inline]
-    pub unsafe fn merge_prev_leaf(&mut self, prev: &mut Node<K, V>) {
+    pub unsafe fn merge_prev_leaf(&mut self, prev: &mut Node<K, V, B>) {
         debug_assert!(self.prev().ptr_eq(&Some(NodePtr::from_ref(prev))));
         debug_assert!(
             prev.parent.ptr_eq(&self.parent),
@@ -674,37 +853,18 @@ impl<K, V> Node<K, V> {
             "sanity check failed: prev.parent_idx + 1 != self.parent_idx (the failure happened before this function call, it was only detected now)"
         );
         debug_assert!(
-            (prev.len + self.len) as usize <= M,
+            (prev.len + self.len) as usize <= B,
             "nodes are too big to merge"
         );
 
-        let new_len = prev.len + self.len;
-        unsafe_copy_slice_overlapping(
-            &mut self.keys,
-            prev.len as usize..new_len as usize,
-            ..self.len as usize,
-        );
-        unsafe_copy_slice_overlapping(
-            &mut self.d.leaf_mut().vals,
-            prev.len as usize..new_len as usize,
-            ..self.len as usize,
-        );
-        unsafe_copy_slice_nonoverlapping(
-            &mut self.keys[..prev.len as usize],
-            &prev.keys[..prev.len as usize],
-        );
-        unsafe_copy_slice_nonoverlapping(
-            &mut self.d.leaf_mut().vals[..prev.len as usize],
-            &prev.d.leaf().vals[..prev.len as usize],
-        );
-        self.len = new_len;
+        Self::LAYOUT.merge_prev_leaf(self.erased(), prev.erased());
         self.set_prev(prev.prev());
     }
 
     /// Absorbs all of `next`'s keys and values and also its `next`. Afterwards `next` should be
     /// discarded and removed from the parent, and `self.next.prev` should be set to `self`.
     #[inline]
-    pub unsafe fn merge_next_leaf(&mut self, next: &mut Node<K, V>) {
+    pub unsafe fn merge_next_leaf(&mut self, next: &mut Node<K, V, B>) {
         debug_assert!(self.next().ptr_eq(&Some(NodePtr::from_ref(next))));
         debug_assert!(
             self.parent.ptr_eq(&next.parent),
@@ -715,27 +875,18 @@ impl<K, V> Node<K, V> {
             "sanity check failed: self.parent_idx + 1 != next.parent_idx (the failure happened before this function call, it was only detected now)"
         );
         debug_assert!(
-            (self.len + next.len) as usize <= M,
+            (self.len + next.len) as usize <= B,
             "nodes are too big to merge"
         );
 
-        let new_len = self.len + next.len;
-        unsafe_copy_slice_nonoverlapping(
-            &mut self.keys[self.len as usize..new_len as usize],
-            &next.keys[..next.len as usize],
-        );
-        unsafe_copy_slice_nonoverlapping(
-            &mut self.d.leaf_mut().vals[self.len as usize..new_len as usize],
-            &next.d.leaf().vals[..next.len as usize],
-        );
-        self.len = new_len;
+        Self::LAYOUT.merge_next_leaf(self.erased(), next.erased());
         self.set_next(next.next());
     }
 
     /// Absorbs all of `prev`'s key and edges. Beforehand `prev`'s edges' parent nodes should be
     /// updated to `self`, and afterwards `prev` should be removed from the parent and discarded.
     #[inline]
-    pub unsafe fn merge_prev_internal(&mut self, middle_key: K, prev: &mut Node<K, V>) {
+    pub unsafe fn merge_prev_internal(&mut self, middle_key: K, prev: &mut Node<K, V, B>) {
         debug_assert!(
             prev.parent.ptr_eq(&self.parent),
             "sanity check failed: prev.parent != self.parent (the failure happened before this function call, it was only detected now)"
@@ -745,44 +896,20 @@ impl<K, V> Node<K, V> {
             "sanity check failed: prev.parent_idx + 1 != self.parent_idx (the failure happened before this function call, it was only detected now)"
         );
         debug_assert!(
-            ((prev.len + self.len) as usize) < M,
+            ((prev.len + self.len) as usize) < B,
             "nodes are too big to merge"
         );
 
-        let new_len = prev.len + self.len + 1;
-        unsafe_copy_slice_overlapping(
-            &mut self.keys,
-            prev.len as usize + 1..new_len as usize,
-            ..self.len as usize,
-        );
-        unsafe_copy_slice_overlapping(
-            &mut self.d.internal_mut().edges,
-            prev.len as usize + 1..new_len as usize + 1,
-            ..self.len as usize + 1,
-        );
-        // Update edge parent indices
-        for edge in self.d.internal_mut().edges[prev.len as usize + 1..new_len as usize + 1]
-            .iter_mut()
-            .map(|e| e.assume_init_mut())
-        {
-            *edge.as_mut().parent_idx.assume_init_mut() += prev.len + 1;
-        }
-        unsafe_copy_slice_nonoverlapping(
-            &mut self.keys[..prev.len as usize],
-            &prev.keys[..prev.len as usize],
-        );
-        unsafe_copy_slice_nonoverlapping(
-            &mut self.d.internal_mut().edges[..prev.len as usize + 1],
-            &prev.d.internal().edges[..prev.len as usize + 1],
-        );
-        self.keys[prev.len as usize].write(middle_key);
-        self.len = new_len;
+        let middle_idx = prev.len as usize;
+        // Also updates edge parent indices
+        Self::LAYOUT.merge_prev_internal(self.erased(), prev.erased());
+        self.keys.get_unchecked_mut(middle_idx).write(middle_key);
     }
 
     /// Absorbs all of `next`'s key and edges. Beforehand `next`'s edges' parent nodes should be
     /// updated to `self`, and afterwards `next` should be removed from the parent and discarded.
     #[inline]
-    pub unsafe fn merge_next_internal(&mut self, middle_key: K, next: &mut Node<K, V>) {
+    pub unsafe fn merge_next_internal(&mut self, middle_key: K, next: &mut Node<K, V, B>) {
         debug_assert!(
             self.parent.ptr_eq(&next.parent),
             "sanity check failed: self.parent != next.parent (the failure happened before this function call, it was only detected now)"
@@ -792,53 +919,77 @@ impl<K, V> Node<K, V> {
             "sanity check failed: self.parent_idx + 1 != next.parent_idx (the failure happened before this function call, it was only detected now)"
         );
         debug_assert!(
-            ((self.len + next.len) as usize) < M,
+            ((self.len + next.len) as usize) < B,
             "nodes are too big to merge"
         );
-        let new_len = self.len + next.len + 1;
-        self.keys[self.len as usize].write(middle_key);
-        unsafe_copy_slice_nonoverlapping(
-            &mut self.keys[self.len as usize + 1..new_len as usize],
-            &next.keys[..next.len as usize],
-        );
-        unsafe_copy_slice_nonoverlapping(
-            &mut self.d.internal_mut().edges[self.len as usize + 1..new_len as usize + 1],
-            &next.d.internal().edges[..next.len as usize + 1],
-        );
-        // Update edge parent indices
-        for edge in self.d.internal_mut().edges[self.len as usize + 1..new_len as usize + 1]
-            .iter_mut()
-            .map(|e| e.assume_init_mut())
-        {
-            *edge.as_mut().parent_idx.assume_init_mut() += self.len + 1;
-        }
-        self.len = new_len;
+        self.keys
+            .get_unchecked_mut(self.len as usize)
+            .write(middle_key);
+        // Also updates edge parent indices
+        Self::LAYOUT.merge_next_internal(self.erased(), next.erased());
     }
 }
 
-impl<K, V> NodeData<K, V> {
-    pub unsafe fn leaf(&self) -> &LeafData<K, V> {
+impl<K, V, const B: usize> NodeData<K, V, B> {
+    pub unsafe fn leaf(&self) -> &LeafData<K, V, B> {
         &self.leaf
     }
 
-    pub unsafe fn leaf_mut(&mut self) -> &mut LeafData<K, V> {
+    pub unsafe fn leaf_mut(&mut self) -> &mut LeafData<K, V, B> {
         &mut self.leaf
     }
 
-    pub unsafe fn internal(&self) -> &InternalData<K, V> {
+    pub unsafe fn internal(&self) -> &InternalData<K, V, B> {
         &self.internal
     }
 
-    pub unsafe fn internal_mut(&mut self) -> &mut InternalData<K, V> {
+    pub unsafe fn internal_mut(&mut self) -> &mut InternalData<K, V, B> {
         &mut self.internal
     }
 }
 
+/// Adds `delta` to the subtree size of every ancestor of `node`. No-op without the
+/// `order-statistic` feature.
+#[inline]
+pub unsafe fn adjust_ancestor_sizes<K, V, const B: usize>(node: NodePtr<K, V, B>, delta: isize) {
+    #[cfg(feature = "order-statistic")]
+    {
+        let mut node = node;
+        while let Some(mut parent) = node.as_ref().parent {
+            let size = &mut parent.as_mut().d.internal_mut().size;
+            *size = size.wrapping_add_signed(delta);
+            node = parent;
+        }
+    }
+    #[cfg(not(feature = "order-statistic"))]
+    let _ = (node, delta);
+}
+
+/// Recomputes the subtree size of the internal `node` (at the given height) from its children. No-op
+/// without the `order-statistic` feature.
+#[inline]
+pub unsafe fn recompute_size<K, V, const B: usize>(node: NodePtr<K, V, B>, height: usize) {
+    #[cfg(feature = "order-statistic")]
+    {
+        debug_assert!(height > 0, "recompute_size called on a leaf");
+        let mut node = node;
+        let size = node
+            .as_ref()
+            .edges()
+            .iter()
+            .map(|child| child.as_ref().subtree_len(height - 1))
+            .sum();
+        node.as_mut().d.internal_mut().size = size;
+    }
+    #[cfg(not(feature = "order-statistic"))]
+    let _ = (node, height);
+}
+
 #[inline]
-pub unsafe fn normalize_address<K, V>(
-    node: NodePtr<K, V>,
+pub unsafe fn normalize_address<K, V, const B: usize>(
+    node: NodePtr<K, V, B>,
     idx: u16,
-) -> Option<(NodePtr<K, V>, u16)> {
+) -> Option<(NodePtr<K, V, B>, u16)> {
     let node_ref = node.as_ref();
     if idx < node_ref.len {
         Some((node, idx))
@@ -849,7 +1000,10 @@ pub unsafe fn normalize_address<K, V>(
 }
 
 #[inline]
-pub unsafe fn address_before<K, V>(node: NodePtr<K, V>, idx: u16) -> Option<(NodePtr<K, V>, u16)> {
+pub unsafe fn address_before<K, V, const B: usize>(
+    node: NodePtr<K, V, B>,
+    idx: u16,
+) -> Option<(NodePtr<K, V, B>, u16)> {
     let node_ref = node.as_ref();
     if idx > 0 {
         Some((node, idx - 1))
@@ -859,7 +1013,10 @@ pub unsafe fn address_before<K, V>(node: NodePtr<K, V>, idx: u16) -> Option<(Nod
 }
 
 #[inline]
-pub unsafe fn address_after<K, V>(node: NodePtr<K, V>, idx: u16) -> Option<(NodePtr<K, V>, u16)> {
+pub unsafe fn address_after<K, V, const B: usize>(
+    node: NodePtr<K, V, B>,
+    idx: u16,
+) -> Option<(NodePtr<K, V, B>, u16)> {
     let node_ref = node.as_ref();
     match idx.cmp(&(node_ref.len - 1)) {
         Ordering::Less => Some((node, idx + 1)),
@@ -873,6 +1030,101 @@ pub unsafe fn address_after<K, V>(node: NodePtr<K, V>, idx: u16) -> Option<(Node
     }
 }
 
+/// Address `n` entries after `node` and `idx` (which must be normalized), or `limit` if that comes
+/// first. `limit` must be at or after the address.
+#[inline]
+pub unsafe fn address_forward<K, V, const B: usize>(
+    mut node: NodePtr<K, V, B>,
+    mut idx: u16,
+    mut n: usize,
+    (limit_node, limit_idx): (NodePtr<K, V, B>, u16),
+) -> (NodePtr<K, V, B>, u16) {
+    loop {
+        if node.ptr_eq(&limit_node) {
+            return (node, (idx as usize + n).min(limit_idx as usize) as u16);
+        }
+        let rest = (node.as_ref().len - 1 - idx) as usize;
+        if n <= rest {
+            return (node, idx + n as u16);
+        }
+        n -= rest + 1;
+        node = node.as_ref().next().unwrap_unchecked();
+        idx = 0;
+    }
+}
+
+/// Address `n` entries before `node` and `idx` (which must be normalized), or `limit` if that comes
+/// first. `limit` must be at or before the address.
+#[inline]
+pub unsafe fn address_backward<K, V, const B: usize>(
+    mut node: NodePtr<K, V, B>,
+    mut idx: u16,
+    mut n: usize,
+    (limit_node, limit_idx): (NodePtr<K, V, B>, u16),
+) -> (NodePtr<K, V, B>, u16) {
+    loop {
+        if node.ptr_eq(&limit_node) {
+            return (
+                node,
+                (idx as usize).saturating_sub(n).max(limit_idx as usize) as u16,
+            );
+        }
+        if n <= idx as usize {
+            return (node, idx - n as u16);
+        }
+        n -= idx as usize + 1;
+        node = node.as_ref().prev().unwrap_unchecked();
+        idx = node.as_ref().len - 1;
+    }
+}
+
+/// Picks how [Node::split_leaf] moves entries: in bulk when keys are [Copy], so the split key can
+/// be duplicated without calling [Clone::clone] partway through.
+///
+/// Specializing on [Copy] needs the full (incomplete) `specialization` feature. It's sound here
+/// because a type can't be [Copy] for only some lifetimes and also need dropping.
+#[cfg(feature = "nightly")]
+trait SplitLeaf<K, V, const B: usize> {
+    unsafe fn split_leaf_impl(&mut self, idx: u16, key: &mut K, val: V) -> Node<K, V, B>;
+}
+
+#[cfg(feature = "nightly")]
+impl<K: Clone, V, const B: usize> SplitLeaf<K, V, B> for Node<K, V, B> {
+    #[inline]
+    default unsafe fn split_leaf_impl(&mut self, idx: u16, key: &mut K, val: V) -> Node<K, V, B> {
+        self.split_leaf_by_swaps(idx, key, val)
+    }
+}
+
+#[cfg(feature = "nightly")]
+impl<K: Copy, V, const B: usize> SplitLeaf<K, V, B> for Node<K, V, B> {
+    #[inline]
+    unsafe fn split_leaf_impl(&mut self, idx: u16, key: &mut K, val: V) -> Node<K, V, B> {
+        self.split_leaf_by_copies(idx, key, val)
+    }
+}
+
+/// Under the `prefetch` feature (on x86 and x86_64), hints the CPU to load the cache lines of the
+/// `len` bytes at `ptr`. Otherwise does nothing.
+#[inline(always)]
+#[allow(unused_variables)]
+fn prefetch<T>(ptr: *const T, len: usize) {
+    #[cfg(all(feature = "prefetch", target_arch = "x86"))]
+    use std::arch::x86::{_mm_prefetch, _MM_HINT_T0};
+    #[cfg(all(feature = "prefetch", target_arch = "x86_64"))]
+    use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+
+    #[cfg(all(feature = "prefetch", any(target_arch = "x86", target_arch = "x86_64")))]
+    unsafe {
+        const CACHE_LINE: usize = 64;
+        let ptr = ptr as *const i8;
+        for offset in (0..len).step_by(CACHE_LINE) {
+            _mm_prefetch::<_MM_HINT_T0>(ptr.add(offset));
+        }
+    }
+}
+
+#[cfg(feature = "nightly")]
 #[inline]
 unsafe fn unsafe_copy_slice_overlapping<T>(
     data: &mut [T],
@@ -908,8 +1160,243 @@ unsafe fn unsafe_copy_slice_overlapping<T>(
     copy(src, dst, src_len);
 }
 
+#[cfg(feature = "nightly")]
 #[inline]
 unsafe fn unsafe_copy_slice_nonoverlapping<T>(dst: &mut [T], src: &[T]) {
     debug_assert_eq!(dst.len(), src.len());
     copy_nonoverlapping(src.as_ptr(), dst.as_mut_ptr(), src.len());
 }
+
+// region type-erased slot bookkeeping
+/// Offsets and sizes of a [Node]'s fields, so that shifting, splitting, and merging nodes' slots
+/// (and updating their children's `parent_idx`s) is compiled once, instead of for every key and
+/// value type. Generic [Node] methods only read and write the individual keys, values, and edges.
+///
+/// Nodes are passed to these methods as `*mut u8`. Keys and values are moved bitwise, which is how
+/// the generic code moved them too. Edges are thin pointers to nodes with the same layout.
+///
+/// The methods called from generic code are `#[inline(never)]`, otherwise they'd be inlined back
+/// into each instantiation. The helpers they share are inlined into them. They're also
+/// `extern "C"`, so callers know they don't unwind without seeing their bodies, which
+/// `tests/no_panic.rs` relies on.
+#[repr(C)]
+struct NodeLayout {
+    key_size: usize,
+    val_size: usize,
+    parent_idx: usize,
+    len: usize,
+    keys: usize,
+    vals: usize,
+    edges: usize,
+}
+
+impl<K, V, const B: usize> Node<K, V, B> {
+    const LAYOUT: NodeLayout = NodeLayout::of::<K, V, B>();
+
+    /// This node with its key and value types erased, for [NodeLayout]'s methods
+    #[inline]
+    fn erased(&mut self) -> *mut u8 {
+        self as *mut Self as *mut u8
+    }
+}
+
+impl NodeLayout {
+    const fn of<K, V, const B: usize>() -> Self {
+        assert!(
+            size_of::<NodePtr<K, V, B>>() == size_of::<*mut u8>(),
+            "edges must be thin pointers"
+        );
+        NodeLayout {
+            key_size: size_of::<K>(),
+            val_size: size_of::<V>(),
+            parent_idx: offset_of!(Node<K, V, B>, parent_idx),
+            len: offset_of!(Node<K, V, B>, len),
+            keys: offset_of!(Node<K, V, B>, keys),
+            // `ManuallyDrop` is `repr(transparent)`, so its contents are at its offset
+            vals: offset_of!(Node<K, V, B>, d.leaf) + offset_of!(LeafData<K, V, B>, vals),
+            edges: offset_of!(Node<K, V, B>, d.internal) + offset_of!(InternalData<K, V, B>, edges),
+        }
+    }
+
+    /// Shifts the keys and values from `idx` on right by one, leaving slot `idx` uninitialized, and
+    /// increments the length
+    #[inline(never)]
+    unsafe extern "C" fn open_leaf_slot(&self, node: *mut u8, idx: u16) {
+        let len = self.len(node);
+        self.move_keys(node, idx + 1, node, idx..*len);
+        self.move_vals(node, idx + 1, node, idx..*len);
+        *len += 1;
+    }
+
+    /// Shifts the keys and values after `idx` left by one, overwriting slot `idx` (which must have
+    /// been read), and decrements the length
+    #[inline(never)]
+    unsafe extern "C" fn close_leaf_slot(&self, node: *mut u8, idx: u16) {
+        let len = self.len(node);
+        self.move_keys(node, idx, node, idx + 1..*len);
+        self.move_vals(node, idx, node, idx + 1..*len);
+        *len -= 1;
+    }
+
+    /// Shifts the keys from `idx` and edges from `edge_idx` on right by one, leaving those slots
+    /// uninitialized, and increments the length
+    #[inline(never)]
+    unsafe extern "C" fn open_internal_slot(&self, node: *mut u8, idx: u16, edge_idx: u16) {
+        let len = self.len(node);
+        self.move_keys(node, idx + 1, node, idx..*len);
+        self.move_edges(node, edge_idx + 1, node, edge_idx..*len + 1);
+        *len += 1;
+    }
+
+    /// Shifts the keys after `idx` and edges after `edge_idx` left by one, overwriting those slots
+    /// (which must have been read), and decrements the length
+    #[inline(never)]
+    unsafe extern "C" fn close_internal_slot(&self, node: *mut u8, idx: u16, edge_idx: u16) {
+        let len = self.len(node);
+        self.move_keys(node, idx, node, idx + 1..*len);
+        self.move_edges(node, edge_idx, node, edge_idx + 1..*len + 1);
+        *len -= 1;
+    }
+
+    /// Moves the entries from `median` on into `right` after its first slot, which is left
+    /// uninitialized
+    #[inline(never)]
+    unsafe extern "C" fn split_leaf(&self, left: *mut u8, right: *mut u8, median: u16) {
+        let left_len = self.len(left);
+        self.move_keys(right, 1, left, median..*left_len);
+        self.move_vals(right, 1, left, median..*left_len);
+        *self.len(right) = *left_len - median + 1;
+        *left_len = median;
+    }
+
+    /// Moves the keys from `median` on and the edges after them into `right` after its first edge,
+    /// which must be set, then updates the `parent_idx`s of all of `right`'s edges
+    #[inline(never)]
+    unsafe extern "C" fn split_internal(&self, left: *mut u8, right: *mut u8, median: u16) {
+        let left_len = self.len(left);
+        self.move_keys(right, 0, left, median..*left_len);
+        self.move_edges(right, 1, left, median + 1..*left_len + 1);
+        let right_len = *left_len - median;
+        *self.len(right) = right_len;
+        *left_len = median;
+        self.renumber_edges(right, 0..right_len + 1);
+    }
+
+    /// Moves the keys from `idx` on, and the values or edges after them, into `right`. An internal
+    /// node's edges' `parent_idx`s aren't updated, and `right`'s first edge is left uninitialized.
+    #[inline(never)]
+    unsafe extern "C" fn split_off(&self, left: *mut u8, right: *mut u8, idx: u16, leaf: bool) {
+        let left_len = self.len(left);
+        self.move_keys(right, 0, left, idx..*left_len);
+        if leaf {
+            self.move_vals(right, 0, left, idx..*left_len);
+        } else {
+            self.copy_slots(
+                right,
+                self.edges,
+                1,
+                left,
+                idx + 1..*left_len + 1,
+                EDGE_SIZE,
+            );
+        }
+        *self.len(right) = *left_len - idx;
+        *left_len = idx;
+    }
+
+    /// Moves all of `prev`'s entries before `node`'s
+    #[inline(never)]
+    unsafe extern "C" fn merge_prev_leaf(&self, node: *mut u8, prev: *mut u8) {
+        let (len, prev_len) = (self.len(node), *self.len(prev));
+        self.move_keys(node, prev_len, node, 0..*len);
+        self.move_vals(node, prev_len, node, 0..*len);
+        self.move_keys(node, 0, prev, 0..prev_len);
+        self.move_vals(node, 0, prev, 0..prev_len);
+        *len += prev_len;
+    }
+
+    /// Moves all of `next`'s entries after `node`'s
+    #[inline(never)]
+    unsafe extern "C" fn merge_next_leaf(&self, node: *mut u8, next: *mut u8) {
+        let (len, next_len) = (self.len(node), *self.len(next));
+        self.move_keys(node, *len, next, 0..next_len);
+        self.move_vals(node, *len, next, 0..next_len);
+        *len += next_len;
+    }
+
+    /// Moves all of `prev`'s keys and edges before `node`'s, leaving a slot for the middle key,
+    /// and updates the `parent_idx`s of `node`'s original edges
+    #[inline(never)]
+    unsafe extern "C" fn merge_prev_internal(&self, node: *mut u8, prev: *mut u8) {
+        let (len, prev_len) = (self.len(node), *self.len(prev));
+        self.move_keys(node, prev_len + 1, node, 0..*len);
+        self.move_edges(node, prev_len + 1, node, 0..*len + 1);
+        self.move_keys(node, 0, prev, 0..prev_len);
+        self.copy_slots(node, self.edges, 0, prev, 0..prev_len + 1, EDGE_SIZE);
+        *len += prev_len + 1;
+    }
+
+    /// Moves all of `next`'s keys and edges after `node`'s and the middle key, which must be set,
+    /// and updates the `parent_idx`s of `next`'s edges
+    #[inline(never)]
+    unsafe extern "C" fn merge_next_internal(&self, node: *mut u8, next: *mut u8) {
+        let (len, next_len) = (self.len(node), *self.len(next));
+        self.move_keys(node, *len + 1, next, 0..next_len);
+        self.move_edges(node, *len + 1, next, 0..next_len + 1);
+        *len += next_len + 1;
+    }
+
+    #[inline]
+    unsafe fn len<'a>(&self, node: *mut u8) -> &'a mut u16 {
+        &mut *(node.add(self.len) as *mut u16)
+    }
+
+    #[inline]
+    unsafe fn move_keys(&self, dst: *mut u8, dst_idx: u16, src: *mut u8, src_idxs: Range<u16>) {
+        self.copy_slots(dst, self.keys, dst_idx, src, src_idxs, self.key_size)
+    }
+
+    #[inline]
+    unsafe fn move_vals(&self, dst: *mut u8, dst_idx: u16, src: *mut u8, src_idxs: Range<u16>) {
+        self.copy_slots(dst, self.vals, dst_idx, src, src_idxs, self.val_size)
+    }
+
+    /// Moves the edges, and updates their `parent_idx`s to their new indices
+    #[inline]
+    unsafe fn move_edges(&self, dst: *mut u8, dst_idx: u16, src: *mut u8, src_idxs: Range<u16>) {
+        let dst_idxs = dst_idx..dst_idx + src_idxs.len() as u16;
+        self.copy_slots(dst, self.edges, dst_idx, src, src_idxs, EDGE_SIZE);
+        self.renumber_edges(dst, dst_idxs);
+    }
+
+    /// Sets the `parent_idx`s of the edges at `idxs` to their indices
+    #[inline]
+    unsafe fn renumber_edges(&self, node: *mut u8, idxs: Range<u16>) {
+        for idx in idxs {
+            let child = *(node.add(self.edges + idx as usize * EDGE_SIZE) as *const *mut u8);
+            *(child.add(self.parent_idx) as *mut u16) = idx;
+        }
+    }
+
+    /// Copies the slots in the array at `offset` from `src_idxs` in `src` to start at `dst_idx` in
+    /// `dst`, which may be the same node
+    #[inline]
+    unsafe fn copy_slots(
+        &self,
+        dst: *mut u8,
+        offset: usize,
+        dst_idx: u16,
+        src: *mut u8,
+        src_idxs: Range<u16>,
+        size: usize,
+    ) {
+        copy(
+            src.add(offset + src_idxs.start as usize * size),
+            dst.add(offset + dst_idx as usize * size),
+            src_idxs.len() * size,
+        );
+    }
+}
+
+const EDGE_SIZE: usize = size_of::<*mut u8>();
+// endregion
@@ -2,26 +2,19 @@ use std::cmp::Ordering;
 use std::marker::PhantomData;
 
 use crate::node::{Node, NodePtr};
-use crate::BTreeStore;
 
-#[doc(hidden)]
-pub trait BTree<'store, K, V> {
-    fn assert_store(&self, store: &BTreeStore<K, V>);
-    fn nodes(&self) -> NodeIter<'store, K, V>;
-}
-
-/// Does a pre-order traversal of all nodes (*not* entries) in the tree.
-#[doc(hidden)]
-pub struct NodeIter<'store, K, V> {
-    current: Option<NodePtr<K, V>>,
+/// Does a pre-order traversal of all nodes (*not* entries) in the tree, yielding each node with its
+/// height (0 = leaf).
+pub struct NodeIter<'store, K, V, const B: usize> {
+    current: Option<NodePtr<K, V, B>>,
     current_height: usize,
     max_height: usize,
-    _p: PhantomData<&'store Node<K, V>>,
+    _p: PhantomData<&'store Node<K, V, B>>,
 }
 
-impl<'store, K, V> NodeIter<'store, K, V> {
+impl<'store, K, V, const B: usize> NodeIter<'store, K, V, B> {
     #[inline]
-    pub(crate) fn new(root: Option<NodePtr<K, V>>, height: usize) -> Self {
+    pub(crate) fn new(root: Option<NodePtr<K, V, B>>, height: usize) -> Self {
         Self {
             current: root,
             current_height: height,
@@ -29,16 +22,21 @@ impl<'store, K, V> NodeIter<'store, K, V> {
             _p: PhantomData,
         }
     }
+
+    /// Height of the tree, so `max_height - height` is a node's depth
+    #[inline]
+    pub(crate) fn max_height(&self) -> usize {
+        self.max_height
+    }
 }
 
-impl<'store, K, V> Iterator for NodeIter<'store, K, V> {
-    type Item = NodePtr<K, V>;
+impl<'store, K, V, const B: usize> Iterator for NodeIter<'store, K, V, B> {
+    type Item = (NodePtr<K, V, B>, usize);
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        let Some(next) = self.current.take() else {
-            return None;
-        };
+        let next = self.current.take()?;
+        let next_height = self.current_height;
 
         // Advance.
         // To get all nodes:
@@ -59,11 +57,12 @@ impl<'store, K, V> Iterator for NodeIter<'store, K, V> {
                 match self.current_height.cmp(&self.max_height) {
                     Ordering::Less => {
                         self.current_height += 1;
-                        node = unsafe { node.as_ref().parent.unwrap() };
                         let index = unsafe { node.as_ref().parent_idx.assume_init() };
+                        node = unsafe { node.as_ref().parent.unwrap() };
                         if index < unsafe { node.as_ref() }.len {
                             // Remember: we've already traversed this node and its children at `index`s
                             // going down. But we haven't traversed its next child at `index + 1`...
+                            self.current_height -= 1;
                             break Some(unsafe { node.as_ref().edge(index + 1) });
                         }
                     }
@@ -73,6 +72,6 @@ impl<'store, K, V> Iterator for NodeIter<'store, K, V> {
             }
         }
 
-        Some(next)
+        Some((next, next_height))
     }
 }
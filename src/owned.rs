@@ -0,0 +1,557 @@
+#[cfg(feature = "set")]
+use crate::BTreeSet;
+use crate::{BTreeMap, BTreeStore};
+use std::borrow::Borrow;
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ptr::NonNull;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// A b-tree map which owns its own [BTreeStore], so it has no `'store` lifetime and can be kept in
+/// struct fields, sent to other threads, or held across `.await`s.
+///
+/// This gives up sharing a store between trees. Reads go through [OwnedBTreeMap::as_map], which
+/// borrows the map (and its store) for only as long as `&self`; common writes have methods here,
+/// and the rest go through [OwnedBTreeMap::with_mut].
+///
+/// By default the map has a store of its own. To share the store with other trees, hold it in an
+/// [Rc] or [Arc] instead (see [StoreRef]) and create the map with [OwnedBTreeMap::new_in].
+///
+/// The map borrows its store for `'static` internally, so keys and values must be `'static`.
+///
+/// # Examples
+///
+/// ```
+/// use btree_plus_store::OwnedBTreeMap;
+/// struct Registry {
+///     names: OwnedBTreeMap<u32, String>,
+/// }
+/// let mut registry = Registry { names: OwnedBTreeMap::new() };
+/// registry.names.insert(1, "one".to_string());
+/// registry.names.with_mut(|map| map.get_mut(&1).unwrap().push('!'));
+/// assert_eq!(registry.names.as_map().get(&1).map(String::as_str), Some("one!"));
+/// ```
+///
+/// Sharing a store:
+///
+/// ```
+/// use btree_plus_store::{BTreeStore, OwnedBTreeMap};
+/// use std::rc::Rc;
+/// let store = Rc::new(BTreeStore::new());
+/// let mut a = OwnedBTreeMap::new_in(store.clone());
+/// let mut b = OwnedBTreeMap::new_in(store);
+/// a.insert(1, "a");
+/// b.insert(2, "b");
+/// drop(a);
+/// assert_eq!(b.get(&2), Some(&"b"));
+/// ```
+pub struct OwnedBTreeMap<
+    K: 'static,
+    V: 'static,
+    S: StoreRef<K, V, B> = Box<BTreeStore<K, V>>,
+    const B: usize = 8,
+> {
+    /// Borrows `store` for `'static`. The borrow is only ever handed out shortened to a borrow of
+    /// `self`, so it can't outlive the store.
+    map: ManuallyDrop<BTreeMap<'static, K, V, B>>,
+    /// The handle from [StoreRef], released after `map` is dropped. A raw pointer, since `map`
+    /// aliases it
+    store: NonNull<BTreeStore<K, V, B>>,
+    _p: PhantomData<S>,
+}
+
+/// A b-tree set which owns its own [BTreeStore], so it has no `'store` lifetime.
+///
+/// See [OwnedBTreeMap].
+#[cfg(feature = "set")]
+pub struct OwnedBTreeSet<
+    T: 'static,
+    S: StoreRef<T, (), B> = Box<BTreeStore<T, ()>>,
+    const B: usize = 8,
+> {
+    /// Borrows `store` for `'static`, see [OwnedBTreeMap]
+    set: ManuallyDrop<BTreeSet<'static, T, B>>,
+    store: NonNull<BTreeStore<T, (), B>>,
+    _p: PhantomData<S>,
+}
+
+/// An owning handle to a [BTreeStore], which [OwnedBTreeMap] and [OwnedBTreeSet] hold instead of
+/// borrowing the store: [Box] (the default) for a store of their own, or [Rc] or [Arc] to share it
+/// with other trees.
+///
+/// Trees sharing a store through an [Arc] still can't be sent to other threads, since the store
+/// isn't [Sync].
+///
+/// This trait is [sealed](https://rust-lang.github.io/api-guidelines/future-proofing.html#sealed-traits-protect-against-downstream-implementations-c-sealed)
+pub trait StoreRef<K, V, const B: usize = 8>: sealed::StoreRef<K, V, B> {}
+
+impl<K, V, const B: usize> StoreRef<K, V, B> for Box<BTreeStore<K, V, B>> {}
+
+impl<K, V, const B: usize> StoreRef<K, V, B> for Rc<BTreeStore<K, V, B>> {}
+
+impl<K, V, const B: usize> StoreRef<K, V, B> for Arc<BTreeStore<K, V, B>> {}
+
+mod sealed {
+    use crate::BTreeStore;
+    use std::ptr::NonNull;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    #[doc(hidden)]
+    pub trait StoreRef<K, V, const B: usize = 8>: Sized {
+        /// Gives up the handle for a pointer to the store, which stays valid until it's passed to
+        /// `from_raw`
+        fn into_raw(self) -> NonNull<BTreeStore<K, V, B>>;
+
+        /// # Safety
+        /// `store` must be from `into_raw`, and can only be passed once
+        unsafe fn from_raw(store: NonNull<BTreeStore<K, V, B>>) -> Self;
+
+        /// The store a clone of a tree in `store` is allocated in
+        fn for_clone(store: &Self) -> Self;
+    }
+
+    impl<K, V, const B: usize> StoreRef<K, V, B> for Box<BTreeStore<K, V, B>> {
+        #[inline]
+        fn into_raw(self) -> NonNull<BTreeStore<K, V, B>> {
+            NonNull::from(Box::leak(self))
+        }
+
+        #[inline]
+        unsafe fn from_raw(store: NonNull<BTreeStore<K, V, B>>) -> Self {
+            Box::from_raw(store.as_ptr())
+        }
+
+        /// A new store (with default settings), since a box can't be shared
+        #[inline]
+        fn for_clone(_: &Self) -> Self {
+            Box::default()
+        }
+    }
+
+    impl<K, V, const B: usize> StoreRef<K, V, B> for Rc<BTreeStore<K, V, B>> {
+        #[inline]
+        fn into_raw(self) -> NonNull<BTreeStore<K, V, B>> {
+            // SAFETY: an `Rc`'s pointer is never null
+            unsafe { NonNull::new_unchecked(Rc::into_raw(self).cast_mut()) }
+        }
+
+        #[inline]
+        unsafe fn from_raw(store: NonNull<BTreeStore<K, V, B>>) -> Self {
+            Rc::from_raw(store.as_ptr())
+        }
+
+        #[inline]
+        fn for_clone(store: &Self) -> Self {
+            Rc::clone(store)
+        }
+    }
+
+    impl<K, V, const B: usize> StoreRef<K, V, B> for Arc<BTreeStore<K, V, B>> {
+        #[inline]
+        fn into_raw(self) -> NonNull<BTreeStore<K, V, B>> {
+            // SAFETY: an `Arc`'s pointer is never null
+            unsafe { NonNull::new_unchecked(Arc::into_raw(self).cast_mut()) }
+        }
+
+        #[inline]
+        unsafe fn from_raw(store: NonNull<BTreeStore<K, V, B>>) -> Self {
+            Arc::from_raw(store.as_ptr())
+        }
+
+        #[inline]
+        fn for_clone(store: &Self) -> Self {
+            Arc::clone(store)
+        }
+    }
+}
+
+// SAFETY: with a boxed store, the map owns its store and every node in it, and nothing outside can
+// borrow either while it's moved. It isn't `Sync`, because some `&BTreeMap` methods (e.g.
+// `reserve`) allocate in the store.
+unsafe impl<K: Send + 'static, V: Send + 'static, const B: usize> Send
+    for OwnedBTreeMap<K, V, Box<BTreeStore<K, V, B>>, B>
+{
+}
+
+// SAFETY: same as `OwnedBTreeMap`
+#[cfg(feature = "set")]
+unsafe impl<T: Send + 'static, const B: usize> Send
+    for OwnedBTreeSet<T, Box<BTreeStore<T, (), B>>, B>
+{
+}
+
+impl<K: 'static, V: 'static> OwnedBTreeMap<K, V> {
+    /// Creates an empty map in a new store.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_store(BTreeStore::new())
+    }
+}
+
+impl<K: 'static, V: 'static, const B: usize> OwnedBTreeMap<K, V, Box<BTreeStore<K, V, B>>, B> {
+    /// Creates an empty map in `store`, e.g. one configured by a
+    /// [BTreeStoreBuilder](crate::BTreeStoreBuilder).
+    #[inline]
+    pub fn with_store(store: BTreeStore<K, V, B>) -> Self {
+        Self::new_in(Box::new(store))
+    }
+}
+
+impl<K: 'static, V: 'static, S: StoreRef<K, V, B>, const B: usize> OwnedBTreeMap<K, V, S, B> {
+    /// Creates an empty map in the store behind the handle, which may be shared with other trees.
+    #[inline]
+    pub fn new_in(store: S) -> Self {
+        let store = store.into_raw();
+        Self {
+            map: ManuallyDrop::new(BTreeMap::new_in(unsafe { store.as_ref() })),
+            store,
+            _p: PhantomData,
+        }
+    }
+
+    /// The map, to read
+    #[inline]
+    pub fn as_map(&self) -> &BTreeMap<'_, K, V, B> {
+        &self.map
+    }
+
+    /// The map's store
+    #[inline]
+    pub fn store(&self) -> &BTreeStore<K, V, B> {
+        unsafe { self.store.as_ref() }
+    }
+
+    /// Another handle to the map's store, e.g. to create another tree sharing it
+    #[inline]
+    pub fn store_ref(&self) -> S
+    where
+        S: Clone,
+    {
+        S::clone(&ManuallyDrop::new(unsafe { S::from_raw(self.store) }))
+    }
+
+    /// Calls `f` with the map, to modify it in ways which don't have a method here.
+    ///
+    /// `f` works for any store lifetime, so it can't move the map's entries into another map or
+    /// keep anything borrowing the store.
+    #[inline]
+    pub fn with_mut<R>(&mut self, f: impl for<'a> FnOnce(&mut BTreeMap<'a, K, V, B>) -> R) -> R {
+        f(&mut *self.map)
+    }
+
+    /// Returns the number of elements in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    #[inline]
+    pub fn get<Q: Ord + ?Sized>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        self.map.get(key)
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key.
+    #[inline]
+    pub fn get_mut<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+    {
+        self.map.get_mut(key)
+    }
+
+    /// Inserts a key-value pair into the map. See [BTreeMap::insert].
+    #[inline]
+    pub fn insert(&mut self, key: K, val: V) -> Option<V>
+    where
+        K: Clone + Ord,
+    {
+        self.map.insert(key, val)
+    }
+
+    /// Removes a key from the map, returning the value at the key if the key was previously in the
+    /// map.
+    #[inline]
+    pub fn remove<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Clone + Borrow<Q>,
+    {
+        self.map.remove(key)
+    }
+
+    /// Clears the map, removing all elements. Its nodes stay in the store, for reuse.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+}
+
+#[cfg(feature = "set")]
+impl<T: 'static> OwnedBTreeSet<T> {
+    /// Creates an empty set in a new store.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_store(BTreeStore::new())
+    }
+}
+
+#[cfg(feature = "set")]
+impl<T: 'static, const B: usize> OwnedBTreeSet<T, Box<BTreeStore<T, (), B>>, B> {
+    /// Creates an empty set in `store`.
+    #[inline]
+    pub fn with_store(store: BTreeStore<T, (), B>) -> Self {
+        Self::new_in(Box::new(store))
+    }
+}
+
+#[cfg(feature = "set")]
+impl<T: 'static, S: StoreRef<T, (), B>, const B: usize> OwnedBTreeSet<T, S, B> {
+    /// Creates an empty set in the store behind the handle, which may be shared with other trees.
+    #[inline]
+    pub fn new_in(store: S) -> Self {
+        let store = store.into_raw();
+        Self {
+            set: ManuallyDrop::new(BTreeSet::new_in(unsafe { store.as_ref() })),
+            store,
+            _p: PhantomData,
+        }
+    }
+
+    /// The set, to read
+    #[inline]
+    pub fn as_set(&self) -> &BTreeSet<'_, T, B> {
+        &self.set
+    }
+
+    /// The set's store
+    #[inline]
+    pub fn store(&self) -> &BTreeStore<T, (), B> {
+        unsafe { self.store.as_ref() }
+    }
+
+    /// Another handle to the set's store, e.g. to create another tree sharing it
+    #[inline]
+    pub fn store_ref(&self) -> S
+    where
+        S: Clone,
+    {
+        S::clone(&ManuallyDrop::new(unsafe { S::from_raw(self.store) }))
+    }
+
+    /// Calls `f` with the set, to modify it in ways which don't have a method here. See
+    /// [OwnedBTreeMap::with_mut].
+    #[inline]
+    pub fn with_mut<R>(&mut self, f: impl for<'a> FnOnce(&mut BTreeSet<'a, T, B>) -> R) -> R {
+        f(&mut *self.set)
+    }
+
+    /// Returns the number of elements in the set.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    /// Returns `true` if the set contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+
+    /// Returns `true` if the set contains a value.
+    #[inline]
+    pub fn contains<U: Ord + ?Sized>(&self, value: &U) -> bool
+    where
+        T: Borrow<U>,
+    {
+        self.set.contains(value)
+    }
+
+    /// Inserts a value into the set. Returns `true` if the value was not already present.
+    #[inline]
+    pub fn insert(&mut self, value: T) -> bool
+    where
+        T: Clone + Ord,
+    {
+        self.set.insert(value)
+    }
+
+    /// Removes a value from the set. Returns `true` if the value was present.
+    #[inline]
+    pub fn remove<U: Ord + ?Sized>(&mut self, value: &U) -> bool
+    where
+        T: Borrow<U> + Clone,
+    {
+        self.set.remove(value)
+    }
+
+    /// Clears the set, removing all values. Its nodes stay in the store, for reuse.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.set.clear()
+    }
+}
+
+// region common trait impls
+impl<K: 'static, V: 'static, S: StoreRef<K, V, B>, const B: usize> Drop
+    for OwnedBTreeMap<K, V, S, B>
+{
+    fn drop(&mut self) {
+        // The map deallocates its nodes in the store, so it's dropped first
+        unsafe {
+            ManuallyDrop::drop(&mut self.map);
+            drop(S::from_raw(self.store));
+        }
+    }
+}
+
+#[cfg(feature = "set")]
+impl<T: 'static, S: StoreRef<T, (), B>, const B: usize> Drop for OwnedBTreeSet<T, S, B> {
+    fn drop(&mut self) {
+        // Same as `OwnedBTreeMap`
+        unsafe {
+            ManuallyDrop::drop(&mut self.set);
+            drop(S::from_raw(self.store));
+        }
+    }
+}
+
+impl<K: 'static, V: 'static> Default for OwnedBTreeMap<K, V> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "set")]
+impl<T: 'static> Default for OwnedBTreeSet<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Debug + 'static, V: Debug + 'static, S: StoreRef<K, V, B>, const B: usize> Debug
+    for OwnedBTreeMap<K, V, S, B>
+{
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.as_map().fmt(f)
+    }
+}
+
+#[cfg(feature = "set")]
+impl<T: Debug + 'static, S: StoreRef<T, (), B>, const B: usize> Debug for OwnedBTreeSet<T, S, B> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.as_set().fmt(f)
+    }
+}
+
+/// Clones the entries into a new store (with default settings) if the store is boxed, otherwise
+/// into the same store.
+impl<K: Clone + 'static, V: Clone + 'static, S: StoreRef<K, V, B>, const B: usize> Clone
+    for OwnedBTreeMap<K, V, S, B>
+{
+    fn clone(&self) -> Self {
+        let store = ManuallyDrop::new(unsafe { S::from_raw(self.store) });
+        let mut clone = Self::new_in(S::for_clone(&store));
+        let store = unsafe { clone.store.as_ref() };
+        let entries = self.map.iter().map(|(k, v)| (k.clone(), v.clone()));
+        *clone.map = BTreeMap::bulk_load_in(store, entries);
+        clone
+    }
+}
+
+/// Clones the values into a new store (with default settings) if the store is boxed, otherwise into
+/// the same store.
+#[cfg(feature = "set")]
+impl<T: Clone + 'static, S: StoreRef<T, (), B>, const B: usize> Clone for OwnedBTreeSet<T, S, B> {
+    fn clone(&self) -> Self {
+        let store = ManuallyDrop::new(unsafe { S::from_raw(self.store) });
+        let mut clone = Self::new_in(S::for_clone(&store));
+        let store = unsafe { clone.store.as_ref() };
+        let values = self.set.iter().map(|value| (value.clone(), ()));
+        *clone.set = BTreeSet(BTreeMap::bulk_load_in(store, values));
+        clone
+    }
+}
+
+impl<K: PartialEq + 'static, V: PartialEq + 'static, S: StoreRef<K, V, B>, const B: usize> PartialEq
+    for OwnedBTreeMap<K, V, S, B>
+{
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.as_map() == other.as_map()
+    }
+}
+
+impl<K: Eq + 'static, V: Eq + 'static, S: StoreRef<K, V, B>, const B: usize> Eq
+    for OwnedBTreeMap<K, V, S, B>
+{
+}
+
+#[cfg(feature = "set")]
+impl<T: PartialEq + 'static, S: StoreRef<T, (), B>, const B: usize> PartialEq
+    for OwnedBTreeSet<T, S, B>
+{
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.as_set() == other.as_set()
+    }
+}
+
+#[cfg(feature = "set")]
+impl<T: Eq + 'static, S: StoreRef<T, (), B>, const B: usize> Eq for OwnedBTreeSet<T, S, B> {}
+
+impl<K: Ord + Clone + 'static, V: 'static, S: StoreRef<K, V, B>, const B: usize> Extend<(K, V)>
+    for OwnedBTreeMap<K, V, S, B>
+{
+    #[inline]
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        self.map.extend(iter)
+    }
+}
+
+#[cfg(feature = "set")]
+impl<T: Ord + Clone + 'static, S: StoreRef<T, (), B>, const B: usize> Extend<T>
+    for OwnedBTreeSet<T, S, B>
+{
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.set.extend(iter)
+    }
+}
+
+/// Builds a map in a new store. See [BTreeMap::from_iter_in].
+impl<K: Ord + Clone + 'static, V: 'static> FromIterator<(K, V)> for OwnedBTreeMap<K, V> {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut map = Self::new();
+        let store = unsafe { map.store.as_ref() };
+        *map.map = BTreeMap::from_iter_in(iter, store);
+        map
+    }
+}
+
+/// Builds a set in a new store. See [BTreeSet::from_iter_in].
+#[cfg(feature = "set")]
+impl<T: Ord + Clone + 'static> FromIterator<T> for OwnedBTreeSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = Self::new();
+        let store = unsafe { set.store.as_ref() };
+        *set.set = BTreeSet::from_iter_in(iter, store);
+        set
+    }
+}
+// endregion
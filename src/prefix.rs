@@ -0,0 +1,89 @@
+use std::borrow::Borrow;
+use std::ops::{Bound, RangeBounds};
+
+/// Key types which can be scanned by prefix: [str] (for `String`, `Box<str>`, ... keys) and `[u8]`
+/// (for `Vec<u8>`, `Box<[u8]>`, ... keys).
+pub trait Prefix: Ord + ToOwned {
+    /// The smallest value greater than every value starting with `self`, or `None` if there isn't
+    /// one (e.g. `self` is empty or only `0xFF` bytes).
+    fn prefix_end(&self) -> Option<Self::Owned>;
+}
+
+impl Prefix for [u8] {
+    #[inline]
+    fn prefix_end(&self) -> Option<Vec<u8>> {
+        // Trailing 0xFF bytes can't be incremented, so they're dropped and the byte before them is
+        // incremented instead
+        let last = self.iter().rposition(|&byte| byte != u8::MAX)?;
+        let mut end = self[..=last].to_vec();
+        end[last] += 1;
+        Some(end)
+    }
+}
+
+impl Prefix for str {
+    /// Strings are ordered by their UTF-8 bytes, which is the same as ordering by code points, so
+    /// the last char which isn't [char::MAX] is replaced by the next char (skipping surrogates).
+    #[inline]
+    fn prefix_end(&self) -> Option<String> {
+        let (idx, last) = self
+            .char_indices()
+            .rev()
+            .find(|&(_, char)| char != char::MAX)?;
+        let next = (last as u32 + 1..=char::MAX as u32)
+            .find_map(char::from_u32)
+            .expect("chars after a char which isn't char::MAX");
+        let mut end = String::with_capacity(idx + next.len_utf8());
+        end.push_str(&self[..idx]);
+        end.push(next);
+        Some(end)
+    }
+}
+
+/// Range of every value starting with a prefix, which can be passed to
+/// [BTreeMap::range](crate::BTreeMap::range) and similar methods.
+///
+/// # Examples
+///
+/// ```
+/// use btree_plus_store::{prefix_range, BTreeMap, BTreeStore};
+/// let store = BTreeStore::new();
+/// let mut map = BTreeMap::new_in(&store);
+/// for key in ["ab", "abc", "abd", "ac", "b"] {
+///     map.insert(key.to_string(), ());
+/// }
+/// let keys = map
+///     .try_range(prefix_range("ab"))
+///     .unwrap()
+///     .map(|(k, ())| k.as_str())
+///     .collect::<Vec<_>>();
+/// assert_eq!(keys, ["ab", "abc", "abd"]);
+/// ```
+pub struct PrefixRange<P: Prefix + ?Sized> {
+    start: P::Owned,
+    end: Option<P::Owned>,
+}
+
+/// Returns the range of every value starting with `prefix`. See [PrefixRange].
+#[inline]
+pub fn prefix_range<P: Prefix + ?Sized>(prefix: &P) -> PrefixRange<P> {
+    PrefixRange {
+        start: prefix.to_owned(),
+        end: prefix.prefix_end(),
+    }
+}
+
+impl<P: Prefix + ?Sized> RangeBounds<P> for PrefixRange<P> {
+    #[inline]
+    fn start_bound(&self) -> Bound<&P> {
+        Bound::Included(self.start.borrow())
+    }
+
+    #[inline]
+    fn end_bound(&self) -> Bound<&P> {
+        match &self.end {
+            None => Bound::Unbounded,
+            Some(end) => Bound::Excluded(end.borrow()),
+        }
+    }
+}
@@ -1,168 +1,1080 @@
-use crate::{BTreeMap, BTreeStore};
+use crate::error::OrPanic;
+use crate::map::NodeSummary;
+use crate::prefix::Prefix;
+use crate::{BTreeMap, BTreeStore, Error};
+#[cfg(feature = "rayon")]
+use rayon::iter::plumbing::UnindexedConsumer;
+#[cfg(feature = "rayon")]
+use rayon::iter::{IntoParallelIterator, ParallelExtend, ParallelIterator};
 use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::fmt::{Debug, Formatter};
-use std::hash::Hash;
-use std::iter::FusedIterator;
-use std::ops::RangeBounds;
+use std::hash::{BuildHasher, Hash};
+#[cfg(feature = "nightly")]
+use std::iter::TrustedLen;
+use std::iter::{FusedIterator, Rev};
+use std::ops::{BitAnd, BitOr, BitXor, RangeBounds, Sub};
 
 /// A b-tree set.
 ///
 /// See [std::collections::BTreeSet] for more info.
-// TODO: impl Clone
 #[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct BTreeSet<'store, T>(BTreeMap<'store, T, ()>);
+pub struct BTreeSet<'store, T, const B: usize = 8>(pub(crate) BTreeMap<'store, T, (), B>);
 
-impl<'store, T> BTreeSet<'store, T> {
-    /// Creates an empty set.
+impl<'store, T, const B: usize> BTreeSet<'store, T, B> {
+    /// Creates an empty set. Like [BTreeMap::new_in], this doesn't allocate.
     #[inline]
-    pub fn new_in(store: &'store BTreeStore<T, ()>) -> Self {
+    pub const fn new_in(store: &'store BTreeStore<T, (), B>) -> Self {
         Self(BTreeMap::new_in(store))
     }
 
+    /// Creates a set in `store` from the values. Like [BTreeMap::from_iter_in], they're sorted and
+    /// then bulk-loaded.
+    #[inline]
+    pub fn from_iter_in(
+        values: impl IntoIterator<Item = T>,
+        store: &'store BTreeStore<T, (), B>,
+    ) -> Self
+    where
+        T: Ord + Clone,
+    {
+        Self(BTreeMap::from_iter_in(
+            values.into_iter().map(|value| (value, ())),
+            store,
+        ))
+    }
+
+    /// Creates a set in `store` from an array of values, like std's `From<[T; N]>`.
+    #[inline]
+    pub fn from_array_in<const N: usize>(
+        values: [T; N],
+        store: &'store BTreeStore<T, (), B>,
+    ) -> Self
+    where
+        T: Ord + Clone,
+    {
+        Self::from_iter_in(values, store)
+    }
+
+    /// Creates a set in `store` from an array of strictly increasing values, without sorting or
+    /// searching.
+    ///
+    /// *Panics* if the values aren't strictly increasing.
+    #[track_caller]
+    #[inline]
+    pub fn from_sorted_array_in<const N: usize>(
+        values: [T; N],
+        store: &'store BTreeStore<T, (), B>,
+    ) -> Self
+    where
+        T: Ord + Clone,
+    {
+        Self::try_from_sorted_array_in(values, store).or_panic("BTreeSet::from_sorted_array_in")
+    }
+
+    /// Creates a set in `store` from an array of strictly increasing values, without sorting or
+    /// searching, or returns [Error::UnorderedKey] if they aren't.
+    #[inline]
+    pub fn try_from_sorted_array_in<const N: usize>(
+        values: [T; N],
+        store: &'store BTreeStore<T, (), B>,
+    ) -> Result<Self, Error>
+    where
+        T: Ord + Clone,
+    {
+        BTreeMap::try_from_sorted_array_in(values.map(|value| (value, ())), store).map(Self)
+    }
+
+    /// Creates a set in `store` from strictly increasing values, in O(n). See
+    /// [BTreeMap::from_sorted_iter_in].
+    ///
+    /// *Panics* if the values aren't strictly increasing.
+    #[track_caller]
+    #[inline]
+    pub fn from_sorted_iter_in(
+        values: impl IntoIterator<Item = T>,
+        store: &'store BTreeStore<T, (), B>,
+    ) -> Self
+    where
+        T: Ord + Clone,
+    {
+        Self::try_from_sorted_iter_in(values, store).or_panic("BTreeSet::from_sorted_iter_in")
+    }
+
+    /// Creates a set in `store` from strictly increasing values, in O(n), or returns
+    /// [Error::UnorderedKey] if they aren't. See [BTreeMap::try_from_sorted_iter_in].
+    #[inline]
+    pub fn try_from_sorted_iter_in(
+        values: impl IntoIterator<Item = T>,
+        store: &'store BTreeStore<T, (), B>,
+    ) -> Result<Self, Error>
+    where
+        T: Ord + Clone,
+    {
+        BTreeMap::try_from_sorted_iter_in(values.into_iter().map(|value| (value, ())), store)
+            .map(Self)
+    }
+
+    /// Creates a set in `store` from a [std::collections::BTreeSet]'s values. See
+    /// [BTreeMap::from_std_in].
+    #[inline]
+    pub fn from_std_in(
+        set: std::collections::BTreeSet<T>,
+        store: &'store BTreeStore<T, (), B>,
+    ) -> Self
+    where
+        T: Clone,
+    {
+        Self(BTreeMap::bulk_load_in(
+            store,
+            set.into_iter().map(|value| (value, ())),
+        ))
+    }
+
+    /// Clones the values into a [std::collections::BTreeSet].
+    #[inline]
+    pub fn to_std(&self) -> std::collections::BTreeSet<T>
+    where
+        T: Ord + Clone,
+    {
+        self.iter().cloned().collect()
+    }
+
     /// Returns the number of elements in the set.
     #[inline]
     pub fn len(&self) -> usize {
         self.0.len()
     }
 
-    /// Returns `true` if the set contains no elements.
+    /// Returns `true` if the set contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Reserves slots in the store for the nodes needed to insert `additional` more values. See
+    /// [BTreeMap::reserve].
+    #[inline]
+    pub fn reserve(&self, additional: usize) {
+        self.0.reserve(additional)
+    }
+
+    /// Like [BTreeSet::reserve], but returns an error instead of panicking if the store's slab
+    /// would be too large. See [BTreeMap::try_reserve].
+    #[inline]
+    pub fn try_reserve(&self, additional: usize) -> Result<(), Error> {
+        self.0.try_reserve(additional)
+    }
+
+    /// Like [Clone::clone], but returns an error (before allocating) if the store's backend may
+    /// not have enough free nodes for the copy. See [BTreeMap::try_clone].
+    #[inline]
+    pub fn try_clone(&self) -> Result<Self, Error>
+    where
+        T: Clone,
+    {
+        Ok(Self(self.0.try_clone()?))
+    }
+
+    /// Consumes the set in O(1), without deallocating its nodes or dropping its values. See
+    /// [BTreeMap::abandon].
+    #[inline]
+    pub fn abandon(self) {
+        self.0.abandon()
+    }
+
+    /// Moves the values into a new set in `store`, freeing this set's nodes. See
+    /// [BTreeMap::compact_in].
+    #[inline]
+    pub fn compact_in<'new_store>(
+        self,
+        store: &'new_store BTreeStore<T, (), B>,
+    ) -> BTreeSet<'new_store, T, B> {
+        BTreeSet(self.0.compact_in(store))
+    }
+
+    /// Clears the set, removing all values.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.0.clear()
+    }
+
+    /// The first value in the set, or `None` if empty.
+    #[inline]
+    pub fn first(&self) -> Option<&T> {
+        self.0.first_key_value().map(|(k, &())| k)
+    }
+
+    /// The last value in the set, or `None` if empty.
+    #[inline]
+    pub fn last(&self) -> Option<&T> {
+        self.0.last_key_value().map(|(k, &())| k)
+    }
+
+    /// Returns `true` if the set contains a value.
+    #[inline]
+    pub fn contains<U: Ord + ?Sized>(&self, value: &U) -> bool
+    where
+        T: Borrow<U>,
+    {
+        self.0.contains_key(value)
+    }
+
+    /// Returns a reference to the equivalent value in the set, if any.
+    ///
+    /// This is (only) useful when `U` is a different type than `T`.
+    #[inline]
+    pub fn get<U: Ord + ?Sized>(&self, value: &U) -> Option<&T>
+    where
+        T: Borrow<U>,
+    {
+        self.0.get_key(value)
+    }
+
+    /// Inserts a value into the set. Returns `true` if the value was not already present.
+    #[inline]
+    pub fn insert(&mut self, value: T) -> bool
+    where
+        T: Clone + Ord,
+    {
+        self.0.insert(value, ()).is_none()
+    }
+
+    /// Like [BTreeSet::insert], but returns an error (without inserting) if the store's backend
+    /// may not have enough free nodes. See [BTreeMap::try_insert].
+    #[inline]
+    pub fn try_insert(&mut self, value: T) -> Result<bool, Error>
+    where
+        T: Clone + Ord,
+    {
+        Ok(self.0.try_insert(value, ())?.is_none())
+    }
+
+    /// Like [Extend::extend], but stops at the first value which [BTreeSet::try_insert] fails to
+    /// insert, and returns its error. See [BTreeMap::try_extend].
+    #[inline]
+    pub fn try_extend(&mut self, iter: impl IntoIterator<Item = T>) -> Result<(), Error>
+    where
+        T: Clone + Ord,
+    {
+        self.0.try_extend(iter.into_iter().map(|value| (value, ())))
+    }
+
+    /// Returns the largest value less than `value`, which needn't be in the set.
+    #[inline]
+    pub fn next_before<U: Ord + ?Sized>(&self, value: &U) -> Option<&T>
+    where
+        T: Borrow<U>,
+    {
+        self.0.next_before(value).map(|(k, ())| k)
+    }
+
+    /// Returns the smallest value greater than `value`, which needn't be in the set.
+    #[inline]
+    pub fn next_after<U: Ord + ?Sized>(&self, value: &U) -> Option<&T>
+    where
+        T: Borrow<U>,
+    {
+        self.0.next_after(value).map(|(k, ())| k)
+    }
+
+    /// Returns the stored value equal to `value`, inserting `value` if it's not present.
+    #[inline]
+    pub fn get_or_insert(&mut self, value: T) -> &T
+    where
+        T: Clone + Ord,
+    {
+        self.0.get_or_insert_key_value(value, ()).0
+    }
+
+    /// Returns the stored value equivalent to `value`, inserting the one `f` makes from it if it's
+    /// not present. Only searches once.
+    ///
+    /// `f` must return a value equal to `value`, otherwise the set will be invalid (but not memory
+    /// unsafe).
+    #[inline]
+    pub fn get_or_insert_with<U: Ord + ?Sized>(&mut self, value: &U, f: impl FnOnce(&U) -> T) -> &T
+    where
+        T: Clone + Borrow<U>,
+    {
+        self.0
+            .get_or_insert_key_value_with(value, |value| (f(value), ()))
+            .0
+    }
+
+    /// Inserts a value into the set, and returns the index it now sits at along with `true` if it
+    /// was not already present.
+    #[cfg(feature = "order-statistic")]
+    #[inline]
+    pub fn insert_full(&mut self, value: T) -> (usize, bool)
+    where
+        T: Clone + Ord,
+    {
+        let (index, old) = self.0.insert_full(value, ());
+        (index, old.is_none())
+    }
+
+    /// Returns the index of the equivalent value (its # of smaller values), if present.
+    #[cfg(feature = "order-statistic")]
+    #[inline]
+    pub fn index_of<U: Ord + ?Sized>(&self, value: &U) -> Option<usize>
+    where
+        T: Borrow<U>,
+    {
+        self.0.index_of(value)
+    }
+
+    /// Returns the value at the given index (the value with `index` smaller values), if any.
+    #[cfg(feature = "order-statistic")]
+    #[inline]
+    pub fn get_index(&self, index: usize) -> Option<&T> {
+        self.0.get_index(index).map(|(k, ())| k)
+    }
+
+    /// Returns the value at the `q` quantile, where `0.0` is the first value and `1.0` is the last,
+    /// if the set isn't empty. See [BTreeMap::quantile].
+    ///
+    /// *Panics* if `q` isn't between `0.0` and `1.0`.
+    #[cfg(feature = "order-statistic")]
+    #[track_caller]
+    #[inline]
+    pub fn quantile(&self, q: f64) -> Option<&T> {
+        self.0.quantile(q).map(|(k, ())| k)
+    }
+
+    /// Returns the value at the `q` quantile if the set isn't empty, or an error if `q` isn't
+    /// between `0.0` and `1.0`.
+    #[cfg(feature = "order-statistic")]
+    #[inline]
+    pub fn try_quantile(&self, q: f64) -> Result<Option<&T>, Error> {
+        Ok(self.0.try_quantile(q)?.map(|(k, ())| k))
+    }
+
+    /// Returns the median value (the lower of the 2 middle values if the length is even), if the
+    /// set isn't empty.
+    #[cfg(feature = "order-statistic")]
+    #[inline]
+    pub fn median(&self) -> Option<&T> {
+        self.0.median().map(|(k, ())| k)
+    }
+
+    /// Removes a value from the set. Returns `true` if the value was present.
+    #[inline]
+    pub fn remove<U: Ord + ?Sized>(&mut self, value: &U) -> bool
+    where
+        T: Borrow<U> + Clone,
+    {
+        self.0.remove(value).is_some()
+    }
+
+    /// Removes a value from the set and returns the stored value, if present.
+    #[inline]
+    pub fn take<U: Ord + ?Sized>(&mut self, value: &U) -> Option<T>
+    where
+        T: Borrow<U> + Clone,
+    {
+        self.0.remove_entry(value).map(|(k, ())| k)
+    }
+
+    /// Inserts a value into the set, replacing an equal value if present and returning it.
+    #[inline]
+    pub fn replace(&mut self, value: T) -> Option<T>
+    where
+        T: Clone + Ord,
+    {
+        self.0.replace_entry(value, ()).map(|(k, ())| k)
+    }
+
+    /// Removes the first value from the set.
+    #[inline]
+    pub fn pop_first(&mut self) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.0.pop_first().map(|(k, ())| k)
+    }
+
+    /// Removes the last value from the set.
+    #[inline]
+    pub fn pop_last(&mut self) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.0.pop_last().map(|(k, ())| k)
+    }
+
+    /// Moves all values from `other` into the set, leaving `other` empty. See
+    /// [BTreeMap::append].
+    #[inline]
+    pub fn append(&mut self, other: &mut Self)
+    where
+        T: Clone + Ord,
+    {
+        self.0.append(&mut other.0)
+    }
+
+    /// Joins two sets in the same store, where every value in `self` is less than every value in
+    /// `other`, in O(log n). See [BTreeMap::concat].
+    ///
+    /// *Panics* if the sets are in different stores, or their values overlap or interleave.
+    #[track_caller]
+    #[inline]
+    pub fn concat(mut self, mut other: Self) -> Self
+    where
+        T: Clone + Ord,
+    {
+        self.try_concat(&mut other).or_panic("BTreeSet::concat");
+        self
+    }
+
+    /// Moves every value of `other` after the values of the set in O(log n), leaving `other`
+    /// empty, or returns an error (without changing either set). See [BTreeMap::try_concat].
+    #[inline]
+    pub fn try_concat(&mut self, other: &mut Self) -> Result<(), Error>
+    where
+        T: Clone + Ord,
+    {
+        self.0.try_concat(&mut other.0)
+    }
+
+    /// Removes the values within the range and returns how many there were, in O(log n) plus the
+    /// nodes freed. See [BTreeMap::remove_range].
+    ///
+    /// *Panics* if the range's start is after its end, or they are equal and both excluded.
+    #[track_caller]
+    #[inline]
+    pub fn remove_range<U: Ord + ?Sized>(&mut self, bounds: impl RangeBounds<U>) -> usize
+    where
+        T: Clone + Borrow<U> + Ord,
+    {
+        self.try_remove_range(bounds)
+            .or_panic("BTreeSet::remove_range")
+    }
+
+    /// Removes the values within the range and returns how many there were, or returns an error
+    /// if the range's start is after its end. See [BTreeMap::try_remove_range].
+    #[inline]
+    pub fn try_remove_range<U: Ord + ?Sized>(
+        &mut self,
+        bounds: impl RangeBounds<U>,
+    ) -> Result<usize, Error>
+    where
+        T: Clone + Borrow<U> + Ord,
+    {
+        self.0.try_remove_range(bounds)
+    }
+
+    /// Removes the values for which `f` returns `false`, in a single O(n) traversal.
+    #[inline]
+    pub fn retain(&mut self, mut f: impl FnMut(&T) -> bool)
+    where
+        T: Clone,
+    {
+        self.0.retain(|value, ()| f(value))
+    }
+
+    /// Splits the set into the values for which `pred` returns `true` and the rest, both in the
+    /// same store, in a single O(n) traversal.
+    #[inline]
+    pub fn partition(self, mut pred: impl FnMut(&T) -> bool) -> (Self, Self)
+    where
+        T: Clone,
+    {
+        let (matching, rest) = self.0.partition(|value, ()| pred(value));
+        (Self(matching), Self(rest))
+    }
+
+    /// Splits the set in two at the value: the set keeps the values less than `value`, and the
+    /// returned set (in the same store) has the rest. See [BTreeMap::split_off].
+    #[inline]
+    pub fn split_off<U: Ord + ?Sized>(&mut self, value: &U) -> Self
+    where
+        T: Clone + Borrow<U>,
+    {
+        Self(self.0.split_off(value))
+    }
+
+    /// Splits the set along the pivots into `pivots.len() + 1` sets in the same store, in order.
+    /// See [BTreeMap::split_by_pivots].
+    #[inline]
+    pub fn split_by_pivots<U: Ord>(self, pivots: &[U]) -> Vec<Self>
+    where
+        T: Clone + Borrow<U>,
+    {
+        self.0
+            .split_by_pivots(pivots)
+            .into_iter()
+            .map(Self)
+            .collect()
+    }
+
+    /// Validates the set, *panic*ing if it is invalid. Specifically, we check that the number of
+    /// entries in each node is within the b-tree invariant bounds, and that the elements are in
+    /// order.
+    ///
+    /// Ideally, this should always be a no-op.
+    #[inline]
+    pub fn validate(&self)
+    where
+        T: Debug + Ord,
+    {
+        self.0.validate()
+    }
+
+    /// Prints the b-tree in ascii to `f`, which can be a [Formatter], a [String], or any other
+    /// [std::fmt::Write].
+    #[inline]
+    pub fn print(&self, f: &mut (impl std::fmt::Write + ?Sized)) -> std::fmt::Result
+    where
+        T: Debug,
+    {
+        self.0.print(f)
+    }
+
+    /// Prints the b-tree in ascii to an [std::io::Write], e.g. a file or stderr.
+    #[inline]
+    pub fn print_to(&self, w: impl std::io::Write) -> std::io::Result<()>
+    where
+        T: Debug,
+    {
+        self.0.print_to(w)
+    }
+
+    /// Prints the b-tree in ascii to a new string.
+    #[inline]
+    pub fn print_to_string(&self) -> String
+    where
+        T: Debug,
+    {
+        self.0.print_to_string()
+    }
+
+    /// Returns an iterator over the set.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, T, B> {
+        Iter(self.0.iter())
+    }
+
+    /// Returns an iterator over the set, starting at the first value `>= value`. See
+    /// [BTreeMap::iter_from].
+    #[inline]
+    pub fn iter_from<U: Ord + ?Sized>(&self, value: &U) -> Iter<'_, T, B>
+    where
+        T: Borrow<U>,
+    {
+        Iter(self.0.iter_from(value))
+    }
+
+    /// Returns an iterator over the set, ending at the last value `<= value`. See
+    /// [BTreeMap::iter_from_back].
+    #[inline]
+    pub fn iter_from_back<U: Ord + ?Sized>(&self, value: &U) -> Iter<'_, T, B>
+    where
+        T: Borrow<U>,
+    {
+        Iter(self.0.iter_from_back(value))
+    }
+
+    /// Returns an iterator over the `k` largest values (or every value if there are fewer), largest
+    /// first. See [BTreeMap::top_k].
+    #[inline]
+    pub fn top_k(&self, k: usize) -> Rev<Iter<'_, T, B>> {
+        Iter(self.0.top_k_ascending(k)).rev()
+    }
+
+    /// Returns an iterator over the `k` smallest values (or every value if there are fewer),
+    /// smallest first. See [BTreeMap::bottom_k].
+    #[inline]
+    pub fn bottom_k(&self, k: usize) -> Iter<'_, T, B> {
+        Iter(self.0.bottom_k(k))
+    }
+
+    /// Returns an iterator over the (up to) `k` largest values within the given bounds, largest
+    /// first.
+    ///
+    /// *Panics* if the range's start is after its end, or they are equal and both excluded.
+    #[track_caller]
+    #[inline]
+    pub fn top_k_in<U: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<U>,
+        k: usize,
+    ) -> Rev<Range<'_, T, B>>
+    where
+        T: Borrow<U>,
+    {
+        self.try_top_k_in(bounds, k).or_panic("BTreeSet::top_k_in")
+    }
+
+    /// Returns an iterator over the (up to) `k` largest values within the given bounds, largest
+    /// first, or an error if the range's start is after its end
+    #[inline]
+    pub fn try_top_k_in<U: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<U>,
+        k: usize,
+    ) -> Result<Rev<Range<'_, T, B>>, Error>
+    where
+        T: Borrow<U>,
+    {
+        Ok(Range(self.0.try_top_k_in_ascending(bounds, k)?).rev())
+    }
+
+    /// Returns an iterator over the (up to) `k` smallest values within the given bounds, smallest
+    /// first.
+    ///
+    /// *Panics* if the range's start is after its end, or they are equal and both excluded.
+    #[track_caller]
+    #[inline]
+    pub fn bottom_k_in<U: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<U>,
+        k: usize,
+    ) -> Range<'_, T, B>
+    where
+        T: Borrow<U>,
+    {
+        Range(self.0.bottom_k_in(bounds, k))
+    }
+
+    /// Returns an iterator over the (up to) `k` smallest values within the given bounds, smallest
+    /// first, or an error if the range's start is after its end
+    #[inline]
+    pub fn try_bottom_k_in<U: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<U>,
+        k: usize,
+    ) -> Result<Range<'_, T, B>, Error>
+    where
+        T: Borrow<U>,
+    {
+        self.0.try_bottom_k_in(bounds, k).map(Range)
+    }
+
+    /// Returns an iterator over the set within the given bounds
+    ///
+    /// *Panics* if the range's start is after its end, or they are equal and both excluded.
+    #[track_caller]
+    #[inline]
+    pub fn range<U: Ord + ?Sized>(&self, bounds: impl RangeBounds<U>) -> Range<T, B>
+    where
+        T: Borrow<U>,
+    {
+        Range(self.0.range(bounds))
+    }
+
+    /// Returns an iterator over the set within the given bounds, or an error if the range's start is
+    /// after its end
+    #[inline]
+    pub fn try_range<U: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<U>,
+    ) -> Result<Range<'_, T, B>, Error>
+    where
+        T: Borrow<U>,
+    {
+        self.0.try_range(bounds).map(Range)
+    }
+
+    /// Returns an iterator over every `step`-th value within the given bounds, in `O(log n)` per
+    /// value. See [BTreeMap::range_step].
+    ///
+    /// *Panics* if the range's start is after its end, or if `step` is 0.
+    #[cfg(feature = "order-statistic")]
+    #[track_caller]
+    #[inline]
+    pub fn range_step<U: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<U>,
+        step: usize,
+    ) -> RangeStep<'_, T, B>
+    where
+        T: Borrow<U>,
+    {
+        RangeStep(self.0.range_step(bounds, step))
+    }
+
+    /// Returns an iterator over every `step`-th value within the given bounds, or an error if the
+    /// range's start is after its end or `step` is 0
+    #[cfg(feature = "order-statistic")]
+    #[inline]
+    pub fn try_range_step<U: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<U>,
+        step: usize,
+    ) -> Result<RangeStep<'_, T, B>, Error>
+    where
+        T: Borrow<U>,
+    {
+        self.0.try_range_step(bounds, step).map(RangeStep)
+    }
+
+    /// Returns an iterator over the set's values which start with `prefix`. See
+    /// [prefix_range](crate::prefix_range).
+    #[inline]
+    pub fn iter_prefix<P: Prefix + ?Sized>(&self, prefix: &P) -> Range<'_, T, B>
+    where
+        T: Borrow<P>,
+    {
+        Range(self.0.iter_prefix(prefix))
+    }
+
+    /// Iterates over the values in either set (once if in both) in order, lazily merging both
+    /// sets' iterators. The sets may be in different stores.
+    #[inline]
+    pub fn union<'a>(&'a self, other: &'a BTreeSet<'_, T, B>) -> Union<'a, T, B>
+    where
+        T: Ord,
+    {
+        Union(self.iter(), other.iter())
+    }
+
+    /// Iterates over the values in both sets in order, lazily merging both sets' iterators. The
+    /// sets may be in different stores.
+    #[inline]
+    pub fn intersection<'a>(&'a self, other: &'a BTreeSet<'_, T, B>) -> Intersection<'a, T, B>
+    where
+        T: Ord,
+    {
+        Intersection(self.iter(), other.iter())
+    }
+
+    /// Iterates over the values in this set but not `other` in order, lazily merging both sets'
+    /// iterators. The sets may be in different stores.
+    #[inline]
+    pub fn difference<'a>(&'a self, other: &'a BTreeSet<'_, T, B>) -> Difference<'a, T, B>
+    where
+        T: Ord,
+    {
+        Difference(self.iter(), other.iter())
+    }
+
+    /// Iterates over the values in exactly one of the sets in order, lazily merging both sets'
+    /// iterators. The sets may be in different stores.
+    #[inline]
+    pub fn symmetric_difference<'a>(
+        &'a self,
+        other: &'a BTreeSet<'_, T, B>,
+    ) -> SymmetricDifference<'a, T, B>
+    where
+        T: Ord,
+    {
+        SymmetricDifference(self.iter(), other.iter())
+    }
+
+    /// Builds a set in `store` of the values in either set. The sets may be in different stores. See
+    /// [BTreeSet::union].
+    #[inline]
+    pub fn union_in<'new>(
+        &self,
+        other: &BTreeSet<'_, T, B>,
+        store: &'new BTreeStore<T, (), B>,
+    ) -> BTreeSet<'new, T, B>
+    where
+        T: Ord + Clone,
+    {
+        let values = self.union(other).map(|value| (value.clone(), ()));
+        BTreeSet(BTreeMap::bulk_load_in(store, values))
+    }
+
+    /// Builds a set in `store` of the values in both sets. The sets may be in different stores. See
+    /// [BTreeSet::intersection].
+    #[inline]
+    pub fn intersection_in<'new>(
+        &self,
+        other: &BTreeSet<'_, T, B>,
+        store: &'new BTreeStore<T, (), B>,
+    ) -> BTreeSet<'new, T, B>
+    where
+        T: Ord + Clone,
+    {
+        let values = self.intersection(other).map(|value| (value.clone(), ()));
+        BTreeSet(BTreeMap::bulk_load_in(store, values))
+    }
+
+    /// Builds a set in `store` of the values in this set but not `other`. The sets may be in different stores. See
+    /// [BTreeSet::difference].
+    #[inline]
+    pub fn difference_in<'new>(
+        &self,
+        other: &BTreeSet<'_, T, B>,
+        store: &'new BTreeStore<T, (), B>,
+    ) -> BTreeSet<'new, T, B>
+    where
+        T: Ord + Clone,
+    {
+        let values = self.difference(other).map(|value| (value.clone(), ()));
+        BTreeSet(BTreeMap::bulk_load_in(store, values))
+    }
+
+    /// Builds a set in `store` of the values in exactly one of the sets. The sets may be in different stores. See
+    /// [BTreeSet::symmetric_difference].
+    #[inline]
+    pub fn symmetric_difference_in<'new>(
+        &self,
+        other: &BTreeSet<'_, T, B>,
+        store: &'new BTreeStore<T, (), B>,
+    ) -> BTreeSet<'new, T, B>
+    where
+        T: Ord + Clone,
+    {
+        let values = self
+            .symmetric_difference(other)
+            .map(|value| (value.clone(), ()));
+        BTreeSet(BTreeMap::bulk_load_in(store, values))
+    }
+
+    /// Whether the set's values equal the iterator's, in order. See [BTreeMap::eq_iter].
+    pub fn eq_iter<U: Borrow<T>>(&self, other: impl IntoIterator<Item = U>) -> bool
+    where
+        T: PartialEq,
+    {
+        let mut other = other.into_iter();
+        self.iter()
+            .all(|value| other.next().is_some_and(|u| value == u.borrow()))
+            && other.next().is_none()
+    }
+
+    /// Compares the set's values with the iterator's lexicographically, like [Ord] does between
+    /// sets. See [BTreeMap::eq_iter].
+    pub fn cmp_with<U: Borrow<T>>(&self, other: impl IntoIterator<Item = U>) -> Ordering
+    where
+        T: Ord,
+    {
+        self.0.cmp_with(other.into_iter().map(|u| (u, ())))
+    }
+
+    /// Iterates over summaries of the set's nodes (*not* elements) in pre-order, e.g. to analyze or
+    /// serialize the tree's structure.
+    #[inline]
+    pub fn nodes(&self) -> Nodes<'_, T, B> {
+        Nodes(self.0.nodes())
+    }
+
+    /// Estimated bytes used by the set. See [BTreeMap::memory_usage].
+    #[inline]
+    pub fn memory_usage(&self) -> usize {
+        self.0.memory_usage()
+    }
+
+    /// Replaces the set's values with clones of `other`'s, reusing the set's nodes. See
+    /// [BTreeMap::copy_from].
+    #[inline]
+    pub fn copy_from(&mut self, other: &BTreeSet<T, B>)
+    where
+        T: Clone,
+    {
+        self.0.copy_from(&other.0)
+    }
+}
+
+// region common trait impls
+/// Compares contents, by looking up each of the set's values in the hash set.
+impl<'store, T: Eq + Hash, S: BuildHasher, const B: usize> PartialEq<HashSet<T, S>>
+    for BTreeSet<'store, T, B>
+{
+    fn eq(&self, other: &HashSet<T, S>) -> bool {
+        self.len() == other.len() && self.iter().all(|value| other.contains(value))
+    }
+}
+
+impl<'store, T: Eq + Hash, S: BuildHasher, const B: usize> PartialEq<BTreeSet<'store, T, B>>
+    for HashSet<T, S>
+{
+    #[inline]
+    fn eq(&self, other: &BTreeSet<'store, T, B>) -> bool {
+        other == self
+    }
+}
+
+impl<'store, T: PartialEq, const B: usize> PartialEq<std::collections::BTreeSet<T>>
+    for BTreeSet<'store, T, B>
+{
+    #[inline]
+    fn eq(&self, other: &std::collections::BTreeSet<T>) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<'store, T: PartialEq, const B: usize> PartialEq<BTreeSet<'store, T, B>>
+    for std::collections::BTreeSet<T>
+{
+    #[inline]
+    fn eq(&self, other: &BTreeSet<'store, T, B>) -> bool {
+        other == self
+    }
+}
+
+impl<'store, T: PartialOrd, const B: usize> PartialOrd<std::collections::BTreeSet<T>>
+    for BTreeSet<'store, T, B>
+{
     #[inline]
-    pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+    fn partial_cmp(&self, other: &std::collections::BTreeSet<T>) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
     }
+}
 
-    /// Clears the set, removing all values.
+impl<'store, T: PartialOrd, const B: usize> PartialOrd<BTreeSet<'store, T, B>>
+    for std::collections::BTreeSet<T>
+{
     #[inline]
-    pub fn clear(&mut self) {
-        self.0.clear()
+    fn partial_cmp(&self, other: &BTreeSet<'store, T, B>) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
     }
+}
 
-    /// The first value in the set, or `None` if empty.
+impl<'store, T: Debug, const B: usize> Debug for BTreeSet<'store, T, B> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.print(f)
+    }
+}
+
+impl<'store, T: Clone, const B: usize> Clone for BTreeSet<'store, T, B> {
     #[inline]
-    pub fn first(&self) -> Option<&T> {
-        self.0.first_key_value().map(|(k, &())| k)
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
     }
 
-    /// The last value in the set, or `None` if empty.
     #[inline]
-    pub fn last(&self) -> Option<&T> {
-        self.0.last_key_value().map(|(k, &())| k)
+    fn clone_from(&mut self, source: &Self) {
+        self.0.clone_from(&source.0)
     }
+}
+
+/// Builds the [union](BTreeSet::union_in) in the left set's store.
+impl<'store, T: Ord + Clone, const B: usize> BitOr<&BTreeSet<'_, T, B>>
+    for &BTreeSet<'store, T, B>
+{
+    type Output = BTreeSet<'store, T, B>;
 
-    /// Returns `true` if the set contains a value.
     #[inline]
-    pub fn contains<U: Ord + ?Sized>(&self, value: &U) -> bool
-    where
-        T: Borrow<U>,
-    {
-        self.0.contains_key(value)
+    fn bitor(self, rhs: &BTreeSet<'_, T, B>) -> Self::Output {
+        self.union_in(rhs, self.0.store())
     }
+}
+
+/// Builds the [intersection](BTreeSet::intersection_in) in the left set's store.
+impl<'store, T: Ord + Clone, const B: usize> BitAnd<&BTreeSet<'_, T, B>>
+    for &BTreeSet<'store, T, B>
+{
+    type Output = BTreeSet<'store, T, B>;
 
-    /// Returns a reference to the equivalent value in the set, if any.
-    ///
-    /// This is (only) useful when `U` is a different type than `T`.
     #[inline]
-    pub fn get<U: Ord + ?Sized>(&self, value: &U) -> Option<&T>
-    where
-        T: Borrow<U>,
-    {
-        self.0.get_key(value)
+    fn bitand(self, rhs: &BTreeSet<'_, T, B>) -> Self::Output {
+        self.intersection_in(rhs, self.0.store())
     }
+}
+
+/// Builds the [difference](BTreeSet::difference_in) in the left set's store.
+impl<'store, T: Ord + Clone, const B: usize> Sub<&BTreeSet<'_, T, B>> for &BTreeSet<'store, T, B> {
+    type Output = BTreeSet<'store, T, B>;
 
-    /// Inserts a value into the set. Returns `true` if the value was not already present.
     #[inline]
-    pub fn insert(&mut self, value: T) -> bool
-    where
-        T: Clone + Ord,
-    {
-        self.0.insert(value, ()).is_none()
+    fn sub(self, rhs: &BTreeSet<'_, T, B>) -> Self::Output {
+        self.difference_in(rhs, self.0.store())
     }
+}
+
+/// Builds the [symmetric_difference](BTreeSet::symmetric_difference_in) in the left set's store.
+impl<'store, T: Ord + Clone, const B: usize> BitXor<&BTreeSet<'_, T, B>>
+    for &BTreeSet<'store, T, B>
+{
+    type Output = BTreeSet<'store, T, B>;
 
-    /// Removes a value from the set. Returns `true` if the value was present.
     #[inline]
-    pub fn remove<U: Ord + ?Sized>(&mut self, value: &U) -> bool
-    where
-        T: Borrow<U> + Clone,
-    {
-        self.0.remove(value).is_some()
+    fn bitxor(self, rhs: &BTreeSet<'_, T, B>) -> Self::Output {
+        self.symmetric_difference_in(rhs, self.0.store())
     }
+}
 
-    /// Removes the first value from the set.
+impl<'store, T: Ord + Clone, const B: usize> Extend<T> for BTreeSet<'store, T, B> {
     #[inline]
-    pub fn pop_first(&mut self) -> Option<T>
-    where
-        T: Clone,
-    {
-        self.0.pop_first().map(|(k, ())| k)
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.0.extend(iter.into_iter().map(|v| (v, ())))
     }
+}
 
-    /// Removes the last value from the set.
+#[cfg(feature = "thread-local-store")]
+impl<T: 'static> BTreeSet<'static, T> {
+    /// Creates an empty set in this thread's [default store](BTreeStore::thread_local).
     #[inline]
-    pub fn pop_last(&mut self) -> Option<T>
-    where
-        T: Clone,
-    {
-        self.0.pop_last().map(|(k, ())| k)
+    pub fn new() -> Self {
+        Self(BTreeMap::new())
     }
+}
 
-    /// Validates the set, *panic*ing if it is invalid. Specifically, we check that the number of
-    /// entries in each node is within the b-tree invariant bounds, and that the elements are in
-    /// order.
-    ///
-    /// Ideally, this should always be a no-op.
+/// Creates an empty set in this thread's [default store](BTreeStore::thread_local).
+#[cfg(feature = "thread-local-store")]
+impl<T: 'static> Default for BTreeSet<'static, T> {
     #[inline]
-    pub fn validate(&self)
-    where
-        T: Debug + Ord,
-    {
-        self.0.validate()
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    /// Prints the b-tree in ascii
+/// Builds a set in this thread's [default store](BTreeStore::thread_local). See
+/// [BTreeSet::from_iter_in].
+#[cfg(feature = "thread-local-store")]
+impl<T: Ord + Clone + 'static> FromIterator<T> for BTreeSet<'static, T> {
     #[inline]
-    pub fn print(&self, f: &mut Formatter<'_>) -> std::fmt::Result
-    where
-        T: Debug,
-    {
-        self.0.print(f)
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from_iter_in(iter, BTreeStore::thread_local())
     }
+}
 
-    /// Returns an iterator over the set.
+#[cfg(feature = "rayon")]
+impl<'store, T, const B: usize> BTreeSet<'store, T, B> {
+    /// Iterates over the set in parallel. See [BTreeMap::par_iter].
     #[inline]
-    pub fn iter(&self) -> Iter<'_, T> {
-        Iter(self.0.iter())
+    pub fn par_iter(&self) -> ParIter<'_, T, B>
+    where
+        T: Sync,
+    {
+        ParIter(self.0.par_iter())
     }
 
-    /// Returns an iterator over the set within the given bounds
+    /// Creates a set in `store` from the values, in parallel. See [BTreeMap::par_from_iter_in].
     #[inline]
-    pub fn range<U: Ord + ?Sized>(&self, bounds: impl RangeBounds<U>) -> Range<T>
+    pub fn par_from_iter_in(
+        values: impl IntoParallelIterator<Item = T>,
+        store: &'store BTreeStore<T, (), B>,
+    ) -> Self
     where
-        T: Borrow<U>,
+        T: Ord + Clone + Send,
     {
-        Range(self.0.range(bounds))
+        Self(BTreeMap::par_from_iter_in(
+            values.into_par_iter().map(|value| (value, ())),
+            store,
+        ))
     }
 }
 
-// region common trait impls
-impl<'store, T: Debug> Debug for BTreeSet<'store, T> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        self.print(f)
+#[cfg(feature = "rayon")]
+impl<'store, T: Ord + Clone + Send, const B: usize> ParallelExtend<T> for BTreeSet<'store, T, B> {
+    #[inline]
+    fn par_extend<I: IntoParallelIterator<Item = T>>(&mut self, par_iter: I) {
+        self.0
+            .par_extend(par_iter.into_par_iter().map(|value| (value, ())))
     }
 }
 
-impl<'store, T: Ord + Clone> Extend<T> for BTreeSet<'store, T> {
+#[cfg(feature = "rayon")]
+impl<'a, 'store, T: Ord + Copy + Send + Sync, const B: usize> ParallelExtend<&'a T>
+    for BTreeSet<'store, T, B>
+{
     #[inline]
-    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
-        self.0.extend(iter.into_iter().map(|v| (v, ())))
+    fn par_extend<I: IntoParallelIterator<Item = &'a T>>(&mut self, par_iter: I) {
+        self.par_extend(par_iter.into_par_iter().copied())
     }
 }
 // endregion
 
 // region iterators
 // region impl
-impl<'store, T> IntoIterator for BTreeSet<'store, T> {
+impl<'store, T, const B: usize> IntoIterator for BTreeSet<'store, T, B> {
     type Item = T;
-    type IntoIter = IntoIter<'store, T>;
+    type IntoIter = IntoIter<'store, T, B>;
 
     #[inline]
     fn into_iter(self) -> Self::IntoIter {
@@ -171,9 +1083,9 @@ impl<'store, T> IntoIterator for BTreeSet<'store, T> {
 }
 
 //noinspection DuplicatedCode
-impl<'a, 'store: 'a, T> IntoIterator for &'a BTreeSet<'store, T> {
+impl<'a, 'store: 'a, T, const B: usize> IntoIterator for &'a BTreeSet<'store, T, B> {
     type Item = &'a T;
-    type IntoIter = Iter<'a, T>;
+    type IntoIter = Iter<'a, T, B>;
 
     #[inline]
     fn into_iter(self) -> Self::IntoIter {
@@ -183,9 +1095,23 @@ impl<'a, 'store: 'a, T> IntoIterator for &'a BTreeSet<'store, T> {
 // endregion
 
 // region Iter
-pub struct Iter<'a, T>(crate::map::Iter<'a, T, ()>);
+pub struct Iter<'a, T, const B: usize = 8>(crate::map::Iter<'a, T, (), B>);
+
+impl<'a, T, const B: usize> Iter<'a, T, B> {
+    /// Get the next element without advancing the iterator
+    #[inline]
+    pub fn peek(&self) -> Option<&'a T> {
+        self.0.peek().map(|(k, &())| k)
+    }
 
-impl<'a, T> Iterator for Iter<'a, T> {
+    /// Get the next back element without advancing the back iterator
+    #[inline]
+    pub fn peek_back(&self) -> Option<&'a T> {
+        self.0.peek_back().map(|(k, &())| k)
+    }
+}
+
+impl<'a, T, const B: usize> Iterator for Iter<'a, T, B> {
     type Item = &'a T;
 
     #[inline]
@@ -199,27 +1125,65 @@ impl<'a, T> Iterator for Iter<'a, T> {
     }
 }
 
-impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+impl<'a, T, const B: usize> DoubleEndedIterator for Iter<'a, T, B> {
     #[inline]
     fn next_back(&mut self) -> Option<Self::Item> {
         self.0.next_back().map(|(k, &())| k)
     }
 }
 
-impl<'a, T> ExactSizeIterator for Iter<'a, T> {
+impl<'a, T, const B: usize> ExactSizeIterator for Iter<'a, T, B> {
     #[inline]
     fn len(&self) -> usize {
         self.0.len()
     }
 }
 
-impl<'a, T> FusedIterator for Iter<'a, T> {}
+impl<'a, T, const B: usize> FusedIterator for Iter<'a, T, B> {}
+
+impl<'a, T, const B: usize> Clone for Iter<'a, T, B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+// SAFETY: `size_hint` is exact
+#[cfg(feature = "nightly")]
+unsafe impl<'a, T, const B: usize> TrustedLen for Iter<'a, T, B> {}
+// endregion
+
+// region ParIter
+/// Parallel iterator over a [BTreeSet], from [BTreeSet::par_iter]
+#[cfg(feature = "rayon")]
+pub struct ParIter<'a, T, const B: usize = 8>(crate::map::ParIter<'a, T, (), B>);
+
+#[cfg(feature = "rayon")]
+impl<'a, T: Sync, const B: usize> ParallelIterator for ParIter<'a, T, B> {
+    type Item = &'a T;
+
+    #[inline]
+    fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+        self.0.map(|(k, &())| k).drive_unindexed(consumer)
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<'a, 'store: 'a, T: Sync, const B: usize> IntoParallelIterator for &'a BTreeSet<'store, T, B> {
+    type Iter = ParIter<'a, T, B>;
+    type Item = &'a T;
+
+    #[inline]
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter()
+    }
+}
 // endregion
 
 // region IntoIter
-pub struct IntoIter<'store, T>(crate::map::IntoIter<'store, T, ()>);
+pub struct IntoIter<'store, T, const B: usize = 8>(crate::map::IntoIter<'store, T, (), B>);
 
-impl<'store, T> Iterator for IntoIter<'store, T> {
+impl<'store, T, const B: usize> Iterator for IntoIter<'store, T, B> {
     type Item = T;
 
     #[inline]
@@ -233,27 +1197,79 @@ impl<'store, T> Iterator for IntoIter<'store, T> {
     }
 }
 
-impl<'store, T> DoubleEndedIterator for IntoIter<'store, T> {
+impl<'store, T, const B: usize> DoubleEndedIterator for IntoIter<'store, T, B> {
     #[inline]
     fn next_back(&mut self) -> Option<Self::Item> {
         self.0.next_back().map(|(k, ())| k)
     }
 }
 
-impl<'store, T> ExactSizeIterator for IntoIter<'store, T> {
+impl<'store, T, const B: usize> ExactSizeIterator for IntoIter<'store, T, B> {
     #[inline]
     fn len(&self) -> usize {
         self.0.len()
     }
 }
 
-impl<'store, T> FusedIterator for IntoIter<'store, T> {}
+impl<'store, T, const B: usize> FusedIterator for IntoIter<'store, T, B> {}
+
+// SAFETY: `size_hint` is exact
+#[cfg(feature = "nightly")]
+unsafe impl<'store, T, const B: usize> TrustedLen for IntoIter<'store, T, B> {}
 // endregion
 
 // region Range
-pub struct Range<'a, T>(crate::map::Range<'a, T, ()>);
+pub struct Range<'a, T, const B: usize = 8>(crate::map::Range<'a, T, (), B>);
 
-impl<'a, T> Iterator for Range<'a, T> {
+impl<'a, T, const B: usize> Range<'a, T, B> {
+    /// Get the next element without advancing the iterator
+    #[inline]
+    pub fn peek(&self) -> Option<&'a T> {
+        self.0.peek().map(|(k, &())| k)
+    }
+
+    /// Get the next back element without advancing the back iterator
+    #[inline]
+    pub fn peek_back(&self) -> Option<&'a T> {
+        self.0.peek_back().map(|(k, &())| k)
+    }
+}
+
+impl<'a, T, const B: usize> Iterator for Range<'a, T, B> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, &())| k)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, T, const B: usize> DoubleEndedIterator for Range<'a, T, B> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(k, &())| k)
+    }
+}
+
+impl<'a, T, const B: usize> Clone for Range<'a, T, B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+// endregion
+
+// region RangeStep
+#[cfg(feature = "order-statistic")]
+pub struct RangeStep<'a, T, const B: usize = 8>(crate::map::RangeStep<'a, T, (), B>);
+
+#[cfg(feature = "order-statistic")]
+impl<'a, T, const B: usize> Iterator for RangeStep<'a, T, B> {
     type Item = &'a T;
 
     #[inline]
@@ -267,24 +1283,237 @@ impl<'a, T> Iterator for Range<'a, T> {
     }
 }
 
-impl<'a, T> DoubleEndedIterator for Range<'a, T> {
+#[cfg(feature = "order-statistic")]
+impl<'a, T, const B: usize> DoubleEndedIterator for RangeStep<'a, T, B> {
     #[inline]
     fn next_back(&mut self) -> Option<Self::Item> {
         self.0.next_back().map(|(k, &())| k)
     }
 }
+
+#[cfg(feature = "order-statistic")]
+impl<'a, T, const B: usize> ExactSizeIterator for RangeStep<'a, T, B> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+#[cfg(feature = "order-statistic")]
+impl<'a, T, const B: usize> FusedIterator for RangeStep<'a, T, B> {}
+
+#[cfg(feature = "order-statistic")]
+impl<'a, T, const B: usize> Clone for RangeStep<'a, T, B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+// SAFETY: `size_hint` is exact
+#[cfg(all(feature = "order-statistic", feature = "nightly"))]
+unsafe impl<'a, T, const B: usize> TrustedLen for RangeStep<'a, T, B> {}
+// endregion
+
+// region Union
+/// Iterator returned by [BTreeSet::union]
+pub struct Union<'a, T, const B: usize = 8>(Iter<'a, T, B>, Iter<'a, T, B>);
+
+impl<'a, T: Ord, const B: usize> Iterator for Union<'a, T, B> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match (self.0.peek(), self.1.peek()) {
+            (Some(a), Some(b)) => match a.cmp(b) {
+                Ordering::Less => self.0.next(),
+                Ordering::Greater => self.1.next(),
+                Ordering::Equal => {
+                    self.1.next();
+                    self.0.next()
+                }
+            },
+            (Some(_), None) => self.0.next(),
+            (None, _) => self.1.next(),
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (a, b) = (self.0.len(), self.1.len());
+        (a.max(b), a.checked_add(b))
+    }
+}
+
+impl<'a, T: Ord, const B: usize> FusedIterator for Union<'a, T, B> {}
+
+impl<'a, T, const B: usize> Clone for Union<'a, T, B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), self.1.clone())
+    }
+}
+// endregion
+
+// region Intersection
+/// Iterator returned by [BTreeSet::intersection]
+pub struct Intersection<'a, T, const B: usize = 8>(Iter<'a, T, B>, Iter<'a, T, B>);
+
+impl<'a, T: Ord, const B: usize> Iterator for Intersection<'a, T, B> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.0.peek()?.cmp(self.1.peek()?) {
+                Ordering::Less => {
+                    self.0.next();
+                }
+                Ordering::Greater => {
+                    self.1.next();
+                }
+                Ordering::Equal => {
+                    self.1.next();
+                    return self.0.next();
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (a, b) = (self.0.len(), self.1.len());
+        (0, Some(a.min(b)))
+    }
+}
+
+impl<'a, T: Ord, const B: usize> FusedIterator for Intersection<'a, T, B> {}
+
+impl<'a, T, const B: usize> Clone for Intersection<'a, T, B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), self.1.clone())
+    }
+}
+// endregion
+
+// region Difference
+/// Iterator returned by [BTreeSet::difference]
+pub struct Difference<'a, T, const B: usize = 8>(Iter<'a, T, B>, Iter<'a, T, B>);
+
+impl<'a, T: Ord, const B: usize> Iterator for Difference<'a, T, B> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some(b) = self.1.peek() else {
+                return self.0.next();
+            };
+            match self.0.peek()?.cmp(b) {
+                Ordering::Less => return self.0.next(),
+                Ordering::Greater => {
+                    self.1.next();
+                }
+                Ordering::Equal => {
+                    self.0.next();
+                    self.1.next();
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (a, b) = (self.0.len(), self.1.len());
+        (a.saturating_sub(b), Some(a))
+    }
+}
+
+impl<'a, T: Ord, const B: usize> FusedIterator for Difference<'a, T, B> {}
+
+impl<'a, T, const B: usize> Clone for Difference<'a, T, B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), self.1.clone())
+    }
+}
+// endregion
+
+// region SymmetricDifference
+/// Iterator returned by [BTreeSet::symmetric_difference]
+pub struct SymmetricDifference<'a, T, const B: usize = 8>(Iter<'a, T, B>, Iter<'a, T, B>);
+
+impl<'a, T: Ord, const B: usize> Iterator for SymmetricDifference<'a, T, B> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match (self.0.peek(), self.1.peek()) {
+                (Some(a), Some(b)) => match a.cmp(b) {
+                    Ordering::Less => return self.0.next(),
+                    Ordering::Greater => return self.1.next(),
+                    Ordering::Equal => {
+                        self.0.next();
+                        self.1.next();
+                    }
+                },
+                (Some(_), None) => return self.0.next(),
+                (None, _) => return self.1.next(),
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (a, b) = (self.0.len(), self.1.len());
+        (0, a.checked_add(b))
+    }
+}
+
+impl<'a, T: Ord, const B: usize> FusedIterator for SymmetricDifference<'a, T, B> {}
+
+impl<'a, T, const B: usize> Clone for SymmetricDifference<'a, T, B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), self.1.clone())
+    }
+}
+// endregion
+
+// region Nodes
+/// Iterator over summaries of a set's nodes, returned by [BTreeSet::nodes]
+pub struct Nodes<'a, T, const B: usize = 8>(crate::map::Nodes<'a, T, (), B>);
+
+impl<'a, T, const B: usize> Iterator for Nodes<'a, T, B> {
+    type Item = NodeSummary<'a, T>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+impl<'a, T, const B: usize> FusedIterator for Nodes<'a, T, B> {}
 // endregion
 // endregion
 
-#[cfg(feature = "copyable")]
-impl<'store, T> crate::copyable::sealed::BTree<'store, T, ()> for BTreeSet<'store, T> {
+impl<'store, T, const B: usize> crate::tree::sealed::BTree<'store, T, (), B>
+    for BTreeSet<'store, T, B>
+{
+    #[inline]
+    fn is_from_store(&self, store: &BTreeStore<T, (), B>) -> bool {
+        self.0.is_from_store(store)
+    }
+
     #[inline]
-    fn assert_store(&self, store: &BTreeStore<T, ()>) {
-        self.0.assert_store(store)
+    fn node_ptrs(&self) -> crate::node_iter::NodeIter<'store, T, (), B> {
+        self.0.node_ptrs()
     }
 
     #[inline]
-    fn nodes(&self) -> crate::copyable::sealed::NodeIter<'store, T, ()> {
-        self.0.nodes()
+    fn as_map_mut(&mut self) -> Option<&mut crate::BTreeMap<'store, T, (), B>> {
+        Some(&mut self.0)
     }
 }
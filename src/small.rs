@@ -0,0 +1,512 @@
+#[cfg(feature = "set")]
+use crate::BTreeSet;
+use crate::{map, BTreeMap, BTreeStore};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt::{Debug, Formatter};
+use std::iter::{once, FusedIterator};
+use std::mem::replace;
+
+/// A b-tree map which keeps up to [SmallBTreeMap::INLINE_CAPACITY] entries inline in the handle,
+/// and only allocates nodes from the store once it grows past that.
+///
+/// Meant for very many tiny maps, where allocating a node for each one is mostly overhead. Once the
+/// map spills into the store it's an ordinary [BTreeMap], until removals shrink it to half the
+/// inline capacity (or it's cleared); it doesn't move back inline as soon as it fits, so inserting
+/// and removing around the capacity doesn't repeatedly allocate and free a node.
+///
+/// # Examples
+///
+/// ```
+/// use btree_plus_store::{BTreeStore, SmallBTreeMap};
+/// let store = BTreeStore::new();
+/// let mut map = SmallBTreeMap::new_in(&store);
+/// map.insert(2, "b");
+/// map.insert(1, "a");
+/// assert!(map.is_inline());
+/// assert_eq!(store.live_nodes(), 0);
+/// map.extend([(3, "c"), (4, "d"), (5, "e")]);
+/// assert!(!map.is_inline());
+/// assert!(map.iter().eq([(&1, &"a"), (&2, &"b"), (&3, &"c"), (&4, &"d"), (&5, &"e")]));
+/// ```
+#[derive(Clone)]
+pub struct SmallBTreeMap<'store, K, V, const B: usize = 8>(Repr<'store, K, V, B>);
+
+#[derive(Clone)]
+enum Repr<'store, K, V, const B: usize> {
+    Inline {
+        /// Where nodes are allocated once the map spills
+        store: &'store BTreeStore<K, V, B>,
+        len: u8,
+        /// Sorted entries, the first `len` of which are `Some`
+        entries: [Option<(K, V)>; INLINE_CAPACITY],
+    },
+    Tree(BTreeMap<'store, K, V, B>),
+}
+
+const INLINE_CAPACITY: usize = 4;
+
+/// A b-tree set which keeps up to [SmallBTreeSet::INLINE_CAPACITY] values inline in the handle.
+///
+/// See [SmallBTreeMap].
+#[cfg(feature = "set")]
+#[derive(Clone, PartialEq, Eq)]
+pub struct SmallBTreeSet<'store, T, const B: usize = 8>(SmallBTreeMap<'store, T, (), B>);
+
+impl<'store, K, V, const B: usize> SmallBTreeMap<'store, K, V, B> {
+    /// Max # of entries stored inline, without allocating a node
+    pub const INLINE_CAPACITY: usize = INLINE_CAPACITY;
+
+    /// Creates an empty map, which will allocate nodes from `store` if it spills.
+    #[inline]
+    pub fn new_in(store: &'store BTreeStore<K, V, B>) -> Self {
+        Self(Repr::Inline {
+            store,
+            len: 0,
+            entries: std::array::from_fn(|_| None),
+        })
+    }
+
+    /// The store the map allocates nodes from once it spills
+    #[inline]
+    pub fn store(&self) -> &'store BTreeStore<K, V, B> {
+        match &self.0 {
+            Repr::Inline { store, .. } => store,
+            Repr::Tree(map) => map.store(),
+        }
+    }
+
+    /// Whether the entries are stored inline, i.e. the map doesn't hold any nodes
+    #[inline]
+    pub fn is_inline(&self) -> bool {
+        matches!(self.0, Repr::Inline { .. })
+    }
+
+    /// The spilled tree, or `None` if the entries are inline
+    #[inline]
+    pub fn as_tree(&self) -> Option<&BTreeMap<'store, K, V, B>> {
+        match &self.0 {
+            Repr::Inline { .. } => None,
+            Repr::Tree(map) => Some(map),
+        }
+    }
+
+    /// Returns the number of elements in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        match &self.0 {
+            Repr::Inline { len, .. } => *len as usize,
+            Repr::Tree(map) => map.len(),
+        }
+    }
+
+    /// Returns `true` if the map contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the map contains the key
+    #[inline]
+    pub fn contains_key<Q: Ord + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    #[inline]
+    pub fn get<Q: Ord + ?Sized>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        match &self.0 {
+            Repr::Inline { len, entries, .. } => {
+                let idx = search(&entries[..*len as usize], key).ok()?;
+                entries[idx].as_ref().map(|(_, val)| val)
+            }
+            Repr::Tree(map) => map.get(key),
+        }
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key.
+    #[inline]
+    pub fn get_mut<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+    {
+        match &mut self.0 {
+            Repr::Inline { len, entries, .. } => {
+                let idx = search(&entries[..*len as usize], key).ok()?;
+                entries[idx].as_mut().map(|(_, val)| val)
+            }
+            Repr::Tree(map) => map.get_mut(key),
+        }
+    }
+
+    /// Inserts a key-value pair into the map, spilling into the store if it doesn't fit inline.
+    pub fn insert(&mut self, key: K, val: V) -> Option<V>
+    where
+        K: Clone + Ord,
+    {
+        let (store, len, entries) = match &mut self.0 {
+            Repr::Inline {
+                store,
+                len,
+                entries,
+            } => (*store, len, entries),
+            Repr::Tree(map) => return map.insert(key, val),
+        };
+        let n = *len as usize;
+        match search(&entries[..n], &key) {
+            Ok(idx) => entries[idx].as_mut().map(|(_, old)| replace(old, val)),
+            Err(idx) if n < INLINE_CAPACITY => {
+                // `entries[n]` is `None`, so this moves it to `idx`
+                entries[idx..=n].rotate_right(1);
+                entries[idx] = Some((key, val));
+                *len += 1;
+                None
+            }
+            Err(_) => {
+                // The key is new, so it's not replacing an inline entry
+                let spilled = entries.iter_mut().filter_map(Option::take);
+                let map = BTreeMap::from_iter_in(spilled.chain(once((key, val))), store);
+                self.0 = Repr::Tree(map);
+                None
+            }
+        }
+    }
+
+    /// Removes the equivalent key and returns the value if present. If the map has spilled and
+    /// this leaves it with at most half of [Self::INLINE_CAPACITY] entries, they're moved back
+    /// inline and its nodes are freed.
+    pub fn remove<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Clone + Borrow<Q>,
+    {
+        match &mut self.0 {
+            Repr::Inline { len, entries, .. } => {
+                let n = *len as usize;
+                let idx = search(&entries[..n], key).ok()?;
+                let (_, val) = entries[idx].take()?;
+                // Moves the `None` to the end
+                entries[idx..n].rotate_left(1);
+                *len -= 1;
+                Some(val)
+            }
+            Repr::Tree(map) => {
+                let val = map.remove(key)?;
+                if map.len() <= INLINE_CAPACITY / 2 {
+                    let store = map.store();
+                    let map = replace(map, BTreeMap::new_in(store));
+                    self.0 = Repr::inline_from_sorted(map, store);
+                }
+                Some(val)
+            }
+        }
+    }
+
+    /// Clears the map, freeing its nodes if it spilled.
+    #[inline]
+    pub fn clear(&mut self) {
+        *self = Self::new_in(self.store());
+    }
+
+    /// Iterates over the map's key-value pairs in order.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, K, V, B> {
+        Iter(match &self.0 {
+            Repr::Inline { len, entries, .. } => IterRepr::Inline(entries[..*len as usize].iter()),
+            Repr::Tree(map) => IterRepr::Tree(map.iter()),
+        })
+    }
+
+    /// Converts into an ordinary [BTreeMap], allocating nodes if the entries are inline.
+    #[inline]
+    pub fn into_map(self) -> BTreeMap<'store, K, V, B>
+    where
+        K: Ord + Clone,
+    {
+        match self.0 {
+            Repr::Inline { store, entries, .. } => {
+                BTreeMap::from_iter_in(entries.into_iter().flatten(), store)
+            }
+            Repr::Tree(map) => map,
+        }
+    }
+}
+
+impl<'store, K, V, const B: usize> Repr<'store, K, V, B> {
+    /// `entries` must be sorted and at most [INLINE_CAPACITY]
+    fn inline_from_sorted(
+        entries: impl IntoIterator<Item = (K, V)>,
+        store: &'store BTreeStore<K, V, B>,
+    ) -> Self {
+        let mut inline = std::array::from_fn(|_| None);
+        let mut len = 0;
+        for (slot, entry) in inline.iter_mut().zip(entries) {
+            *slot = Some(entry);
+            len += 1;
+        }
+        Repr::Inline {
+            store,
+            len,
+            entries: inline,
+        }
+    }
+}
+
+/// Binary search of inline entries, which are all `Some`
+#[inline]
+fn search<K: Borrow<Q>, V, Q: Ord + ?Sized>(
+    entries: &[Option<(K, V)>],
+    key: &Q,
+) -> Result<usize, usize> {
+    entries.binary_search_by(|entry| match entry {
+        Some((k, _)) => k.borrow().cmp(key),
+        None => Ordering::Greater,
+    })
+}
+
+#[cfg(feature = "set")]
+impl<'store, T, const B: usize> SmallBTreeSet<'store, T, B> {
+    /// Max # of values stored inline, without allocating a node
+    pub const INLINE_CAPACITY: usize = INLINE_CAPACITY;
+
+    /// Creates an empty set, which will allocate nodes from `store` if it spills.
+    #[inline]
+    pub fn new_in(store: &'store BTreeStore<T, (), B>) -> Self {
+        Self(SmallBTreeMap::new_in(store))
+    }
+
+    /// The store the set allocates nodes from once it spills
+    #[inline]
+    pub fn store(&self) -> &'store BTreeStore<T, (), B> {
+        self.0.store()
+    }
+
+    /// Whether the values are stored inline, i.e. the set doesn't hold any nodes
+    #[inline]
+    pub fn is_inline(&self) -> bool {
+        self.0.is_inline()
+    }
+
+    /// Returns the number of elements in the set.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if the set contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether the set contains the value
+    #[inline]
+    pub fn contains<U: Ord + ?Sized>(&self, value: &U) -> bool
+    where
+        T: Borrow<U>,
+    {
+        self.0.contains_key(value)
+    }
+
+    /// Adds a value to the set, spilling into the store if it doesn't fit inline. Returns whether
+    /// the value was newly inserted.
+    #[inline]
+    pub fn insert(&mut self, value: T) -> bool
+    where
+        T: Clone + Ord,
+    {
+        self.0.insert(value, ()).is_none()
+    }
+
+    /// Removes a value from the set. Returns whether the value was present. Like
+    /// [SmallBTreeMap::remove], a spilled set moves back inline once it's small enough.
+    #[inline]
+    pub fn remove<U: Ord + ?Sized>(&mut self, value: &U) -> bool
+    where
+        T: Clone + Borrow<U>,
+    {
+        self.0.remove(value).is_some()
+    }
+
+    /// Clears the set, freeing its nodes if it spilled.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.0.clear()
+    }
+
+    /// Iterates over the set's values in order.
+    #[inline]
+    pub fn iter(&self) -> SetIter<'_, T, B> {
+        SetIter(self.0.iter())
+    }
+
+    /// Converts into an ordinary [BTreeSet], allocating nodes if the values are inline.
+    #[inline]
+    pub fn into_set(self) -> BTreeSet<'store, T, B>
+    where
+        T: Ord + Clone,
+    {
+        BTreeSet(self.0.into_map())
+    }
+}
+
+impl<'store, K: Debug, V: Debug, const B: usize> Debug for SmallBTreeMap<'store, K, V, B> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(feature = "set")]
+impl<'store, T: Debug, const B: usize> Debug for SmallBTreeSet<'store, T, B> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<'store, K: PartialEq, V: PartialEq, const B: usize> PartialEq
+    for SmallBTreeMap<'store, K, V, B>
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<'store, K: Eq, V: Eq, const B: usize> Eq for SmallBTreeMap<'store, K, V, B> {}
+
+impl<'store, K: Ord + Clone, V, const B: usize> Extend<(K, V)> for SmallBTreeMap<'store, K, V, B> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, val) in iter {
+            self.insert(key, val);
+        }
+    }
+}
+
+#[cfg(feature = "set")]
+impl<'store, T: Ord + Clone, const B: usize> Extend<T> for SmallBTreeSet<'store, T, B> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl<'store: 'a, 'a, K, V, const B: usize> IntoIterator for &'a SmallBTreeMap<'store, K, V, B> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V, B>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(feature = "set")]
+impl<'store: 'a, 'a, T, const B: usize> IntoIterator for &'a SmallBTreeSet<'store, T, B> {
+    type Item = &'a T;
+    type IntoIter = SetIter<'a, T, B>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over a [SmallBTreeMap]'s entries, whether they're inline or in nodes
+pub struct Iter<'a, K, V, const B: usize = 8>(IterRepr<'a, K, V, B>);
+
+enum IterRepr<'a, K, V, const B: usize> {
+    Inline(std::slice::Iter<'a, Option<(K, V)>>),
+    Tree(map::Iter<'a, K, V, B>),
+}
+
+impl<'a, K, V, const B: usize> Clone for Iter<'a, K, V, B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Iter(match &self.0 {
+            IterRepr::Inline(entries) => IterRepr::Inline(entries.clone()),
+            IterRepr::Tree(iter) => IterRepr::Tree(iter.clone()),
+        })
+    }
+}
+
+impl<'a, K, V, const B: usize> Iterator for Iter<'a, K, V, B> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.0 {
+            IterRepr::Inline(entries) => entries.next()?.as_ref().map(|(k, v)| (k, v)),
+            IterRepr::Tree(iter) => iter.next(),
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.0 {
+            IterRepr::Inline(entries) => entries.size_hint(),
+            IterRepr::Tree(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<'a, K, V, const B: usize> DoubleEndedIterator for Iter<'a, K, V, B> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match &mut self.0 {
+            IterRepr::Inline(entries) => entries.next_back()?.as_ref().map(|(k, v)| (k, v)),
+            IterRepr::Tree(iter) => iter.next_back(),
+        }
+    }
+}
+
+impl<'a, K, V, const B: usize> ExactSizeIterator for Iter<'a, K, V, B> {}
+
+impl<'a, K, V, const B: usize> FusedIterator for Iter<'a, K, V, B> {}
+
+/// Iterator over a [SmallBTreeSet]'s values, whether they're inline or in nodes
+#[cfg(feature = "set")]
+pub struct SetIter<'a, T, const B: usize = 8>(Iter<'a, T, (), B>);
+
+#[cfg(feature = "set")]
+impl<'a, T, const B: usize> Clone for SetIter<'a, T, B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        SetIter(self.0.clone())
+    }
+}
+
+#[cfg(feature = "set")]
+impl<'a, T, const B: usize> Iterator for SetIter<'a, T, B> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(value, ())| value)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+#[cfg(feature = "set")]
+impl<'a, T, const B: usize> DoubleEndedIterator for SetIter<'a, T, B> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(value, ())| value)
+    }
+}
+
+#[cfg(feature = "set")]
+impl<'a, T, const B: usize> ExactSizeIterator for SetIter<'a, T, B> {}
+
+#[cfg(feature = "set")]
+impl<'a, T, const B: usize> FusedIterator for SetIter<'a, T, B> {}
@@ -1,48 +1,1426 @@
+use crate::error::OrPanic;
 use crate::node::{Node, NodePtr};
-use rustc_arena_modified::SlabArena;
+use crate::utils::abort_on_unwind;
+use crate::{BTree, Error};
+use std::alloc::Layout;
+#[cfg(feature = "thread-local-store")]
+use std::any::{Any, TypeId};
+#[cfg(any(
+    feature = "thread-local-store",
+    all(feature = "generations", not(feature = "sync-store"))
+))]
+use std::cell::RefCell;
+#[cfg(not(feature = "sync-store"))]
+use std::cell::{Cell, UnsafeCell};
+#[cfg(any(feature = "thread-local-store", feature = "generations"))]
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::mem::{align_of, size_of};
+use std::ptr;
+use std::ptr::NonNull;
+#[cfg(feature = "generations")]
+use std::sync::atomic::AtomicU64;
+#[cfg(feature = "sync-store")]
+use std::sync::atomic::AtomicUsize;
+#[cfg(any(feature = "sync-store", feature = "generations"))]
+use std::sync::atomic::Ordering;
+#[cfg(feature = "sync-store")]
+use std::sync::{Mutex, PoisonError};
+
+#[cfg(feature = "nightly")]
+mod allocator;
+mod fixed;
+mod slab;
+#[cfg(feature = "nightly")]
+pub use allocator::AllocatorBackend;
+pub use fixed::FixedBackend;
+use slab::Slab;
+pub(crate) use slab::UnsafeRef;
 
 /// Arena to store nodes from multiple b-trees.
-pub struct BTreeStore<K, V> {
-    pub(crate) nodes: SlabArena<Node<K, V>>,
+///
+/// Under the `sync-store` feature, the slab is behind a mutex, so the store is [Sync] and trees in
+/// it can be sent to and mutated from different threads (each tree still needs `&mut` to mutate).
+/// Each thread allocates from and frees to its own shard of free slots. An empty shard steals from
+/// another shard or takes a chunk of fresh slots from the slab at once, so threads building trees
+/// at once only lock the slab once per chunk of nodes, not once per node.
+pub struct BTreeStore<K, V, const B: usize = 8> {
+    nodes: Nodes<K, V, B>,
+    /// Freed slots and slots taken from the slab in chunks, not yet used
+    #[cfg(feature = "sync-store")]
+    free: FreeLists<K, V, B>,
+    /// Allocates nodes instead of the slab, if set
+    backend: Option<Box<dyn SlabBackend>>,
+    /// How b-trees in this store search within a node
+    pub(crate) search: SearchStrategy,
+    /// When and how b-trees in this store rebalance underfull nodes
+    pub(crate) rebalance: RebalancePolicy,
+    /// How the slab sizes the chunks it allocates
+    growth: GrowthPolicy,
+    on_alloc: Option<Hook>,
+    on_dealloc: Option<Hook>,
+    #[cfg(feature = "generations")]
+    generations: Generations,
+    /// Declared after `nodes`, so it observes the slab shrinking after the slab is dropped
+    observed: Observed,
+}
+
+/// Instrumentation hook, called when a node is allocated or deallocated.
+type Hook = Box<dyn Fn() + Send + Sync>;
+
+/// Only accessed through [BTreeStore::with_slab], which never nests, since the store isn't [Sync]
+#[cfg(not(feature = "sync-store"))]
+type Nodes<K, V, const B: usize> = UnsafeCell<Slab<Node<K, V, B>>>;
+/// Locked, since trees on different threads allocate and deallocate through `&BTreeStore`
+#[cfg(feature = "sync-store")]
+type Nodes<K, V, const B: usize> = Mutex<Slab<Node<K, V, B>>>;
+
+// SAFETY: under `sync-store`, the slab is only accessed while locked, and the counts are atomic.
+// Hooks and observers are `Send + Sync`. Nodes are moved in and out of the store from any thread
+// which has it, so keys and values must be `Send`.
+#[cfg(feature = "sync-store")]
+unsafe impl<K: Send, V: Send, const B: usize> Send for BTreeStore<K, V, B> {}
+
+// SAFETY: same as `Send`
+#[cfg(feature = "sync-store")]
+unsafe impl<K: Send, V: Send, const B: usize> Sync for BTreeStore<K, V, B> {}
+
+/// How b-trees search for a key within a node.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SearchStrategy {
+    /// Binary search, fewer comparisons
+    #[default]
+    Binary,
+    /// Linear scan, more comparisons but predictable branches, which may be faster for cheap keys
+    Linear,
+    /// Counts the keys less than the searched key without stopping early, so there are no
+    /// data-dependent branches, and comparisons of primitive keys (e.g. `u64`) are vectorized
+    /// (SIMD where the target supports it, otherwise scalar). Usually the fastest for small
+    /// integer keys, but compares every key in the node, so it's slow for expensive comparisons.
+    Branchless,
+}
+
+/// When and how b-trees rebalance nodes which became underfull from removals.
+///
+/// By default a node is underfull below half capacity, and takes entries from a sibling if it can,
+/// otherwise merges with it. Alternating inserts and removals at a node boundary can then
+/// repeatedly merge and split the same nodes; a lower minimum fill makes that rarer, at the cost
+/// of sparser nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RebalancePolicy {
+    /// Fraction of a node's capacity below which it's underfull, in 1/65536ths, so the policy
+    /// doesn't depend on the capacity and is still [Eq] and [Hash]
+    min_fill: u32,
+    underflow: UnderflowStrategy,
+    relaxed: bool,
+}
+
+/// How an underfull node is rebalanced with its sibling.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum UnderflowStrategy {
+    /// Take entries from a sibling if it has enough, otherwise merge with it. Fewer merges, and
+    /// never more than needed to fix the underfull node
+    #[default]
+    Steal,
+    /// Merge with a sibling if they fit in one node, otherwise take entries from it. Denser nodes,
+    /// but more merges
+    Merge,
+}
+
+/// How a store's slab sizes the chunks of nodes it allocates when it has no free slots.
+///
+/// The first chunk is `initial_chunk` bytes, and each one after it is `factor` times the last, up
+/// to `max_chunk` bytes. Sizes are rounded down to whole nodes, but a chunk always has room for at
+/// least one node, and for every node a [reservation](BTreeStore::reserve) needs. Larger chunks
+/// grow the store in fewer steps, but may leave more of it unused, since chunks are only released
+/// when the store is reset or dropped.
+///
+/// Defaults to a page (4 KiB), doubling up to a huge page (2 MiB).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GrowthPolicy {
+    initial_chunk: usize,
+    max_chunk: usize,
+    factor: usize,
+}
+
+/// Snapshot of a [BTreeStore]'s node counts and memory. See [BTreeStore::stats].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct StoreStats {
+    /// Nodes allocated by b-trees in the store
+    pub live_nodes: usize,
+    /// Freed or reserved slots, which are reused before the slab grows
+    pub free_nodes: usize,
+    /// Most nodes which were live at once, since the store was created
+    pub peak_nodes: usize,
+    /// Bytes of the slab used by live nodes
+    pub used_bytes: usize,
+    /// Bytes of the slab, used or free
+    pub reserved_bytes: usize,
+}
+
+/// Which of a [BTreeStore]'s live nodes are reachable from its trees. See
+/// [BTreeStore::leak_check].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct LeakReport {
+    /// Nodes allocated in the store
+    pub live_nodes: usize,
+    /// Live nodes in at least one of the given trees
+    pub reachable_nodes: usize,
+    /// Live nodes in none of the given trees, e.g. from a tree which was leaked instead of dropped
+    pub orphaned_nodes: usize,
+}
+
+impl LeakReport {
+    /// Whether every live node is reachable from the given trees
+    #[inline]
+    pub fn is_leak_free(&self) -> bool {
+        self.orphaned_nodes == 0
+    }
+}
+
+impl RebalancePolicy {
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            min_fill: FILL_ONE / 2,
+            underflow: UnderflowStrategy::Steal,
+            relaxed: false,
+        }
+    }
+
+    /// A non-root node is underfull when less than `fraction` of it is filled. Defaults to `0.5`.
+    ///
+    /// Clamped so that nodes need at least 1 entry, and at most half capacity (splitting a full node
+    /// makes two half-full nodes).
+    #[inline]
+    pub fn min_fill(mut self, fraction: f64) -> Self {
+        self.min_fill = ((fraction * FILL_ONE as f64) as u32).min(FILL_ONE / 2);
+        self
+    }
+
+    /// How an underfull node is rebalanced with its sibling. Defaults to [UnderflowStrategy::Steal].
+    #[inline]
+    pub fn underflow(mut self, underflow: UnderflowStrategy) -> Self {
+        self.underflow = underflow;
+        self
+    }
+
+    /// Whether removals skip rebalancing nodes unless they would be empty, leaving underfull nodes
+    /// until [BTreeMap::repair](crate::BTreeMap::repair) rebalances them all in one pass. Defaults
+    /// to `false`.
+    #[inline]
+    pub fn relaxed(mut self, relaxed: bool) -> Self {
+        self.relaxed = relaxed;
+        self
+    }
+
+    /// Minimum # of entries in a non-root leaf, and keys in a non-root internal node, whose
+    /// capacity is `node_capacity` (see [BTreeStore::NODE_CAPACITY])
+    #[inline]
+    pub fn min_len(&self, node_capacity: usize) -> usize {
+        (node_capacity * self.min_fill as usize / FILL_ONE as usize).clamp(1, node_capacity / 2)
+    }
+
+    /// How an underfull node is rebalanced with its sibling
+    #[inline]
+    pub fn underflow_strategy(&self) -> UnderflowStrategy {
+        self.underflow
+    }
+
+    /// Whether removals leave underfull nodes until they're repaired
+    #[inline]
+    pub fn is_relaxed(&self) -> bool {
+        self.relaxed
+    }
+
+    /// Minimum # of entries or keys in a non-root node between repairs, which is 1 if relaxed
+    #[inline]
+    pub(crate) fn relaxed_min_len(&self, node_capacity: usize) -> usize {
+        match self.relaxed {
+            false => self.min_len(node_capacity),
+            true => 1,
+        }
+    }
+}
+
+/// [RebalancePolicy::min_fill] of 1
+const FILL_ONE: u32 = 1 << 16;
+
+impl Default for RebalancePolicy {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GrowthPolicy {
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            initial_chunk: 4096,
+            max_chunk: 2 * 1024 * 1024,
+            factor: 2,
+        }
+    }
+
+    /// Bytes of the first chunk. Defaults to 4 KiB.
+    #[inline]
+    pub fn initial_chunk(mut self, bytes: usize) -> Self {
+        self.initial_chunk = bytes;
+        self
+    }
+
+    /// Most bytes of a chunk, unless a reservation needs more. Defaults to 2 MiB.
+    #[inline]
+    pub fn max_chunk(mut self, bytes: usize) -> Self {
+        self.max_chunk = bytes;
+        self
+    }
+
+    /// How many times larger each chunk is than the last, until it's `max_chunk` bytes. Defaults
+    /// to 2; 1 makes every chunk `initial_chunk` bytes. Clamped to at least 1.
+    #[inline]
+    pub fn factor(mut self, factor: usize) -> Self {
+        self.factor = factor.max(1);
+        self
+    }
+
+    /// # of nodes of `node_size` bytes in the chunk after one of `last_len` nodes (or the first
+    /// chunk), which has room for at least `min_len`
+    #[inline]
+    fn chunk_len(&self, node_size: usize, last_len: Option<usize>, min_len: usize) -> usize {
+        let bytes = match last_len {
+            None => self.initial_chunk,
+            Some(last_len) => (last_len * node_size)
+                .saturating_mul(self.factor)
+                .clamp(self.initial_chunk, self.max_chunk.max(self.initial_chunk)),
+        };
+        (bytes / node_size).max(min_len).max(1)
+    }
+}
+
+impl Default for GrowthPolicy {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<K, V> BTreeStore<K, V> {
+    /// Creates a store whose nodes hold up to 8 entries. For another capacity, use
+    /// [BTreeStoreBuilder::node_capacity].
     #[inline]
     pub fn new() -> Self {
-        Self {
-            nodes: SlabArena::new(),
+        BTreeStoreBuilder::new().build()
+    }
+
+    /// Creates a store with slots for at least this many nodes, so the first b-trees in it don't
+    /// grow it. See [BTreeStoreBuilder::capacity].
+    #[inline]
+    pub fn with_capacity(nodes: usize) -> Self {
+        BTreeStoreBuilder::new().capacity(nodes).build()
+    }
+}
+
+impl<K, V, const B: usize> BTreeStore<K, V, B> {
+    /// Maximum # of entries in a leaf node, and maximum # of keys in an internal node (which has 1
+    /// more child than keys).
+    ///
+    /// This is the store's `B` parameter, 8 by default, chosen with
+    /// [BTreeStoreBuilder::node_capacity]. Wider nodes suit small keys and values (fewer levels to
+    /// descend, and less overhead per entry), narrower ones suit large values (less moved per
+    /// insertion and removal).
+    pub const NODE_CAPACITY: usize = B;
+    /// Size of a node in bytes. Leaf and internal nodes are the same size.
+    pub const NODE_SIZE: usize = size_of::<Node<K, V, B>>();
+    /// Alignment of a node in bytes.
+    pub const NODE_ALIGN: usize = align_of::<Node<K, V, B>>();
+    /// Layout of a node, which a [SlabBackend] allocates slots for.
+    pub const NODE_LAYOUT: Layout = Layout::new::<Node<K, V, B>>();
+    /// Bytes used per entry in a full leaf, besides the key and value themselves.
+    pub const ENTRY_OVERHEAD: usize = Self::NODE_SIZE / B - size_of::<K>() - size_of::<V>();
+
+    /// How b-trees in this store search within a node.
+    #[inline]
+    pub fn search_strategy(&self) -> SearchStrategy {
+        self.search
+    }
+
+    /// When and how b-trees in this store rebalance underfull nodes.
+    #[inline]
+    pub fn rebalance_policy(&self) -> RebalancePolicy {
+        self.rebalance
+    }
+
+    /// How this store's slab sizes the chunks it allocates.
+    #[inline]
+    pub fn growth_policy(&self) -> GrowthPolicy {
+        self.growth
+    }
+
+    /// # of nodes the store has slots for, live or free. Freed slots are reused, so the slab
+    /// doesn't grow until more nodes are live than this.
+    ///
+    /// The slab may allocate more slots than requested at once, so this is a lower bound.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.observed.slots.get()
+    }
+
+    /// # of nodes allocated by b-trees in the store, which haven't been freed.
+    #[inline]
+    pub fn live_nodes(&self) -> usize {
+        self.observed.live_nodes.get()
+    }
+
+    /// Counts the store's live nodes which aren't in any of `trees`, to find trees which were
+    /// leaked (e.g. via [std::mem::forget] or [BTreeMap::abandon](crate::BTreeMap::abandon), or
+    /// copyable trees which were never garbage-collected) instead of dropped or cleared.
+    ///
+    /// Pass every tree in the store which is still alive; nodes shared by several (copyable) trees
+    /// are counted once. Under `sync-store`, trees on other threads must not allocate or free
+    /// nodes during the check.
+    ///
+    /// *Panics* if any of the trees aren't from this store.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use btree_plus_store::{BTreeMap, BTreeStore};
+    /// let store = BTreeStore::new();
+    /// let mut kept = BTreeMap::new_in(&store);
+    /// let mut leaked = BTreeMap::new_in(&store);
+    /// kept.insert(1, 1);
+    /// leaked.insert(2, 2);
+    /// std::mem::forget(leaked);
+    /// let report = store.leak_check(&[&kept]);
+    /// assert_eq!(report.orphaned_nodes, 1);
+    /// assert!(!report.is_leak_free());
+    /// ```
+    #[track_caller]
+    #[inline]
+    pub fn leak_check<'a>(&self, trees: &[&dyn BTree<'a, K, V, B>]) -> LeakReport
+    where
+        K: 'a,
+        V: 'a,
+    {
+        self.try_leak_check(trees)
+            .or_panic("BTreeStore::leak_check")
+    }
+
+    /// Counts the store's live nodes which aren't in any of `trees`, or returns an error if any of
+    /// the trees aren't from this store. See [BTreeStore::leak_check].
+    pub fn try_leak_check<'a>(&self, trees: &[&dyn BTree<'a, K, V, B>]) -> Result<LeakReport, Error>
+    where
+        K: 'a,
+        V: 'a,
+    {
+        let mut reachable = HashSet::new();
+        for tree in trees {
+            if !tree.is_from_store(self) {
+                return Err(Error::WrongStore);
+            }
+            reachable.extend(tree.node_ptrs().map(|(node, _height)| node));
         }
+        let live_nodes = self.live_nodes();
+        Ok(LeakReport {
+            live_nodes,
+            reachable_nodes: reachable.len(),
+            // Reachable nodes only outnumber live ones if a tree on another thread freed nodes
+            // during the check
+            orphaned_nodes: live_nodes.saturating_sub(reachable.len()),
+        })
     }
 
+    /// Ensures the store has free slots for at least this many more nodes, growing the slab at
+    /// once if not, so the next `nodes` allocations don't grow it. Does nothing if the store has a
+    /// custom [SlabBackend], which manages its own capacity.
+    ///
+    /// Panics if the slab would be larger than `isize::MAX` bytes, and aborts if the allocator is
+    /// out of memory; see [BTreeStore::try_reserve].
+    pub fn reserve(&self, nodes: usize) {
+        if self.backend.is_some() {
+            return;
+        }
+        self.with_slab(|slab| slab.reserve(nodes));
+        self.observed.reserve(nodes);
+    }
+
+    /// Like [BTreeStore::reserve], but returns [Error::CapacityOverflow] instead of panicking if
+    /// the slab would be larger than `isize::MAX` bytes, [Error::OutOfMemory] instead of aborting
+    /// if the allocator can't grow the slab, or [Error::StoreFull] if the store's backend may not
+    /// have this many free nodes.
+    ///
+    /// The other `try_` operations which allocate
+    /// ([BTreeMap::try_insert](crate::BTreeMap::try_insert),
+    /// [BTreeMap::try_extend](crate::BTreeMap::try_extend),
+    /// [BTreeMap::try_clone](crate::BTreeMap::try_clone)) reserve the nodes they may need this way
+    /// before changing anything, so they fail the same way instead of running out partway through.
+    pub fn try_reserve(&self, nodes: usize) -> Result<(), Error> {
+        let live_nodes = self.observed.live_nodes.get();
+        let slots = live_nodes
+            .checked_add(nodes)
+            .ok_or(Error::CapacityOverflow)?;
+        match slots.checked_mul(Self::NODE_SIZE) {
+            Some(bytes) if bytes <= isize::MAX as usize => {}
+            _ => return Err(Error::CapacityOverflow),
+        }
+        self.ensure_available(nodes)?;
+        if self.backend.is_none() {
+            self.observed.reserve(nodes);
+        }
+        Ok(())
+    }
+
+    /// Counts the store's live and free nodes and their bytes, e.g. for capacity planning or to
+    /// find leaks. Free slots are counted like [BTreeStore::capacity], so they're a lower bound.
     #[inline]
-    pub(crate) fn alloc(&self, node: Node<K, V>) -> NodePtr<K, V> {
-        self.nodes.alloc(node).into_unsafe()
+    pub fn stats(&self) -> StoreStats {
+        let live_nodes = self.observed.live_nodes.get();
+        let slots = self.observed.slots.get();
+        StoreStats {
+            live_nodes,
+            // Under `sync-store`, another thread may have allocated a node and not counted its slot
+            free_nodes: slots.saturating_sub(live_nodes),
+            peak_nodes: self.observed.peak_nodes.get(),
+            used_bytes: live_nodes * Self::NODE_SIZE,
+            reserved_bytes: slots * Self::NODE_SIZE,
+        }
     }
 
+    /// Releases the slab's chunks which have no live nodes to the allocator, or all of its memory
+    /// if no nodes are live, e.g. after every tree in the store was dropped or
+    /// [moved out](crate::BTreeMap::compact_in).
+    ///
+    /// Live nodes aren't moved, so a chunk with even one live node is kept. To move the nodes of
+    /// mostly-empty chunks into the others first, use [BTreeStore::compact]. Does nothing while
+    /// nodes are live if the store has a custom [SlabBackend].
+    pub fn shrink_to_fit(&mut self) {
+        if self.observed.live_nodes.get() == 0 {
+            self.reset();
+        } else if self.backend.is_none() {
+            let slots = self.with_slab(|slab| {
+                #[cfg(feature = "sync-store")]
+                self.free.return_to(slab);
+                // SAFETY: No node is moved
+                unsafe { slab.compact(|_| false) };
+                slab.capacity()
+            });
+            self.observed.shrink_to(slots);
+        }
+    }
+
+    /// Moves the nodes of `trees` out of the slab's emptiest chunks into free slots of its fullest
+    /// ones, then releases the emptied chunks to the allocator, e.g. after large trees in the
+    /// store were cleared or shrunk. Each tree's node pointers are fixed up, so the trees stay
+    /// valid.
+    ///
+    /// Pass every tree in the store which is still alive. Nodes which aren't in a passed
+    /// [BTreeMap](crate::BTreeMap) or [BTreeSet](crate::BTreeSet) can't be fixed up, so they stay
+    /// where they are and keep their chunks: the nodes of leaked trees, and of copyable trees,
+    /// since other copies may point to them. Under `sync-store`, trees on other threads must not
+    /// allocate or free nodes during compaction. Does nothing if the store has a custom
+    /// [SlabBackend].
+    ///
+    /// *Panics* if any of the trees aren't from this store.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use btree_plus_store::{BTreeMap, BTreeStore};
+    /// let store = BTreeStore::new();
+    /// let mut big = BTreeMap::new_in(&store);
+    /// let mut small = BTreeMap::new_in(&store);
+    /// big.extend((0..100_000).map(|i| (i, i)));
+    /// small.extend((0..100).map(|i| (i, i)));
+    /// big.retain(|k, _| k % 1000 == 0);
+    /// let capacity = store.capacity();
+    /// store.compact(&mut [&mut big, &mut small]);
+    /// assert!(store.capacity() < capacity);
+    /// assert_eq!(big.get(&5000), Some(&5000));
+    /// ```
+    #[track_caller]
     #[inline]
-    pub(crate) fn dealloc(&self, node: NodePtr<K, V>) {
-        unsafe { node.discard(&self.nodes) }
+    pub fn compact<'a>(&self, trees: &mut [&mut dyn BTree<'a, K, V, B>])
+    where
+        K: 'a,
+        V: 'a,
+    {
+        self.try_compact(trees).or_panic("BTreeStore::compact")
+    }
+
+    /// Compacts the store's slab, or returns an error before moving any node if any of the trees
+    /// aren't from this store. See [BTreeStore::compact].
+    pub fn try_compact<'a>(&self, trees: &mut [&mut dyn BTree<'a, K, V, B>]) -> Result<(), Error>
+    where
+        K: 'a,
+        V: 'a,
+    {
+        if trees.iter().any(|tree| !tree.is_from_store(self)) {
+            return Err(Error::WrongStore);
+        }
+        if self.backend.is_some() {
+            return Ok(());
+        }
+        let mut movable = HashSet::new();
+        for tree in trees.iter_mut() {
+            if tree.as_map_mut().is_some() {
+                movable.extend(tree.node_ptrs().map(|(node, _height)| node));
+            }
+        }
+        let (moved, slots) = self.with_slab(|slab| {
+            #[cfg(feature = "sync-store")]
+            self.free.return_to(slab);
+            // SAFETY: Only nodes of the maps are moved, and they're fixed up below before being
+            // accessed again
+            let moved = unsafe { slab.compact(|node| movable.contains(&node)) };
+            (moved, slab.capacity())
+        });
+        for tree in trees.iter_mut() {
+            if let Some(map) = tree.as_map_mut() {
+                // SAFETY: `moved` has every moved node, and the maps' nodes are in `movable`
+                unsafe { map.relocate(&moved) };
+            }
+        }
+        #[cfg(feature = "generations")]
+        for (from, to) in &moved {
+            self.generations.relocate(from, to);
+        }
+        self.observed.shrink_to(slots);
+        Ok(())
+    }
+
+    /// Frees every node in the store at once and releases the slab's memory to the allocator.
+    ///
+    /// Taking `&mut self` ensures no b-tree in the store is still alive. For "build, use, throw
+    /// away" cycles, end the trees with [BTreeMap::abandon](crate::BTreeMap::abandon), which
+    /// doesn't free their nodes one by one, then reset the store. The nodes' keys and values
+    /// aren't dropped, only leaked, so this is meant for types without drop glue.
+    ///
+    /// Nodes freed this way aren't reported to [BTreeStoreBuilder::on_dealloc] or
+    /// [StoreAllocObserver::node_free], only the slab shrinking is.
+    pub fn reset(&mut self) {
+        self.nodes = new_slab(self.growth);
+        #[cfg(feature = "sync-store")]
+        {
+            self.free = FreeLists::new();
+        }
+        #[cfg(feature = "generations")]
+        self.generations.clear();
+        self.observed.live_nodes.set(0);
+        self.observed.release();
     }
 
-    #[allow(unused)]
     #[inline]
-    pub(crate) fn dealloc_and_return(&self, node: NodePtr<K, V>) -> Node<K, V> {
-        unsafe { node.take(&self.nodes) }
+    pub(crate) fn alloc(&self, node: Node<K, V, B>) -> NodePtr<K, V, B> {
+        if let Some(on_alloc) = &self.on_alloc {
+            abort_on_unwind(on_alloc);
+        }
+        self.observed.alloc();
+        let slot = self.alloc_slot(node);
+        #[cfg(feature = "generations")]
+        self.generations.stamp(&slot);
+        slot
+    }
+
+    /// Moves the node into a slot from the backend, the slab, or (under `sync-store`) the free
+    /// lists
+    #[inline]
+    fn alloc_slot(&self, node: Node<K, V, B>) -> NodePtr<K, V, B> {
+        if let Some(backend) = &self.backend {
+            let slot = abort_on_unwind(|| backend.alloc(Self::NODE_LAYOUT)).cast::<Node<K, V, B>>();
+            return unsafe {
+                slot.as_ptr().write(node);
+                NodePtr::from_ref(slot.as_ref())
+            };
+        }
+        #[cfg(not(feature = "sync-store"))]
+        {
+            self.with_slab(|slab| slab.alloc(node))
+        }
+        #[cfg(feature = "sync-store")]
+        {
+            let mut slot = self.free.pop(|| self.take_chunk());
+            // The slot's old node was moved out, discarded, or is a placeholder, so it's overwritten
+            // without dropping
+            unsafe { ptr::write(slot.as_mut(), node) };
+            slot
+        }
+    }
+
+    /// Takes up to [CHUNK] slots from the slab (holding placeholder nodes), locking it once.
+    /// Takes fewer if the slab has fewer free, so it doesn't grow past what `try_` operations
+    /// reserved.
+    #[cfg(feature = "sync-store")]
+    fn take_chunk(&self) -> Vec<NodePtr<K, V, B>> {
+        let slots = self.with_slab(|slab| {
+            let len = match slab.available() {
+                0 => CHUNK,
+                available => available.min(CHUNK),
+            };
+            (0..len)
+                .map(|_| slab.alloc(Node::leaf()))
+                .collect::<Vec<_>>()
+        });
+        // One slot is for the node being allocated, which is already counted as live
+        self.observed.reserve(slots.len() - 1);
+        slots
+    }
+
+    #[inline]
+    pub(crate) fn dealloc(&self, node: NodePtr<K, V, B>) {
+        self.observe_dealloc(unsafe { node.as_ref() });
+        if let Some(backend) = &self.backend {
+            // Nodes have no drop glue, so the slot can be freed without dropping it
+            abort_on_unwind(|| unsafe {
+                backend.free(NonNull::from(node.as_ref()).cast(), Self::NODE_LAYOUT)
+            });
+            return;
+        }
+        #[cfg(not(feature = "sync-store"))]
+        self.with_slab(|slab| unsafe { slab.discard(node) });
+        // Nodes have no drop glue (keys and values are dropped by the tree), so discarding is only
+        // freeing the slot
+        #[cfg(feature = "sync-store")]
+        self.free.push(node);
+    }
+
+    #[inline]
+    pub(crate) fn dealloc_and_return(&self, node: NodePtr<K, V, B>) -> Node<K, V, B> {
+        self.observe_dealloc(unsafe { node.as_ref() });
+        if let Some(backend) = &self.backend {
+            return unsafe {
+                let taken = ptr::read(node.as_ref());
+                abort_on_unwind(|| {
+                    backend.free(NonNull::from(node.as_ref()).cast(), Self::NODE_LAYOUT)
+                });
+                taken
+            };
+        }
+        #[cfg(not(feature = "sync-store"))]
+        let taken = self.with_slab(|slab| unsafe { slab.take(node) });
+        #[cfg(feature = "sync-store")]
+        let taken = unsafe { ptr::read(node.as_ref()) };
+        #[cfg(feature = "sync-store")]
+        self.free.push(node);
+        taken
     }
 
     #[allow(unused)]
     #[inline]
     pub(crate) unsafe fn retain_shared<F>(&self, mut f: F)
     where
-        F: FnMut(&Node<K, V>) -> bool,
+        F: FnMut(&Node<K, V, B>) -> bool,
     {
-        self.nodes.retain_shared(|node| f(node))
+        let mut released = Vec::new();
+        self.with_slab(|slab| {
+            // Otherwise the slab would see free slots as live nodes
+            #[cfg(feature = "sync-store")]
+            self.free.return_to(slab);
+            slab.retain_shared(|node| {
+                let retain = f(node);
+                if !retain {
+                    released.push(NodePtr::from_ref(node));
+                }
+                retain
+            })
+        });
+        // Observed after the slab is released, since hooks are user code. The freed slots are
+        // only looked up by address.
+        for node in released {
+            self.observe_dealloc(node.as_ref());
+        }
+    }
+
+    /// Ensures the store can allocate this many more nodes without growing the slab infallibly:
+    /// grows the slab fallibly, or fails if the store's backend may not have them. Under
+    /// `sync-store`, another thread may allocate the nodes between this and their use.
+    #[inline]
+    pub(crate) fn ensure_available(&self, nodes: usize) -> Result<(), Error> {
+        match &self.backend {
+            Some(backend) => match abort_on_unwind(|| backend.available()) {
+                Some(available) if available < nodes => Err(Error::StoreFull),
+                _ => Ok(()),
+            },
+            None => self
+                .with_slab(|slab| slab.try_reserve(nodes))
+                .map_err(Error::OutOfMemory),
+        }
+    }
+
+    /// Calls `f` with the slab, locked under `sync-store`. `f` must not call back into the store.
+    #[inline]
+    fn with_slab<R>(&self, f: impl FnOnce(&mut Slab<Node<K, V, B>>) -> R) -> R {
+        #[cfg(not(feature = "sync-store"))]
+        {
+            // SAFETY: the store isn't `Sync`, and `f` doesn't call back into it, so this is the
+            // only reference to the slab
+            f(unsafe { &mut *self.nodes.get() })
+        }
+        #[cfg(feature = "sync-store")]
+        {
+            f(&mut lock(&self.nodes))
+        }
+    }
+
+    #[inline]
+    #[allow(unused_variables)]
+    fn observe_dealloc(&self, node: &Node<K, V, B>) {
+        if let Some(on_dealloc) = &self.on_dealloc {
+            abort_on_unwind(on_dealloc);
+        }
+        #[cfg(feature = "generations")]
+        self.generations.forget(node);
+        self.observed.dealloc();
+    }
+
+    /// The node's generation, which changes when it's freed (and its slot reused), or 0 if it's
+    /// freed. Only looks up the node's address, so it may be freed.
+    #[cfg(feature = "generations")]
+    #[inline]
+    pub(crate) fn generation(&self, node: NodePtr<K, V, B>) -> u64 {
+        self.generations.get(&node)
+    }
+
+    /// The store's ID, unique within the process, so trees can tell whether they're used with
+    /// the store they were created in
+    #[cfg(feature = "generations")]
+    #[inline]
+    pub(crate) const fn id(&self) -> u64 {
+        self.generations.id
+    }
+
+    /// Gives the node a new generation, when a tree reuses its slot for another node without
+    /// freeing it
+    #[cfg(feature = "generations")]
+    #[inline]
+    pub(crate) fn restamp(&self, node: NodePtr<K, V, B>) {
+        self.generations.stamp(&node)
     }
 }
 
-impl<K, V> Default for BTreeStore<K, V> {
+// region backend
+/// Allocates a [BTreeStore]'s nodes instead of its built-in slab, e.g. to use a `Vec` of chunks,
+/// the `slab` crate, or a bump arena. Set with [BTreeStoreBuilder::backend].
+///
+/// Every slot has the store's [BTreeStore::NODE_LAYOUT]. Nodes have no drop glue (trees drop
+/// their keys and values), so a backend which never frees slots until it's dropped (like a bump
+/// arena) is fine. The store can't enumerate a custom backend's slots, so copyable trees' tracing
+/// garbage collection doesn't free nodes in it, and [BTreeStore::reset] only forgets them.
+///
+/// A panic in a backend's methods aborts the process, since a tree may be partway through
+/// restructuring when it allocates or frees a node.
+///
+/// # Safety
+///
+/// `alloc` must return memory valid for reads and writes of `layout`, which isn't used by anything
+/// else and doesn't move until it's passed to `free` or the backend is dropped.
+///
+/// # Examples
+///
+/// ```
+/// use btree_plus_store::{BTreeMap, BTreeStoreBuilder, SlabBackend};
+/// use std::alloc::{alloc, dealloc, handle_alloc_error, Layout};
+/// use std::ptr::NonNull;
+///
+/// /// Allocates each node separately with the global allocator
+/// struct Global;
+///
+/// unsafe impl SlabBackend for Global {
+///     fn alloc(&self, layout: Layout) -> NonNull<u8> {
+///         NonNull::new(unsafe { alloc(layout) }).unwrap_or_else(|| handle_alloc_error(layout))
+///     }
+///
+///     unsafe fn free(&self, slot: NonNull<u8>, layout: Layout) {
+///         dealloc(slot.as_ptr(), layout)
+///     }
+/// }
+///
+/// let store = BTreeStoreBuilder::new().backend(Global).build();
+/// let mut map = BTreeMap::new_in(&store);
+/// map.insert(1, "one");
+/// assert_eq!(map.get(&1), Some(&"one"));
+/// ```
+pub unsafe trait SlabBackend: Send + Sync {
+    /// Allocates a slot for a node
+    fn alloc(&self, layout: Layout) -> NonNull<u8>;
+
+    /// Frees a slot. It may be reused by `alloc`, or only when the backend is dropped.
+    ///
+    /// # Safety
+    /// `slot` was returned by `alloc` with the same `layout`, and hasn't been freed.
+    unsafe fn free(&self, slot: NonNull<u8>, layout: Layout);
+
+    /// How many more slots `alloc` can return, or `None` (the default) if it can always grow.
+    /// The `try_` operations which allocate (e.g.
+    /// [BTreeMap::try_insert](crate::BTreeMap::try_insert)) check this
+    /// first, so they fail instead of running out partway through.
+    #[inline]
+    fn available(&self) -> Option<usize> {
+        None
+    }
+}
+// endregion
+
+// region observer
+/// Observes a [BTreeStore]'s memory usage, e.g. to report it to a heap profiler like `dhat`. Set
+/// with [BTreeStoreBuilder::observer]. Every method does nothing by default.
+///
+/// Every tree in a store shares its nodes' slab, so to attribute usage per tree, give each tree
+/// its own store and observer. A panic in an observer aborts the process, like in a
+/// [SlabBackend].
+pub trait StoreAllocObserver: Send + Sync {
+    /// The store's slab reserved `bytes` more for nodes, `total_bytes` in all. Freed nodes' slots
+    /// are reused, so the slab only grows when more nodes are live than ever before (under
+    /// `sync-store`, by a chunk of nodes at a time).
+    #[allow(unused_variables)]
+    #[inline]
+    fn slab_grew(&self, bytes: usize, total_bytes: usize) {}
+
+    /// The store's slab released `bytes`, leaving `total_bytes`: when the store is dropped or
+    /// [reset](BTreeStore::reset), or a [compaction](BTreeStore::compact) or
+    /// [shrink](BTreeStore::shrink_to_fit) released chunks of slots.
+    #[allow(unused_variables)]
+    #[inline]
+    fn slab_shrank(&self, bytes: usize, total_bytes: usize) {}
+
+    /// A node of `bytes` was allocated in the slab
+    #[allow(unused_variables)]
+    #[inline]
+    fn node_alloc(&self, bytes: usize) {}
+
+    /// A node of `bytes` was freed from the slab, including in garbage collection
+    #[allow(unused_variables)]
+    #[inline]
+    fn node_free(&self, bytes: usize) {}
+}
+
+/// A store's node and slot counts, and the observer (if any) it reports them to
+struct Observed {
+    observer: Option<Box<dyn StoreAllocObserver>>,
+    node_size: usize,
+    live_nodes: Counter,
+    peak_nodes: Counter,
+    slots: Counter,
+}
+
+impl Observed {
+    #[inline]
+    fn new(observer: Option<Box<dyn StoreAllocObserver>>, node_size: usize) -> Self {
+        Self {
+            observer,
+            node_size,
+            live_nodes: Counter::default(),
+            peak_nodes: Counter::default(),
+            slots: Counter::default(),
+        }
+    }
+
+    #[inline]
+    fn reserve(&self, nodes: usize) {
+        let slots = self.live_nodes.get() + nodes;
+        self.grow_to(slots);
+    }
+
+    #[inline]
+    fn alloc(&self) {
+        let live_nodes = self.live_nodes.add(1);
+        self.peak_nodes.max(live_nodes);
+        self.grow_to(live_nodes);
+        if let Some(observer) = &self.observer {
+            abort_on_unwind(|| observer.node_alloc(self.node_size));
+        }
+    }
+
+    /// The slab has at least this many slots
+    #[inline]
+    fn grow_to(&self, slots: usize) {
+        let prev_slots = self.slots.max(slots);
+        if slots > prev_slots {
+            if let Some(observer) = &self.observer {
+                abort_on_unwind(|| {
+                    observer.slab_grew(
+                        (slots - prev_slots) * self.node_size,
+                        slots * self.node_size,
+                    )
+                });
+            }
+        }
+    }
+
+    #[inline]
+    fn dealloc(&self) {
+        self.live_nodes.sub(1);
+        if let Some(observer) = &self.observer {
+            abort_on_unwind(|| observer.node_free(self.node_size));
+        }
+    }
+
+    /// The slab has at most this many slots
+    #[inline]
+    fn shrink_to(&self, slots: usize) {
+        let prev_slots = self.slots.get();
+        if slots < prev_slots {
+            self.slots.set(slots);
+            if let Some(observer) = &self.observer {
+                abort_on_unwind(|| {
+                    observer.slab_shrank(
+                        (prev_slots - slots) * self.node_size,
+                        slots * self.node_size,
+                    )
+                });
+            }
+        }
+    }
+
+    /// The slab released every slot
+    #[inline]
+    fn release(&self) {
+        self.shrink_to(0);
+    }
+}
+
+impl Drop for Observed {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// Generations of a store's live nodes, keyed by address. They're kept outside of the nodes, so
+/// checking whether a node was freed never reads it.
+#[cfg(feature = "generations")]
+struct Generations {
+    /// The store's ID, unique within the process, which its trees are stamped with
+    id: u64,
+    last: AtomicU64,
+    #[cfg(not(feature = "sync-store"))]
+    by_node: RefCell<HashMap<usize, u64>>,
+    #[cfg(feature = "sync-store")]
+    by_node: Mutex<HashMap<usize, u64>>,
+}
+
+/// The next store's [Generations::id]
+#[cfg(feature = "generations")]
+static NEXT_STORE_ID: AtomicU64 = AtomicU64::new(1);
+
+#[cfg(feature = "generations")]
+impl Generations {
+    fn new() -> Self {
+        Generations {
+            id: NEXT_STORE_ID.fetch_add(1, Ordering::Relaxed),
+            last: AtomicU64::new(0),
+            by_node: Default::default(),
+        }
+    }
+
+    #[inline]
+    fn stamp<K, V, const B: usize>(&self, node: &NodePtr<K, V, B>) {
+        let generation = self.last.fetch_add(1, Ordering::Relaxed) + 1;
+        self.by_node()
+            .insert(node.as_ptr().as_ptr() as usize, generation);
+    }
+
+    #[inline]
+    fn forget<K, V, const B: usize>(&self, node: &Node<K, V, B>) {
+        self.by_node()
+            .remove(&(node as *const Node<K, V, B> as usize));
+    }
+
+    #[inline]
+    fn get<K, V, const B: usize>(&self, node: &NodePtr<K, V, B>) -> u64 {
+        let key = node.as_ptr().as_ptr() as usize;
+        self.by_node().get(&key).copied().unwrap_or(0)
+    }
+
+    /// The node at `from` was moved to `to`
+    #[inline]
+    fn relocate<K, V, const B: usize>(&self, from: &NodePtr<K, V, B>, to: &NodePtr<K, V, B>) {
+        let mut by_node = self.by_node();
+        if let Some(generation) = by_node.remove(&(from.as_ptr().as_ptr() as usize)) {
+            by_node.insert(to.as_ptr().as_ptr() as usize, generation);
+        }
+    }
+
+    #[inline]
+    fn clear(&self) {
+        self.by_node().clear()
+    }
+
+    #[inline]
+    fn by_node(&self) -> impl std::ops::DerefMut<Target = HashMap<usize, u64>> + '_ {
+        #[cfg(not(feature = "sync-store"))]
+        {
+            self.by_node.borrow_mut()
+        }
+        #[cfg(feature = "sync-store")]
+        {
+            lock(&self.by_node)
+        }
+    }
+}
+
+/// Free slots in a `sync-store` store, sharded by thread. Each thread frees to and allocates from
+/// its own shard. When its shard is empty, it steals half of another shard's slots (skipping any
+/// which are locked), or if none have any, takes a [CHUNK] of fresh slots from the slab.
+#[cfg(feature = "sync-store")]
+struct FreeLists<K, V, const B: usize>([Mutex<Vec<NodePtr<K, V, B>>>; SHARDS]);
+
+#[cfg(feature = "sync-store")]
+const SHARDS: usize = 16;
+
+/// # of fresh slots an empty shard takes from the slab at once
+#[cfg(feature = "sync-store")]
+const CHUNK: usize = 64;
+
+#[cfg(feature = "sync-store")]
+impl<K, V, const B: usize> FreeLists<K, V, B> {
+    #[inline]
+    fn new() -> Self {
+        Self(std::array::from_fn(|_| Mutex::new(Vec::new())))
+    }
+
+    /// Pops a slot from this thread's shard, refilling it from other shards or `take_chunk` if
+    /// it's empty
+    #[inline]
+    fn pop(&self, take_chunk: impl FnOnce() -> Vec<NodePtr<K, V, B>>) -> NodePtr<K, V, B> {
+        let home = Self::home_shard();
+        let mut shard = lock(&self.0[home]);
+        if let Some(slot) = shard.pop() {
+            return slot;
+        }
+        for offset in 1..SHARDS {
+            let Ok(mut other) = self.0[(home + offset) % SHARDS].try_lock() else {
+                continue;
+            };
+            let stolen = other.len().div_ceil(2);
+            if stolen > 0 {
+                let rest = other.len() - stolen;
+                shard.extend(other.drain(rest..));
+                return shard.pop().unwrap();
+            }
+        }
+        // Unlocked while taking the chunk, since `return_to` locks the slab before the shards
+        drop(shard);
+        let mut chunk = take_chunk();
+        let slot = chunk.pop().expect("chunks aren't empty");
+        lock(&self.0[home]).append(&mut chunk);
+        slot
+    }
+
+    #[inline]
+    fn push(&self, slot: NodePtr<K, V, B>) {
+        lock(&self.0[Self::home_shard()]).push(slot)
+    }
+
+    /// Frees every slot in the slab itself
+    fn return_to(&self, slab: &mut Slab<Node<K, V, B>>) {
+        for shard in &self.0 {
+            for slot in lock(shard).drain(..) {
+                unsafe { slab.discard(slot) }
+            }
+        }
+    }
+
+    /// This thread's shard
+    #[inline]
+    fn home_shard() -> usize {
+        static NEXT_SHARD: AtomicUsize = AtomicUsize::new(0);
+        thread_local! {
+            static HOME_SHARD: usize = NEXT_SHARD.fetch_add(1, Ordering::Relaxed) % SHARDS;
+        }
+        HOME_SHARD.with(|shard| *shard)
+    }
+}
+
+/// Locks the mutex even if a thread panicked while holding it: the slab and free lists are only
+/// locked to push or pop slots, which don't call user code, so they can't be left inconsistent.
+#[cfg(feature = "sync-store")]
+#[inline]
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A count, atomic under `sync-store`
+#[cfg(not(feature = "sync-store"))]
+#[derive(Default)]
+struct Counter(Cell<usize>);
+
+/// A count, atomic under `sync-store`
+#[cfg(feature = "sync-store")]
+#[derive(Default)]
+struct Counter(AtomicUsize);
+
+#[cfg(not(feature = "sync-store"))]
+impl Counter {
+    #[inline]
+    fn get(&self) -> usize {
+        self.0.get()
+    }
+
+    #[inline]
+    fn set(&self, count: usize) {
+        self.0.set(count)
+    }
+
+    /// Adds `n`, returning the new count
+    #[inline]
+    fn add(&self, n: usize) -> usize {
+        let count = self.0.get() + n;
+        self.0.set(count);
+        count
+    }
+
+    #[inline]
+    fn sub(&self, n: usize) {
+        self.0.set(self.0.get() - n)
+    }
+
+    /// Raises the count to `count` if it's less, returning the previous count
+    #[inline]
+    fn max(&self, count: usize) -> usize {
+        let prev = self.0.get();
+        self.0.set(prev.max(count));
+        prev
+    }
+}
+
+#[cfg(feature = "sync-store")]
+impl Counter {
+    #[inline]
+    fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    fn set(&self, count: usize) {
+        self.0.store(count, Ordering::Relaxed)
+    }
+
+    /// Adds `n`, returning the new count
+    #[inline]
+    fn add(&self, n: usize) -> usize {
+        self.0.fetch_add(n, Ordering::Relaxed) + n
+    }
+
+    #[inline]
+    fn sub(&self, n: usize) {
+        self.0.fetch_sub(n, Ordering::Relaxed);
+    }
+
+    /// Raises the count to `count` if it's less, returning the previous count
+    #[inline]
+    fn max(&self, count: usize) -> usize {
+        self.0.fetch_max(count, Ordering::Relaxed)
+    }
+}
+// endregion
+
+// region builder
+/// Configures a [BTreeStore].
+///
+/// The node capacity is the builder's `B` parameter, set with [BTreeStoreBuilder::node_capacity].
+///
+/// The slab reuses freed slots before it grows, by chunks sized by its [GrowthPolicy]. To grow
+/// the store in fewer, larger steps, reserve slots up front with [BTreeStoreBuilder::capacity] or
+/// later with [BTreeStore::reserve]. To control growth entirely, allocate nodes with a
+/// [SlabBackend] (e.g. a [FixedBackend], which never grows).
+#[derive(Default)]
+pub struct BTreeStoreBuilder<const B: usize = 8> {
+    capacity: usize,
+    search: SearchStrategy,
+    rebalance: RebalancePolicy,
+    growth: GrowthPolicy,
+    on_alloc: Option<Hook>,
+    on_dealloc: Option<Hook>,
+    observer: Option<Box<dyn StoreAllocObserver>>,
+    backend: Option<Box<dyn SlabBackend>>,
+}
+
+impl BTreeStoreBuilder {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<const B: usize> BTreeStoreBuilder<B> {
+    /// Maximum # of entries per node, which is [BTreeStore::NODE_CAPACITY]. Defaults to 8.
+    ///
+    /// It's a type parameter of the store, and of the trees in it, since nodes are arrays of this
+    /// many keys and values. It must be at least 4.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use btree_plus_store::{BTreeMap, BTreeStore, BTreeStoreBuilder};
+    /// let store: BTreeStore<u8, u8, 32> = BTreeStoreBuilder::new().node_capacity::<32>().build();
+    /// let map = BTreeMap::from_iter_in((0..=255).map(|i| (i, i)), &store);
+    /// assert_eq!(map.nodes().count(), 9);
+    /// ```
+    #[inline]
+    pub fn node_capacity<const C: usize>(self) -> BTreeStoreBuilder<C> {
+        BTreeStoreBuilder {
+            capacity: self.capacity,
+            search: self.search,
+            rebalance: self.rebalance,
+            growth: self.growth,
+            on_alloc: self.on_alloc,
+            on_dealloc: self.on_dealloc,
+            observer: self.observer,
+            backend: self.backend,
+        }
+    }
+
+    /// Pre-allocate slots for this many nodes, so the first b-trees in the store don't grow it.
+    /// See [BTreeStore::reserve].
+    #[inline]
+    pub fn capacity(mut self, nodes: usize) -> Self {
+        self.capacity = nodes;
+        self
+    }
+
+    /// How b-trees in the store search within a node. Defaults to [SearchStrategy::Binary].
+    #[inline]
+    pub fn search_strategy(mut self, search: SearchStrategy) -> Self {
+        self.search = search;
+        self
+    }
+
+    /// When and how b-trees in the store rebalance underfull nodes. Defaults to
+    /// [RebalancePolicy::new].
+    #[inline]
+    pub fn rebalance_policy(mut self, rebalance: RebalancePolicy) -> Self {
+        self.rebalance = rebalance;
+        self
+    }
+
+    /// How the store's slab sizes the chunks it allocates. Defaults to [GrowthPolicy::new]. Doesn't
+    /// apply to a [SlabBackend], which allocates nodes itself.
+    #[inline]
+    pub fn growth_policy(mut self, growth: GrowthPolicy) -> Self {
+        self.growth = growth;
+        self
+    }
+
+    /// Call `f` whenever the store allocates a node. If `f` panics, the process aborts.
+    #[inline]
+    pub fn on_alloc(mut self, f: impl Fn() + Send + Sync + 'static) -> Self {
+        self.on_alloc = Some(Box::new(f));
+        self
+    }
+
+    /// Call `f` whenever the store deallocates a node, including in garbage collection. If `f`
+    /// panics, the process aborts.
+    #[inline]
+    pub fn on_dealloc(mut self, f: impl Fn() + Send + Sync + 'static) -> Self {
+        self.on_dealloc = Some(Box::new(f));
+        self
+    }
+
+    /// Report the store's slab growth and node (de)allocations, with byte counts, to `observer`.
+    #[inline]
+    pub fn observer(mut self, observer: impl StoreAllocObserver + 'static) -> Self {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    /// Allocate nodes with `backend` instead of the store's slab. Defaults to the slab, which
+    /// allocates chunks of nodes with the global allocator and reuses freed nodes.
+    #[inline]
+    pub fn backend(mut self, backend: impl SlabBackend + 'static) -> Self {
+        self.backend = Some(Box::new(backend));
+        self
+    }
+
+    /// Create the store.
+    pub fn build<K, V>(self) -> BTreeStore<K, V, B> {
+        #[allow(clippy::let_unit_value)]
+        let () = Node::<K, V, B>::VALID_CAPACITY;
+        let store = BTreeStore {
+            nodes: new_slab(self.growth),
+            #[cfg(feature = "sync-store")]
+            free: FreeLists::new(),
+            backend: self.backend,
+            search: self.search,
+            rebalance: self.rebalance,
+            growth: self.growth,
+            on_alloc: self.on_alloc,
+            on_dealloc: self.on_dealloc,
+            #[cfg(feature = "generations")]
+            generations: Generations::new(),
+            observed: Observed::new(self.observer, size_of::<Node<K, V, B>>()),
+        };
+        store.reserve(self.capacity);
+        store
+    }
+}
+// endregion
+
+#[inline]
+fn new_slab<K, V, const B: usize>(growth: GrowthPolicy) -> Nodes<K, V, B> {
+    #[cfg(not(feature = "sync-store"))]
+    {
+        UnsafeCell::new(Slab::new(growth))
+    }
+    #[cfg(feature = "sync-store")]
+    {
+        Mutex::new(Slab::new(growth))
+    }
+}
+
+impl<K, V, const B: usize> Default for BTreeStore<K, V, B> {
     #[inline]
     fn default() -> Self {
-        Self::new()
+        BTreeStoreBuilder::new().node_capacity().build()
     }
 }
+
+#[cfg(feature = "thread-local-store")]
+impl<K: 'static, V: 'static, const B: usize> BTreeStore<K, V, B> {
+    /// This thread's default store for `K` and `V`, created on first use. Maps and sets built with
+    /// `new`, [Default], or [FromIterator] are allocated in it.
+    ///
+    /// The store is leaked so that trees can borrow it for `'static`: its nodes are reused by
+    /// trees on the same thread, but never freed, even after the thread exits.
+    pub fn thread_local() -> &'static Self {
+        thread_local! {
+            static STORES: RefCell<HashMap<TypeId, &'static dyn Any>> =
+                RefCell::new(HashMap::new());
+        }
+        STORES.with(|stores| {
+            let store = *stores
+                .borrow_mut()
+                .entry(TypeId::of::<Self>())
+                .or_insert_with(|| Box::leak(Box::<Self>::default()));
+            store
+                .downcast_ref()
+                .expect("thread-local store is keyed by its type")
+        })
+    }
+}
+
+/// Fails to compile if a node for keys of type `$key` and values of type `$val` is larger than
+/// `$max_bytes`. The node capacity defaults to 8, or can be given with `capacity = ...`.
+///
+/// # Examples
+///
+/// ```
+/// btree_plus_store::assert_node_size!(u64, u64, 256);
+/// btree_plus_store::assert_node_size!(u64, u64, capacity = 32, 1024);
+/// ```
+///
+/// ```compile_fail
+/// btree_plus_store::assert_node_size!(u64, [u8; 1024], 256);
+/// ```
+#[macro_export]
+macro_rules! assert_node_size {
+    ($key:ty, $val:ty, $max_bytes:expr) => {
+        $crate::assert_node_size!($key, $val, capacity = 8, $max_bytes);
+    };
+    ($key:ty, $val:ty, capacity = $capacity:expr, $max_bytes:expr) => {
+        const _: () = assert!(
+            <$crate::BTreeStore<$key, $val, $capacity>>::NODE_SIZE <= $max_bytes,
+            "b-tree node is larger than the given size"
+        );
+    };
+}
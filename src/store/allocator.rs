@@ -0,0 +1,127 @@
+use std::alloc::{handle_alloc_error, Allocator, Layout};
+use std::ptr::NonNull;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::store::SlabBackend;
+
+/// Slots per chunk which the backend allocates at once
+const CHUNK_SLOTS: usize = 64;
+
+/// [SlabBackend] which allocates nodes in chunks from an [Allocator] (e.g. a huge-page or
+/// instrumented allocator), instead of the global heap. Like the built-in slab, freed slots are
+/// reused, and the chunks are only deallocated when the store is dropped.
+///
+/// The store itself isn't generic over the allocator, so trees in stores with different
+/// allocators have the same type.
+///
+/// # Examples
+///
+/// ```
+/// #![feature(allocator_api)]
+/// use btree_plus_store::{AllocatorBackend, BTreeMap, BTreeStoreBuilder};
+/// use std::alloc::Global;
+/// let store = BTreeStoreBuilder::new()
+///     .backend(AllocatorBackend::new(Global))
+///     .build();
+/// let mut map = BTreeMap::new_in(&store);
+/// map.insert(1, "one");
+/// assert_eq!(map.get(&1), Some(&"one"));
+/// ```
+pub struct AllocatorBackend<A: Allocator> {
+    alloc: A,
+    state: Mutex<ChunkState>,
+}
+
+/// An [AllocatorBackend]'s chunks and which of their slots are free
+struct ChunkState {
+    /// Chunks and their layouts, to deallocate them
+    chunks: Vec<(NonNull<u8>, Layout)>,
+    /// Next slot in the newest chunk which has never been allocated
+    next_unused: Option<NonNull<u8>>,
+    /// # of never-allocated slots from `next_unused` on
+    unused: usize,
+    /// Most recently freed slot, which stores a pointer to the slot freed before it
+    free_head: Option<NonNull<u8>>,
+}
+
+// SAFETY: the chunks are only accessed while the state is locked, or through slots handed out to
+// the store, which the allocator doesn't touch until they're freed.
+unsafe impl<A: Allocator + Send> Send for AllocatorBackend<A> {}
+
+// SAFETY: same as `Send`, and the allocator is only shared if it's `Sync`
+unsafe impl<A: Allocator + Sync> Sync for AllocatorBackend<A> {}
+
+impl<A: Allocator> AllocatorBackend<A> {
+    /// Allocates nodes from `alloc`.
+    #[inline]
+    pub fn new(alloc: A) -> Self {
+        Self {
+            alloc,
+            state: Mutex::new(ChunkState {
+                chunks: Vec::new(),
+                next_unused: None,
+                unused: 0,
+                free_head: None,
+            }),
+        }
+    }
+
+    /// The allocator nodes are allocated from.
+    #[inline]
+    pub fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    /// Slots are only pushed and popped while it's locked, so it can't be left inconsistent
+    #[inline]
+    fn state(&self) -> MutexGuard<'_, ChunkState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+// SAFETY: each slot is returned by `alloc` at most once until it's freed, and chunks aren't
+// deallocated until the backend (which the store owns) is dropped.
+unsafe impl<A: Allocator + Send + Sync> SlabBackend for AllocatorBackend<A> {
+    fn alloc(&self, layout: Layout) -> NonNull<u8> {
+        let mut state = self.state();
+        if let Some(slot) = state.free_head {
+            state.free_head = unsafe { slot.cast::<Option<NonNull<u8>>>().read() };
+            return slot;
+        }
+        if state.unused == 0 {
+            let chunk_layout = Layout::from_size_align(layout.size() * CHUNK_SLOTS, layout.align())
+                .unwrap_or_else(|_| handle_alloc_error(layout));
+            let chunk = self
+                .alloc
+                .allocate(chunk_layout)
+                .unwrap_or_else(|_| handle_alloc_error(chunk_layout))
+                .cast::<u8>();
+            state.chunks.push((chunk, chunk_layout));
+            state.next_unused = Some(chunk);
+            state.unused = CHUNK_SLOTS;
+        }
+        let slot = state.next_unused.unwrap();
+        state.unused -= 1;
+        state.next_unused =
+            Some(unsafe { NonNull::new_unchecked(slot.as_ptr().add(layout.size())) });
+        slot
+    }
+
+    unsafe fn free(&self, slot: NonNull<u8>, _layout: Layout) {
+        let mut state = self.state();
+        // Nodes hold pointers, so a slot has room (and alignment) for the next free slot
+        slot.cast::<Option<NonNull<u8>>>()
+            .as_ptr()
+            .write(state.free_head);
+        state.free_head = Some(slot);
+    }
+}
+
+impl<A: Allocator> Drop for AllocatorBackend<A> {
+    fn drop(&mut self) {
+        let state = self.state.get_mut().unwrap_or_else(PoisonError::into_inner);
+        for (chunk, layout) in state.chunks.drain(..) {
+            unsafe { self.alloc.deallocate(chunk, layout) }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::alloc::Layout;
+use std::cell::UnsafeCell;
+use std::mem::{align_of, size_of, MaybeUninit};
+use std::ptr::NonNull;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::node::Node;
+use crate::store::SlabBackend;
+use crate::utils::maybe_uninit_array;
+
+/// [SlabBackend] with a fixed pool of `N` nodes, stored inline, so trees in the store never
+/// allocate after it's built. `B` is the store's node capacity.
+///
+/// Use the `try_` operations (e.g. [BTreeMap::try_insert](crate::BTreeMap::try_insert)), which
+/// return [Error::StoreFull](crate::Error::StoreFull) when the pool may not have enough nodes left;
+/// other operations panic if the pool runs out partway through.
+///
+/// # Examples
+///
+/// ```
+/// use btree_plus_store::{BTreeMap, BTreeStoreBuilder, Error, FixedBackend};
+/// let store = BTreeStoreBuilder::new()
+///     .backend(FixedBackend::<u32, u32, 4>::new())
+///     .build();
+/// let mut map = BTreeMap::new_in(&store);
+/// let mut len = 0;
+/// while map.try_insert(len, len) != Err(Error::StoreFull) {
+///     len += 1;
+/// }
+/// assert_eq!(map.len(), len as usize);
+/// ```
+pub struct FixedBackend<K, V, const N: usize, const B: usize = 8> {
+    slots: UnsafeCell<[MaybeUninit<Node<K, V, B>>; N]>,
+    state: Mutex<FixedState>,
+}
+
+/// Which of a [FixedBackend]'s slots are free
+struct FixedState {
+    /// Slots from here on have never been allocated
+    next_unused: usize,
+    /// Most recently freed slot, which stores the index of the slot freed before it (or `N`)
+    free_head: usize,
+    available: usize,
+}
+
+// SAFETY: slots are only handed out and taken back while the state is locked, and each slot is
+// only accessed by the tree it's allocated to. Nodes are moved in and out from any thread which
+// has the store, so keys and values must be `Send`.
+unsafe impl<K: Send, V: Send, const N: usize, const B: usize> Send for FixedBackend<K, V, N, B> {}
+
+// SAFETY: same as `Send`
+unsafe impl<K: Send, V: Send, const N: usize, const B: usize> Sync for FixedBackend<K, V, N, B> {}
+
+impl<K, V, const N: usize, const B: usize> FixedBackend<K, V, N, B> {
+    /// Creates a pool of `N` free nodes.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            slots: UnsafeCell::new(maybe_uninit_array()),
+            state: Mutex::new(FixedState {
+                next_unused: 0,
+                free_head: N,
+                available: N,
+            }),
+        }
+    }
+
+    /// Slots are only pushed and popped while it's locked, so it can't be left inconsistent
+    #[inline]
+    fn state(&self) -> MutexGuard<'_, FixedState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    #[inline]
+    fn slot(&self, index: usize) -> NonNull<Node<K, V, B>> {
+        let first = self.slots.get() as *mut MaybeUninit<Node<K, V, B>>;
+        unsafe { NonNull::new_unchecked(first.add(index) as *mut Node<K, V, B>) }
+    }
+}
+
+impl<K, V, const N: usize, const B: usize> Default for FixedBackend<K, V, N, B> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// SAFETY: each slot is returned by `alloc` at most once until it's freed, and slots are inline, so
+// they don't move while the backend (which the store boxes) is alive.
+unsafe impl<K: Send + 'static, V: Send + 'static, const N: usize, const B: usize> SlabBackend
+    for FixedBackend<K, V, N, B>
+{
+    fn alloc(&self, layout: Layout) -> NonNull<u8> {
+        assert!(
+            layout.size() <= size_of::<Node<K, V, B>>()
+                && layout.align() <= align_of::<Node<K, V, B>>(),
+            "FixedBackend is for a store with different key or value types or node capacity"
+        );
+        let mut state = self.state();
+        let index = if state.free_head < N {
+            let index = state.free_head;
+            state.free_head = unsafe { self.slot(index).cast::<usize>().read() };
+            index
+        } else if state.next_unused < N {
+            state.next_unused += 1;
+            state.next_unused - 1
+        } else {
+            panic!("FixedBackend is full ({} nodes)", N)
+        };
+        state.available -= 1;
+        self.slot(index).cast()
+    }
+
+    unsafe fn free(&self, slot: NonNull<u8>, _layout: Layout) {
+        let mut state = self.state();
+        let index = slot.as_ptr().offset_from(self.slot(0).as_ptr().cast()) as usize
+            / size_of::<Node<K, V, B>>();
+        // Nodes hold pointers, so a slot has room (and alignment) for the index
+        slot.cast::<usize>().as_ptr().write(state.free_head);
+        state.free_head = index;
+        state.available += 1;
+    }
+
+    #[inline]
+    fn available(&self) -> Option<usize> {
+        Some(self.state().available)
+    }
+}
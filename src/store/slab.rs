@@ -0,0 +1,396 @@
+use std::collections::TryReserveError;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::mem::{size_of, ManuallyDrop, MaybeUninit};
+use std::ptr::NonNull;
+
+use crate::store::GrowthPolicy;
+use crate::utils::abort_on_unwind;
+
+/// A store's built-in slab: slots in chunks allocated with the global allocator, sized by its
+/// [GrowthPolicy]. Freed slots are reused (most recently freed first) before the slab grows, and
+/// chunks are only released when the slab is dropped or [compacted](Slab::compact).
+///
+/// Growing is fallible through [Slab::try_reserve], so a `try_` operation can allocate every chunk
+/// it may need before it mutates anything. Items are never dropped by the slab: it holds nodes,
+/// which have no drop glue (trees drop their keys and values).
+pub(crate) struct Slab<T> {
+    growth: GrowthPolicy,
+    chunks: Vec<Chunk<T>>,
+    /// Most recently freed slot, which holds a pointer to the slot freed before it
+    free_head: Option<NonNull<T>>,
+    /// # of slots in the free list
+    freed: usize,
+    /// Slots in the last chunk from here on have never been allocated
+    next_unused: usize,
+}
+
+/// A chunk of slots, allocated as a `Vec<MaybeUninit<T>>`'s buffer
+struct Chunk<T> {
+    slots: NonNull<T>,
+    len: usize,
+}
+
+// SAFETY: the slab owns its chunks, and items are only moved in and out through `&mut self`
+unsafe impl<T: Send> Send for Slab<T> {}
+
+/// Pointer to an item in a [Slab], which isn't tied to the slab's lifetime. Whoever has it must
+/// ensure the item is still allocated when they dereference it.
+pub struct UnsafeRef<T>(NonNull<T>);
+
+impl<T> Slab<T> {
+    #[inline]
+    pub fn new(growth: GrowthPolicy) -> Self {
+        Self {
+            growth,
+            chunks: Vec::new(),
+            free_head: None,
+            freed: 0,
+            next_unused: 0,
+        }
+    }
+
+    /// # of slots, allocated or free
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.len).sum()
+    }
+
+    /// # of slots which can be allocated without growing
+    #[inline]
+    pub fn available(&self) -> usize {
+        self.freed + self.unused()
+    }
+
+    /// Moves the item into a free slot, growing the slab if there are none. Aborts if the
+    /// allocator is out of memory, like `Vec`, and also if the chunk would be larger than
+    /// `isize::MAX` bytes, so allocating never panics.
+    #[inline]
+    pub fn alloc(&mut self, item: T) -> UnsafeRef<T> {
+        if self.available() == 0 {
+            let chunk = abort_on_unwind(|| Vec::with_capacity(self.next_chunk_len(1)));
+            self.add_chunk(chunk);
+        }
+        // SAFETY: the slab has a free slot, since it just grew if it didn't
+        unsafe { self.alloc_available(item).unwrap_unchecked() }
+    }
+
+    /// Moves the item into a free slot, or returns `None` without growing if there are none.
+    #[inline]
+    pub fn alloc_available(&mut self, item: T) -> Option<UnsafeRef<T>> {
+        let slot = match self.free_head {
+            Some(slot) => {
+                self.free_head = unsafe { slot.cast::<Option<NonNull<T>>>().read() };
+                self.freed -= 1;
+                slot
+            }
+            None if self.unused() > 0 => {
+                let chunk = self.chunks.last()?;
+                self.next_unused += 1;
+                unsafe { chunk.slot(self.next_unused - 1) }
+            }
+            None => return None,
+        };
+        unsafe { slot.as_ptr().write(item) };
+        Some(UnsafeRef(slot))
+    }
+
+    /// Frees the slot without dropping its item.
+    ///
+    /// # Safety
+    /// The slot was allocated in this slab and hasn't been freed.
+    #[inline]
+    pub unsafe fn discard(&mut self, slot: UnsafeRef<T>) {
+        // Nodes hold pointers, so a slot has room (and alignment) for the next free slot
+        slot.0.cast::<Option<NonNull<T>>>().write(self.free_head);
+        self.free_head = Some(slot.0);
+        self.freed += 1;
+    }
+
+    /// Moves the item out of its slot and frees the slot.
+    ///
+    /// # Safety
+    /// The slot was allocated in this slab and hasn't been freed.
+    #[cfg(not(feature = "sync-store"))]
+    #[inline]
+    pub unsafe fn take(&mut self, slot: UnsafeRef<T>) -> T {
+        let item = slot.0.as_ptr().read();
+        self.discard(slot);
+        item
+    }
+
+    /// Ensures at least `slots` more slots can be allocated without growing, panicking on
+    /// capacity overflow and aborting if the allocator is out of memory, like `Vec`.
+    #[inline]
+    pub fn reserve(&mut self, slots: usize) {
+        if let Some(missing) = slots.checked_sub(self.available()).filter(|&n| n > 0) {
+            self.add_chunk(Vec::with_capacity(self.next_chunk_len(missing)));
+        }
+    }
+
+    /// Ensures at least `slots` more slots can be allocated without growing, or returns an error
+    /// (without growing) if the chunk would be larger than `isize::MAX` bytes or the allocator is
+    /// out of memory.
+    #[inline]
+    pub fn try_reserve(&mut self, slots: usize) -> Result<(), TryReserveError> {
+        if let Some(missing) = slots.checked_sub(self.available()).filter(|&n| n > 0) {
+            let mut chunk = Vec::new();
+            chunk.try_reserve_exact(self.next_chunk_len(missing))?;
+            self.chunks.try_reserve(1)?;
+            self.add_chunk(chunk);
+        }
+        Ok(())
+    }
+
+    /// Frees every allocated slot for which `retain` returns `false`, without dropping its item.
+    ///
+    /// # Safety
+    /// Every allocated slot must hold an initialized item.
+    pub unsafe fn retain_shared(&mut self, mut retain: impl FnMut(&T) -> bool) {
+        let free = self.free_slots();
+        let mut released = Vec::new();
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            for slot in (0..self.used(index)).map(|idx| chunk.slot(idx)) {
+                if !free.contains(&slot) && !retain(slot.as_ref()) {
+                    released.push(slot);
+                }
+            }
+        }
+        for slot in released {
+            self.discard(UnsafeRef(slot));
+        }
+    }
+
+    /// Releases as many chunks as possible to the allocator, moving allocated items out of them
+    /// into free slots in the chunks which are kept. Returns where each moved item was moved to.
+    ///
+    /// The smallest chunks are kept, until they have room for every allocated item. A chunk with an
+    /// item which isn't `movable` is always kept, and its items aren't moved.
+    ///
+    /// # Safety
+    /// Moved items are copied bitwise, so they must not be borrowed, and anything which points to
+    /// them must be updated to point to where they were moved.
+    pub unsafe fn compact(
+        &mut self,
+        mut movable: impl FnMut(UnsafeRef<T>) -> bool,
+    ) -> HashMap<UnsafeRef<T>, UnsafeRef<T>> {
+        let free = self.free_slots();
+        // (# allocated, whether every allocated item is movable) per chunk
+        let chunks = (0..self.chunks.len())
+            .map(|index| {
+                let chunk = &self.chunks[index];
+                (0..self.used(index))
+                    .map(|idx| chunk.slot(idx))
+                    .filter(|slot| !free.contains(slot))
+                    .fold((0, true), |(allocated, all_movable), slot| {
+                        (allocated + 1, all_movable && movable(UnsafeRef(slot)))
+                    })
+            })
+            .collect::<Vec<_>>();
+        // Chunks with unmovable items first, then the smallest ones (so the kept chunks have
+        // little room to spare), the fullest of equal ones first (so fewer items are moved)
+        let mut order = (0..chunks.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&index| {
+            let (allocated, all_movable) = chunks[index];
+            (
+                all_movable,
+                self.chunks[index].len,
+                std::cmp::Reverse(allocated),
+            )
+        });
+        let allocated = chunks
+            .iter()
+            .map(|&(allocated, _)| allocated)
+            .sum::<usize>();
+        let mut kept = vec![false; chunks.len()];
+        let mut kept_len = 0;
+        for index in order {
+            if kept_len >= allocated && chunks[index].1 {
+                break;
+            }
+            kept[index] = true;
+            kept_len += self.chunks[index].len;
+        }
+
+        // Free slots in kept chunks, lowest first, including the unused tail of the last chunk
+        let mut vacant = Vec::new();
+        let mut moving = Vec::new();
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            let used = self.used(index);
+            for idx in 0..chunk.len {
+                let slot = chunk.slot(idx);
+                match (kept[index], idx < used && !free.contains(&slot)) {
+                    (true, false) => vacant.push(slot),
+                    (false, true) => moving.push(slot),
+                    _ => {}
+                }
+            }
+        }
+        let mut vacant = vacant.into_iter();
+        let mut moved = HashMap::with_capacity(moving.len());
+        for from in moving {
+            let to = vacant.next().expect("kept chunks have room for every item");
+            from.as_ptr().copy_to_nonoverlapping(to.as_ptr(), 1);
+            moved.insert(UnsafeRef(from), UnsafeRef(to));
+        }
+
+        let mut index = 0;
+        self.chunks.retain(|chunk| {
+            index += 1;
+            if !kept[index - 1] {
+                drop(Vec::<MaybeUninit<T>>::from_raw_parts(
+                    chunk.slots.as_ptr().cast(),
+                    0,
+                    chunk.len,
+                ));
+            }
+            kept[index - 1]
+        });
+        // Every remaining vacant slot is in the free list, so none are unused
+        self.next_unused = self.chunks.last().map_or(0, |chunk| chunk.len);
+        self.free_head = None;
+        self.freed = 0;
+        for slot in vacant.rev() {
+            self.discard(UnsafeRef(slot));
+        }
+        moved
+    }
+
+    /// Slots in the free list
+    unsafe fn free_slots(&self) -> HashSet<NonNull<T>> {
+        let mut free = HashSet::with_capacity(self.freed);
+        let mut next = self.free_head;
+        while let Some(slot) = next {
+            free.insert(slot);
+            next = slot.cast::<Option<NonNull<T>>>().read();
+        }
+        free
+    }
+
+    /// # of slots at the start of the chunk at `index` which were ever allocated
+    #[inline]
+    fn used(&self, index: usize) -> usize {
+        match index + 1 == self.chunks.len() {
+            false => self.chunks[index].len,
+            true => self.next_unused,
+        }
+    }
+
+    /// # of never-allocated slots at the end of the last chunk
+    #[inline]
+    fn unused(&self) -> usize {
+        self.chunks
+            .last()
+            .map_or(0, |chunk| chunk.len - self.next_unused)
+    }
+
+    /// Length of a new chunk with at least `min_len` slots
+    #[inline]
+    fn next_chunk_len(&self, min_len: usize) -> usize {
+        let last_len = self.chunks.last().map(|chunk| chunk.len);
+        self.growth
+            .chunk_len(size_of::<T>().max(1), last_len, min_len)
+    }
+
+    /// Adds the vector's buffer as the last chunk. The last chunk's unused slots are freed first,
+    /// so they're still available.
+    #[cold]
+    fn add_chunk(&mut self, slots: Vec<MaybeUninit<T>>) {
+        if let Some(&Chunk { slots, len }) = self.chunks.last() {
+            let last = Chunk { slots, len };
+            for idx in self.next_unused..len {
+                unsafe { self.discard(UnsafeRef(last.slot(idx))) };
+            }
+        }
+        let mut slots = ManuallyDrop::new(slots);
+        let chunk = Chunk {
+            slots: unsafe { NonNull::new_unchecked(slots.as_mut_ptr().cast()) },
+            len: slots.capacity(),
+        };
+        // `try_reserve` reserved room for the chunk, but this doesn't know that
+        abort_on_unwind(|| self.chunks.push(chunk));
+        self.next_unused = 0;
+    }
+}
+
+impl<T> Chunk<T> {
+    /// # Safety
+    /// `idx` is less than the chunk's length
+    #[inline]
+    unsafe fn slot(&self, idx: usize) -> NonNull<T> {
+        NonNull::new_unchecked(self.slots.as_ptr().add(idx))
+    }
+}
+
+impl<T> Drop for Slab<T> {
+    fn drop(&mut self) {
+        for chunk in &self.chunks {
+            drop(unsafe {
+                Vec::<MaybeUninit<T>>::from_raw_parts(chunk.slots.as_ptr().cast(), 0, chunk.len)
+            });
+        }
+    }
+}
+
+impl<T> UnsafeRef<T> {
+    #[inline]
+    pub fn from_ref(item: &T) -> Self {
+        Self(NonNull::from(item))
+    }
+
+    #[inline]
+    pub fn as_ptr(&self) -> NonNull<T> {
+        self.0
+    }
+
+    /// # Safety
+    /// The item is still allocated, and not mutably borrowed for `'a`.
+    #[inline]
+    pub unsafe fn as_ref<'a>(&self) -> &'a T {
+        &*self.0.as_ptr()
+    }
+
+    /// # Safety
+    /// The item is still allocated, and not otherwise borrowed for `'a`.
+    #[inline]
+    pub unsafe fn as_mut<'a>(&mut self) -> &'a mut T {
+        &mut *self.0.as_ptr()
+    }
+
+    #[inline]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T> Clone for UnsafeRef<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for UnsafeRef<T> {}
+
+impl<T> PartialEq for UnsafeRef<T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other)
+    }
+}
+
+impl<T> Eq for UnsafeRef<T> {}
+
+impl<T> Hash for UnsafeRef<T> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl<T> Debug for UnsafeRef<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("UnsafeRef").field(&self.0).finish()
+    }
+}
@@ -0,0 +1,121 @@
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+
+/// Key wrapper which orders floats by the IEEE 754 `totalOrder` predicate, so that `f32` and `f64`
+/// can be used as keys in [BTreeMap](crate::BTreeMap) and [BTreeSet](crate::BTreeSet).
+///
+/// The order is the same as [f64::total_cmp]: `-NaN < -∞ < ... < -0.0 < +0.0 < ... < +∞ < +NaN`.
+/// Notably, `-0.0` and `+0.0` are different keys, and every NaN payload is a different key.
+///
+/// # Examples
+///
+/// ```
+/// use btree_plus_store::{BTreeMap, BTreeStore, TotalOrd};
+/// let store = BTreeStore::new();
+/// let mut map = BTreeMap::new_in(&store);
+/// map.insert(TotalOrd(1.5), "a");
+/// map.insert(TotalOrd(-2.0), "b");
+/// map.insert(TotalOrd(f64::NAN), "c");
+/// assert_eq!(
+///     map.try_range(TotalOrd(-3.0)..TotalOrd(2.0))
+///         .unwrap()
+///         .map(|(_, v)| *v)
+///         .collect::<Vec<_>>(),
+///     ["b", "a"]
+/// );
+/// ```
+#[derive(Clone, Copy, Default)]
+#[repr(transparent)]
+pub struct TotalOrd<F>(pub F);
+
+/// Floating-point types which can be wrapped in [TotalOrd].
+pub trait TotalOrdFloat: Copy {
+    /// Compares by the IEEE 754 `totalOrder` predicate
+    fn total_cmp(&self, other: &Self) -> Ordering;
+    /// Hashes the raw bits, which is consistent with [TotalOrdFloat::total_cmp]
+    fn hash_bits<H: Hasher>(&self, state: &mut H);
+}
+
+impl TotalOrdFloat for f32 {
+    #[inline]
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        f32::total_cmp(self, other)
+    }
+
+    #[inline]
+    fn hash_bits<H: Hasher>(&self, state: &mut H) {
+        self.to_bits().hash(state)
+    }
+}
+
+impl TotalOrdFloat for f64 {
+    #[inline]
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        f64::total_cmp(self, other)
+    }
+
+    #[inline]
+    fn hash_bits<H: Hasher>(&self, state: &mut H) {
+        self.to_bits().hash(state)
+    }
+}
+
+impl<F> TotalOrd<F> {
+    /// Returns the wrapped float
+    #[inline]
+    pub fn into_inner(self) -> F {
+        self.0
+    }
+}
+
+impl<F> From<F> for TotalOrd<F> {
+    #[inline]
+    fn from(value: F) -> Self {
+        Self(value)
+    }
+}
+
+impl<F: TotalOrdFloat> PartialEq for TotalOrd<F> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0.total_cmp(&other.0) == Ordering::Equal
+    }
+}
+
+impl<F: TotalOrdFloat> Eq for TotalOrd<F> {}
+
+impl<F: TotalOrdFloat> PartialOrd for TotalOrd<F> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<F: TotalOrdFloat> Ord for TotalOrd<F> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl<F: TotalOrdFloat> Hash for TotalOrd<F> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash_bits(state)
+    }
+}
+
+impl<F: Debug> Debug for TotalOrd<F> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<F: Display> Display for TotalOrd<F> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
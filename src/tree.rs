@@ -0,0 +1,39 @@
+/// Generic trait for different b-tree maps and sets, which returns reachable nodes, e.g. to check a
+/// store for leaked nodes with [BTreeStore::leak_check](crate::BTreeStore::leak_check).
+///
+/// This trait is [sealed](https://rust-lang.github.io/api-guidelines/future-proofing.html#sealed-traits-protect-against-downstream-implementations-c-sealed)
+pub trait BTree<'store, K, V, const B: usize = 8>: sealed::BTree<'store, K, V, B> {}
+
+impl<'store, K, V, const B: usize> BTree<'store, K, V, B> for crate::BTreeMap<'store, K, V, B> {}
+
+#[cfg(feature = "set")]
+impl<'store, T, const B: usize> BTree<'store, T, (), B> for crate::BTreeSet<'store, T, B> {}
+
+#[cfg(feature = "copyable")]
+impl<'store, K, V, const B: usize> BTree<'store, K, V, B>
+    for crate::copyable::BTreeMap<'store, K, V, B>
+{
+}
+
+#[cfg(all(feature = "copyable", feature = "set"))]
+impl<'store, T, const B: usize> BTree<'store, T, (), B>
+    for crate::copyable::BTreeSet<'store, T, B>
+{
+}
+
+pub(crate) mod sealed {
+    use crate::node_iter::NodeIter;
+    use crate::BTreeStore;
+
+    #[doc(hidden)]
+    pub trait BTree<'store, K, V, const B: usize> {
+        fn is_from_store(&self, store: &BTreeStore<K, V, B>) -> bool;
+        fn node_ptrs(&self) -> NodeIter<'store, K, V, B>;
+        /// The map whose node pointers are fixed up when the store moves its nodes, if the tree
+        /// owns its nodes
+        #[inline]
+        fn as_map_mut(&mut self) -> Option<&mut crate::BTreeMap<'store, K, V, B>> {
+            None
+        }
+    }
+}
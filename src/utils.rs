@@ -1,5 +1,5 @@
 pub use ptr_eq::*;
-use std::mem::MaybeUninit;
+use std::mem::{ManuallyDrop, MaybeUninit};
 
 mod ptr_eq;
 
@@ -8,3 +8,63 @@ mod ptr_eq;
 pub fn maybe_uninit_array<T, const N: usize>() -> [MaybeUninit<T>; N] {
     unsafe { MaybeUninit::uninit().assume_init() }
 }
+
+/// Calls `f`, aborting the process if it panics, so the caller doesn't unwind. For user code
+/// called while a tree is partway through restructuring, which an unwinding panic would leave
+/// inconsistent.
+#[inline]
+pub fn abort_on_unwind<F: FnOnce() -> R, R>(f: F) -> R {
+    /// `extern "C"` functions abort instead of unwinding, so they're known not to unwind and calls
+    /// to them need no landing pad. Not inlined, since that would bring `f`'s landing pad along.
+    #[inline(never)]
+    extern "C" fn call<F: FnOnce() -> R, R>(f: *mut (), result: *mut ()) {
+        // SAFETY: `f` points to an `F` which is moved out here, and `result` to room for an `R`
+        unsafe {
+            let f = f.cast::<F>().read();
+            result.cast::<R>().write(f());
+        }
+    }
+
+    let mut f = ManuallyDrop::new(f);
+    let mut result = MaybeUninit::<R>::uninit();
+    call::<F, R>((&mut *f as *mut F).cast(), result.as_mut_ptr().cast());
+    // SAFETY: `call` returned, so it wrote the result
+    unsafe { result.assume_init() }
+}
+
+/// Adapts an [std::io::Write] into a [std::fmt::Write], keeping the io error (which
+/// [std::fmt::Error] can't carry).
+pub struct IoFmtWriter<W> {
+    inner: W,
+    error: std::io::Result<()>,
+}
+
+impl<W: std::io::Write> IoFmtWriter<W> {
+    #[inline]
+    pub fn new(inner: W) -> Self {
+        IoFmtWriter {
+            inner,
+            error: Ok(()),
+        }
+    }
+
+    /// Converts the result of formatting into `self` into an io result.
+    #[inline]
+    pub fn finish(self, result: std::fmt::Result) -> std::io::Result<()> {
+        match (result, self.error) {
+            (_, Err(err)) => Err(err),
+            (Err(std::fmt::Error), Ok(())) => Err(std::io::Error::other("formatter error")),
+            (Ok(()), Ok(())) => Ok(()),
+        }
+    }
+}
+
+impl<W: std::io::Write> std::fmt::Write for IoFmtWriter<W> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|err| {
+            self.error = Err(err);
+            std::fmt::Error
+        })
+    }
+}
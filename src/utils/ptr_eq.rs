@@ -1,4 +1,4 @@
-use rustc_arena_modified::slab_arena::UnsafeRef;
+use crate::store::UnsafeRef;
 
 pub trait PtrEq {
     /// Whether both pointers point to the same item
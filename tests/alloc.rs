@@ -0,0 +1,137 @@
+#[cfg(feature = "set")]
+use btree_plus_store::BTreeSet;
+use btree_plus_store::{BTreeMap, BTreeStore};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::ops::Bound;
+
+/// Counts allocations on the current thread, so other tests running in parallel don't interfere
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCS: Cell<usize> = const { Cell::new(0) };
+    static LARGEST: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCS.try_with(|allocs| allocs.set(allocs.get() + 1));
+        let _ = LARGEST.try_with(|largest| largest.set(largest.get().max(layout.size())));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn allocs_during(f: impl FnOnce()) -> usize {
+    let before = ALLOCS.with(Cell::get);
+    f();
+    ALLOCS.with(Cell::get) - before
+}
+
+/// Bytes of the largest allocation during `f`, or 0 if it didn't allocate
+#[cfg(not(any(feature = "sync-store", feature = "generations")))]
+fn largest_alloc_during(f: impl FnOnce()) -> usize {
+    LARGEST.with(|largest| largest.set(0));
+    f();
+    LARGEST.with(Cell::get)
+}
+
+#[test]
+fn iterators_and_cursors_dont_allocate() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..1000 {
+        map.insert(i, i);
+    }
+    #[cfg(feature = "set")]
+    let set_store = BTreeStore::new();
+    #[cfg(feature = "set")]
+    let mut set = BTreeSet::new_in(&set_store);
+    #[cfg(feature = "set")]
+    for i in 0..1000 {
+        set.insert(i);
+    }
+
+    let allocs = allocs_during(|| {
+        let mut sum = 0;
+        for start in 0..1000 {
+            sum += map
+                .try_range(start..start + 10)
+                .unwrap()
+                .map(|(_, v)| *v)
+                .sum::<usize>();
+            sum += map.try_range(..=start).unwrap().rev().take(3).count();
+            #[cfg(feature = "set")]
+            {
+                sum += set.try_range(start..).unwrap().next().copied().unwrap_or(0);
+            }
+        }
+        sum += map.iter().count() + map.keys().count() + map.values().rev().count();
+        for (_, v) in map.iter_mut() {
+            *v += 1;
+        }
+        for (_, v) in map.try_range_mut(100..200).unwrap() {
+            *v -= 1;
+        }
+
+        let mut cursor = map.lower_bound(Bound::Included(&500));
+        while let Some((_, v)) = cursor.next() {
+            sum += *v;
+        }
+        while cursor.prev().is_some() {}
+        sum += cursor.peek_next().map_or(0, |(_, v)| *v);
+        let mut cursor = map.upper_bound_mut(Bound::Excluded(&500));
+        for _ in 0..100 {
+            let (_, v) = cursor.prev().unwrap();
+            *v += 1;
+        }
+        while let Some((_, v)) = cursor.next() {
+            *v -= 1;
+        }
+        sum += cursor.as_cursor().peek_prev().map_or(0, |(_, v)| *v);
+        assert!(sum > 0);
+    });
+    assert_eq!(allocs, 0);
+}
+
+// Under `sync-store`, threads take free slots in batches, which may span several chunks, and
+// `generations` allocate their own table alongside nodes
+#[cfg(not(any(feature = "sync-store", feature = "generations")))]
+#[test]
+fn slab_grows_by_growth_policy() {
+    use btree_plus_store::{BTreeStoreBuilder, GrowthPolicy};
+
+    const NODE_SIZE: usize = BTreeStore::<u32, u32>::NODE_SIZE;
+    let growth = GrowthPolicy::new()
+        .initial_chunk(10 * NODE_SIZE)
+        .factor(3)
+        .max_chunk(50 * NODE_SIZE);
+    let store = BTreeStoreBuilder::new().growth_policy(growth).build();
+    let mut map = BTreeMap::new_in(&store);
+    let mut chunks = Vec::new();
+    for i in 0..2000u32 {
+        let largest = largest_alloc_during(|| {
+            map.insert(i, i);
+        });
+        if largest >= NODE_SIZE {
+            chunks.push(largest / NODE_SIZE);
+        }
+    }
+    assert_eq!(chunks[..3], [10, 30, 50]);
+    assert!(chunks[3..].iter().all(|&len| len == 50));
+
+    // A reservation gets a chunk large enough for it
+    let largest = largest_alloc_during(|| {
+        BTreeStoreBuilder::new()
+            .growth_policy(growth)
+            .capacity(1000)
+            .build::<u32, u32>();
+    });
+    assert_eq!(largest / NODE_SIZE, 1000);
+}
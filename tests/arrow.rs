@@ -0,0 +1,32 @@
+#![cfg(feature = "arrow")]
+
+use arrow_array::types::{Float64Type, UInt64Type};
+use arrow_array::PrimitiveArray;
+use btree_plus_store::{BTreeMap, BTreeStore};
+
+#[test]
+fn round_trip() {
+    let keys = PrimitiveArray::<UInt64Type>::from_iter_values((0..1000).map(|i| i * 2));
+    let values = PrimitiveArray::<Float64Type>::from_iter_values((0..1000).map(|i| i as f64));
+
+    let store = BTreeStore::new();
+    let map = BTreeMap::from_arrow_in(&store, &keys, &values);
+    map.validate();
+    assert_eq!(map.len(), 1000);
+    assert_eq!(map.get(&84), Some(&42.0));
+    assert_eq!(map.get(&85), None);
+
+    let (keys2, values2) = map.to_arrow::<UInt64Type, Float64Type>();
+    assert_eq!(keys, keys2);
+    assert_eq!(values, values2);
+}
+
+#[test]
+#[should_panic]
+fn unsorted() {
+    let keys = PrimitiveArray::<UInt64Type>::from_iter_values([1, 3, 2]);
+    let values = PrimitiveArray::<UInt64Type>::from_iter_values([1, 2, 3]);
+
+    let store = BTreeStore::new();
+    BTreeMap::from_arrow_in(&store, &keys, &values);
+}
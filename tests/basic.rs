@@ -1,5 +1,9 @@
-use btree_plus_store::{BTreeMap, BTreeStore};
-use rand::{rngs::SmallRng, seq::SliceRandom, SeedableRng};
+use btree_plus_store::map::BatchOp;
+#[cfg(feature = "set")]
+use btree_plus_store::BTreeSet;
+use btree_plus_store::{BTreeMap, BTreeStore, Error};
+use rand::{rngs::SmallRng, seq::SliceRandom, Rng, SeedableRng};
+use std::ops::Bound;
 
 const SEED: &'static [u8; 32] = b"testseedtestseedtestseedtestseed";
 
@@ -42,6 +46,110 @@ pub fn remove() {
     assert!(btree.is_empty())
 }
 
+#[test]
+pub fn pop_first_last() {
+    let store = BTreeStore::new();
+    let mut btree = BTreeMap::new_in(&store);
+
+    let mut items = ITEMS;
+    for (key, value) in &items {
+        btree.insert(*key, *value);
+    }
+    items.sort();
+
+    for i in 0..50 {
+        assert_eq!(btree.first_key_value(), Some((&items[i].0, &items[i].1)));
+        assert_eq!(btree.pop_first(), Some(items[i]));
+        btree.validate();
+        assert_eq!(
+            btree.last_key_value(),
+            Some((&items[99 - i].0, &items[99 - i].1))
+        );
+        assert_eq!(btree.pop_last(), Some(items[99 - i]));
+        btree.validate();
+    }
+    assert!(btree.is_empty());
+    assert_eq!(btree.pop_first(), None);
+
+    for (key, value) in &ITEMS {
+        btree.insert(*key, *value);
+    }
+    btree.clear();
+    btree.validate();
+    btree.insert(1, 1);
+    btree.validate();
+    assert_eq!(btree.first_key_value(), Some((&1, &1)));
+    assert_eq!(btree.last_key_value(), Some((&1, &1)));
+}
+
+#[test]
+pub fn clustered_access() {
+    let store = BTreeStore::new();
+    let mut btree = BTreeMap::new_in(&store);
+
+    for i in 0..1000 {
+        btree.insert(i * 2, i);
+    }
+    // Jump between a few clusters, so searches start from a finger which is sometimes near and
+    // sometimes far away
+    for cluster in [500, 100, 1990, 960, 0, 1200] {
+        for key in cluster..(cluster + 20).min(2000) {
+            if key % 2 == 0 {
+                assert_eq!(btree.get(&key), Some(&(key / 2)));
+                *btree.get_mut(&key).unwrap() += 1;
+                assert_eq!(btree.get(&key), Some(&(key / 2 + 1)));
+            } else {
+                assert_eq!(btree.get(&key), None);
+                btree.insert(key, 0);
+                assert_eq!(btree.remove(&key), Some(0));
+            }
+            btree.validate();
+        }
+    }
+    for i in (0..1000).rev() {
+        assert!(btree.remove(&(i * 2)).is_some());
+        btree.validate();
+    }
+    assert!(btree.is_empty());
+}
+
+#[test]
+pub fn sorted_runs() {
+    let store = BTreeStore::new();
+    let mut btree = BTreeMap::new_in(&store);
+    let mut expected = std::collections::BTreeMap::new();
+
+    // Ascending runs append to the last leaf, interleaved with keys before it
+    for run in 0..20 {
+        btree.extend((run * 100..run * 100 + 90).map(|i| (i, run)));
+        expected.extend((run * 100..run * 100 + 90).map(|i| (i, run)));
+        for i in (0..run * 100).step_by(37) {
+            assert_eq!(btree.insert(i, -1), expected.insert(i, -1));
+        }
+        // Equal to the maximum, so it replaces rather than appends
+        let max = run * 100 + 89;
+        assert_eq!(btree.insert(max, -2), expected.insert(max, -2));
+        btree.validate();
+    }
+    assert!(btree.iter().eq(expected.iter()));
+}
+
+#[test]
+pub fn clear_then_insert() {
+    let store = BTreeStore::new();
+    let mut btree = BTreeMap::new_in(&store);
+    for i in 0..1000 {
+        btree.insert(i, i);
+    }
+    btree.clear();
+    btree.validate();
+    for i in 0..100 {
+        btree.insert(i, i);
+    }
+    btree.validate();
+    assert!(btree.keys().copied().eq(0..100));
+}
+
 #[test]
 pub fn update() {
     let store = BTreeStore::new();
@@ -112,6 +220,673 @@ pub fn extend() {
     }
 }
 
+#[test]
+pub fn optimize() {
+    let store = BTreeStore::new();
+    let mut btree = BTreeMap::new_in(&store);
+
+    for i in 0..1000 {
+        btree.insert(i, i);
+    }
+    for i in 0..1000 {
+        if i % 2 == 0 {
+            btree.remove(&i);
+        }
+    }
+    let num_nodes = btree.nodes().count();
+
+    btree.optimize();
+    btree.validate();
+    println!("{:?}", btree);
+
+    assert!(btree.nodes().count() < num_nodes);
+    assert_eq!(btree.len(), 500);
+    assert!(btree
+        .iter()
+        .map(|(k, _)| *k)
+        .eq((0..1000).filter(|i| i % 2 == 1)));
+}
+
+#[test]
+pub fn map_values() {
+    let store = BTreeStore::new();
+    let mut btree = BTreeMap::new_in(&store);
+
+    for (key, value) in &ITEMS {
+        btree.insert(*key, *value);
+    }
+
+    let mapped_store = BTreeStore::new();
+    let mapped = btree.map_values(|value| value.to_string(), &mapped_store);
+    mapped.validate();
+    println!("{:?}", mapped);
+
+    assert_eq!(mapped.len(), 100);
+    for (key, value) in &ITEMS {
+        assert_eq!(mapped.get(key), Some(&value.to_string()));
+    }
+}
+
+#[test]
+pub fn apply_batch() {
+    let store = BTreeStore::new();
+    let mut btree = BTreeMap::new_in(&store);
+    for i in 0..100 {
+        btree.insert(i, i);
+    }
+
+    // Inserts and removes enough to split and merge nodes before failing
+    let failing = (100..150)
+        .map(|i| BatchOp::Insert(i, i))
+        .chain((0..50).map(BatchOp::Remove))
+        .chain([BatchOp::Update(60, 0), BatchOp::Update(1000, 0)]);
+    assert_eq!(
+        btree.apply_batch(failing),
+        Err(Error::BatchRejected { index: 101 })
+    );
+    btree.validate();
+    assert!(btree
+        .iter()
+        .map(|(k, v)| (*k, *v))
+        .eq((0..100).map(|i| (i, i))));
+
+    let rejected = [
+        BatchOp::Remove(1),
+        BatchOp::Update(2, 20),
+        BatchOp::Insert(3, 30),
+    ];
+    assert_eq!(
+        btree.apply_batch_if(rejected, |_, val| val != Some(&3)),
+        Err(Error::BatchRejected { index: 2 })
+    );
+    assert_eq!(btree.get(&1), Some(&1));
+    assert_eq!(btree.get(&2), Some(&2));
+
+    let ok = [
+        BatchOp::Remove(1),
+        BatchOp::Update(2, 20),
+        BatchOp::Insert(100, 100),
+    ];
+    assert_eq!(btree.apply_batch(ok), Ok(()));
+    btree.validate();
+    assert_eq!(btree.len(), 100);
+    assert_eq!(btree.get(&1), None);
+    assert_eq!(btree.get(&2), Some(&20));
+    assert_eq!(btree.get(&100), Some(&100));
+}
+
+#[test]
+pub fn checkpoint() {
+    let store = BTreeStore::new();
+    let mut btree = BTreeMap::new_in(&store);
+    for i in 0..100 {
+        btree.insert(i, i);
+    }
+
+    // Enough edits to split and merge nodes
+    let mut checkpoint = btree.checkpoint();
+    for i in 100..150 {
+        assert_eq!(checkpoint.insert(i, i), None);
+    }
+    for i in 0..50 {
+        assert_eq!(checkpoint.remove(&i), Some(i));
+    }
+    assert_eq!(checkpoint.insert(60, 0), Some(60));
+    assert_eq!(checkpoint.remove(&60), Some(0));
+    assert_eq!(checkpoint.len(), 99);
+    drop(checkpoint);
+    btree.validate();
+    assert!(btree
+        .iter()
+        .map(|(k, v)| (*k, *v))
+        .eq((0..100).map(|i| (i, i))));
+
+    let mut checkpoint = btree.checkpoint();
+    checkpoint.remove(&1);
+    checkpoint.insert(2, 20);
+    checkpoint.insert(100, 100);
+    checkpoint.commit();
+    btree.validate();
+    assert_eq!(btree.len(), 100);
+    assert_eq!(btree.get(&1), None);
+    assert_eq!(btree.get(&2), Some(&20));
+    assert_eq!(btree.get(&100), Some(&100));
+}
+
+#[test]
+pub fn transfer_range() {
+    let mut rng = SmallRng::from_seed(*SEED);
+    for len in [0, 1, 7, 30, 100, 1000] {
+        for dest_keys in [0..0, 0..len / 2, 5000..5500, 0..5000] {
+            let store = BTreeStore::new();
+            let mut src = BTreeMap::new_in(&store);
+            let mut dest = BTreeMap::new_in(&store);
+            let mut src_model = std::collections::BTreeMap::new();
+            let mut dest_model = std::collections::BTreeMap::new();
+            for i in 0..len {
+                let key = i * 5 + 1000;
+                src.insert(key, i);
+                src_model.insert(key, i);
+            }
+            for key in dest_keys.step_by(3) {
+                dest.insert(key, key);
+                dest_model.insert(key, key);
+            }
+
+            let start = rng.gen_range(0..len * 5 + 2000);
+            let end = rng.gen_range(start..len * 5 + 2001);
+            assert_eq!(src.try_transfer_range(start..end, &mut dest), Ok(()));
+            src.validate();
+            dest.validate();
+
+            let moved = src_model
+                .range(start..end)
+                .map(|(k, v)| (*k, *v))
+                .collect::<Vec<_>>();
+            for (key, val) in moved {
+                src_model.remove(&key);
+                dest_model.insert(key, val);
+            }
+            assert!(src.iter().eq(src_model.iter()));
+            assert!(dest.iter().eq(dest_model.iter()));
+        }
+    }
+
+    let (store, other_store) = (BTreeStore::new(), BTreeStore::new());
+    let mut src = BTreeMap::new_in(&store);
+    let mut dest = BTreeMap::new_in(&other_store);
+    src.insert(1, 1);
+    assert_eq!(
+        src.try_transfer_range(.., &mut dest),
+        Err(Error::WrongStore)
+    );
+}
+
+#[test]
+pub fn first_last_entry() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    assert!(map.first_entry().is_none());
+    assert!(map.last_entry().is_none());
+    for i in 0..100 {
+        map.insert(i, i * 10);
+    }
+
+    let mut first = map.first_entry().unwrap();
+    assert_eq!((first.key(), first.get()), (&0, &0));
+    *first.get_mut() += 1;
+    assert_eq!(first.insert(5), 1);
+    assert_eq!(map.first_key_value(), Some((&0, &5)));
+
+    let last = map.last_entry().unwrap();
+    assert_eq!(
+        format!("{:?}", last),
+        "OccupiedEntry { key: 99, value: 990 }"
+    );
+    *last.into_mut() = 0;
+    assert_eq!(map.last_key_value(), Some((&99, &0)));
+
+    // Conditionally pop based on the value
+    let mut popped = Vec::new();
+    while let Some(entry) = map.last_entry() {
+        if *entry.key() < 50 {
+            break;
+        }
+        popped.push(entry.remove_entry());
+        map.validate();
+    }
+    assert_eq!(popped.len(), 50);
+    assert_eq!(popped[0], (99, 0));
+    assert_eq!(map.len(), 50);
+    while map.first_entry().is_some_and(|entry| *entry.get() < 30) {
+        map.first_entry().unwrap().remove();
+    }
+    map.validate();
+    assert_eq!(map.first_key_value(), Some((&3, &30)));
+    assert!(map.keys().copied().eq(3..50));
+}
+
+#[test]
+pub fn drain() {
+    use btree_plus_store::BTreeStoreBuilder;
+    use std::sync::atomic::{AtomicIsize, Ordering};
+    use std::sync::Arc;
+
+    let mut rng = SmallRng::from_seed(*SEED);
+    let live_nodes = Arc::new(AtomicIsize::new(0));
+    let store = BTreeStoreBuilder::new()
+        .on_alloc({
+            let live_nodes = live_nodes.clone();
+            move || {
+                live_nodes.fetch_add(1, Ordering::Relaxed);
+            }
+        })
+        .on_dealloc({
+            let live_nodes = live_nodes.clone();
+            move || {
+                live_nodes.fetch_sub(1, Ordering::Relaxed);
+            }
+        })
+        .build();
+    let mut map = BTreeMap::new_in(&store);
+    let mut std_map = std::collections::BTreeMap::new();
+    for i in 0..2000 {
+        map.insert(i, i * 2);
+        std_map.insert(i, i * 2);
+    }
+
+    for _ in 0..20 {
+        let start = rng.gen_range(-10..2010);
+        let end = rng.gen_range(start..start + 300);
+        let expected = std_map
+            .range(start..end)
+            .map(|(k, v)| (*k, *v))
+            .collect::<Vec<_>>();
+        std_map.retain(|k, _| !(start..end).contains(k));
+
+        let mut drain = map.try_drain(start..end).unwrap();
+        // The entries are already removed, even if the iterator is dropped early
+        assert_eq!(drain.len(), expected.len());
+        map.validate();
+        assert!(map.iter().eq(std_map.iter()));
+        let take = rng.gen_range(0..expected.len() + 1);
+        assert!(drain
+            .by_ref()
+            .take(take)
+            .eq(expected[..take].iter().copied()));
+        drop(drain);
+        assert_eq!(
+            live_nodes.load(Ordering::Relaxed),
+            map.nodes().count() as isize
+        );
+    }
+
+    assert!(map.try_drain(..).unwrap().eq(std_map.into_iter()));
+    assert!(map.is_empty());
+    assert_eq!(live_nodes.load(Ordering::Relaxed), 0);
+    assert_eq!(
+        map.try_drain((Bound::Included(2), Bound::Included(1)))
+            .err(),
+        Some(Error::InvalidRange)
+    );
+}
+
+#[test]
+pub fn split_off() {
+    for len in [0, 1, 7, 8, 9, 30, 100, 1000] {
+        for key in [-1, 0, 1, len / 2, len - 1, len, len * 2 - 2, len * 2 + 1] {
+            let store = BTreeStore::new();
+            let mut map = BTreeMap::new_in(&store);
+            let mut std_map = std::collections::BTreeMap::new();
+            for i in 0..len {
+                map.insert(i * 2, i);
+                std_map.insert(i * 2, i);
+            }
+
+            let split = map.split_off(&key);
+            let std_split = std_map.split_off(&key);
+            map.validate();
+            split.validate();
+            assert!(map.iter().eq(std_map.iter()), "len {}, key {}", len, key);
+            assert!(
+                split.iter().eq(std_split.iter()),
+                "len {}, key {}",
+                len,
+                key
+            );
+            assert_eq!(map.len(), std_map.len());
+            assert_eq!(split.len(), std_split.len());
+            assert_eq!(map.last_key_value(), std_map.last_key_value());
+            assert_eq!(split.first_key_value(), std_split.first_key_value());
+
+            // Both halves stay usable in the same store
+            map.insert(key, -1);
+            map.validate();
+            drop(split);
+            map.validate();
+        }
+    }
+
+    #[cfg(feature = "set")]
+    {
+        let store = BTreeStore::new();
+        let mut set = BTreeSet::new_in(&store);
+        set.extend(0..100);
+        let split = set.split_off(&40);
+        assert!(set.iter().copied().eq(0..40));
+        assert!(split.iter().copied().eq(40..100));
+    }
+}
+
+#[test]
+#[allow(clippy::reversed_empty_ranges)]
+pub fn remove_range() {
+    let mut rng = SmallRng::from_seed(*SEED);
+    for len in [0, 1, 7, 30, 100, 1000, 5000] {
+        let store = BTreeStore::new();
+        let mut map = BTreeMap::new_in(&store);
+        let mut std_map = std::collections::BTreeMap::new();
+        for i in 0..len {
+            map.insert(i * 2, i);
+            std_map.insert(i * 2, i);
+        }
+        // Expire windows from the front, like time-windowed data, and cut random ranges
+        for step in 0..20 {
+            let (start, end) = if step % 2 == 0 {
+                (i32::MIN, step * len / 10)
+            } else {
+                let start = rng.gen_range(-10..len * 2 + 10);
+                (start, start + rng.gen_range(0..len / 2 + 2))
+            };
+            let expected = std_map.range(start..end).count();
+            std_map.retain(|k, _| !(start..end).contains(k));
+            assert_eq!(map.try_remove_range(start..end), Ok(expected));
+            map.validate();
+            assert!(map.iter().eq(std_map.iter()), "len {len}, {start}..{end}");
+        }
+        assert_eq!(store.stats().live_nodes, map.nodes().count());
+    }
+
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::from_iter_in([(1, 1), (2, 2)], &store);
+    assert_eq!(map.try_remove_range(2..1), Err(Error::InvalidRange));
+    assert_eq!(map.try_remove_range(..=1), Ok(1));
+    assert_eq!(map.try_remove_range(..), Ok(1));
+    assert!(map.is_empty());
+
+    #[cfg(feature = "set")]
+    {
+        let store = BTreeStore::new();
+        let mut set = BTreeSet::from_iter_in(0..100, &store);
+        assert_eq!(set.try_remove_range(10..90), Ok(80));
+        assert!(set.iter().copied().eq((0..10).chain(90..100)));
+    }
+}
+
+#[test]
+pub fn concat() {
+    for left_len in [0, 1, 7, 8, 9, 100, 1000] {
+        for right_len in [0, 1, 8, 30, 1000, 5000] {
+            let store = BTreeStore::new();
+            let mut map = BTreeMap::from_iter_in((0..left_len).map(|i| (i, i)), &store);
+            let mut right =
+                BTreeMap::from_iter_in((0..right_len).map(|i| (left_len + i, i)), &store);
+            assert_eq!(map.try_concat(&mut right), Ok(()));
+            map.validate();
+            assert_eq!(map.len(), (left_len + right_len) as usize);
+            assert!(map.keys().copied().eq(0..left_len + right_len));
+        }
+    }
+
+    let store = BTreeStore::new();
+    // Declared before `left`, which may only borrow stores that outlive it
+    let other_store = BTreeStore::new();
+    let mut left = BTreeMap::from_iter_in((0..100).map(|i| (i, i)), &store);
+    let mut overlapping = BTreeMap::from_iter_in((99..200).map(|i| (i, i)), &store);
+    assert_eq!(left.try_concat(&mut overlapping), Err(Error::UnorderedKey));
+    assert_eq!((left.len(), overlapping.len()), (100, 101));
+    let mut other = BTreeMap::from_iter_in([(200, 200)], &other_store);
+    assert_eq!(left.try_concat(&mut other), Err(Error::WrongStore));
+    overlapping.remove(&99);
+    assert_eq!(left.try_concat(&mut overlapping), Ok(()));
+    left.validate();
+    assert!(overlapping.is_empty());
+    assert!(left.keys().copied().eq(0..200));
+
+    #[cfg(feature = "set")]
+    {
+        let set_store = BTreeStore::new();
+        let mut low = BTreeSet::from_iter_in(0..40, &set_store);
+        let mut high = BTreeSet::from_iter_in(40..100, &set_store);
+        assert_eq!(low.try_concat(&mut high), Ok(()));
+        assert!(low.iter().copied().eq(0..100));
+    }
+}
+
+#[test]
+pub fn split_by_pivots() {
+    let mut rng = SmallRng::from_seed(*SEED);
+    for len in [0, 1, 7, 30, 100, 1000, 5000] {
+        for num_pivots in [0, 1, 3, 20, 100] {
+            let store = BTreeStore::new();
+            let mut map = BTreeMap::new_in(&store);
+            for i in 0..len {
+                map.insert(i * 2, i);
+            }
+            let pivots = (0..num_pivots)
+                .map(|_| rng.gen_range(-10..len * 2 + 10))
+                .collect::<Vec<_>>();
+            let mut sorted = pivots.clone();
+            sorted.sort();
+
+            let pieces = map.split_by_pivots(&pivots);
+            assert_eq!(pieces.len(), num_pivots + 1);
+            for (i, piece) in pieces.iter().enumerate() {
+                piece.validate();
+                let start = i.checked_sub(1).map_or(i32::MIN, |i| sorted[i]);
+                let end = sorted.get(i).copied().unwrap_or(i32::MAX);
+                assert!(
+                    piece
+                        .keys()
+                        .copied()
+                        .eq((0..len).map(|i| i * 2).filter(|k| (start..end).contains(k))),
+                    "len {}, pivots {:?}, piece {}",
+                    len,
+                    sorted,
+                    i
+                );
+            }
+            assert_eq!(pieces.iter().map(|p| p.len()).sum::<usize>(), len as usize);
+        }
+    }
+
+    #[cfg(feature = "set")]
+    {
+        let store = BTreeStore::new();
+        let mut set = BTreeSet::new_in(&store);
+        set.extend(0..100);
+        let pieces = set.split_by_pivots(&[50, 10]);
+        assert!(pieces[0].iter().copied().eq(0..10));
+        assert!(pieces[1].iter().copied().eq(10..50));
+        assert!(pieces[2].iter().copied().eq(50..100));
+    }
+}
+
+#[test]
+pub fn retain() {
+    let store = BTreeStore::new();
+    for len in [0, 1, 9, 100, 1000] {
+        let mut btree = BTreeMap::new_in(&store);
+        for i in 0..len {
+            btree.insert(i, i * 2);
+        }
+
+        btree.retain(|k, v| {
+            *v += 1;
+            k % 7 < 2 || (k / 50) % 2 < 1
+        });
+        btree.validate();
+        assert!(btree.iter().map(|(k, v)| (*k, *v)).eq((0..len)
+            .filter(|k| k % 7 < 2 || (k / 50) % 2 < 1)
+            .map(|i| (i, i * 2 + 1))));
+
+        btree.retain(|_, _| false);
+        btree.validate();
+        assert!(btree.is_empty());
+    }
+
+    // If the predicate panics, the entries kept so far stay
+    let mut btree = BTreeMap::new_in(&store);
+    btree.extend((0..100).map(|i| (i, i)));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        btree.retain(|k, _| match *k {
+            50 => panic!("retain predicate panicked"),
+            k => k % 2 == 0,
+        })
+    }));
+    assert!(result.is_err());
+    btree.validate();
+    assert!(btree.keys().copied().eq((0..50).step_by(2)));
+
+    #[cfg(feature = "set")]
+    {
+        let set_store = BTreeStore::new();
+        let mut set = BTreeSet::new_in(&set_store);
+        set.extend(0..100);
+        set.retain(|k| k % 3 == 0);
+        set.validate();
+        assert!(set.iter().copied().eq((0..100).step_by(3)));
+    }
+}
+
+#[test]
+pub fn extract_if() {
+    let mut rng = SmallRng::from_seed(*SEED);
+    let store = BTreeStore::new();
+    for len in [0, 1, 9, 100, 1000] {
+        for _ in 0..5 {
+            let mut btree = BTreeMap::new_in(&store);
+            let mut model = std::collections::BTreeMap::new();
+            for i in 0..len {
+                btree.insert(i, i * 2);
+                model.insert(i, i * 2);
+            }
+            let start = rng.gen_range(0..len + 10);
+            let end = rng.gen_range(start..len + 20);
+            let modulo = rng.gen_range(1..5);
+
+            let extracted = btree
+                .try_extract_if(start..end, |k, v| {
+                    *v += 1;
+                    k % modulo != 0
+                })
+                .unwrap()
+                .collect::<Vec<_>>();
+            btree.validate();
+
+            let expected = model
+                .range_mut(start..end)
+                .filter_map(|(k, v)| {
+                    *v += 1;
+                    (k % modulo != 0).then_some((*k, *v))
+                })
+                .collect::<Vec<_>>();
+            for (k, _) in &expected {
+                model.remove(k);
+            }
+            assert_eq!(extracted, expected, "len {len}, {start}..{end} % {modulo}");
+            assert!(btree.iter().eq(model.iter()));
+        }
+    }
+
+    // Entries which aren't visited are kept
+    let mut btree = BTreeMap::new_in(&store);
+    btree.extend((0..100).map(|i| (i, i)));
+    {
+        let mut extract = btree.try_extract_if(.., |_, _| true).unwrap();
+        assert_eq!(extract.nth(9), Some((9, 9)));
+    }
+    btree.validate();
+    assert!(btree.keys().copied().eq(10..100));
+    assert!(matches!(
+        btree.try_extract_if((Bound::Included(5), Bound::Included(2)), |_, _| true),
+        Err(Error::InvalidRange)
+    ));
+}
+
+#[test]
+pub fn append() {
+    for (a, b) in [
+        (0..0, 0..10),
+        (0..10, 0..0),
+        (0..100, 100..200),
+        (100..200, 0..100),
+        (0..100, 50..150),
+        (0..1000, 3..4),
+    ] {
+        for same_store in [true, false] {
+            let store = BTreeStore::new();
+            let other_store = BTreeStore::new();
+            let mut map = BTreeMap::new_in(&store);
+            let mut other = BTreeMap::new_in(if same_store { &store } else { &other_store });
+            let mut model = std::collections::BTreeMap::new();
+            for i in a.clone() {
+                map.insert(i, 0);
+                model.insert(i, 0);
+            }
+            for i in b.clone() {
+                other.insert(i, 1);
+            }
+            let mut other_model = other.iter().map(|(k, v)| (*k, *v)).collect();
+
+            map.append(&mut other);
+            model.append(&mut other_model);
+            map.validate();
+            other.validate();
+            assert!(other.is_empty());
+            assert!(
+                map.iter().eq(model.iter()),
+                "{:?} {:?} {}",
+                a,
+                b,
+                same_store
+            );
+        }
+    }
+
+    #[cfg(feature = "set")]
+    {
+        let set_store = BTreeStore::new();
+        let mut set = BTreeSet::new_in(&set_store);
+        let mut other = BTreeSet::new_in(&set_store);
+        set.extend(0..10);
+        other.extend(10..20);
+        set.append(&mut other);
+        assert!(other.is_empty());
+        assert!(set.iter().copied().eq(0..20));
+    }
+}
+
+#[test]
+pub fn partition() {
+    let store = BTreeStore::new();
+    for len in [0, 1, 9, 100, 1000] {
+        let mut btree = BTreeMap::new_in(&store);
+        for i in 0..len {
+            btree.insert(i, i * 2);
+        }
+
+        // Runs of matching and non-matching keys of different lengths
+        let (matching, rest) = btree.partition(|k, _| k % 7 < 2 || (k / 50) % 2 < 1);
+        matching.validate();
+        rest.validate();
+        let is_match = |k: &usize| k % 7 < 2 || (k / 50) % 2 < 1;
+        assert!(matching
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .eq((0..len).filter(is_match).map(|i| (i, i * 2))));
+        assert!(rest
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .eq((0..len).filter(|k| !is_match(k)).map(|i| (i, i * 2))));
+    }
+
+    #[cfg(feature = "set")]
+    {
+        let set_store = BTreeStore::new();
+        let mut set = BTreeSet::new_in(&set_store);
+        for (key, _) in &ITEMS {
+            set.insert(*key);
+        }
+        let (low, high) = set.partition(|k| k % 10 < 5);
+        low.validate();
+        high.validate();
+        assert_eq!(low.len() + high.len(), 100);
+        assert!(low.iter().all(|k| k % 10 < 5));
+        assert!(high.iter().all(|k| k % 10 >= 5));
+    }
+}
+
 const ITEMS: [(usize, usize); 100] = [
     (4223, 5948),
     (8175, 4629),
@@ -214,3 +989,555 @@ const ITEMS: [(usize, usize); 100] = [
     (1553, 5964),
     (4493, 3677),
 ];
+
+#[test]
+fn keys_eq() {
+    let store = BTreeStore::new();
+    let strings = BTreeStore::new();
+    let mut a = BTreeMap::new_in(&store);
+    let mut b = BTreeMap::new_in(&strings);
+    // Different insertion orders give different leaves
+    for i in 0..1000 {
+        a.insert(i, i);
+        b.insert(999 - i, (999 - i).to_string());
+    }
+    assert!(a.keys_eq(&b));
+    assert!(b.keys_eq(&a));
+    assert_eq!(a.keys_cmp(&b), std::cmp::Ordering::Equal);
+
+    b.remove(&500);
+    assert!(!a.keys_eq(&b));
+    assert_eq!(a.keys_cmp(&b), std::cmp::Ordering::Less);
+    b.insert(1000, String::new());
+    assert!(!a.keys_eq(&b));
+    assert_eq!(a.keys_cmp(&b), std::cmp::Ordering::Less);
+    assert_eq!(b.keys_cmp(&a), std::cmp::Ordering::Greater);
+    b.remove(&1000);
+    assert_eq!(a.keys_cmp(&b), std::cmp::Ordering::Less);
+    a.remove(&999);
+    a.remove(&500);
+    assert_eq!(a.keys_cmp(&b), std::cmp::Ordering::Less);
+    assert_eq!(b.keys_cmp(&a), std::cmp::Ordering::Greater);
+
+    a.clear();
+    b.clear();
+    assert!(a.keys_eq(&b));
+    b.insert(0, String::new());
+    assert_eq!(a.keys_cmp(&b), std::cmp::Ordering::Less);
+}
+
+#[test]
+fn diff_with() {
+    use btree_plus_store::map::DiffItem;
+
+    let store = BTreeStore::new();
+    let mut old = BTreeMap::new_in(&store);
+    let mut new = BTreeMap::new_in(&store);
+    for i in 0..1000 {
+        old.insert(i, i);
+        new.insert(999 - i, 999 - i);
+    }
+    assert_eq!(old.diff_with(&new).next(), None);
+
+    old.remove(&0);
+    new.remove(&500);
+    new.insert(1000, 0);
+    *new.get_mut(&10).unwrap() = 11;
+    *new.get_mut(&999).unwrap() = 0;
+    assert_eq!(
+        old.diff_with(&new).collect::<Vec<_>>(),
+        [
+            DiffItem::Added(&0, &0),
+            DiffItem::Changed(&10, &10, &11),
+            DiffItem::Removed(&500, &500),
+            DiffItem::Changed(&999, &999, &0),
+            DiffItem::Added(&1000, &0),
+        ]
+    );
+
+    let empty = BTreeMap::new_in(&store);
+    assert_eq!(empty.diff_with(&new).count(), new.len());
+    assert!(new
+        .diff_with(&empty)
+        .all(|item| matches!(item, DiffItem::Removed(..))));
+}
+
+#[test]
+fn get_mut_and_key_value() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..100 {
+        map.insert(format!("{:02}", i), i);
+    }
+
+    *map.get_mut("42").unwrap() *= 10;
+    assert_eq!(map.get("42"), Some(&420));
+    assert_eq!(map.get_mut("100"), None);
+
+    // The stored key instance, not an equal one
+    let (key, value) = map.get_key_value("07").unwrap();
+    assert_eq!((key.as_str(), *value), ("07", 7));
+    assert!(std::ptr::eq(key, map.get_key("07").unwrap()));
+    assert_eq!(map.get_key_value("7"), None);
+}
+
+#[test]
+fn get_many_mut() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..100 {
+        map.insert(i, vec![i]);
+    }
+
+    let [a, b] = map.get_many_mut([&3, &97]).unwrap();
+    std::mem::swap(a, b);
+    let [a, b, c] = map.get_many_mut([&50, &51, &0]).unwrap();
+    a.append(b);
+    c.push(-1);
+    assert_eq!(map.get(&3), Some(&vec![97]));
+    assert_eq!(map.get(&97), Some(&vec![3]));
+    assert_eq!(map.get(&50), Some(&vec![50, 51]));
+    assert_eq!(map.get(&51), Some(&vec![]));
+    assert_eq!(map.get(&0), Some(&vec![0, -1]));
+
+    assert!(map.get_many_mut([&1, &100]).is_none());
+    assert!(map.get_many_mut([&1, &2, &1]).is_none());
+    assert_eq!(map.get_many_mut::<i32, 0>([]), Some([]));
+    map.validate();
+}
+
+#[test]
+fn remove_entry() {
+    /// A key which carries extra data that isn't compared
+    #[derive(Debug, Clone)]
+    struct Tagged(u32, &'static str);
+
+    impl PartialEq for Tagged {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for Tagged {}
+
+    impl PartialOrd for Tagged {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Tagged {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.0.cmp(&other.0)
+        }
+    }
+
+    impl std::borrow::Borrow<u32> for Tagged {
+        fn borrow(&self) -> &u32 {
+            &self.0
+        }
+    }
+
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..100 {
+        map.insert(Tagged(i, "stored"), i * 2);
+    }
+
+    let (key, value) = map.remove_entry(&42).unwrap();
+    assert_eq!((key.0, key.1, value), (42, "stored", 84));
+    assert_eq!(map.remove_entry(&42).map(|(k, _)| k), None);
+    assert_eq!(
+        map.remove_key_value(&7).map(|(k, v)| (k.1, v)),
+        Some(("stored", 14))
+    );
+    assert_eq!(map.len(), 98);
+    map.validate();
+}
+
+#[cfg(feature = "set")]
+#[test]
+fn set_replace_and_take() {
+    use std::rc::Rc;
+
+    let store = BTreeStore::new();
+    let mut set = BTreeSet::new_in(&store);
+    let handles = (0..100).map(Rc::new).collect::<Vec<_>>();
+    for handle in &handles {
+        assert_eq!(set.replace(handle.clone()), None);
+    }
+
+    // Equal values are swapped out, so the set now holds the new instance
+    let new_42 = Rc::new(42);
+    let old_42 = set.replace(new_42.clone()).unwrap();
+    assert!(Rc::ptr_eq(&old_42, &handles[42]));
+    assert!(Rc::ptr_eq(set.get(&42).unwrap(), &new_42));
+    assert_eq!(set.len(), 100);
+    set.validate();
+
+    let taken = set.take(&42).unwrap();
+    assert!(Rc::ptr_eq(&taken, &new_42));
+    assert_eq!(set.take(&42), None);
+    assert!(Rc::ptr_eq(&set.take(&7).unwrap(), &handles[7]));
+    assert_eq!(set.len(), 98);
+    set.validate();
+}
+
+#[cfg(feature = "set")]
+#[test]
+fn set_get_or_insert() {
+    use std::rc::Rc;
+
+    let store = BTreeStore::new();
+    let mut set = BTreeSet::new_in(&store);
+
+    // Interning: each string is only allocated once, across leaf splits
+    let mut interned = Vec::new();
+    for i in 0..1000 {
+        let key = format!("{:04}", (i * 7) % 500);
+        let value = set.get_or_insert_with(key.as_str(), |key| Rc::from(key));
+        assert_eq!(&**value, key);
+        interned.push(value.clone());
+        set.validate();
+    }
+    assert_eq!(set.len(), 500);
+    for (i, value) in interned.iter().enumerate() {
+        let key = format!("{:04}", (i * 7) % 500);
+        assert!(Rc::ptr_eq(set.get(key.as_str()).unwrap(), value));
+    }
+
+    let store = BTreeStore::new();
+    let mut set = BTreeSet::new_in(&store);
+    for i in (0..1000).rev() {
+        assert_eq!(*set.get_or_insert(i % 300), i % 300);
+    }
+    set.validate();
+    assert!(set.iter().copied().eq(0..300));
+
+    let map_store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&map_store);
+    for i in 0..1000 {
+        *map.get_or_insert(i % 100, 0) += 1;
+    }
+    map.validate();
+    assert!(map.values().all(|count| *count == 10));
+}
+
+#[test]
+fn from_iter_in() {
+    use btree_plus_store::CollectIn;
+
+    let mut rng = SmallRng::from_seed(*SEED);
+    let mut std_map = std::collections::BTreeMap::new();
+    let mut entries = Vec::new();
+    for i in 0..2000 {
+        let key = rng.gen_range(0..500);
+        std_map.insert(key, i);
+        entries.push((key, i));
+    }
+
+    let store = BTreeStore::new();
+    let map = BTreeMap::from_iter_in(entries.iter().copied(), &store);
+    map.validate();
+    assert!(map.iter().eq(std_map.iter()));
+
+    let map = entries.into_iter().collect_in::<BTreeMap<_, _>>(&store);
+    map.validate();
+    assert!(map.iter().eq(std_map.iter()));
+
+    let empty = std::iter::empty::<(i32, i32)>().collect_in::<BTreeMap<_, _>>(&store);
+    assert!(empty.is_empty());
+
+    #[cfg(feature = "set")]
+    {
+        let store = BTreeStore::new();
+        let set = [3, 1, 2, 3, 1]
+            .into_iter()
+            .collect_in::<BTreeSet<_>>(&store);
+        set.validate();
+        assert!(set.iter().copied().eq(1..=3));
+    }
+}
+
+#[test]
+fn std_conversions() {
+    let std_map = (0..1000)
+        .map(|i| (i * 3, i.to_string()))
+        .collect::<std::collections::BTreeMap<_, _>>();
+    let store = BTreeStore::new();
+    let map = BTreeMap::from_std_in(std_map.clone(), &store);
+    map.validate();
+    assert!(map.iter().eq(std_map.iter()));
+    assert_eq!(map.to_std(), std_map);
+
+    #[cfg(feature = "set")]
+    {
+        let std_set = (0..1000)
+            .map(|i| i * 3)
+            .collect::<std::collections::BTreeSet<_>>();
+        let store = BTreeStore::new();
+        let set = BTreeSet::from_std_in(std_set.clone(), &store);
+        set.validate();
+        assert!(set.iter().eq(std_set.iter()));
+        assert_eq!(set.to_std(), std_set);
+    }
+}
+
+#[test]
+fn construction_macros() {
+    use btree_plus_store::btreemap_in;
+
+    let store = BTreeStore::new();
+    let map = btreemap_in!(store; "b" => 2, "a" => 1, "c" => 3, "a" => 0,);
+    map.validate();
+    assert!(map.into_iter().eq([("a", 0), ("b", 2), ("c", 3)]));
+    let store_ref = &store;
+    let mut map = btreemap_in!(store_ref);
+    map.insert("d", 4);
+    assert_eq!(map.len(), 1);
+
+    #[cfg(feature = "set")]
+    {
+        use btree_plus_store::btreeset_in;
+
+        let store = BTreeStore::new();
+        let set = btreeset_in!(store; 1, 2, 3, 4);
+        set.validate();
+        assert!(set.iter().copied().eq(1..=4));
+        assert!(btreeset_in!(store;).is_empty());
+    }
+}
+
+#[test]
+fn clone_in_same_store() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..1000 {
+        map.insert(i, i.to_string());
+    }
+
+    // Speculatively mutate a copy, leaving the original untouched
+    let mut copy = map.clone();
+    copy.validate();
+    assert_eq!(copy, map);
+    copy.retain(|k, _| k % 3 == 0);
+    copy.get_mut(&0).unwrap().push('!');
+    copy.validate();
+    map.validate();
+    assert_eq!(map.len(), 1000);
+    assert_eq!(map[&0], "0");
+    assert_eq!(copy.len(), 334);
+    assert_eq!(copy[&0], "0!");
+
+    // And copy back over it, reusing its nodes
+    copy.clone_from(&map);
+    copy.validate();
+    assert_eq!(copy, map);
+    drop(map);
+    assert!(copy.iter().all(|(k, v)| v.parse::<i32>() == Ok(*k)));
+
+    #[cfg(feature = "set")]
+    {
+        let store = BTreeStore::new();
+        let mut set = BTreeSet::new_in(&store);
+        set.extend(0..100);
+        let mut copy = set.clone();
+        copy.remove(&50);
+        assert!(set.contains(&50));
+        assert!(!copy.contains(&50));
+        copy.validate();
+    }
+}
+
+#[test]
+fn print_to() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    assert_eq!(map.print_to_string(), "empty\n");
+
+    for i in 0..100 {
+        map.insert(i, i * 2);
+    }
+    let string = map.print_to_string();
+    assert_eq!(string, format!("{:?}", map));
+    assert!(string.contains("42 = 84"));
+
+    let mut bytes = Vec::new();
+    map.print_to(&mut bytes).unwrap();
+    assert_eq!(String::from_utf8(bytes).unwrap(), string);
+
+    struct Failing;
+    impl std::io::Write for Failing {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::BrokenPipe,
+                "closed",
+            ))
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    assert_eq!(
+        map.print_to(Failing).unwrap_err().kind(),
+        std::io::ErrorKind::BrokenPipe
+    );
+
+    #[cfg(feature = "set")]
+    {
+        let set_store = BTreeStore::new();
+        let mut set = BTreeSet::new_in(&set_store);
+        set.extend(0..10);
+        assert_eq!(set.print_to_string(), format!("{:?}", set));
+    }
+}
+
+#[cfg(feature = "set")]
+#[test]
+fn retain_keys_in() {
+    let store = BTreeStore::new();
+    let set_store = BTreeStore::new();
+    let mut rng = SmallRng::from_seed(*SEED);
+    for (len, set_len) in [(0i32, 10), (10, 0), (100, 50), (1000, 300), (1000, 5000)] {
+        let mut map = BTreeMap::new_in(&store);
+        map.extend((0..len).map(|i| (i, i.to_string())));
+        let mut keys = BTreeSet::new_in(&set_store);
+        keys.extend((0..set_len).map(|_| rng.gen_range(-10..len + 10)));
+
+        let expected = map
+            .iter()
+            .filter(|(k, _)| keys.contains(*k))
+            .map(|(k, v)| (*k, v.clone()))
+            .collect::<Vec<_>>();
+        let filtered = map.filter_by_keys(&keys);
+        filtered.validate();
+        assert_eq!(
+            filtered.into_iter().collect::<Vec<_>>(),
+            expected,
+            "filter_by_keys, len {}, set_len {}",
+            len,
+            set_len
+        );
+        assert_eq!(map.len(), len as usize);
+
+        map.retain_keys_in(&keys);
+        map.validate();
+        assert_eq!(
+            map.into_iter().collect::<Vec<_>>(),
+            expected,
+            "retain_keys_in, len {}, set_len {}",
+            len,
+            set_len
+        );
+    }
+}
+
+#[test]
+fn eq_hash_map() {
+    use std::collections::HashMap;
+
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    let mut hash_map = HashMap::new();
+    assert_eq!(map, hash_map);
+    for i in 0..100 {
+        map.insert(i, i * 2);
+        hash_map.insert(i, i * 2);
+    }
+    assert_eq!(map, hash_map);
+    assert_eq!(hash_map, map);
+
+    hash_map.insert(50, 0);
+    assert_ne!(map, hash_map);
+    hash_map.insert(50, 100);
+    hash_map.insert(100, 200);
+    assert_ne!(map, hash_map);
+    assert_ne!(hash_map, map);
+
+    #[cfg(feature = "set")]
+    {
+        use std::collections::HashSet;
+
+        let set_store = BTreeStore::new();
+        let mut set = BTreeSet::new_in(&set_store);
+        set.extend(0..100);
+        let mut hash_set = (0..100).collect::<HashSet<_>>();
+        assert_eq!(set, hash_set);
+        assert_eq!(hash_set, set);
+        hash_set.remove(&42);
+        hash_set.insert(-1);
+        assert_ne!(set, hash_set);
+    }
+}
+
+#[test]
+fn eq_and_cmp_std() {
+    use std::cmp::Ordering;
+
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    let mut std_map = std::collections::BTreeMap::new();
+    for i in 0..100 {
+        map.insert(i, i * 2);
+        std_map.insert(i, i * 2);
+    }
+    assert_eq!(map, std_map);
+    assert_eq!(std_map, map);
+    assert_eq!(map.partial_cmp(&std_map), Some(Ordering::Equal));
+    assert!(map.eq_iter(&std_map));
+    assert!(map.eq_iter((0..100).map(|i| (i, i * 2))));
+    assert!(!map.eq_iter((0..99).map(|i| (i, i * 2))));
+    assert_eq!(map.cmp_with(&std_map), Ordering::Equal);
+
+    std_map.insert(50, 0);
+    assert_ne!(map, std_map);
+    assert!(map > std_map);
+    assert!(std_map < map);
+    assert_eq!(map.cmp_with(&std_map), Ordering::Greater);
+    std_map.insert(50, 100);
+    std_map.insert(100, 0);
+    assert!(map < std_map);
+    assert_eq!(map.cmp_with(&std_map), Ordering::Less);
+    assert_eq!(map.cmp_with([(0, 0)]), Ordering::Greater);
+    assert_eq!(map.cmp_with([(1, 0)]), Ordering::Less);
+
+    #[cfg(feature = "set")]
+    {
+        let set_store = BTreeStore::new();
+        let mut set = BTreeSet::new_in(&set_store);
+        set.extend(0..100);
+        let mut std_set = (0..100).collect::<std::collections::BTreeSet<_>>();
+        assert_eq!(set, std_set);
+        assert_eq!(std_set, set);
+        assert!(set.eq_iter(0..100));
+        assert!(set.eq_iter(&std_set));
+        assert_eq!(set.cmp_with(0..50), Ordering::Greater);
+        std_set.remove(&42);
+        assert_ne!(set, std_set);
+        assert!(set < std_set);
+        assert_eq!(set.cmp_with(&std_set), Ordering::Less);
+    }
+}
+
+#[test]
+fn get_batch() {
+    let store = BTreeStore::new();
+    let empty = BTreeMap::<i32, i32>::new_in(&store);
+    assert!(empty.get_batch(&[1, 2]).eq([None, None]));
+
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..1000 {
+        map.insert(i * 2, i);
+    }
+    let sorted_keys = (-5..2005).collect::<Vec<_>>();
+    assert_eq!(map.get_batch(&sorted_keys).len(), sorted_keys.len());
+    assert!(map
+        .get_batch(&sorted_keys)
+        .eq(sorted_keys.iter().map(|key| map.get(key))));
+
+    // Still correct (just slower) when unsorted
+    let mut keys = sorted_keys;
+    keys.shuffle(&mut SmallRng::seed_from_u64(0));
+    assert!(map.get_batch(&keys).eq(keys.iter().map(|key| map.get(key))));
+}
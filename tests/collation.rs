@@ -0,0 +1,48 @@
+#![cfg(feature = "icu")]
+
+use btree_plus_store::{BTreeMap, BTreeStore, Collated};
+use icu_collator::{Collator, CollatorOptions, Strength};
+
+#[test]
+fn locale_order() {
+    let collator = Collator::try_new(&Default::default(), CollatorOptions::new()).unwrap();
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    for (i, word) in ["cote", "côte", "Côte", "coté", "Cote", "côté", "b", "d"]
+        .into_iter()
+        .enumerate()
+    {
+        map.insert(Collated::new(&collator, word.to_string()), i);
+    }
+    map.validate();
+
+    let words = map
+        .keys()
+        .map(|word| word.get().as_str())
+        .collect::<Vec<_>>();
+    let mut expected = words.clone();
+    expected.sort_by(|a, b| collator.compare(a, b));
+    assert_eq!(words, expected);
+    assert_eq!(words.first(), Some(&"b"));
+    assert_eq!(words.last(), Some(&"d"));
+
+    // Every accented and capitalized variant sorts between "c" and "d", unlike in byte order
+    let start = Collated::new(&collator, "c".to_string());
+    let end = Collated::new(&collator, "d".to_string());
+    assert_eq!(map.try_range(start..end).unwrap().count(), 6);
+}
+
+#[test]
+fn strength() {
+    let mut options = CollatorOptions::new();
+    options.strength = Some(Strength::Primary);
+    let collator = Collator::try_new(&Default::default(), options).unwrap();
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+
+    // At primary strength case and accents are ignored, so these are the same key
+    assert_eq!(map.insert(Collated::new(&collator, "Resume"), 1), None);
+    assert_eq!(map.insert(Collated::new(&collator, "résumé"), 2), Some(1));
+    assert_eq!(map.len(), 1);
+    assert_eq!(map.get(&Collated::new(&collator, "RESUME")), Some(&2));
+}
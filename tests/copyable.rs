@@ -22,6 +22,18 @@ fn test_copy() {
     assert_eq!(map2.get(&2), Some(&20));
 }
 
+#[test]
+fn test_index() {
+    let store = BTreeStore::new();
+
+    let mut map = BTreeMap::new_in(&store);
+    map.extend((0..100).map(|i| (i.to_string(), i)));
+
+    let map = copyable::BTreeMap::from(map);
+    assert_eq!(map["42"], 42);
+    assert_eq!(map[&"99".to_string()], 99);
+}
+
 #[test]
 fn test_large_map() {
     let store = BTreeStore::new();
@@ -142,3 +154,62 @@ fn test_drop_contents() {
     drop(map3);
     assert_eq!(drop_count.get(), 2);
 }
+
+#[test]
+fn test_keys_eq() {
+    thread_local!(static EQ_CALLS: Cell<usize> = const { Cell::new(0) });
+
+    #[derive(Debug, Clone, Eq, PartialOrd, Ord)]
+    struct Key(usize);
+
+    impl PartialEq for Key {
+        fn eq(&self, other: &Self) -> bool {
+            EQ_CALLS.with(|calls| calls.set(calls.get() + 1));
+            self.0 == other.0
+        }
+    }
+
+    let store = BTreeStore::new();
+    let map = copyable::BTreeMap::build(&store, |map| {
+        for i in 0..1000 {
+            map.insert(Key(i), i);
+        }
+    });
+    let other_store = BTreeStore::new();
+    let other = copyable::BTreeMap::build(&other_store, |map| {
+        for i in (0..1000).rev() {
+            map.insert(Key(i), ());
+        }
+    });
+    EQ_CALLS.with(|calls| calls.set(0));
+
+    // Copies share their nodes, so they're equal without comparing keys
+    let copy = map;
+    assert!(map.keys_eq(&copy));
+    assert!(map.diff_with(&copy).next().is_none());
+    assert_eq!(EQ_CALLS.with(Cell::get), 0);
+
+    assert!(map.keys_eq(&other));
+    assert_eq!(EQ_CALLS.with(Cell::get), 1000);
+    assert_eq!(other.keys_cmp(&copy), std::cmp::Ordering::Equal);
+}
+
+#[cfg(feature = "generations")]
+#[test]
+#[should_panic(expected = "freed")]
+fn test_use_after_gc() {
+    use btree_plus_store::copyable::BTreeStoreExt;
+
+    let store = BTreeStore::new();
+
+    let mut kept = BTreeMap::new_in(&store);
+    kept.insert(1, 10);
+    let kept = copyable::BTreeMap::from(kept);
+    let mut collected = BTreeMap::new_in(&store);
+    collected.insert(1, 10);
+    let collected = copyable::BTreeMap::from(collected);
+
+    unsafe { store.tracing_gc([kept]) };
+    assert_eq!(kept.get(&1), Some(&10));
+    collected.get(&1);
+}
@@ -0,0 +1,199 @@
+use btree_plus_store::{BTreeMap, BTreeStore, Error};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use std::ops::Bound;
+
+const SEED: &[u8; 32] = b"testseedtestseedtestseedtestseed";
+
+/// The bound on the other side of the same key, or `None` if unbounded
+fn flip<T>(bound: Bound<T>) -> Option<Bound<T>> {
+    match bound {
+        Bound::Included(key) => Some(Bound::Excluded(key)),
+        Bound::Excluded(key) => Some(Bound::Included(key)),
+        Bound::Unbounded => None,
+    }
+}
+
+#[test]
+fn cursor_bounds() {
+    for len in [0, 1, 8, 9, 100, 1000] {
+        let store = BTreeStore::new();
+        let mut map = BTreeMap::new_in(&store);
+        let mut std_map = std::collections::BTreeMap::new();
+        for i in 0..len {
+            map.insert(i * 2, i);
+            std_map.insert(i * 2, i);
+        }
+
+        let bounds = (-2..len * 2 + 2).flat_map(|key| [Bound::Included(key), Bound::Excluded(key)]);
+        for bound in bounds.chain([Bound::Unbounded]) {
+            let bound = bound.as_ref();
+            // The lower bound is after the entries outside of `(bound, ..)`, and the upper bound is
+            // before the entries outside of `(.., bound)`
+            let cursor = map.lower_bound(bound);
+            let next = std_map.range((bound, Bound::Unbounded)).next();
+            let prev = flip(bound).and_then(|b| std_map.range((Bound::Unbounded, b)).next_back());
+            assert_eq!(cursor.peek_next(), next, "len {len}, {bound:?}");
+            assert_eq!(cursor.peek_prev(), prev, "len {len}, {bound:?}");
+
+            let cursor = map.upper_bound(bound);
+            let prev = std_map.range((Bound::Unbounded, bound)).next_back();
+            let next = flip(bound).and_then(|b| std_map.range((b, Bound::Unbounded)).next());
+            assert_eq!(cursor.peek_prev(), prev, "len {len}, {bound:?}");
+            assert_eq!(cursor.peek_next(), next, "len {len}, {bound:?}");
+        }
+
+        // Walk the whole map forwards and backwards, across every leaf
+        let mut cursor = map.lower_bound(Bound::Unbounded);
+        assert_eq!(cursor.prev(), None);
+        assert!(std::iter::from_fn(|| cursor.next()).eq(std_map.iter()));
+        assert_eq!(cursor.next(), None);
+        assert!(std::iter::from_fn(|| cursor.prev()).eq(std_map.iter().rev()));
+        assert_eq!(cursor.peek_next(), std_map.iter().next());
+
+        let mut cursor = map.upper_bound_mut(Bound::Unbounded);
+        while let Some((_, value)) = cursor.prev() {
+            *value += 1;
+        }
+        assert!(map.values().copied().eq(1..len + 1));
+    }
+}
+
+#[test]
+fn cursor_mut_edits() {
+    let mut rng = SmallRng::from_seed(*SEED);
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    let mut std_map = std::collections::BTreeMap::new();
+    for i in 0..200 {
+        map.insert(i * 4, i);
+        std_map.insert(i * 4, i);
+    }
+
+    for i in 0..2000 {
+        let key = rng.gen_range(-10..900);
+        let mut cursor = match rng.gen_range(0..2) {
+            0 => map.lower_bound_mut(Bound::Included(&key)),
+            _ => map.upper_bound_mut(Bound::Excluded(&key)),
+        };
+        // Both bounds are at the same gap: after keys below `key` and before the rest
+        let next = cursor.peek_next().map(|(k, _)| *k);
+        let prev = cursor.peek_prev().map(|(k, _)| *k);
+        assert_eq!(next, std_map.range(key..).next().map(|(k, _)| *k));
+        assert_eq!(prev, std_map.range(..key).next_back().map(|(k, _)| *k));
+
+        match rng.gen_range(0..4) {
+            0 => {
+                let removed = cursor.remove_next();
+                assert_eq!(removed.map(|(k, _)| k), next);
+                if let Some((k, v)) = removed {
+                    assert_eq!(std_map.remove(&k), Some(v));
+                }
+                assert_eq!(cursor.peek_prev().map(|(k, _)| *k), prev);
+            }
+            1 => {
+                let removed = cursor.remove_prev();
+                assert_eq!(removed.map(|(k, _)| k), prev);
+                if let Some((k, v)) = removed {
+                    assert_eq!(std_map.remove(&k), Some(v));
+                }
+                assert_eq!(cursor.peek_next().map(|(k, _)| *k), next);
+            }
+            op => {
+                let result = match op {
+                    2 => cursor.insert_after(key, i),
+                    _ => cursor.insert_before(key, i),
+                };
+                if next == Some(key) {
+                    assert_eq!(result, Err(Error::UnorderedKey));
+                    continue;
+                }
+                assert_eq!(result, Ok(()));
+                std_map.insert(key, i);
+                let (before, after) = match op {
+                    2 => (prev, Some(key)),
+                    _ => (Some(key), next),
+                };
+                assert_eq!(cursor.peek_prev().map(|(k, _)| *k), before);
+                assert_eq!(cursor.peek_next().map(|(k, _)| *k), after);
+            }
+        }
+        map.validate();
+        assert_eq!(map.len(), std_map.len());
+    }
+    assert!(map.iter().eq(std_map.iter()));
+
+    // Keys out of order are rejected
+    let mut cursor = map.lower_bound_mut(Bound::Unbounded);
+    cursor.next();
+    let first = *cursor.peek_prev().unwrap().0;
+    assert_eq!(cursor.insert_after(first - 1, 0), Err(Error::UnorderedKey));
+    assert_eq!(cursor.insert_before(first, 0), Err(Error::UnorderedKey));
+
+    // Removing everything through a cursor leaves it usable on the empty map
+    let mut cursor = map.lower_bound_mut(Bound::Unbounded);
+    while cursor.remove_next().is_some() {}
+    assert!(cursor.peek_prev().is_none() && cursor.peek_next().is_none());
+    cursor.insert_before(1, 1).unwrap();
+    cursor.insert_after(3, 3).unwrap();
+    cursor.insert_before(2, 2).unwrap();
+    map.validate();
+    assert!(map.keys().copied().eq(1..=3));
+}
+
+#[test]
+fn insert_hint() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    let mut std_map = std::collections::BTreeMap::new();
+    let mut rng = SmallRng::from_seed(*SEED);
+    let mut cursor = map.lower_bound_mut(Bound::Unbounded);
+    // A stream of keys near each other, with occasional jumps and repeats
+    let mut key = 0;
+    for i in 0..5000 {
+        key = match rng.gen_range(0..20) {
+            0 => rng.gen_range(-1000..6000),
+            1..=3 => key - rng.gen_range(0..5),
+            _ => key + rng.gen_range(1..5),
+        };
+        assert_eq!(cursor.insert_hint(key, i), std_map.insert(key, i));
+        assert_eq!(cursor.peek_prev().map(|(k, v)| (*k, *v)), Some((key, i)));
+    }
+    map.validate();
+    assert!(map.iter().eq(std_map.iter()));
+}
+
+#[test]
+fn next_before_after() {
+    for len in [0, 1, 8, 9, 100, 1000] {
+        let store = BTreeStore::new();
+        let mut map = BTreeMap::new_in(&store);
+        map.extend((0..len).map(|i| (i * 2, i)));
+        for key in -2..len * 2 + 2 {
+            let before = (0..len).map(|i| i * 2).rev().find(|k| *k < key);
+            let after = (0..len).map(|i| i * 2).find(|k| *k > key);
+            assert_eq!(
+                map.next_before(&key).map(|(k, _)| *k),
+                before,
+                "len {len}, key {key}"
+            );
+            assert_eq!(
+                map.next_after(&key).map(|(k, _)| *k),
+                after,
+                "len {len}, key {key}"
+            );
+        }
+    }
+
+    #[cfg(feature = "set")]
+    {
+        let store = BTreeStore::new();
+        let mut set = btree_plus_store::BTreeSet::new_in(&store);
+        set.extend(["b", "d", "f"]);
+        assert_eq!(set.next_before("d"), Some(&"b"));
+        assert_eq!(set.next_after("d"), Some(&"f"));
+        assert_eq!(set.next_before("c"), Some(&"b"));
+        assert_eq!(set.next_after("c"), Some(&"d"));
+        assert_eq!(set.next_before("b"), None);
+        assert_eq!(set.next_after("f"), None);
+    }
+}
@@ -1,4 +1,6 @@
-use btree_plus_store::{BTreeMap, BTreeSet, BTreeStore};
+#[cfg(feature = "set")]
+use btree_plus_store::BTreeSet;
+use btree_plus_store::{BTreeMap, BTreeStore};
 use std::fmt::{Debug, Formatter};
 use std::{cell::Cell, rc::Rc};
 
@@ -222,6 +224,74 @@ fn iter_mut() {
     }
 }
 
+#[test]
+fn values_mut() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+
+    for i in 0..100 {
+        map.insert(i, i);
+    }
+
+    for v in map.values_mut() {
+        *v *= 3;
+    }
+    for (_, v) in &mut map {
+        *v += 1;
+    }
+
+    assert!(map.values().copied().eq((0..100).map(|i| i * 3 + 1)));
+}
+
+#[test]
+fn range_mut() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+
+    for i in 0..100 {
+        map.insert(i, i);
+    }
+
+    let mut range = map.try_range_mut(20..80).unwrap();
+    // Alternate ends, so the cursors meet in the middle
+    while let Some((k, v)) = range.next() {
+        *v = -*k;
+        if let Some((k, v)) = range.next_back() {
+            *v = -*k;
+        }
+    }
+
+    assert!(map
+        .iter()
+        .all(|(k, v)| *v == if (20..80).contains(k) { -*k } else { *k }));
+}
+
+#[test]
+fn range_includes_both_ends() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..100 {
+        map.insert(i, i);
+    }
+
+    for (start, end) in [(0, 100), (20, 80), (7, 8), (8, 9), (42, 42)] {
+        let range = map.range(start..end);
+        assert!(range.map(|(k, _)| *k).eq(start..end), "{start}..{end}");
+        let range = map.range(start..end);
+        assert!(
+            range.rev().map(|(k, _)| *k).eq((start..end).rev()),
+            "{start}..{end}"
+        );
+        let range = map.range_mut(start..end);
+        assert!(range.map(|(k, _)| *k).eq(start..end), "{start}..{end}");
+        let range = map.range_mut(start..end);
+        assert!(
+            range.rev().map(|(k, _)| *k).eq((start..end).rev()),
+            "{start}..{end}"
+        );
+    }
+}
+
 #[test]
 fn keys() {
     let store = BTreeStore::new();
@@ -258,6 +328,29 @@ fn values() {
     assert_eq!(values.next(), None);
 }
 
+#[test]
+fn into_keys_values() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+
+    for i in 0..100 {
+        map.insert(i, i.to_string());
+    }
+
+    let mut keys = map.clone().into_keys();
+    assert_eq!(keys.len(), 100);
+    assert_eq!(keys.next_back(), Some(99));
+    assert!(keys.eq(0..99));
+
+    let mut values = map.into_values();
+    assert_eq!(values.next(), Some("0".to_string()));
+    assert_eq!(values.next_back(), Some("99".to_string()));
+    assert_eq!(values.len(), 98);
+    // The remaining values are dropped with the iterator
+    assert_eq!(values.nth(40), Some("41".to_string()));
+}
+
+#[cfg(feature = "set")]
 #[test]
 fn set_iter() {
     let store = BTreeStore::new();
@@ -276,6 +369,7 @@ fn set_iter() {
     assert_eq!(i, 10);
 }
 
+#[cfg(feature = "set")]
 #[test]
 fn set_into_iter() {
     #[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -308,3 +402,389 @@ fn set_into_iter() {
 
     assert_eq!(counter.get(), 100);
 }
+
+#[test]
+pub fn nodes() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    assert_eq!(map.nodes().count(), 0);
+
+    for i in 0..1000 {
+        map.insert(i, i);
+    }
+
+    let nodes = map.nodes().collect::<Vec<_>>();
+    assert_eq!(nodes[0].depth(), 0);
+    assert_eq!(nodes.iter().filter(|node| node.depth() == 0).count(), 1);
+
+    let leaves = nodes
+        .iter()
+        .filter(|node| node.is_leaf())
+        .collect::<Vec<_>>();
+    let leaf_depth = leaves[0].depth();
+    assert!(leaves.iter().all(|leaf| leaf.depth() == leaf_depth));
+    assert_eq!(leaves.iter().map(|leaf| leaf.len()).sum::<usize>(), 1000);
+    assert_eq!(
+        leaves
+            .iter()
+            .flat_map(|leaf| leaf.keys().iter().copied())
+            .collect::<Vec<_>>(),
+        (0..1000).collect::<Vec<_>>()
+    );
+    for window in leaves.windows(2) {
+        assert!(window[0].last_key() < window[1].first_key());
+    }
+}
+
+#[test]
+fn iter_from() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..500 {
+        map.insert(i * 2, i);
+    }
+
+    for key in [0, 1, 2, 99, 100, 500, 997, 998, 999, 5000] {
+        let iter = map.iter_from(&key);
+        assert_eq!(iter.len(), (key..1000).filter(|k| k % 2 < 1).count());
+        assert!(iter.map(|(k, _)| *k).eq((key..1000).filter(|k| k % 2 < 1)));
+        assert!(map
+            .iter_from(&key)
+            .rev()
+            .map(|(k, _)| *k)
+            .eq((key..1000).filter(|k| k % 2 < 1).rev()));
+
+        let back = map.iter_from_back(&key);
+        assert_eq!(back.len(), (0..=key.min(998)).filter(|k| k % 2 < 1).count());
+        assert!(back
+            .rev()
+            .map(|(k, _)| *k)
+            .eq((0..=key.min(998)).filter(|k| k % 2 < 1).rev()));
+    }
+
+    #[cfg(feature = "set")]
+    {
+        let set_store = BTreeStore::new();
+        let mut set = BTreeSet::new_in(&set_store);
+        assert_eq!(set.iter_from(&"a").next(), None);
+        assert_eq!(set.iter_from_back(&"a").next(), None);
+        set.extend(["a", "c", "e"]);
+        assert!(set.iter_from(&"b").eq(&["c", "e"]));
+        assert!(set.iter_from_back(&"d").eq(&["a", "c"]));
+    }
+}
+
+#[test]
+fn top_k() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    assert_eq!(map.top_k(3).next(), None);
+    assert_eq!(map.bottom_k(3).next(), None);
+    for i in 0..300 {
+        map.insert(i, i * 2);
+    }
+
+    for k in [0, 1, 7, 8, 9, 100, 300, 301, 1000] {
+        let top = map.top_k(k);
+        assert_eq!(top.len(), k.min(300));
+        assert!(top.map(|(k, _)| *k).eq((0..300).rev().take(k)));
+        assert!(map
+            .top_k(k)
+            .rev()
+            .map(|(k, _)| *k)
+            .eq((0..300).rev().take(k).rev()));
+        let bottom = map.bottom_k(k);
+        assert_eq!(bottom.len(), k.min(300));
+        assert!(bottom.map(|(k, _)| *k).eq(0..k.min(300)));
+
+        for (start, end) in [(0, 300), (10, 20), (55, 250), (100, 100), (299, 1000)] {
+            assert!(map
+                .try_top_k_in(start..end, k)
+                .unwrap()
+                .map(|(k, _)| *k)
+                .eq((start..end.min(300)).rev().take(k)));
+            assert!(map
+                .try_bottom_k_in(start..end, k)
+                .unwrap()
+                .map(|(k, _)| *k)
+                .eq((start..end.min(300)).take(k)));
+        }
+    }
+
+    #[cfg(feature = "set")]
+    {
+        let set_store = BTreeStore::new();
+        let mut set = BTreeSet::new_in(&set_store);
+        set.extend(0..50);
+        assert!(set.top_k(3).eq(&[49, 48, 47]));
+        assert!(set.bottom_k(2).eq(&[0, 1]));
+        assert!(set.try_top_k_in(..=20, 2).unwrap().eq(&[20, 19]));
+        assert!(set
+            .try_bottom_k_in(45.., 10)
+            .unwrap()
+            .eq(&[45, 46, 47, 48, 49]));
+    }
+}
+
+#[test]
+fn group_by_key_prefix() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    // Groups of sizes 0..50, so some fit in a leaf and some span many
+    for day in 0..50 {
+        for hour in 0..day {
+            map.insert((day, hour), day * 100 + hour);
+        }
+    }
+
+    let mut groups = map.group_by_key_prefix(|&(day, _)| day);
+    for day in 1..50 {
+        let (prefix, entries) = groups.next().unwrap();
+        assert_eq!(prefix, day);
+        assert!(entries.eq(map.iter().filter(|((d, _), _)| *d == day)));
+    }
+    assert!(groups.next().is_none());
+
+    // Monotonic but coarser prefixes, and one group for everything
+    let tens = map
+        .group_by_key_prefix(|&(day, _)| day / 10)
+        .map(|(prefix, entries)| (prefix, entries.count()))
+        .collect::<Vec<_>>();
+    assert_eq!(tens, [(0, 45), (1, 145), (2, 245), (3, 345), (4, 445)]);
+    assert_eq!(map.group_by_key_prefix(|_| 0).count(), 1);
+
+    // Non-monotonic prefixes still yield every entry exactly once
+    let count = map
+        .group_by_key_prefix(|&(day, hour)| (day + hour) % 3)
+        .map(|(_, entries)| entries.count())
+        .sum::<usize>();
+    assert_eq!(count, map.len());
+
+    map.clear();
+    assert!(map.group_by_key_prefix(|&(day, _)| day).next().is_none());
+}
+
+#[test]
+fn entries_with_handles() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..500 {
+        map.insert(i, i * 2);
+    }
+
+    let handles = map
+        .entries_with_handles()
+        .filter(|(_, &k, _)| k % 50 == 7)
+        .map(|(handle, _, _)| handle)
+        .collect::<Vec<_>>();
+    assert_eq!(handles.len(), 10);
+    for (i, handle) in handles.iter().enumerate() {
+        let key = i * 50 + 7;
+        assert_eq!(handle.key_value(), (&key, &(key * 2)));
+        assert_eq!(handle.value(), map.get(handle.key()).unwrap());
+        assert!(handle.iter_from().eq(map.iter_from(&key)));
+    }
+
+    let mut entries = map.entries_with_handles();
+    assert_eq!(entries.len(), 500);
+    let (last, _, _) = entries.next_back().unwrap();
+    assert_eq!(last.key(), &499);
+    assert_eq!(last.iter_from().count(), 1);
+    let (first, _, _) = entries.next().unwrap();
+    assert_eq!(first.iter_from().count(), 500);
+    assert_eq!(entries.len(), 498);
+    assert_eq!(
+        entries.nth(6).map(|(handle, _, _)| handle),
+        Some(handles[0])
+    );
+    assert_ne!(first, last);
+
+    map.clear();
+    assert!(map.entries_with_handles().next().is_none());
+}
+
+#[test]
+fn peek_both_ends() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..100 {
+        map.insert(i, i);
+    }
+
+    // Alternate ends in different patterns, so the cursors meet in and across leaves
+    for pattern in [0b0u32, 0b1, 0b10, 0b110, 0b1011, 0b100101] {
+        for (start, end) in [(0, 100), (10, 13), (5, 6), (7, 60)] {
+            let mut model = (start..end).collect::<std::collections::VecDeque<_>>();
+            let mut iter = map.try_range(start..end).unwrap();
+            let mut full = map.iter();
+            for _ in 0..start {
+                full.next();
+            }
+            for _ in end..100 {
+                full.next_back();
+            }
+            for step in 0.. {
+                assert_eq!(iter.peek().map(|(k, _)| *k), model.front().copied());
+                assert_eq!(iter.peek_back().map(|(k, _)| *k), model.back().copied());
+                assert_eq!(full.peek().map(|(k, _)| *k), model.front().copied());
+                assert_eq!(full.peek_back().map(|(k, _)| *k), model.back().copied());
+                if model.is_empty() {
+                    break;
+                }
+                if pattern >> (step % 8) & 1 == 0 {
+                    assert_eq!(iter.next().map(|(k, _)| *k), model.pop_front());
+                    full.next();
+                } else {
+                    assert_eq!(iter.next_back().map(|(k, _)| *k), model.pop_back());
+                    full.next_back();
+                }
+            }
+            assert_eq!(iter.next(), None);
+            assert_eq!(iter.next_back(), None);
+        }
+    }
+
+    #[cfg(feature = "set")]
+    {
+        let set_store = BTreeStore::new();
+        let mut set = BTreeSet::new_in(&set_store);
+        set.extend(0..10);
+        let mut iter = set.iter();
+        assert_eq!((iter.peek(), iter.peek_back()), (Some(&0), Some(&9)));
+        iter.next_back();
+        assert_eq!(iter.peek_back(), Some(&8));
+        let mut range = set.try_range(3..5).unwrap();
+        assert_eq!((range.peek(), range.peek_back()), (Some(&3), Some(&4)));
+        range.next();
+        assert_eq!((range.peek(), range.peek_back()), (Some(&4), Some(&4)));
+        range.next_back();
+        assert_eq!((range.peek(), range.peek_back()), (None, None));
+    }
+}
+
+#[test]
+fn clone_iterators() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..100 {
+        map.insert(i, i * 2);
+    }
+
+    let mut iter = map.iter();
+    iter.nth(10);
+    iter.nth_back(10);
+    let mut probe = iter.clone();
+    assert_eq!(probe.nth(20), Some((&31, &62)));
+    assert_eq!(probe.len(), 57);
+    assert_eq!(iter.len(), 78);
+    assert!(iter.clone().eq(map.iter().skip(11).take(78)));
+    assert_eq!(iter.next(), Some((&11, &22)));
+
+    let mut range = map.try_range(40..60).unwrap();
+    range.next_back();
+    let saved = range.clone();
+    assert_eq!(range.by_ref().count(), 19);
+    assert_eq!(range.next(), None);
+    assert!(saved.map(|(k, _)| *k).eq(40..59));
+
+    let keys = map.keys().skip(5);
+    assert!(keys.clone().eq(keys));
+    let values = map.values();
+    assert_eq!(values.clone().sum::<i32>(), values.sum::<i32>());
+
+    #[cfg(feature = "set")]
+    {
+        let set_store = BTreeStore::new();
+        let mut set = BTreeSet::new_in(&set_store);
+        set.extend(0..50);
+        // Two pointers over a single set: pairs summing to 49
+        let mut pairs = 0;
+        let mut lo = set.iter();
+        while let Some(&a) = lo.next() {
+            let mut hi = lo.clone();
+            if hi.any(|&b| a + b == 49) {
+                pairs += 1;
+            }
+        }
+        assert_eq!(pairs, 25);
+        let range = set.try_range(10..20).unwrap();
+        assert!(range.clone().eq(range));
+    }
+}
+
+#[cfg(feature = "set")]
+#[test]
+fn set_operations() {
+    use std::collections::BTreeSet as StdSet;
+
+    let a_store = BTreeStore::new();
+    let b_store = BTreeStore::new();
+    for (a_len, b_len) in [(0, 0), (0, 10), (10, 0), (100, 100), (1000, 30), (30, 1000)] {
+        // In different stores
+        let mut a = BTreeSet::new_in(&a_store);
+        let mut b = BTreeSet::new_in(&b_store);
+        a.extend((0..a_len).map(|i| i * 2));
+        b.extend((0..b_len).map(|i| i * 3));
+        let std_a = a.iter().copied().collect::<StdSet<_>>();
+        let std_b = b.iter().copied().collect::<StdSet<_>>();
+
+        let union = a.union(&b);
+        let (lower, upper) = union.size_hint();
+        let len = std_a.union(&std_b).count();
+        assert!(lower <= len && len <= upper.unwrap());
+        assert!(union.eq(std_a.union(&std_b)), "{a_len}, {b_len}");
+        assert!(a.intersection(&b).eq(std_a.intersection(&std_b)));
+        assert!(a.difference(&b).eq(std_a.difference(&std_b)));
+        assert!(b.difference(&a).eq(std_b.difference(&std_a)));
+        assert!(a
+            .symmetric_difference(&b)
+            .eq(std_a.symmetric_difference(&std_b)));
+
+        // Lazy: a partially consumed clone continues from the same position
+        let mut intersection = a.intersection(&b);
+        intersection.next();
+        assert!(intersection.clone().eq(intersection));
+    }
+}
+
+#[cfg(feature = "set")]
+#[test]
+fn set_operators() {
+    let a_store = BTreeStore::new();
+    let b_store = BTreeStore::new();
+    let mut a = BTreeSet::new_in(&a_store);
+    let mut b = BTreeSet::new_in(&b_store);
+    a.extend((0..1000).map(|i| i * 2));
+    b.extend((0..1000).map(|i| i * 3));
+
+    // Operators build in the left set's store, so `&b | &a` is in `b_store`
+    let union = &a | &b;
+    union.validate();
+    assert!(union.iter().eq(a.union(&b)));
+    assert_eq!(&b | &a, union);
+    let intersection = &a & &b;
+    intersection.validate();
+    assert!(intersection
+        .iter()
+        .copied()
+        .eq((0..3000).step_by(6).take(334)));
+    let difference = &a - &b;
+    difference.validate();
+    assert!(difference.iter().eq(a.difference(&b)));
+    let symmetric_difference = &a ^ &b;
+    symmetric_difference.validate();
+    assert!(symmetric_difference.iter().eq(a.symmetric_difference(&b)));
+    assert_eq!(symmetric_difference.len(), union.len() - intersection.len());
+
+    // Or in any other store
+    let out_store = BTreeStore::new();
+    let union = a.union_in(&b, &out_store);
+    union.validate();
+    assert!(union.iter().eq(a.union(&b)));
+    assert!(a.intersection_in(&b, &out_store).iter().eq(&intersection));
+    assert!(b.difference_in(&a, &out_store).iter().eq(b.difference(&a)));
+    assert!(BTreeSet::new_in(&out_store)
+        .symmetric_difference_in(&a, &out_store)
+        .iter()
+        .eq(&a));
+}
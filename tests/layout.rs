@@ -0,0 +1,21 @@
+use btree_plus_store::{assert_node_size, BTreeStore};
+use std::mem::size_of;
+
+type Store = BTreeStore<u64, u64>;
+
+assert_node_size!(u32, u32, 256);
+assert_node_size!(u64, (), 256);
+assert_node_size!(u64, (), capacity = 32, 1024);
+const _: () = assert!(Store::NODE_SIZE >= Store::NODE_CAPACITY * 2 * size_of::<u64>());
+const _: () = assert!(BTreeStore::<u64, [u8; 64]>::NODE_SIZE > Store::NODE_SIZE);
+
+#[test]
+fn layout_constants() {
+    assert_eq!(Store::NODE_SIZE % Store::NODE_ALIGN, 0);
+    assert_eq!(
+        Store::ENTRY_OVERHEAD,
+        Store::NODE_SIZE / Store::NODE_CAPACITY - 2 * size_of::<u64>()
+    );
+    assert_eq!(BTreeStore::<u64, u64, 4>::NODE_CAPACITY, 4);
+    assert!(BTreeStore::<u64, u64, 32>::NODE_SIZE > Store::NODE_SIZE);
+}
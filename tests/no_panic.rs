@@ -0,0 +1,74 @@
+//! Checks that lookups, iteration, and the `try_` variants of insertion and removal have no panic
+//! branches, by failing to link if they do (the same trick as the `dont_panic` crate). Only
+//! meaningful with optimizations, so it only runs with `cargo test --release`.
+#![cfg(not(debug_assertions))]
+
+use btree_plus_store::{BTreeMap, BTreeStore};
+
+/// If `f` may unwind, the guard is dropped in the landing pad, which references a symbol that
+/// doesn't exist.
+#[inline(always)]
+fn dont_panic<R>(f: impl FnOnce() -> R) -> R {
+    struct Guard;
+
+    impl Drop for Guard {
+        #[inline(always)]
+        fn drop(&mut self) {
+            extern "C" {
+                #[link_name = "\n\nERROR: a core path of btree-plus-store may panic\n\n"]
+                fn may_panic() -> !;
+            }
+            unsafe { may_panic() }
+        }
+    }
+
+    let guard = Guard;
+    let result = f();
+    std::mem::forget(guard);
+    result
+}
+
+#[inline(never)]
+fn lookups(map: &BTreeMap<u64, u64>, key: u64) -> (Option<u64>, bool, usize, u64) {
+    dont_panic(|| {
+        let value = map.get(&key).copied();
+        let contains = map.contains_key(&(key + 1));
+        let range_len = match map.try_range(key..key + 10) {
+            Ok(range) => range.count(),
+            Err(_) => 0,
+        };
+        let sum = map.iter().map(|(_, v)| *v).fold(0, u64::wrapping_add);
+        (value, contains, range_len, sum)
+    })
+}
+
+#[inline(never)]
+fn mutations<'store>(
+    map: &mut BTreeMap<'store, u64, u64>,
+    other: &mut BTreeMap<'store, u64, u64>,
+    key: u64,
+) -> Result<usize, btree_plus_store::Error> {
+    dont_panic(|| {
+        map.try_insert(key, key)?;
+        map.try_extend([(key + 1, 0), (key + 3, 0)])?;
+        let removed = map.try_remove_range(key..key + 2)?;
+        map.try_concat(other)?;
+        Ok(removed)
+    })
+}
+
+#[test]
+fn core_paths_dont_panic() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..100 {
+        map.insert(i * 2, i);
+    }
+    assert_eq!(lookups(&map, 20), (Some(10), false, 5, 4950));
+
+    let mut other = BTreeMap::new_in(&store);
+    other.insert(1000, 0);
+    assert_eq!(mutations(&mut map, &mut other, 301), Ok(2));
+    assert!(other.is_empty());
+    assert_eq!(map.len(), 102);
+}
@@ -0,0 +1,158 @@
+#![cfg(feature = "order-statistic")]
+
+#[cfg(feature = "set")]
+use btree_plus_store::BTreeSet;
+use btree_plus_store::{BTreeMap, BTreeStore, Error};
+use rand::{rngs::SmallRng, seq::SliceRandom, SeedableRng};
+use std::ops::Bound;
+
+const SEED: &[u8; 32] = b"testseedtestseedtestseedtestseed";
+
+#[test]
+fn insert_full() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    let mut keys = (0..500).map(|i| i * 2).collect::<Vec<_>>();
+    keys.shuffle(&mut SmallRng::from_seed(*SEED));
+
+    let mut sorted = Vec::new();
+    for &key in &keys {
+        let expected_index = sorted.partition_point(|&k| k < key);
+        sorted.insert(expected_index, key);
+        assert_eq!(map.insert_full(key, key), (expected_index, None));
+        map.validate();
+    }
+    for (index, &key) in sorted.iter().enumerate() {
+        assert_eq!(map.insert_full(key, key + 1), (index, Some(key)));
+        assert_eq!(map.index_of(&key), Some(index));
+        assert_eq!(map.get_index(index), Some((&key, &(key + 1))));
+        assert_eq!(map.index_of(&(key + 1)), None);
+    }
+    assert_eq!(map.get_index(500), None);
+
+    for &key in &keys[..250] {
+        map.remove(&key);
+        map.validate();
+    }
+    sorted.retain(|key| !keys[..250].contains(key));
+    for (index, key) in sorted.iter().enumerate() {
+        assert_eq!(map.index_of(key), Some(index));
+        assert_eq!(map.get_index(index).map(|(k, _)| k), Some(key));
+    }
+}
+
+#[cfg(feature = "set")]
+#[test]
+fn set_insert_full() {
+    let store = BTreeStore::new();
+    let mut set = BTreeSet::new_in(&store);
+
+    for i in (0..100).rev() {
+        assert_eq!(set.insert_full(i), (0, true));
+    }
+    assert_eq!(set.insert_full(42), (42, false));
+    assert_eq!(set.index_of(&99), Some(99));
+    assert_eq!(set.get_index(7), Some(&7));
+    set.validate();
+}
+
+#[test]
+fn quantile() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    assert_eq!(map.try_quantile(0.5), Ok(None));
+    assert_eq!(map.median(), None);
+
+    map.insert(10, 0);
+    assert_eq!(map.try_quantile(0.0), Ok(Some((&10, &0))));
+    assert_eq!(map.try_quantile(1.0), Ok(Some((&10, &0))));
+    assert_eq!(map.median(), Some((&10, &0)));
+
+    for i in 0..=1000 {
+        map.insert(i * 10, i);
+    }
+    assert_eq!(map.try_quantile(0.0), Ok(Some((&0, &0))));
+    assert_eq!(map.try_quantile(0.25), Ok(Some((&2500, &250))));
+    assert_eq!(map.try_quantile(0.99), Ok(Some((&9900, &990))));
+    assert_eq!(map.try_quantile(1.0), Ok(Some((&10000, &1000))));
+    assert_eq!(map.median(), Some((&5000, &500)));
+    map.remove(&10000);
+    assert_eq!(map.median(), Some((&4990, &499)));
+
+    assert_eq!(map.try_quantile(-0.1), Err(Error::InvalidQuantile));
+    assert_eq!(map.try_quantile(1.5), Err(Error::InvalidQuantile));
+    assert_eq!(map.try_quantile(f64::NAN), Err(Error::InvalidQuantile));
+
+    #[cfg(feature = "set")]
+    {
+        let set_store = BTreeStore::new();
+        let mut set = BTreeSet::new_in(&set_store);
+        set.extend(0..100);
+        assert_eq!(set.median(), Some(&49));
+        assert_eq!(set.try_quantile(0.9), Ok(Some(&89)));
+    }
+}
+
+#[test]
+fn range_step() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..1000 {
+        map.insert(i * 2, i);
+    }
+
+    let bounds = [
+        (0, 2000),
+        (1, 1999),
+        (10, 11),
+        (500, 501),
+        (3, 3),
+        (1998, 5000),
+    ];
+    for step in [1, 2, 7, 100, 5000] {
+        for (start, end) in bounds {
+            let expected = map
+                .try_range(start..end)
+                .unwrap()
+                .step_by(step)
+                .collect::<Vec<_>>();
+            let stepped = map.try_range_step(start..end, step).unwrap();
+            assert_eq!(stepped.len(), expected.len());
+            assert_eq!(stepped.collect::<Vec<_>>(), expected);
+
+            let stepped = map.try_range_step(start..=end, step).unwrap();
+            let expected = map.try_range(start..=end).unwrap().step_by(step);
+            assert!(stepped
+                .rev()
+                .eq(expected.collect::<Vec<_>>().into_iter().rev()));
+        }
+    }
+
+    let stepped = map.try_range_step(.., 300).unwrap();
+    assert!(stepped.clone().eq(stepped));
+    let mut stepped = map.try_range_step(.., 300).unwrap();
+    assert_eq!(stepped.next(), Some((&0, &0)));
+    assert_eq!(stepped.next_back(), Some((&1800, &900)));
+    assert_eq!(stepped.next(), Some((&600, &300)));
+    assert_eq!(stepped.next_back(), Some((&1200, &600)));
+    assert_eq!(stepped.next(), None);
+    assert_eq!(stepped.next_back(), None);
+
+    assert_eq!(map.try_range_step(.., 0).err(), Some(Error::InvalidStep));
+    assert_eq!(
+        map.try_range_step((Bound::Included(5), Bound::Excluded(3)), 1)
+            .err(),
+        Some(Error::InvalidRange)
+    );
+
+    #[cfg(feature = "set")]
+    {
+        let store = BTreeStore::new();
+        let mut set = BTreeSet::new_in(&store);
+        set.extend(0..100);
+        assert!(set
+            .try_range_step(10.., 25)
+            .unwrap()
+            .eq([10, 35, 60, 85].iter()));
+    }
+}
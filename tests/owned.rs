@@ -0,0 +1,140 @@
+#[cfg(feature = "set")]
+use btree_plus_store::OwnedBTreeSet;
+use btree_plus_store::{BTreeStoreBuilder, OwnedBTreeMap, SearchStrategy};
+
+/// No `'store` lifetime, so it can be a field of a `'static` struct
+struct Index {
+    by_id: OwnedBTreeMap<u32, String>,
+}
+
+#[test]
+fn owned_map() {
+    let mut index = Index {
+        by_id: OwnedBTreeMap::new(),
+    };
+    for i in 0..1000 {
+        assert_eq!(index.by_id.insert(i, i.to_string()), None);
+    }
+    index.by_id.as_map().validate();
+    assert_eq!(index.by_id.len(), 1000);
+    assert_eq!(index.by_id.get(&42).map(String::as_str), Some("42"));
+    index.by_id.get_mut(&42).unwrap().push('!');
+    assert_eq!(index.by_id.remove(&42).as_deref(), Some("42!"));
+    index.by_id.with_mut(|map| map.retain(|k, _| k % 2 == 0));
+    index.by_id.as_map().validate();
+    assert_eq!(index.by_id.len(), 499);
+
+    // Moves to another thread along with its store
+    let by_id = std::thread::spawn(move || {
+        let mut by_id = index.by_id;
+        by_id.insert(1, "one".to_string());
+        by_id
+    })
+    .join()
+    .unwrap();
+    assert_eq!(by_id.get(&1).map(String::as_str), Some("one"));
+
+    let copy = by_id.clone();
+    assert_eq!(copy, by_id);
+    drop(by_id);
+    assert_eq!(copy.len(), 500);
+
+    let collected = (0..100)
+        .map(|i| (i % 10, i))
+        .collect::<OwnedBTreeMap<_, _>>();
+    collected.as_map().validate();
+    assert!(collected
+        .as_map()
+        .iter()
+        .map(|(k, v)| (*k, *v))
+        .eq((0..10).map(|i| (i, i + 90))));
+
+    let store = BTreeStoreBuilder::new()
+        .search_strategy(SearchStrategy::Linear)
+        .build();
+    let mut map = OwnedBTreeMap::with_store(store);
+    map.extend([(2, 'b'), (1, 'a')]);
+    assert_eq!(map.store().search_strategy(), SearchStrategy::Linear);
+    assert!(map.as_map().keys().copied().eq([1, 2]));
+    map.clear();
+    assert!(map.is_empty());
+}
+
+#[cfg(feature = "set")]
+#[test]
+fn owned_set() {
+    let mut set = OwnedBTreeSet::new();
+    for i in (0..100).rev() {
+        assert!(set.insert(i));
+    }
+    assert!(!set.insert(50));
+    assert!(set.remove(&50));
+    assert!(!set.contains(&50));
+    set.with_mut(|set| set.retain(|value| value % 10 != 0));
+    set.as_set().validate();
+    assert_eq!(set.len(), 90);
+
+    let copy = set.clone();
+    set.clear();
+    assert!(set.is_empty());
+    assert_eq!(copy.len(), 90);
+    let collected = [3, 1, 2].into_iter().collect::<OwnedBTreeSet<_>>();
+    assert!(collected.as_set().iter().copied().eq(1..=3));
+}
+
+#[test]
+fn shared_store() {
+    use btree_plus_store::BTreeStore;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    // The trees and the store they share can live in the same struct
+    type Edges = OwnedBTreeMap<u32, u32, Rc<BTreeStore<u32, u32>>>;
+    struct Graph {
+        store: Rc<BTreeStore<u32, u32>>,
+        edges: Vec<Edges>,
+    }
+    let mut graph = Graph {
+        store: Rc::new(BTreeStore::new()),
+        edges: Vec::new(),
+    };
+    for i in 0..10 {
+        let mut edges = OwnedBTreeMap::new_in(graph.store.clone());
+        edges.extend((0..i * 10).map(|j| (j, i)));
+        graph.edges.push(edges);
+    }
+    let copy = graph.edges[9].clone();
+    assert!(Rc::ptr_eq(&copy.store_ref(), &graph.store));
+    assert_eq!(copy, graph.edges[9]);
+    graph.edges.truncate(5);
+    drop(copy);
+    assert_eq!(Rc::strong_count(&graph.store), 6);
+    for (i, edges) in graph.edges.iter().enumerate() {
+        edges.as_map().validate();
+        assert_eq!(edges.len(), i * 10);
+    }
+    // The store outlives the handle it was created from
+    let edges = graph.edges.pop().unwrap();
+    drop(graph);
+    assert_eq!(edges.get(&0), Some(&4));
+
+    // The store is only `Sync` with `sync-store`, but `Arc` works as a handle either way
+    #[allow(clippy::arc_with_non_send_sync)]
+    let store = Arc::new(BTreeStore::new());
+    let mut a = OwnedBTreeMap::new_in(Arc::clone(&store));
+    let mut b = OwnedBTreeMap::new_in(store);
+    a.insert("a", 1);
+    b.insert("b", 2);
+    drop(a);
+    assert_eq!(b.get("b"), Some(&2));
+
+    #[cfg(feature = "set")]
+    {
+        let store = Rc::new(BTreeStore::new());
+        let mut set = OwnedBTreeSet::new_in(store.clone());
+        set.extend(0..100);
+        let copy = set.clone();
+        assert!(Rc::ptr_eq(&copy.store_ref(), &store));
+        assert_eq!(copy, set);
+    }
+}
@@ -0,0 +1,97 @@
+#[cfg(feature = "set")]
+use btree_plus_store::BTreeSet;
+use btree_plus_store::{prefix_range, BTreeMap, BTreeStore, Prefix};
+use std::ops::{Bound, RangeBounds};
+
+#[test]
+fn prefix_end() {
+    assert_eq!(b"ab".prefix_end(), Some(b"ac".to_vec()));
+    assert_eq!(b"a\xFF\xFF".prefix_end(), Some(b"b".to_vec()));
+    assert_eq!(b"\xFF\xFF".prefix_end(), None);
+    assert_eq!(b"".prefix_end(), None);
+    assert_eq!("ab".prefix_end(), Some("ac".to_string()));
+    assert_eq!("a\u{D7FF}".prefix_end(), Some("a\u{E000}".to_string()));
+    assert_eq!("a\u{10FFFF}".prefix_end(), Some("b".to_string()));
+    assert_eq!("\u{10FFFF}".prefix_end(), None);
+    assert_eq!("".prefix_end(), None);
+
+    let range = prefix_range("");
+    assert_eq!(range.start_bound(), Bound::Included(""));
+    assert_eq!(range.end_bound(), Bound::Unbounded);
+}
+
+#[test]
+fn byte_prefixes() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    let bytes = [0x00, 0x01, 0x7F, 0xFE, 0xFF];
+    for a in bytes {
+        map.insert(vec![a], 1);
+        for b in bytes {
+            map.insert(vec![a, b], 2);
+            for c in bytes {
+                map.insert(vec![a, b, c], 3);
+            }
+        }
+    }
+    map.insert(vec![], 0);
+
+    let prefixes: [&[u8]; 7] = [
+        b"",
+        b"\x00",
+        b"\xFE",
+        b"\xFF",
+        b"\x7F\xFF",
+        b"\xFF\xFF",
+        b"\xFF\xFF\xFF",
+    ];
+    for prefix in prefixes {
+        assert!(map
+            .iter_prefix(prefix)
+            .map(|(k, _)| k)
+            .eq(map.iter().map(|(k, _)| k).filter(|k| k.starts_with(prefix))));
+    }
+
+    for (_, val) in map.iter_prefix_mut(b"\xFF".as_slice()) {
+        *val += 10;
+    }
+    assert_eq!(map.get(b"\xFF\xFF".as_slice()), Some(&12));
+    assert_eq!(map.get(b"\xFE\xFF".as_slice()), Some(&2));
+}
+
+#[cfg(feature = "set")]
+#[test]
+fn string_prefixes() {
+    let store = BTreeStore::new();
+    let mut set = BTreeSet::new_in(&store);
+    let chars = [
+        'a',
+        'b',
+        '\u{7F}',
+        '\u{D7FF}',
+        '\u{E000}',
+        '\u{FFFF}',
+        '\u{10FFFF}',
+    ];
+    for a in chars {
+        for b in chars {
+            set.insert(format!("{}{}", a, b));
+            set.insert(format!("{}{}z", a, b));
+        }
+    }
+
+    for prefix in [
+        "",
+        "a",
+        "ab",
+        "\u{D7FF}",
+        "\u{10FFFF}",
+        "a\u{10FFFF}",
+        "\u{10FFFF}\u{10FFFF}",
+    ] {
+        assert!(set
+            .iter_prefix(prefix)
+            .eq(set.iter().filter(|k| k.starts_with(prefix))));
+    }
+    assert_eq!(set.iter_prefix("\u{D7FF}").count(), 14);
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "rayon")]
+
+#[cfg(feature = "set")]
+use btree_plus_store::BTreeSet;
+use btree_plus_store::{BTreeMap, BTreeStore};
+use rayon::prelude::*;
+
+#[test]
+fn par_iter() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::from_iter_in((0..10_000).map(|i| (i, i * 2)), &store);
+    let entries = map.par_iter().map(|(&k, &v)| (k, v)).collect::<Vec<_>>();
+    assert!(map.iter().map(|(&k, &v)| (k, v)).eq(entries));
+    assert_eq!(
+        map.par_iter().map(|(_, &v)| v as u64).sum::<u64>(),
+        9999 * 10_000
+    );
+    assert_eq!(
+        map.par_iter().find_first(|(&k, _)| k > 5000),
+        Some((&5001, &10_002))
+    );
+
+    map.par_iter_mut().for_each(|(k, v)| *v += k);
+    assert!(map.iter().all(|(&k, &v)| v == k * 3));
+
+    let empty = BTreeMap::<i32, i32>::new_in(&store);
+    assert_eq!(empty.par_iter().count(), 0);
+}
+
+#[test]
+fn par_build_and_extend() {
+    let store = BTreeStore::new();
+    for len in [0, 1, 7, 9, 100, 10_000] {
+        // Repeated keys keep the last value
+        let entries = (0..len).chain(0..len / 2).enumerate().map(|(v, k)| (k, v));
+        let map = BTreeMap::par_from_iter_in(entries.clone().collect::<Vec<_>>(), &store);
+        map.validate();
+        assert_eq!(map, BTreeMap::from_iter_in(entries, &store));
+    }
+
+    let mut map = BTreeMap::new_in(&store);
+    map.par_extend((0..1000).into_par_iter().map(|i| (i * 2, i)));
+    map.par_extend((0..1000).into_par_iter().map(|i| (i * 3, i)));
+    map.validate();
+    let mut expected = std::collections::BTreeMap::new();
+    expected.extend((0..1000).map(|i| (i * 2, i)));
+    expected.extend((0..1000).map(|i| (i * 3, i)));
+    assert_eq!(map, expected);
+}
+
+#[cfg(feature = "set")]
+#[test]
+fn par_set() {
+    let store = BTreeStore::new();
+    let mut set = BTreeSet::par_from_iter_in((0..5000).into_par_iter().rev(), &store);
+    set.par_extend(vec![10_000, 3]);
+    assert_eq!(set.len(), 5001);
+    assert_eq!(set.par_iter().copied().max(), Some(10_000));
+    assert!(set
+        .par_iter()
+        .copied()
+        .collect::<Vec<_>>()
+        .iter()
+        .eq(set.iter()));
+}
@@ -0,0 +1,57 @@
+#![cfg(feature = "serde")]
+
+use btree_plus_store::map::{MapSeed, SerdeForm};
+use btree_plus_store::{BTreeMap, BTreeStore};
+use serde::de::DeserializeSeed;
+
+#[test]
+fn object_form() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..100 {
+        map.insert(format!("key{:03}", i), i);
+    }
+
+    let json = serde_json::to_string(&map).unwrap();
+    assert!(json.starts_with(r#"{"key000":0,"key001":1,"#));
+
+    let store2 = BTreeStore::new();
+    let mut deserializer = serde_json::Deserializer::from_str(&json);
+    let map2: BTreeMap<String, i32> = MapSeed::new(&store2)
+        .deserialize(&mut deserializer)
+        .unwrap();
+    map2.validate();
+    assert_eq!(map2.len(), 100);
+    assert!(map.iter().eq(map2.iter()));
+}
+
+#[test]
+fn seq_form() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..100u32 {
+        map.insert((i, i * 2), -(i as i64));
+    }
+
+    let json = serde_json::to_string(&map.serialize_as(SerdeForm::Seq)).unwrap();
+    assert!(json.starts_with("[[[0,0],0],[[1,2],-1],"));
+
+    let mut deserializer = serde_json::Deserializer::from_str(&json);
+    let map2: BTreeMap<(u32, u32), i64> = MapSeed::new(&store)
+        .form(SerdeForm::Seq)
+        .deserialize(&mut deserializer)
+        .unwrap();
+    map2.validate();
+    assert!(map.iter().eq(map2.iter()));
+
+    // An object isn't accepted when expecting a sequence, and vice versa
+    let mut deserializer = serde_json::Deserializer::from_str(r#"{"a": 1}"#);
+    assert!(MapSeed::<String, i32>::new(&BTreeStore::new())
+        .form(SerdeForm::Seq)
+        .deserialize(&mut deserializer)
+        .is_err());
+    let mut deserializer = serde_json::Deserializer::from_str(r#"[["a", 1]]"#);
+    assert!(MapSeed::<String, i32>::new(&BTreeStore::new())
+        .deserialize(&mut deserializer)
+        .is_err());
+}
@@ -1,4 +1,8 @@
-use btree_plus_store::{BTreeMap, BTreeStore};
+use btree_plus_store::{
+    BTreeMap, BTreeStore, FrozenExportHeader, FrozenLayout, FrozenMap, Interner,
+};
+#[cfg(feature = "set")]
+use btree_plus_store::{BTreeSet, FrozenSet};
 
 #[test]
 fn shared_between_2() {
@@ -127,3 +131,187 @@ fn shared_between_3() {
     assert_eq!(map2.get(&3).map(|x| *x), Some("Tres"));
     assert_eq!(map3.get(&3).map(|x| *x), Some("Drei"));
 }
+
+#[test]
+fn shared_interned_keys() {
+    let interner = Interner::<str>::new();
+    let store = BTreeStore::new();
+
+    let mut maps = (0..10)
+        .map(|_| BTreeMap::new_in(&store))
+        .collect::<Vec<_>>();
+
+    for (i, map) in maps.iter_mut().enumerate() {
+        for j in 0..100 {
+            map.insert(interner.intern(&format!("key {}", j)), i * j);
+        }
+    }
+
+    assert_eq!(interner.len(), 100);
+    for (i, map) in maps.iter().enumerate() {
+        map.validate();
+        assert_eq!(map.len(), 100);
+        assert_eq!(map.get("key 42"), Some(&(i * 42)));
+        assert_eq!(map.get("key 100"), None);
+    }
+
+    let key = interner.get("key 7").unwrap();
+    assert!(maps
+        .iter()
+        .all(|map| std::ptr::eq(map.get_key("key 7").unwrap().get(), key.get())));
+}
+
+#[test]
+fn frozen_across_threads() {
+    let store = BTreeStore::new();
+    let mut maps = (0..4)
+        .map(|i| {
+            let mut map = BTreeMap::new_in(&store);
+            for j in 0..1000 {
+                map.insert(j, i * j);
+            }
+            FrozenMap::new(map)
+        })
+        .collect::<Vec<_>>();
+    #[cfg(feature = "set")]
+    let set_store = BTreeStore::new();
+    #[cfg(feature = "set")]
+    let set = {
+        let mut set = BTreeSet::new_in(&set_store);
+        set.extend(0..1000);
+        FrozenSet::from(set)
+    };
+
+    std::thread::scope(|scope| {
+        for t in 0..8 {
+            let maps = &maps;
+            #[cfg(feature = "set")]
+            let set = &set;
+            scope.spawn(move || {
+                for (i, map) in maps.iter().enumerate() {
+                    assert_eq!(map.len(), 1000);
+                    assert_eq!(map.get(&t), Some(&(i * t)));
+                    assert!(map.iter_from(&(t * 100)).map(|(k, _)| *k).eq(t * 100..1000));
+                }
+                #[cfg(feature = "set")]
+                {
+                    assert!(set.contains(&(t * 10)));
+                    assert_eq!(set.iter().count(), 1000);
+                }
+            });
+        }
+
+        // The owning thread can keep using the store for other trees meanwhile
+        let mut other = BTreeMap::new_in(&store);
+        for j in 0..1000 {
+            other.insert(j, j);
+        }
+        other.validate();
+    });
+
+    let mut map = maps.pop().unwrap().into_inner();
+    map.insert(1000, 0);
+    map.validate();
+}
+
+#[test]
+fn frozen_eytzinger() {
+    let store = BTreeStore::new();
+    for len in [0i32, 1, 2, 3, 7, 8, 100, 1000] {
+        let mut map = BTreeMap::new_in(&store);
+        map.extend((0..len).map(|i| (i * 2, i)));
+        let map = FrozenMap::with_layout(map, FrozenLayout::Eytzinger);
+        assert_eq!(map.layout(), FrozenLayout::Eytzinger);
+        for key in -1..len * 2 + 1 {
+            let expected = ((0..len * 2).contains(&key) && key % 2 == 0).then_some(key / 2);
+            assert_eq!(map.get(&key), expected.as_ref(), "len {len}, key {key}");
+            assert_eq!(map.contains_key(&key), expected.is_some());
+        }
+        assert_eq!(map.len(), len as usize);
+        assert_eq!(map.into_inner().len(), len as usize);
+    }
+
+    let string_store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&string_store);
+    map.extend((0..100).map(|i| (format!("{:03}", i), i)));
+    let map = FrozenMap::with_layout(map, FrozenLayout::Eytzinger);
+    assert_eq!(map.get_key_value("042"), Some((&"042".to_string(), &42)));
+    assert_eq!(map.get("42"), None);
+    assert_eq!(
+        FrozenMap::new(map.into_inner()).layout(),
+        FrozenLayout::Tree
+    );
+}
+
+#[test]
+fn frozen_export() {
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Point {
+        x: u8,
+        y: f64,
+    }
+
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    map.extend((0..1000u16).map(|i| {
+        (
+            i * 3,
+            Point {
+                x: i as u8,
+                y: i as f64,
+            },
+        )
+    }));
+    let export = FrozenMap::new(map).export();
+
+    let header = export.header();
+    assert_eq!(header.magic, FrozenExportHeader::MAGIC);
+    assert_eq!(header.version, FrozenExportHeader::VERSION);
+    assert_eq!(header.len, 1000);
+    assert_eq!(header.key_size, 2);
+    assert_eq!(header.value_size, 16);
+    assert_eq!(header.total_size as usize, export.size());
+    assert_eq!(export.as_ptr() as usize % 8, 0);
+
+    // Read the buffer through the documented layout only, like a C reader
+    let ptr = export.as_ptr();
+    let get = |key: u16| unsafe {
+        let header = &*ptr.cast::<FrozenExportHeader>();
+        let keys = ptr.add(header.keys_offset as usize).cast::<u16>();
+        let values = ptr.add(header.values_offset as usize).cast::<Point>();
+        let (mut lo, mut hi) = (0, header.len as usize);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if *keys.add(mid) < key {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        (lo < header.len as usize && *keys.add(lo) == key).then(|| *values.add(lo))
+    };
+    for key in 0..3001 {
+        let expected = (key % 3 == 0 && key < 3000).then_some(Point {
+            x: (key / 3) as u8,
+            y: (key / 3) as f64,
+        });
+        assert_eq!(get(key), expected);
+        assert_eq!(export.get(&key).copied(), expected);
+    }
+    assert!(export.keys().windows(2).all(|w| w[0] < w[1]));
+
+    let empty = FrozenMap::new(BTreeMap::<u64, u64>::new_in(&BTreeStore::new())).export();
+    assert!(empty.is_empty());
+    assert_eq!(empty.get(&0), None);
+
+    #[cfg(feature = "set")]
+    {
+        let set_store = BTreeStore::new();
+        let mut set = BTreeSet::new_in(&set_store);
+        set.extend([5i32, 1, 3]);
+        let export = FrozenSet::new(set).export();
+        assert_eq!(export.keys(), [1, 3, 5]);
+        assert_eq!(export.header().value_size, 0);
+    }
+}
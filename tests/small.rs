@@ -0,0 +1,75 @@
+#[cfg(feature = "set")]
+use btree_plus_store::SmallBTreeSet;
+use btree_plus_store::{BTreeStore, SmallBTreeMap};
+
+#[test]
+fn small_map() {
+    let store = BTreeStore::new();
+    let mut map = SmallBTreeMap::new_in(&store);
+    let mut expected = std::collections::BTreeMap::new();
+    for i in [3, 1, 4, 2] {
+        assert_eq!(map.insert(i, i * 10), None);
+        expected.insert(i, i * 10);
+    }
+    assert_eq!(map.insert(4, 41), Some(40));
+    expected.insert(4, 41);
+    *map.get_mut(&1).unwrap() += 1;
+    expected.insert(1, 11);
+    assert!(map.is_inline());
+    assert_eq!(store.live_nodes(), 0);
+    assert!(map.iter().eq(expected.iter()));
+    assert!(map.iter().rev().eq(expected.iter().rev()));
+
+    // Spills into the store past the inline capacity
+    for i in 5..20 {
+        map.insert(i, i * 10);
+        expected.insert(i, i * 10);
+    }
+    assert!(!map.is_inline());
+    map.as_tree().unwrap().validate();
+    assert!(store.live_nodes() > 0);
+    assert!(map.iter().eq(expected.iter()));
+    assert_eq!(map.get(&7), Some(&70));
+
+    // Moves back inline once it's small enough
+    for i in 3..20 {
+        assert_eq!(map.remove(&i), expected.remove(&i));
+    }
+    assert!(map.is_inline());
+    assert_eq!(store.live_nodes(), 0);
+    assert!(map.iter().eq(expected.iter()));
+    assert_eq!(map.remove(&1), Some(11));
+    assert_eq!(map.remove(&1), None);
+    assert_eq!(map.len(), 1);
+
+    let copy = map.clone();
+    assert_eq!(copy, map);
+    let map = map.into_map();
+    assert!(map.iter().eq([(&2, &20)]));
+}
+
+#[cfg(feature = "set")]
+#[test]
+fn small_set() {
+    let store = BTreeStore::new();
+    let mut sets = (0..1000)
+        .map(|i| {
+            let mut set = SmallBTreeSet::new_in(&store);
+            set.extend([i % 3, i % 5, i % 3]);
+            set
+        })
+        .collect::<Vec<_>>();
+    assert!(sets.iter().all(SmallBTreeSet::is_inline));
+    assert_eq!(store.live_nodes(), 0);
+    assert!(sets[7].iter().eq(&[1, 2]));
+    assert!(sets[7].contains(&2));
+    assert!(!sets[7].remove(&3));
+
+    sets[0].extend(10..20);
+    assert!(!sets[0].is_inline());
+    assert_eq!(sets[0].len(), 11);
+    sets[0].clear();
+    assert!(sets[0].is_inline());
+    assert!(sets[0].is_empty());
+    assert_eq!(store.live_nodes(), 0);
+}
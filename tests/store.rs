@@ -0,0 +1,860 @@
+#![cfg_attr(feature = "nightly", feature(allocator_api))]
+
+use btree_plus_store::{
+    BTreeMap, BTreeStore, BTreeStoreBuilder, Error, FixedBackend, LeakReport, RebalancePolicy,
+    SearchStrategy, SlabBackend, StoreAllocObserver, StoreStats, UnderflowStrategy,
+};
+use std::alloc::Layout;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[test]
+fn builder() {
+    let live_nodes = Arc::new(AtomicIsize::new(0));
+    let store = BTreeStoreBuilder::new()
+        .capacity(16)
+        .search_strategy(SearchStrategy::Linear)
+        .on_alloc({
+            let live_nodes = live_nodes.clone();
+            move || {
+                live_nodes.fetch_add(1, Ordering::Relaxed);
+            }
+        })
+        .on_dealloc({
+            let live_nodes = live_nodes.clone();
+            move || {
+                live_nodes.fetch_sub(1, Ordering::Relaxed);
+            }
+        })
+        .build();
+    assert_eq!(store.search_strategy(), SearchStrategy::Linear);
+
+    let mut map = BTreeMap::new_in(&store);
+    for i in (0..1000).rev() {
+        map.insert(i, i * 2);
+    }
+    map.validate();
+    assert!(live_nodes.load(Ordering::Relaxed) > 1);
+    for i in 0..1000 {
+        assert_eq!(map.get(&i), Some(&(i * 2)));
+        assert_eq!(map.get(&(i + 1000)), None);
+    }
+    for i in (0..1000).step_by(2) {
+        assert_eq!(map.remove(&i), Some(i * 2));
+    }
+    map.validate();
+
+    drop(map);
+    assert_eq!(live_nodes.load(Ordering::Relaxed), 0);
+}
+
+#[test]
+fn search_strategies() {
+    for strategy in [
+        SearchStrategy::Binary,
+        SearchStrategy::Linear,
+        SearchStrategy::Branchless,
+    ] {
+        let store = BTreeStoreBuilder::new().search_strategy(strategy).build();
+        let mut map = BTreeMap::new_in(&store);
+        for i in (0..1000u64).rev().step_by(2) {
+            map.insert(i, i);
+        }
+        map.validate();
+        for i in 0..1001 {
+            assert_eq!(map.get(&i), (i % 2 == 1).then_some(&i), "{strategy:?}");
+        }
+        assert_eq!(
+            map.try_range(100..=200).unwrap().count(),
+            50,
+            "{strategy:?}"
+        );
+    }
+}
+
+#[test]
+fn node_capacities() {
+    fn check<const B: usize>() {
+        let store = BTreeStoreBuilder::new().node_capacity::<B>().build();
+        let mut map = BTreeMap::new_in(&store);
+        for i in (0..1000u64).rev().step_by(2) {
+            map.insert(i, i);
+        }
+        map.validate();
+        assert!(map.nodes().all(|node| node.keys().len() <= B));
+        for i in 0..1001 {
+            assert_eq!(map.get(&i), (i % 2 == 1).then_some(&i), "{B}");
+        }
+        assert_eq!(map.range(100..=200).count(), 50, "{B}");
+        map.retain(|k, _| k % 3 == 0);
+        map.validate();
+        assert!(map.keys().copied().eq((3..1000).step_by(6)), "{B}");
+        map.clear();
+        assert!(map.is_empty());
+    }
+
+    check::<4>();
+    check::<8>();
+    check::<32>();
+}
+
+#[test]
+fn into_iter_frees_nodes() {
+    let live_nodes = Arc::new(AtomicIsize::new(0));
+    let store = BTreeStoreBuilder::new()
+        .on_alloc({
+            let live_nodes = live_nodes.clone();
+            move || {
+                live_nodes.fetch_add(1, Ordering::Relaxed);
+            }
+        })
+        .on_dealloc({
+            let live_nodes = live_nodes.clone();
+            move || {
+                live_nodes.fetch_sub(1, Ordering::Relaxed);
+            }
+        })
+        .build();
+
+    for len in [1, 5, 100, 2000] {
+        // Consumed from both ends, meeting in the middle
+        let mut map = BTreeMap::new_in(&store);
+        map.extend((0..len).map(|i| (i, i.to_string())));
+        let mut iter = map.into_iter();
+        while iter.next().is_some() && iter.next_back().is_some() {}
+        assert_eq!(live_nodes.load(Ordering::Relaxed), 0, "len {}", len);
+
+        // Dropped early
+        let mut map = BTreeMap::new_in(&store);
+        map.extend((0..len).map(|i| (i, i.to_string())));
+        let mut iter = map.into_iter();
+        iter.nth(len / 2);
+        drop(iter);
+        assert_eq!(live_nodes.load(Ordering::Relaxed), 0, "len {}", len);
+    }
+}
+
+#[derive(Default)]
+struct Usage {
+    slab_bytes: AtomicUsize,
+    node_bytes: AtomicUsize,
+    peak_node_bytes: AtomicUsize,
+}
+
+struct Profiler(Arc<Usage>);
+
+impl StoreAllocObserver for Profiler {
+    fn slab_grew(&self, bytes: usize, total_bytes: usize) {
+        let old = self.0.slab_bytes.fetch_add(bytes, Ordering::Relaxed);
+        assert_eq!(old + bytes, total_bytes);
+    }
+
+    fn slab_shrank(&self, bytes: usize, total_bytes: usize) {
+        let old = self.0.slab_bytes.fetch_sub(bytes, Ordering::Relaxed);
+        assert_eq!(old - bytes, total_bytes);
+    }
+
+    fn node_alloc(&self, bytes: usize) {
+        let node_bytes = self.0.node_bytes.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        self.0
+            .peak_node_bytes
+            .fetch_max(node_bytes, Ordering::Relaxed);
+    }
+
+    fn node_free(&self, bytes: usize) {
+        self.0.node_bytes.fetch_sub(bytes, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn observer() {
+    const NODE_SIZE: usize = BTreeStore::<i32, i32>::NODE_SIZE;
+    let usage = Arc::new(Usage::default());
+    let store = BTreeStoreBuilder::new()
+        .capacity(4)
+        .observer(Profiler(usage.clone()))
+        .build::<i32, i32>();
+    assert_eq!(usage.slab_bytes.load(Ordering::Relaxed), 4 * NODE_SIZE);
+
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..1000 {
+        map.insert(i, i);
+    }
+    let node_bytes = usage.node_bytes.load(Ordering::Relaxed);
+    assert_eq!(node_bytes, map.nodes().count() * NODE_SIZE);
+    let slab_bytes = usage.slab_bytes.load(Ordering::Relaxed);
+    let peak_bytes = usage
+        .peak_node_bytes
+        .load(Ordering::Relaxed)
+        .max(4 * NODE_SIZE);
+    // Under `sync-store`, the slab grows a chunk of nodes at a time
+    #[cfg(not(feature = "sync-store"))]
+    assert_eq!(slab_bytes, peak_bytes);
+    #[cfg(feature = "sync-store")]
+    assert!(slab_bytes >= peak_bytes);
+
+    // Freed slots are reused, so the slab doesn't grow again
+    for i in 0..1000 {
+        map.remove(&i);
+    }
+    assert_eq!(usage.node_bytes.load(Ordering::Relaxed), 0);
+    for i in 0..500 {
+        map.insert(i, i);
+    }
+    assert_eq!(usage.slab_bytes.load(Ordering::Relaxed), slab_bytes);
+
+    drop(map);
+    assert_eq!(usage.node_bytes.load(Ordering::Relaxed), 0);
+    drop(store);
+    assert_eq!(usage.slab_bytes.load(Ordering::Relaxed), 0);
+}
+
+#[test]
+fn rebalance_policy() {
+    let policy = RebalancePolicy::new();
+    let capacity = BTreeStore::<i32, i32>::NODE_CAPACITY;
+    assert_eq!(policy.min_len(capacity), capacity / 2);
+    assert_eq!(policy.min_fill(0.0).min_len(capacity), 1);
+    assert_eq!(policy.min_fill(1.0), policy);
+
+    /// Allocations from repeatedly inserting and removing entries at the start of sparse nodes
+    fn churn(policy: RebalancePolicy) -> isize {
+        let allocs = Arc::new(AtomicIsize::new(0));
+        let store = BTreeStoreBuilder::new()
+            .rebalance_policy(policy)
+            .on_alloc({
+                let allocs = allocs.clone();
+                move || {
+                    allocs.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+            .build();
+        assert_eq!(store.rebalance_policy(), policy);
+        let mut map = BTreeMap::new_in(&store);
+        for i in 0..1000 {
+            map.insert(i * 10, i);
+        }
+        for i in 0..1000 {
+            if i % 4 > 0 {
+                map.remove(&(i * 10));
+            }
+        }
+        map.validate();
+
+        allocs.store(0, Ordering::Relaxed);
+        for i in 0..250 {
+            for _ in 0..10 {
+                map.insert(i * 40 + 1, 0);
+                map.insert(i * 40 + 2, 0);
+                map.remove(&(i * 40 + 1));
+                map.remove(&(i * 40 + 2));
+            }
+        }
+        map.validate();
+        assert_eq!(map.len(), 250);
+        allocs.load(Ordering::Relaxed)
+    }
+
+    assert!(churn(RebalancePolicy::new()) > 0);
+    assert_eq!(churn(RebalancePolicy::new().min_fill(0.25)), 0);
+    churn(RebalancePolicy::new().underflow(UnderflowStrategy::Merge));
+    churn(
+        RebalancePolicy::new()
+            .min_fill(0.0)
+            .underflow(UnderflowStrategy::Merge),
+    );
+}
+
+#[test]
+fn relaxed_repair() {
+    for policy in [
+        RebalancePolicy::new(),
+        RebalancePolicy::new().min_fill(0.25),
+        RebalancePolicy::new().underflow(UnderflowStrategy::Merge),
+    ] {
+        let relaxed_store = BTreeStoreBuilder::new()
+            .rebalance_policy(policy.relaxed(true))
+            .build();
+        let store = BTreeStoreBuilder::new().rebalance_policy(policy).build();
+        for removed in [
+            |i: i32| i % 10 > 0,
+            |i: i32| i % 100 < 97,
+            |i: i32| (300..1900).contains(&i) || i % 7 > 0,
+        ] {
+            let mut relaxed = BTreeMap::new_in(&relaxed_store);
+            let mut map = BTreeMap::new_in(&store);
+            for i in 0..2000 {
+                relaxed.insert(i, i);
+                map.insert(i, i);
+            }
+            for i in (0..2000).filter(|&i| removed(i)) {
+                relaxed.remove(&i);
+                map.remove(&i);
+            }
+            relaxed.validate();
+            assert!(relaxed.nodes().count() > map.nodes().count());
+
+            relaxed.repair();
+            relaxed.validate();
+            assert!(relaxed
+                .nodes()
+                .filter(|node| node.depth() > 0)
+                .all(|node| node.keys().len()
+                    >= policy.min_len(BTreeStore::<i32, i32>::NODE_CAPACITY)));
+            assert!(relaxed.iter().eq(map.iter()));
+
+            // Already balanced, so there's nothing to repair
+            let nodes = map.nodes().count();
+            map.repair();
+            assert_eq!(map.nodes().count(), nodes);
+        }
+    }
+}
+
+#[test]
+fn clone_from_reuses_nodes() {
+    let allocs = Arc::new(AtomicUsize::new(0));
+    let live_nodes = Arc::new(AtomicIsize::new(0));
+    let store = BTreeStoreBuilder::new()
+        .on_alloc({
+            let (allocs, live_nodes) = (allocs.clone(), live_nodes.clone());
+            move || {
+                allocs.fetch_add(1, Ordering::Relaxed);
+                live_nodes.fetch_add(1, Ordering::Relaxed);
+            }
+        })
+        .on_dealloc({
+            let live_nodes = live_nodes.clone();
+            move || {
+                live_nodes.fetch_sub(1, Ordering::Relaxed);
+            }
+        })
+        .build();
+
+    let mut baseline = BTreeMap::new_in(&store);
+    for i in 0..1000 {
+        baseline.insert(i, i.to_string());
+    }
+    let mut working = baseline.clone();
+    working.validate();
+    assert_eq!(working, baseline);
+
+    for frame in 0..10 {
+        for i in (frame..1000).step_by(7) {
+            working.insert(i * 3 + 1, "changed".to_string());
+            working.remove(&(i + 1));
+        }
+        assert_ne!(working, baseline);
+
+        allocs.store(0, Ordering::Relaxed);
+        working.clone_from(&baseline);
+        assert_eq!(allocs.load(Ordering::Relaxed), 0);
+        working.validate();
+        assert_eq!(working, baseline);
+    }
+
+    // Growing allocates only the extra nodes, and shrinking frees the leftovers
+    let mut small = BTreeMap::new_in(&store);
+    small.insert(0, String::new());
+    allocs.store(0, Ordering::Relaxed);
+    small.clone_from(&baseline);
+    let fresh_allocs = allocs.load(Ordering::Relaxed);
+    assert!(fresh_allocs > 0);
+    allocs.store(0, Ordering::Relaxed);
+    drop(baseline.clone());
+    assert_eq!(allocs.load(Ordering::Relaxed), fresh_allocs + 1);
+    let live_before = live_nodes.load(Ordering::Relaxed);
+    small.clone_from(&BTreeMap::new_in(&store));
+    assert!(small.is_empty());
+    assert!(live_nodes.load(Ordering::Relaxed) < live_before);
+
+    // Across stores, `copy_from` keeps the map in its own store
+    let other_store = BTreeStore::new();
+    let mut other = BTreeMap::new_in(&other_store);
+    other.extend((0..100).map(|i| (i, "other".to_string())));
+    working.copy_from(&other);
+    working.validate();
+    assert!(working.iter().eq(other.iter()));
+
+    drop((working, small, baseline));
+    assert_eq!(live_nodes.load(Ordering::Relaxed), 0);
+}
+
+#[test]
+fn copy_from_panicking_clone() {
+    /// Panics when cloned while `PANIC_ON_CLONE` is set
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct Fragile(i32);
+
+    thread_local! {
+        static PANIC_ON_CLONE: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+    }
+
+    impl Clone for Fragile {
+        fn clone(&self) -> Self {
+            if self.0 == 500 && PANIC_ON_CLONE.get() {
+                panic!("clone of {self:?}");
+            }
+            Fragile(self.0)
+        }
+    }
+
+    let store = BTreeStore::new();
+    let mut working = BTreeMap::new_in(&store);
+    working.extend((0..1000).map(|i| (i, Fragile(i))));
+    let baseline = working.clone();
+
+    PANIC_ON_CLONE.set(true);
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        working.clone_from(&baseline)
+    }));
+    PANIC_ON_CLONE.set(false);
+    assert!(result.is_err());
+
+    // The map is empty rather than pointing into its old, emptied nodes
+    working.validate();
+    assert!(working.is_empty());
+    assert_eq!(working.first_key_value(), None);
+    assert_eq!(working.iter().next(), None);
+
+    working.clone_from(&baseline);
+    working.validate();
+    assert_eq!(working, baseline);
+}
+
+#[test]
+fn empty_trees_dont_allocate() {
+    let allocs = Arc::new(AtomicUsize::new(0));
+    let live_nodes = Arc::new(AtomicIsize::new(0));
+    let store = BTreeStoreBuilder::new()
+        .on_alloc({
+            let (allocs, live_nodes) = (allocs.clone(), live_nodes.clone());
+            move || {
+                allocs.fetch_add(1, Ordering::Relaxed);
+                live_nodes.fetch_add(1, Ordering::Relaxed);
+            }
+        })
+        .on_dealloc({
+            let live_nodes = live_nodes.clone();
+            move || {
+                live_nodes.fetch_sub(1, Ordering::Relaxed);
+            }
+        })
+        .build();
+
+    let mut maps = (0..10_000)
+        .map(|_| BTreeMap::new_in(&store))
+        .collect::<Vec<_>>();
+    assert_eq!(allocs.load(Ordering::Relaxed), 0);
+    for map in &maps {
+        map.validate();
+        assert_eq!(map.nodes().count(), 0);
+    }
+
+    // Emptying a tree, however it's done, frees all of its nodes
+    let mut others = BTreeMap::new_in(&store);
+    for (i, map) in maps.iter_mut().enumerate().step_by(100) {
+        for j in 0..i as u32 % 50 {
+            map.insert(j, j);
+        }
+        match i % 300 {
+            0 => {
+                for j in 0..i as u32 % 50 {
+                    map.remove(&j);
+                }
+            }
+            100 => while map.pop_last().is_some() {},
+            _ => map.try_transfer_range(.., &mut others).unwrap(),
+        }
+        map.validate();
+        assert_eq!(map.nodes().count(), 0);
+    }
+    others.clear();
+    assert_eq!(live_nodes.load(Ordering::Relaxed), 0);
+}
+
+struct GrowthCount(Arc<AtomicUsize>);
+
+impl StoreAllocObserver for GrowthCount {
+    fn slab_grew(&self, _bytes: usize, _total_bytes: usize) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn reserve() {
+    let grew = Arc::new(AtomicUsize::new(0));
+    for sequential in [true, false] {
+        let store = BTreeStoreBuilder::new()
+            .observer(GrowthCount(grew.clone()))
+            .build();
+        let mut map = BTreeMap::new_in(&store);
+        map.reserve(10_000);
+        grew.store(0, Ordering::Relaxed);
+        for i in 0..10_000u32 {
+            let key = match sequential {
+                true => i,
+                false => i.wrapping_mul(2_654_435_761),
+            };
+            map.insert(key, i);
+        }
+        assert_eq!(grew.load(Ordering::Relaxed), 0);
+
+        // Reserving is relative to the current length
+        map.reserve(1000);
+        grew.store(0, Ordering::Relaxed);
+        map.extend((10_000..11_000).map(|i| (u32::MAX - i, i)));
+        assert_eq!(grew.load(Ordering::Relaxed), 0);
+        map.validate();
+    }
+
+    // Reserving slots that are already free doesn't grow the slab
+    let store = BTreeStoreBuilder::new()
+        .capacity(100)
+        .observer(GrowthCount(grew.clone()))
+        .build::<u32, u32>();
+    assert_eq!(grew.load(Ordering::Relaxed), 1);
+    store.reserve(50);
+    assert_eq!(grew.load(Ordering::Relaxed), 1);
+    store.reserve(150);
+    assert_eq!(grew.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn capacity() {
+    let store = BTreeStore::<u32, u32>::with_capacity(100);
+    assert_eq!(store.capacity(), 100);
+    let mut map = BTreeMap::new_in(&store);
+    map.extend((0..1000).map(|i| (i, i)));
+    let capacity = store.capacity();
+    assert!(capacity >= map.nodes().count());
+
+    // Freed slots are still counted
+    map.clear();
+    assert_eq!(store.capacity(), capacity);
+    store.reserve(capacity);
+    assert_eq!(store.capacity(), capacity);
+    assert_eq!(map.try_reserve(10_000), Ok(()));
+    assert!(store.capacity() > capacity);
+
+    assert_eq!(store.try_reserve(usize::MAX), Err(Error::CapacityOverflow));
+    assert_eq!(
+        store.try_reserve(isize::MAX as usize / BTreeStore::<u32, u32>::NODE_SIZE + 1),
+        Err(Error::CapacityOverflow)
+    );
+    assert_eq!(map.try_reserve(usize::MAX), Err(Error::CapacityOverflow));
+}
+
+#[test]
+fn try_reserve_out_of_memory() {
+    let store = BTreeStore::<u32, u32>::new();
+    let mut map = BTreeMap::new_in(&store);
+    map.insert(0, 0);
+    let capacity = store.capacity();
+
+    // Small enough for the slab, but too large for any allocator
+    let nodes = isize::MAX as usize / BTreeStore::<u32, u32>::NODE_SIZE - 1;
+    assert!(matches!(
+        store.try_reserve(nodes),
+        Err(Error::OutOfMemory(_))
+    ));
+    assert_eq!(store.capacity(), capacity);
+    assert_eq!(map.try_insert(1, 1), Ok(None));
+    map.validate();
+    assert!(map.keys().copied().eq(0..2));
+}
+
+#[test]
+fn shrink_and_compact() {
+    let mut store = BTreeStore::<u32, u32>::new();
+    let compact_store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    map.extend((0..1000).map(|i| (i, i)));
+    map.retain(|k, _| k % 100 == 0);
+    let capacity = store.capacity();
+
+    let compacted = map.compact_in(&compact_store);
+    compacted.validate();
+    assert!(compacted.keys().copied().eq((0..1000).step_by(100)));
+    #[cfg(not(feature = "sync-store"))]
+    assert_eq!(compact_store.capacity(), compacted.nodes().count());
+    assert!(compact_store.capacity() < capacity);
+    store.shrink_to_fit();
+    assert_eq!(store.capacity(), 0);
+
+    // Leaked nodes can't be released
+    let mut leaked = BTreeMap::new_in(&store);
+    leaked.insert(1, 1);
+    std::mem::forget(leaked);
+    store.shrink_to_fit();
+    #[cfg(not(feature = "sync-store"))]
+    assert_eq!(store.capacity(), 1);
+    #[cfg(feature = "sync-store")]
+    assert!(store.capacity() >= 1);
+}
+
+#[test]
+fn compact() {
+    const NODE_SIZE: usize = BTreeStore::<u32, u32>::NODE_SIZE;
+    let usage = Arc::new(Usage::default());
+    let other_store = BTreeStore::new();
+    let store = BTreeStoreBuilder::new()
+        .observer(Profiler(usage.clone()))
+        .build::<u32, u32>();
+    let mut big = BTreeMap::new_in(&store);
+    let mut small = BTreeMap::new_in(&store);
+    big.extend((0..100_000).map(|i| (i, i)));
+    small.extend((0..1000).map(|i| (i, i)));
+    big.retain(|k, _| k % 1000 == 0);
+    let mut emptied = BTreeMap::new_in(&store);
+    emptied.extend((0..1000).map(|i| (i, i)));
+    emptied.clear();
+    let capacity = store.capacity();
+
+    store.compact(&mut [&mut big, &mut small, &mut emptied]);
+    big.validate();
+    small.validate();
+    assert!(big.keys().copied().eq((0..100_000).step_by(1000)));
+    assert!(small.keys().copied().eq(0..1000));
+    assert!(store.capacity() < capacity);
+    assert_eq!(
+        usage.slab_bytes.load(Ordering::Relaxed),
+        store.capacity() * NODE_SIZE
+    );
+    assert!(store.leak_check(&[&big, &small]).is_leak_free());
+
+    // The trees still work after their nodes moved
+    big.extend((0..100_000).map(|i| (i, i)));
+    small.retain(|k, _| k % 2 == 0);
+    big.validate();
+    small.validate();
+    assert_eq!(big.len(), 100_000);
+    assert_eq!(small.len(), 500);
+
+    let mut other = BTreeMap::new_in(&other_store);
+    assert_eq!(
+        store.try_compact(&mut [&mut big, &mut other]),
+        Err(Error::WrongStore)
+    );
+}
+
+#[test]
+fn stats() {
+    const NODE_SIZE: usize = BTreeStore::<u32, u32>::NODE_SIZE;
+    let store = BTreeStore::<u32, u32>::with_capacity(10);
+    assert_eq!(
+        store.stats(),
+        StoreStats {
+            live_nodes: 0,
+            free_nodes: 10,
+            peak_nodes: 0,
+            used_bytes: 0,
+            reserved_bytes: 10 * NODE_SIZE,
+        }
+    );
+
+    let mut a = BTreeMap::new_in(&store);
+    let mut b = BTreeMap::new_in(&store);
+    a.extend((0..1000).map(|i| (i, i)));
+    b.extend((0..100).map(|i| (i, i)));
+    let a_nodes = a.nodes().count();
+    let b_nodes = b.nodes().count();
+    let stats = store.stats();
+    assert_eq!(stats.live_nodes, a_nodes + b_nodes);
+    assert_eq!(stats.used_bytes, stats.live_nodes * NODE_SIZE);
+    assert_eq!(stats.reserved_bytes, store.capacity() * NODE_SIZE);
+    assert_eq!(
+        a.memory_usage(),
+        std::mem::size_of::<BTreeMap<u32, u32>>() + a_nodes * NODE_SIZE
+    );
+
+    drop(a);
+    let stats = store.stats();
+    assert_eq!(stats.live_nodes, b_nodes);
+    assert_eq!(stats.free_nodes, store.capacity() - b_nodes);
+    assert!(stats.peak_nodes >= a_nodes + b_nodes);
+}
+
+#[test]
+fn reset() {
+    let mut store = BTreeStore::new();
+    for round in 0..3 {
+        let mut a = BTreeMap::new_in(&store);
+        let mut b = BTreeMap::new_in(&store);
+        a.extend((0..1000).map(|i| (i, round)));
+        b.extend((0..100).map(|i| (i, round)));
+        assert_eq!(a.get(&500), Some(&round));
+        a.abandon();
+        b.abandon();
+        assert!(store.stats().live_nodes > 0);
+
+        store.reset();
+        assert_eq!(store.stats().live_nodes, 0);
+        assert_eq!(store.capacity(), 0);
+    }
+}
+
+#[test]
+fn leak_check() {
+    let store = BTreeStore::new();
+    let mut a = BTreeMap::new_in(&store);
+    let mut b = BTreeMap::new_in(&store);
+    a.extend((0..1000).map(|i| (i, i)));
+    b.extend((0..100).map(|i| (i, i)));
+    let a_nodes = a.nodes().count();
+    let b_nodes = b.nodes().count();
+    assert_eq!(store.live_nodes(), a_nodes + b_nodes);
+    assert!(store.try_leak_check(&[&a, &b]).unwrap().is_leak_free());
+
+    std::mem::forget(b);
+    assert_eq!(
+        store.try_leak_check(&[&a]),
+        Ok(LeakReport {
+            live_nodes: a_nodes + b_nodes,
+            reachable_nodes: a_nodes,
+            orphaned_nodes: b_nodes,
+        })
+    );
+
+    let other_store = BTreeStore::<i32, i32>::new();
+    let other = BTreeMap::new_in(&other_store);
+    assert_eq!(store.try_leak_check(&[&a, &other]), Err(Error::WrongStore));
+}
+
+#[cfg(feature = "generations")]
+#[test]
+#[should_panic(expected = "freed")]
+fn lookup_after_clear() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    map.extend((0..100).map(|i| (i, i)));
+    // Another handle to the same nodes, as buggy unsafe code might make
+    let alias = std::mem::ManuallyDrop::new(unsafe { std::ptr::read(&map) });
+    map.clear();
+    alias.get(&1);
+}
+
+#[cfg(feature = "generations")]
+#[test]
+#[should_panic(expected = "freed")]
+fn iter_after_clear() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    map.extend((0..100).map(|i| (i, i)));
+    let alias = std::mem::ManuallyDrop::new(unsafe { std::ptr::read(&map) });
+    map.clear();
+    alias.iter().count();
+}
+
+/// Allocates each node with the global allocator, counting live slots
+struct CountingBackend(Arc<AtomicIsize>);
+
+unsafe impl SlabBackend for CountingBackend {
+    fn alloc(&self, layout: Layout) -> NonNull<u8> {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        NonNull::new(unsafe { std::alloc::alloc(layout) }).unwrap()
+    }
+
+    unsafe fn free(&self, slot: NonNull<u8>, layout: Layout) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+        std::alloc::dealloc(slot.as_ptr(), layout)
+    }
+}
+
+#[test]
+fn custom_backend() {
+    let slots = Arc::new(AtomicIsize::new(0));
+    let store = BTreeStoreBuilder::new()
+        .backend(CountingBackend(slots.clone()))
+        .capacity(100)
+        .build();
+    assert_eq!(slots.load(Ordering::Relaxed), 0);
+
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..10_000u32 {
+        map.insert(i.wrapping_mul(2_654_435_761), i);
+    }
+    map.retain(|_, v| *v % 2 == 0);
+    map.validate();
+    assert_eq!(slots.load(Ordering::Relaxed), map.nodes().count() as isize);
+    let copy = map.clone().compact_in(&store);
+    assert_eq!(copy, map);
+    drop(map);
+    drop(copy);
+    assert_eq!(slots.load(Ordering::Relaxed), 0);
+}
+
+#[test]
+fn fixed_backend() {
+    let store = BTreeStoreBuilder::new()
+        .backend(FixedBackend::<u32, u32, 16>::new())
+        .build();
+    let mut map = BTreeMap::new_in(&store);
+    let mut len = 0;
+    while let Ok(prev) = map.try_insert(len, len) {
+        assert_eq!(prev, None);
+        len += 1;
+    }
+    map.validate();
+    assert_eq!(map.len(), len as usize);
+    assert!(map.nodes().count() <= 16);
+    // Replacing doesn't need new nodes
+    assert_eq!(map.try_insert(0, 100), Ok(Some(0)));
+    assert_eq!(map.try_insert(len, len), Err(Error::StoreFull));
+    assert_eq!(map.get(&len), None);
+
+    // Freed nodes are reused (removing never allocates, unlike e.g. `retain`)
+    for key in (0..len).filter(|key| key % 2 == 1) {
+        map.remove(&key);
+    }
+    map.validate();
+    let mut other = BTreeMap::new_in(&store);
+    let mut other_len = 0;
+    while other.try_insert(other_len, other_len).is_ok() {
+        other_len += 1;
+    }
+    assert!(other_len > 0);
+    let live_nodes = map.nodes().count() + other.nodes().count();
+    assert_eq!(store.stats().live_nodes, live_nodes);
+    assert!(live_nodes <= 16);
+}
+
+#[test]
+fn try_ops_with_bounded_backend() {
+    let store = BTreeStoreBuilder::new()
+        .backend(FixedBackend::<u32, u32, 16>::new())
+        .build();
+    let mut small = BTreeMap::new_in(&store);
+    assert_eq!(small.try_extend((0..4).map(|i| (i, i))), Ok(()));
+    let copy = small.try_clone().unwrap();
+    assert_eq!(copy, small);
+    drop(copy);
+
+    let mut map = BTreeMap::new_in(&store);
+    assert_eq!(map.try_extend((0..).map(|i| (i, i))), Err(Error::StoreFull));
+    map.validate();
+    assert!(!map.is_empty());
+    assert!(map.iter().map(|(k, _)| *k).eq(0..map.len() as u32));
+    assert_eq!(map.try_clone().err(), Some(Error::StoreFull));
+    assert_eq!(store.try_reserve(16), Err(Error::StoreFull));
+}
+
+#[cfg(feature = "nightly")]
+#[test]
+fn allocator_backend() {
+    use btree_plus_store::AllocatorBackend;
+    use std::alloc::Global;
+
+    let store = BTreeStoreBuilder::new()
+        .backend(AllocatorBackend::new(Global))
+        .build();
+    let mut map = BTreeMap::new_in(&store);
+    map.extend((0..1000).map(|i| (i, i)));
+    map.retain(|k, _| k % 2 == 0);
+    map.extend((0..1000).filter(|i| i % 2 == 1).map(|i| (i, -i)));
+    map.validate();
+    assert_eq!(map.len(), 1000);
+    assert_eq!(map.get(&501), Some(&-501));
+    assert_eq!(store.stats().live_nodes, map.nodes().count());
+}
@@ -0,0 +1,77 @@
+#![cfg(feature = "sync-store")]
+
+use btree_plus_store::{BTreeMap, BTreeStore};
+use std::thread;
+
+#[test]
+fn trees_on_threads() {
+    let store = BTreeStore::new();
+    let mut maps = (0..4).map(|_| BTreeMap::new_in(&store)).collect::<Vec<_>>();
+
+    // Each thread mutates its own tree, allocating in the shared store
+    thread::scope(|s| {
+        for (t, map) in maps.iter_mut().enumerate() {
+            s.spawn(move || {
+                for i in 0..10_000u32 {
+                    map.insert(i, t);
+                }
+                map.retain(|k, _| k % 3 == 0);
+            });
+        }
+    });
+    for (t, map) in maps.iter().enumerate() {
+        map.validate();
+        assert_eq!(map.len(), 3334);
+        assert!(map.values().all(|v| *v == t));
+    }
+    assert_eq!(
+        store.stats().live_nodes,
+        maps.iter().map(|map| map.nodes().count()).sum::<usize>()
+    );
+
+    // And any thread can read any tree
+    thread::scope(|s| {
+        for map in &maps {
+            s.spawn(move || assert!(map.keys().copied().eq((0..10_000).step_by(3))));
+        }
+    });
+
+    // Trees move between threads along with their entries
+    let map = maps.pop().unwrap();
+    let map = thread::scope(|s| {
+        s.spawn(move || {
+            let mut map = map;
+            map.clear();
+            map
+        })
+        .join()
+        .unwrap()
+    });
+    assert!(map.is_empty());
+}
+
+#[test]
+fn concurrent_churn() {
+    let store = BTreeStore::new();
+    thread::scope(|s| {
+        for t in 0..8 {
+            let store = &store;
+            s.spawn(move || {
+                for round in 0..20u32 {
+                    let mut map = BTreeMap::new_in(store);
+                    map.extend((0..1000).map(|i| (i * 8 + t, round)));
+                    map.retain(|k, _| k % 2 == 0);
+                    map.validate();
+                    assert_eq!(map.len(), if t % 2 == 0 { 1000 } else { 0 });
+                }
+            });
+        }
+    });
+    assert_eq!(store.stats().live_nodes, 0);
+
+    // Slots freed to the threads' shards are reused from this thread
+    let mut map = BTreeMap::new_in(&store);
+    map.extend((0..10_000).map(|i| (i, 0)));
+    map.validate();
+    assert_eq!(store.stats().live_nodes, map.nodes().count());
+}
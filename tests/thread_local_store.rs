@@ -0,0 +1,45 @@
+#![cfg(feature = "thread-local-store")]
+
+#[cfg(feature = "set")]
+use btree_plus_store::BTreeSet;
+use btree_plus_store::{BTreeMap, BTreeStore};
+
+#[test]
+fn new_default_and_collect() {
+    let mut map = BTreeMap::new();
+    map.insert(1, "a");
+    assert!(std::ptr::eq(
+        BTreeStore::<i32, &str>::thread_local(),
+        BTreeStore::<i32, &str>::thread_local()
+    ));
+
+    let mut other: BTreeMap<i32, &str> = Default::default();
+    other.insert(1, "a");
+    assert_eq!(map, other);
+
+    let collected = (0..1000).map(|i| (i % 100, i)).collect::<BTreeMap<_, _>>();
+    collected.validate();
+    assert!(collected
+        .iter()
+        .map(|(k, v)| (*k, *v))
+        .eq((0..100).map(|i| (i, i + 900))));
+
+    // Each thread has its own store
+    let main_store = BTreeStore::<u8, u8>::thread_local() as *const _ as usize;
+    let thread_store = std::thread::spawn(|| {
+        let map = [(1u8, 2u8)].into_iter().collect::<BTreeMap<_, _>>();
+        assert_eq!(map.get(&1), Some(&2));
+        BTreeStore::<u8, u8>::thread_local() as *const _ as usize
+    })
+    .join()
+    .unwrap();
+    assert_ne!(main_store, thread_store);
+
+    #[cfg(feature = "set")]
+    {
+        let set = [3, 1, 2, 1].into_iter().collect::<BTreeSet<_>>();
+        set.validate();
+        assert!(set.iter().copied().eq(1..=3));
+        assert!(BTreeSet::<String>::default().is_empty());
+    }
+}
@@ -0,0 +1,56 @@
+#[cfg(feature = "set")]
+use btree_plus_store::BTreeSet;
+use btree_plus_store::{BTreeMap, BTreeStore, TotalOrd};
+
+#[cfg(feature = "set")]
+#[test]
+fn float_keys() {
+    let store = BTreeStore::new();
+    let mut set = BTreeSet::new_in(&store);
+
+    for value in [
+        1.5,
+        -0.0,
+        0.0,
+        f64::INFINITY,
+        -2.25,
+        f64::NAN,
+        f64::NEG_INFINITY,
+        -f64::NAN,
+        1.5,
+    ] {
+        set.insert(TotalOrd(value));
+    }
+    set.validate();
+
+    let values = set.iter().map(|value| value.0).collect::<Vec<_>>();
+    assert_eq!(values.len(), 8);
+    assert!(values[0].is_nan() && values[0].is_sign_negative());
+    assert_eq!(values[1], f64::NEG_INFINITY);
+    assert_eq!(values[2], -2.25);
+    assert!(values[3] == 0.0 && values[3].is_sign_negative());
+    assert!(values[4] == 0.0 && values[4].is_sign_positive());
+    assert_eq!(values[5], 1.5);
+    assert_eq!(values[6], f64::INFINITY);
+    assert!(values[7].is_nan() && values[7].is_sign_positive());
+}
+
+#[test]
+fn float_range() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+
+    for i in 0..100 {
+        map.insert(TotalOrd(i as f32 / 10.0), i);
+    }
+
+    assert_eq!(
+        map.try_range(TotalOrd(2.0)..TotalOrd(3.0))
+            .unwrap()
+            .map(|(_, i)| *i)
+            .collect::<Vec<_>>(),
+        (20..30).collect::<Vec<_>>()
+    );
+    assert_eq!(map.get(&TotalOrd(4.2)), Some(&42));
+    assert_eq!(map.get(&TotalOrd(f32::NAN)), None);
+}
@@ -0,0 +1,46 @@
+#![cfg(feature = "nightly")]
+#![feature(trusted_len)]
+
+#[cfg(feature = "set")]
+use btree_plus_store::BTreeSet;
+use btree_plus_store::{BTreeMap, BTreeStore};
+use std::iter::TrustedLen;
+
+fn collect_exact<I: TrustedLen>(iter: I) -> Vec<I::Item> {
+    let (len, upper) = iter.size_hint();
+    assert_eq!(upper, Some(len));
+    let vec = iter.collect::<Vec<_>>();
+    assert_eq!(vec.len(), len);
+    vec
+}
+
+#[test]
+fn trusted_len() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    map.extend((0..1000).map(|i| (i, i * 2)));
+    let mut iter = map.iter();
+    iter.nth(10);
+    iter.nth_back(20);
+    assert_eq!(collect_exact(iter).len(), 968);
+    assert_eq!(
+        collect_exact(map.keys().copied()),
+        (0..1000).collect::<Vec<_>>()
+    );
+    assert_eq!(collect_exact(map.values()).len(), 1000);
+    assert_eq!(collect_exact(map.iter_mut()).len(), 1000);
+    assert_eq!(collect_exact(map.values_mut()).len(), 1000);
+    assert_eq!(collect_exact(map.entries_with_handles()).len(), 1000);
+    assert_eq!(collect_exact(map.clone().into_keys()).len(), 1000);
+    assert_eq!(collect_exact(map.clone().into_values()).len(), 1000);
+    assert_eq!(collect_exact(map.into_iter()).len(), 1000);
+
+    #[cfg(feature = "set")]
+    {
+        let set_store = BTreeStore::new();
+        let mut set = BTreeSet::new_in(&set_store);
+        set.extend(0..100);
+        assert_eq!(collect_exact(set.iter()).len(), 100);
+        assert_eq!(collect_exact(set.into_iter()).len(), 100);
+    }
+}
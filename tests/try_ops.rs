@@ -0,0 +1,188 @@
+#![allow(clippy::reversed_empty_ranges)]
+
+#[cfg(feature = "set")]
+use btree_plus_store::BTreeSet;
+use btree_plus_store::{BTreeMap, BTreeStore, Error};
+use std::ops::Bound;
+
+#[test]
+fn try_range() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..100 {
+        map.insert(i, i);
+    }
+
+    assert_eq!(map.try_range(10..20).unwrap().count(), 10);
+    assert_eq!(map.try_range(10..10).unwrap().count(), 0);
+    assert_eq!(map.try_range(10..=10).unwrap().count(), 1);
+    assert!(matches!(map.try_range(20..10), Err(Error::InvalidRange)));
+    assert!(matches!(
+        map.try_range((Bound::Excluded(10), Bound::Excluded(10))),
+        Err(Error::InvalidRange)
+    ));
+    assert!(matches!(
+        map.try_range_mut(20..=10),
+        Err(Error::InvalidRange)
+    ));
+
+    #[cfg(feature = "set")]
+    {
+        let set_store = BTreeStore::new();
+        let mut set = BTreeSet::new_in(&set_store);
+        set.insert(1);
+        assert!(matches!(set.try_range(2..1), Err(Error::InvalidRange)));
+    }
+}
+
+#[test]
+fn try_advance() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    map.insert(1, 1);
+    map.insert(2, 2);
+
+    let mut iter = map.iter();
+    assert_eq!(iter.try_advance(), Ok(()));
+    assert_eq!(iter.try_advance_back(), Ok(()));
+    assert_eq!(iter.try_advance(), Err(Error::IterationDone));
+
+    let mut range = map.try_range(2..).unwrap();
+    assert_eq!(range.try_advance(), Ok(()));
+    assert_eq!(range.try_advance(), Err(Error::IterationDone));
+    assert_eq!(range.try_advance_back(), Err(Error::IterationDone));
+}
+
+#[test]
+#[should_panic(expected = "BTreeMap::range: range start is greater than range end")]
+fn range_panics() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    map.insert(1, 1);
+    map.range(2..1);
+}
+
+#[test]
+#[should_panic(expected = "BTreeMap::transfer_range: dest is in store")]
+fn transfer_range_wrong_store_panics() {
+    let store = BTreeStore::new();
+    let other_store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    let mut dest = BTreeMap::new_in(&other_store);
+    map.insert(1, 1);
+    map.transfer_range(.., &mut dest);
+}
+
+#[test]
+fn index() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    map.extend((0..100).map(|i| (i.to_string(), i)));
+    assert_eq!(map["42"], 42);
+    assert_eq!(map[&"7".to_string()], 7);
+}
+
+#[test]
+#[should_panic(expected = "BTreeMap::index: key not found")]
+fn index_panics() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    map.insert(1, 1);
+    let _ = map[&2];
+}
+
+#[test]
+fn panics_report_caller_location() {
+    use std::panic::{catch_unwind, set_hook, take_hook, AssertUnwindSafe};
+    use std::sync::{Arc, Mutex};
+
+    let store = BTreeStore::new();
+    let map = BTreeMap::<i32, i32>::new_in(&store);
+
+    // Other tests may panic concurrently, so only record our panic's location
+    let location = Arc::new(Mutex::new(None));
+    let hook_location = location.clone();
+    let prev_hook = take_hook();
+    set_hook(Box::new(move |info| {
+        if info
+            .to_string()
+            .contains("Iter::advance: iteration is done")
+        {
+            *hook_location.lock().unwrap() =
+                info.location().map(|l| (l.file().to_string(), l.line()));
+        }
+    }));
+    let line = line!() + 1;
+    let result = catch_unwind(AssertUnwindSafe(|| map.iter().advance()));
+    set_hook(prev_hook);
+
+    assert!(result.is_err());
+    assert_eq!(*location.lock().unwrap(), Some((file!().to_string(), line)));
+}
+
+#[test]
+fn from_array_in() {
+    let store = BTreeStore::new();
+    let map = BTreeMap::from_array_in([(3, 'c'), (1, 'a'), (2, 'b')], &store);
+    map.validate();
+    assert!(map.into_iter().eq([(1, 'a'), (2, 'b'), (3, 'c')]));
+
+    let store = BTreeStore::new();
+    let entries: [(usize, usize); 100] = std::array::from_fn(|i| (i, i * 2));
+    let map = BTreeMap::try_from_sorted_array_in(entries, &store).unwrap();
+    map.validate();
+    assert!(map.iter().map(|(k, v)| (*k, *v)).eq(entries));
+    assert_eq!(
+        BTreeMap::try_from_sorted_array_in([(1, 1), (1, 2)], &BTreeStore::new()).err(),
+        Some(Error::UnorderedKey)
+    );
+
+    #[cfg(feature = "set")]
+    {
+        let store = BTreeStore::new();
+        let set = BTreeSet::from_array_in([2, 3, 1, 2], &store);
+        assert!(set.iter().copied().eq(1..=3));
+        assert_eq!(
+            BTreeSet::try_from_sorted_array_in([1, 3, 2], &store).err(),
+            Some(Error::UnorderedKey)
+        );
+    }
+}
+
+#[test]
+fn from_sorted_iter_in() {
+    let store = BTreeStore::new();
+    for len in [0, 1, 7, 8, 9, 100, 10_000] {
+        let map = BTreeMap::try_from_sorted_iter_in((0..len).map(|i| (i, i * 2)), &store).unwrap();
+        map.validate();
+        assert!(map
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .eq((0..len).map(|i| (i, i * 2))));
+    }
+    let unordered = (0..1000).chain(500..600).map(|i| (i, i));
+    assert_eq!(
+        BTreeMap::try_from_sorted_iter_in(unordered, &store).err(),
+        Some(Error::UnorderedKey)
+    );
+    // The partial map was freed
+    assert_eq!(store.stats().live_nodes, 0);
+
+    #[cfg(feature = "set")]
+    {
+        let store = BTreeStore::new();
+        let set = BTreeSet::try_from_sorted_iter_in((0..100).map(|i| i * 3), &store).unwrap();
+        assert!(set.iter().copied().eq((0..100).map(|i| i * 3)));
+        assert_eq!(
+            BTreeSet::try_from_sorted_iter_in([1, 3, 3], &store).err(),
+            Some(Error::UnorderedKey)
+        );
+    }
+}
+
+#[test]
+#[should_panic(expected = "BTreeMap::from_sorted_array_in: key is out of order")]
+fn from_sorted_array_in_panics() {
+    let store = BTreeStore::new();
+    BTreeMap::from_sorted_array_in([(2, 2), (1, 1)], &store);
+}